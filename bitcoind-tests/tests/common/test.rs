@@ -1,12 +1,8 @@
-use std::str::FromStr;
-
+use elements::confidential;
 use elements::hashes::Hash;
 use elements::pset::PartiallySignedTransaction as Psbt;
-use elements::{confidential, secp256k1_zkp as secp256k1};
 use elementsd::ElementsD;
-use secp256k1::XOnlyPublicKey;
-use simplicity::jet::elements::{ElementsEnv, ElementsUtxo};
-use simplicityhl::{elements, simplicity};
+use simplicityhl::elements;
 
 use crate::common::daemon::Call;
 
@@ -93,34 +89,6 @@ impl<'a> TestCase<'a> {
         self
     }
 
-    fn script_ver(&self) -> (elements::Script, elements::taproot::LeafVersion) {
-        let script = elements::script::Script::from(
-            self.compiled
-                .as_ref()
-                .expect("program should be defined")
-                .commit()
-                .cmr()
-                .as_ref()
-                .to_vec(),
-        );
-        (script, simplicity::leaf_version())
-    }
-
-    fn taproot_spend_info(&self) -> elements::taproot::TaprootSpendInfo {
-        let internal_key = XOnlyPublicKey::from_str(
-            "50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0",
-        )
-        .unwrap();
-        let builder = elements::taproot::TaprootBuilder::new();
-        let (script, version) = self.script_ver();
-        let builder = builder
-            .add_leaf_with_ver(0, script, version)
-            .expect("tap tree should be valid");
-        builder
-            .finalize(secp256k1::SECP256K1, internal_key)
-            .expect("tap tree should be valid")
-    }
-
     // Find the Outpoint by value.
     // Ideally, we should find by scriptPubkey, but this
     // works for temp test case
@@ -139,15 +107,8 @@ impl<'a> TestCase<'a> {
     }
 
     pub fn create_utxo(&mut self) {
-        let info = self.taproot_spend_info();
-        let blinder = None;
-        let address = elements::Address::p2tr(
-            secp256k1::SECP256K1,
-            info.internal_key(),
-            info.merkle_root(),
-            blinder,
-            &elements::AddressParams::ELEMENTS,
-        );
+        let compiled = self.compiled.as_ref().expect("test program is missing");
+        let address = compiled.address(&elements::AddressParams::ELEMENTS);
         let amount = "1";
         let txid = self.daemon.send_to_address(&address, amount);
         self.funding = Some(Funding { address, txid });
@@ -178,40 +139,27 @@ impl<'a> TestCase<'a> {
                 elements::TxOut::new_fee(3_000, utxo.asset.explicit().unwrap()),
             ],
         });
-        let info = self.taproot_spend_info();
-        let script_ver = self.script_ver();
-        let control_block = info
-            .control_block(&script_ver)
-            .expect("control block should exist");
+        let control_block = compiled.control_block(simplicityhl::taproot::nums_key());
+        let leaf = simplicityhl::taproot::leaf(compiled.commit().cmr());
+        psbt.inputs_mut()[0].witness_utxo = Some(utxo);
+        psbt.inputs_mut()[0].tap_scripts.insert(control_block, leaf);
         let sighash_all = {
-            let tx = psbt
-                .extract_tx()
-                .expect("transaction should be extractable");
-            let utxos = vec![ElementsUtxo::from(utxo)];
-            let index = 0;
-            let annex = None;
-            let env = ElementsEnv::new(
-                &tx,
-                utxos,
-                index,
+            let env = simplicityhl::environment::from_pset(
+                &psbt,
+                0,
                 compiled.commit().cmr(),
-                control_block.clone(),
-                annex,
                 self.genesis_hash,
-            );
+            )
+            .expect("environment should be derivable from PSET");
             env.c_tx_env().sighash_all()
         };
         let witness_values = (self.witness)(sighash_all.to_byte_array());
         let satisfied_program = compiled
             .satisfy(witness_values)
             .expect("program should be satisfiable");
-        let (program_bytes, witness_bytes) = satisfied_program.redeem().encode_to_vec();
-        psbt.inputs_mut()[0].final_script_witness = Some(vec![
-            witness_bytes,
-            program_bytes,
-            script_ver.0.into_bytes(),
-            control_block.serialize(),
-        ]);
+        satisfied_program
+            .finalize_pset(&mut psbt, 0, self.genesis_hash)
+            .expect("PSET input should be finalizable");
         let tx = psbt
             .extract_tx()
             .expect("transaction should be extractable");
@@ -8,22 +8,25 @@ use tokio::sync::RwLock;
 
 use tower_lsp_server::jsonrpc::Result;
 use tower_lsp_server::lsp_types::{
-    CompletionOptions, CompletionParams, CompletionResponse, Diagnostic,
-    DidChangeConfigurationParams, DidChangeTextDocumentParams, DidChangeWatchedFilesParams,
-    DidChangeWorkspaceFoldersParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
-    DidSaveTextDocumentParams, ExecuteCommandParams, GotoDefinitionParams, GotoDefinitionResponse,
-    Hover, HoverParams, HoverProviderCapability, InitializeParams, InitializeResult,
-    InitializedParams, Location, MarkupContent, MarkupKind, MessageType, OneOf, Range,
+    CompletionOptions, CompletionParams, CompletionResponse, Diagnostic, DiagnosticSeverity,
+    DiagnosticTag, DidChangeConfigurationParams, DidChangeTextDocumentParams,
+    DidChangeWatchedFilesParams, DidChangeWorkspaceFoldersParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DidSaveTextDocumentParams, DocumentFormattingParams,
+    ExecuteCommandParams, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverParams,
+    HoverProviderCapability, InitializeParams, InitializeResult, InitializedParams, Location,
+    MarkupContent, MarkupKind, MessageType, NumberOrString, OneOf, Position, Range,
     ReferenceParams, SaveOptions, SemanticTokensParams, SemanticTokensResult, ServerCapabilities,
     TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
-    TextDocumentSyncSaveOptions, Uri, WorkDoneProgressOptions, WorkspaceFoldersServerCapabilities,
-    WorkspaceServerCapabilities,
+    TextDocumentSyncSaveOptions, TextEdit, Uri, WorkDoneProgressOptions,
+    WorkspaceFoldersServerCapabilities, WorkspaceServerCapabilities,
 };
 use tower_lsp_server::{Client, LanguageServer};
 
 use simplicityhl::{
     ast,
     error::{RichError, WithFile},
+    format::Formatter,
+    lint::{Level, Lint, Linter},
     parse,
     parse::ParseFromStr,
 };
@@ -89,6 +92,7 @@ impl LanguageServer for Backend {
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 definition_provider: Some(OneOf::Left(true)),
                 references_provider: Some(OneOf::Left(true)),
+                document_formatting_provider: Some(OneOf::Left(true)),
                 ..ServerCapabilities::default()
             },
         })
@@ -363,6 +367,17 @@ impl LanguageServer for Backend {
             Ok(None)
         }
     }
+
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let documents = self.document_map.read().await;
+        let uri = &params.text_document.uri;
+
+        let doc = documents
+            .get(uri)
+            .ok_or(LspError::DocumentNotFound(uri.to_owned()))?;
+
+        Ok(format_document(&doc.text))
+    }
 }
 
 impl Backend {
@@ -388,7 +403,11 @@ impl Backend {
         match err {
             None => {
                 self.client
-                    .publish_diagnostics(params.uri.clone(), vec![], params.version)
+                    .publish_diagnostics(
+                        params.uri.clone(),
+                        lint_diagnostics(params.text),
+                        params.version,
+                    )
                     .await;
             }
             Some(err) => {
@@ -450,6 +469,64 @@ fn create_document(program: &simplicityhl::parse::Program, text: &str) -> Docume
     document
 }
 
+/// Format the code using [`Formatter`] and return an edit that replaces the whole document.
+///
+/// Return `None` if the code is not a valid program, so there is nothing to format.
+fn format_document(text: &Rope) -> Option<Vec<TextEdit>> {
+    let source = text.to_string();
+    let formatted = Formatter::new().format(&source).ok()?;
+    if formatted == source {
+        return Some(vec![]);
+    }
+
+    let last_line = text.len_lines() - 1;
+    let end = Position::new(
+        u32::try_from(last_line).ok()?,
+        u32::try_from(text.line(last_line).len_utf16_cu()).ok()?,
+    );
+    Some(vec![TextEdit::new(
+        Range::new(Position::new(0, 0), end),
+        formatted,
+    )])
+}
+
+/// Lint the code using [`Linter`] and return a diagnostic for each warning.
+///
+/// Return no diagnostics if the code is not a valid program.
+fn lint_diagnostics(text: &str) -> Vec<Diagnostic> {
+    let Ok(warnings) = Linter::new().lint(text) else {
+        return vec![];
+    };
+
+    warnings
+        .iter()
+        .filter_map(|warning| {
+            let (start, end) = span_to_positions(warning.span()).ok()?;
+            let severity = match warning.level() {
+                Level::Deny => DiagnosticSeverity::ERROR,
+                Level::Allow | Level::Warn => DiagnosticSeverity::WARNING,
+            };
+            // Editors fade out code that is marked as unnecessary
+            let tags = match warning.lint() {
+                Lint::UnusedVariables
+                | Lint::UnusedFunctions
+                | Lint::UnusedWitnesses
+                | Lint::UnreachableCode => Some(vec![DiagnosticTag::UNNECESSARY]),
+                _ => None,
+            };
+            Some(Diagnostic {
+                range: Range::new(start, end),
+                severity: Some(severity),
+                code: Some(NumberOrString::String(warning.lint().to_string())),
+                source: Some("simplicityhl".to_string()),
+                message: warning.message().to_string(),
+                tags,
+                ..Diagnostic::default()
+            })
+        })
+        .collect()
+}
+
 /// Parse program using [`simplicityhl`] compiler and return [`RichError`],
 /// which used in Diagnostic. Also create [`Document`] from parsed program.
 fn parse_program(text: &str) -> (Option<RichError>, Option<Document>) {
@@ -501,6 +578,39 @@ mod tests {
         assert!(doc.is_some(), "Expected problem in AST build, not parse");
     }
 
+    #[test]
+    fn test_lint_diagnostics() {
+        let diagnostics = lint_diagnostics("fn main() {\n    let x: u8 = 1;\n}");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(1, 4), Position::new(1, 13))
+        );
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("unused_variables".to_string()))
+        );
+        assert!(lint_diagnostics(invalid_program_on_ast()).is_empty());
+    }
+
+    #[test]
+    fn test_format_document() {
+        let text = Rope::from_str(sample_program());
+        let edits = format_document(&text).expect("Expected valid program");
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range.start, Position::new(0, 0));
+        assert_eq!(edits[0].range.end, Position::new(1, 21));
+        assert_eq!(
+            edits[0].new_text,
+            Formatter::new().format(sample_program()).unwrap()
+        );
+
+        let formatted = Rope::from_str(&edits[0].new_text);
+        assert_eq!(format_document(&formatted), Some(vec![]));
+        assert!(format_document(&Rope::from_str(invalid_program_on_parsing())).is_none());
+    }
+
     #[test]
     fn test_parse_program_invalid_parse() {
         let (err, doc) = parse_program(invalid_program_on_parsing());
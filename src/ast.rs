@@ -1,5 +1,5 @@
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::num::NonZeroUsize;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -8,7 +8,7 @@ use either::Either;
 use miniscript::iter::{Tree, TreeLike};
 use simplicity::jet::Elements;
 
-use crate::debug::{CallTracker, DebugSymbols, TrackedCallName};
+use crate::debug::{CallTracker, DebugSymbols, TrackedCallName, VisibleVariables};
 use crate::error::{Error, RichError, Span, WithSpan};
 use crate::num::{NonZeroPow2Usize, Pow2Usize};
 use crate::parse::MatchPattern;
@@ -25,14 +25,43 @@ use crate::{impl_eq_hash, parse};
 ///
 /// Other items such as custom functions or type aliases
 /// are resolved during the creation of the AST.
+///
+/// A program may declare further entry points with the `#[entry_point]` attribute.
+/// Exactly one entry point is selected as the main function of the AST.
+/// The other entry points are type-checked, but they are not part of the AST.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Program {
     main: Expression,
+    entry_points: Arc<[FunctionName]>,
     parameters: Parameters,
     witness_types: WitnessTypes,
+    declared_types: Arc<DeclaredTypes>,
     call_tracker: Arc<CallTracker>,
 }
 
+/// Types of parameters and witnesses as they are written in the program, with type aliases.
+///
+/// A type is known if the parameter or witness is used where a type is written:
+/// as the value of a `let` statement, as the scrutinee of a `match` expression,
+/// as the argument of a jet or custom function, or as a component of a tuple in these places.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DeclaredTypes {
+    parameters: HashMap<WitnessName, AliasedType>,
+    witnesses: HashMap<WitnessName, AliasedType>,
+}
+
+impl DeclaredTypes {
+    /// Access the declared type of the parameter of the given name.
+    pub fn parameter(&self, name: &WitnessName) -> Option<&AliasedType> {
+        self.parameters.get(name)
+    }
+
+    /// Access the declared type of the witness of the given name.
+    pub fn witness(&self, name: &WitnessName) -> Option<&AliasedType> {
+        self.witnesses.get(name)
+    }
+}
+
 impl Program {
     /// Access the main function.
     ///
@@ -41,6 +70,11 @@ impl Program {
         &self.main
     }
 
+    /// Access the names of all entry points of the program, in order of declaration.
+    pub fn entry_points(&self) -> &[FunctionName] {
+        &self.entry_points
+    }
+
     /// Access the parameters of the program.
     pub fn parameters(&self) -> &Parameters {
         &self.parameters
@@ -51,6 +85,11 @@ impl Program {
         &self.witness_types
     }
 
+    /// Access the types of the parameters and witnesses as they are written in the program.
+    pub fn declared_types(&self) -> &DeclaredTypes {
+        &self.declared_types
+    }
+
     /// Access the debug symbols of the program.
     pub fn debug_symbols(&self, file: &str) -> DebugSymbols {
         self.call_tracker.with_file(file)
@@ -86,6 +125,10 @@ pub enum Function {
     ///
     /// A stub because the definition of the function was moved to its calls in the main function.
     Custom,
+    /// An entry point or a unit test that was not selected as the main function.
+    ///
+    /// A stub because the entry point is compiled separately.
+    EntryPoint,
     /// The main function.
     ///
     /// An expression that takes no inputs (unit) and that produces no output (unit).
@@ -337,6 +380,7 @@ impl CustomFunction {
 pub struct FunctionParam {
     identifier: Identifier,
     ty: ResolvedType,
+    declared: AliasedType,
 }
 
 impl FunctionParam {
@@ -349,6 +393,11 @@ impl FunctionParam {
     pub fn ty(&self) -> &ResolvedType {
         &self.ty
     }
+
+    /// Access the type of the parameter as it is written in the program.
+    pub fn declared(&self) -> &AliasedType {
+        &self.declared
+    }
 }
 
 /// Match expression.
@@ -385,10 +434,11 @@ impl Match {
 impl_eq_hash!(Match; scrutinee, left, right);
 
 /// Arm of a [`Match`] expression.
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug)]
 pub struct MatchArm {
     pattern: MatchPattern,
     expression: Arc<Expression>,
+    span: Span,
 }
 
 impl MatchArm {
@@ -401,8 +451,15 @@ impl MatchArm {
     pub fn expression(&self) -> &Expression {
         &self.expression
     }
+
+    /// Access the span of the match arm.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
 }
 
+impl_eq_hash!(MatchArm; pattern, expression);
+
 /// Item when analyzing modules.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub enum ModuleItem {
@@ -523,12 +580,20 @@ impl TreeLike for ExprTree<'_> {
 #[derive(Clone, Debug, Eq, PartialEq, Default)]
 struct Scope {
     variables: Vec<HashMap<Identifier, ResolvedType>>,
+    visible: Vec<VisibleVariables>,
     aliases: HashMap<AliasName, ResolvedType>,
     parameters: HashMap<WitnessName, ResolvedType>,
     witnesses: HashMap<WitnessName, ResolvedType>,
     functions: HashMap<FunctionName, CustomFunction>,
+    entry_point: Option<FunctionName>,
+    entry_points: Vec<FunctionName>,
     is_main: bool,
     call_tracker: CallTracker,
+    main_witnesses: Option<HashMap<WitnessName, ResolvedType>>,
+    declared: Option<AliasedType>,
+    declared_parameters: HashMap<WitnessName, AliasedType>,
+    declared_witnesses: HashMap<WitnessName, AliasedType>,
+    main_declared_witnesses: HashMap<WitnessName, AliasedType>,
 }
 
 impl Scope {
@@ -537,9 +602,41 @@ impl Scope {
         self.variables.is_empty()
     }
 
+    /// Return to the topmost scope after the analysis of an item failed halfway.
+    ///
+    /// The witnesses of the failed entry point are discarded.
+    pub fn reset(&mut self) {
+        self.variables.clear();
+        self.visible.clear();
+        self.witnesses.clear();
+        self.declared_witnesses.clear();
+        self.declared = None;
+        self.is_main = false;
+    }
+
     /// Push a new scope onto the stack.
     pub fn push_scope(&mut self) {
         self.variables.push(HashMap::new());
+        let visible = self.visible.last().cloned().unwrap_or_default();
+        self.visible.push(visible);
+    }
+
+    /// Create a new scope where the entry point of the given name is the main function.
+    pub fn with_entry_point(entry_point: FunctionName) -> Self {
+        Self {
+            entry_point: Some(entry_point),
+            ..Self::default()
+        }
+    }
+
+    /// Check if the entry point of the given name is selected as the main function.
+    ///
+    /// The entry point `main` is selected by default.
+    pub fn is_selected(&self, name: &FunctionName) -> bool {
+        match &self.entry_point {
+            Some(entry_point) => entry_point == name,
+            None => name.as_inner() == "main",
+        }
     }
 
     /// Push the scope of the main function onto the stack.
@@ -555,6 +652,27 @@ impl Scope {
         self.is_main = true;
     }
 
+    /// Push the scope of an entry point onto the stack.
+    ///
+    /// Each entry point has its own witnesses.
+    ///
+    /// ## Errors
+    ///
+    /// An entry point of the same name has already been defined.
+    ///
+    /// ## Panics
+    ///
+    /// - The current scope is already inside the main function.
+    /// - The current scope is not topmost.
+    pub fn push_entry_point_scope(&mut self, name: FunctionName) -> Result<(), Error> {
+        if self.entry_points.contains(&name) {
+            return Err(Error::FunctionRedefined(name));
+        }
+        self.entry_points.push(name);
+        self.push_main_scope();
+        Ok(())
+    }
+
     /// Pop the current scope from the stack.
     ///
     /// ## Panics
@@ -562,6 +680,7 @@ impl Scope {
     /// The stack is empty.
     pub fn pop_scope(&mut self) {
         self.variables.pop().expect("Stack is empty");
+        self.visible.pop();
     }
 
     /// Pop the scope of the main function from the stack.
@@ -580,12 +699,39 @@ impl Scope {
         )
     }
 
+    /// Pop the scope of an entry point from the stack.
+    ///
+    /// Return `true` if the entry point is selected as the main function.
+    /// The witnesses of the other entry points are discarded.
+    ///
+    /// ## Panics
+    ///
+    /// - The current scope is not inside the main function.
+    /// - The current scope is not nested in the topmost scope.
+    pub fn pop_entry_point_scope(&mut self) -> bool {
+        self.pop_main_scope();
+        let name = self.entry_points.last().expect("Entry point was pushed");
+        let witnesses = std::mem::take(&mut self.witnesses);
+        let declared_witnesses = std::mem::take(&mut self.declared_witnesses);
+        if self.is_selected(name) {
+            self.main_witnesses = Some(witnesses);
+            self.main_declared_witnesses = declared_witnesses;
+            true
+        } else {
+            false
+        }
+    }
+
     /// Push a variable onto the current stack.
     ///
     /// ## Panics
     ///
     /// The stack is empty.
     pub fn insert_variable(&mut self, identifier: Identifier, ty: ResolvedType) {
+        self.visible
+            .last_mut()
+            .expect("Stack is empty")
+            .insert(identifier.clone(), ty.clone());
         self.variables
             .last_mut()
             .expect("Stack is empty")
@@ -622,6 +768,28 @@ impl Scope {
         Ok(())
     }
 
+    /// Declare the type of the expression that is analyzed next, as it is written in the program.
+    pub fn declare(&mut self, ty: AliasedType) {
+        self.declared = Some(ty);
+    }
+
+    /// Take the declared type of the expression that is analyzed now.
+    pub fn take_declared(&mut self) -> Option<AliasedType> {
+        self.declared.take()
+    }
+
+    /// Insert the declared type of a parameter into the global map.
+    ///
+    /// The first declaration is kept.
+    pub fn insert_declared_parameter(&mut self, name: WitnessName, ty: AliasedType) {
+        self.declared_parameters.entry(name).or_insert(ty);
+    }
+
+    /// Insert the declared type of a witness into the map of the current entry point.
+    pub fn insert_declared_witness(&mut self, name: WitnessName, ty: AliasedType) {
+        self.declared_witnesses.insert(name, ty);
+    }
+
     /// Insert a parameter into the global map.
     ///
     /// ## Errors
@@ -661,13 +829,29 @@ impl Scope {
     /// Consume the scope and return its contents:
     ///
     /// 1. The map of parameter types.
-    /// 2. The map of witness types.
-    /// 3. The function call tracker.
-    pub fn destruct(self) -> (Parameters, WitnessTypes, CallTracker) {
+    /// 2. The map of witness types of the main function.
+    /// 3. The declared types of the parameters and of the witnesses of the main function.
+    /// 4. The function call tracker.
+    /// 5. The names of all entry points.
+    pub fn destruct(
+        self,
+    ) -> (
+        Parameters,
+        WitnessTypes,
+        DeclaredTypes,
+        CallTracker,
+        Vec<FunctionName>,
+    ) {
+        let declared_types = DeclaredTypes {
+            parameters: self.declared_parameters,
+            witnesses: self.main_declared_witnesses,
+        };
         (
             Parameters::from(self.parameters),
-            WitnessTypes::from(self.witnesses),
+            WitnessTypes::from(self.main_witnesses.unwrap_or_default()),
+            declared_types,
             self.call_tracker,
+            self.entry_points,
         )
     }
 
@@ -695,9 +879,13 @@ impl Scope {
         self.functions.get(name)
     }
 
-    /// Track a call expression with its span.
+    /// Track a call expression with its span,
+    /// together with the variables that are visible at the call.
+    ///
+    /// The variables are resolved only when debug symbols are created.
     pub fn track_call<S: AsRef<Span>>(&mut self, span: &S, name: TrackedCallName) {
-        self.call_tracker.track_call(*span.as_ref(), name);
+        let visible = self.visible.last().cloned().unwrap_or_default();
+        self.call_tracker.track_call(*span.as_ref(), name, visible);
     }
 }
 
@@ -716,30 +904,77 @@ trait AbstractSyntaxTree: Sized {
 
 impl Program {
     pub fn analyze(from: &parse::Program) -> Result<Self, RichError> {
+        Self::analyze_entry_point(from, &FunctionName::main())
+    }
+
+    /// Analyze the program with the entry point of the given name as its main function.
+    pub fn analyze_entry_point(
+        from: &parse::Program,
+        entry_point: &FunctionName,
+    ) -> Result<Self, RichError> {
         let unit = ResolvedType::unit();
-        let mut scope = Scope::default();
+        let mut scope = Scope::with_entry_point(entry_point.clone());
         let items = from
             .items()
             .iter()
             .map(|s| Item::analyze(s, &unit, &mut scope))
             .collect::<Result<Vec<Item>, RichError>>()?;
         debug_assert!(scope.is_topmost());
-        let (parameters, witness_types, call_tracker) = scope.destruct();
+        let (parameters, witness_types, declared_types, call_tracker, entry_points) =
+            scope.destruct();
         let mut iter = items.into_iter().filter_map(|item| match item {
             Item::Function(Function::Main(expr)) => Some(expr),
             _ => None,
         });
-        let main = iter.next().ok_or(Error::MainRequired).with_span(from)?;
+        let main = match iter.next() {
+            Some(main) => main,
+            None if entry_point.as_inner() == "main" => {
+                return Err(Error::MainRequired).with_span(from)
+            }
+            None => return Err(Error::EntryPointUndefined(entry_point.clone())).with_span(from),
+        };
         if iter.next().is_some() {
-            return Err(Error::FunctionRedefined(FunctionName::main())).with_span(from);
+            return Err(Error::FunctionRedefined(entry_point.clone())).with_span(from);
         }
         Ok(Self {
             main,
+            entry_points: Arc::from(entry_points),
             parameters,
             witness_types,
+            declared_types: Arc::new(declared_types),
             call_tracker: Arc::new(call_tracker),
         })
     }
+
+    /// Analyze the program and return the errors of all items,
+    /// instead of stopping at the first error.
+    ///
+    /// Calls of functions whose definitions contain errors are not reported again.
+    pub fn diagnose(from: &parse::Program) -> Vec<RichError> {
+        let unit = ResolvedType::unit();
+        let mut scope = Scope::default();
+        let mut errors = Vec::new();
+        let mut failed_functions = HashSet::new();
+        for item in from.items() {
+            if let Err(error) = Item::analyze(item, &unit, &mut scope) {
+                if let parse::Item::Function(function) = item {
+                    failed_functions.insert(function.name().clone());
+                }
+                errors.push(error);
+                scope.reset();
+            }
+        }
+        errors.retain(|error| {
+            !matches!(error.error(), Error::FunctionUndefined(name) if failed_functions.contains(name))
+        });
+        let has_main = from.items().iter().any(|item| {
+            matches!(item, parse::Item::Function(function) if function.name().as_inner() == "main")
+        });
+        if !has_main {
+            errors.push(Error::MainRequired.with_span(Span::from(from)));
+        }
+        errors
+    }
 }
 
 impl AbstractSyntaxTree for Item {
@@ -771,14 +1006,20 @@ impl AbstractSyntaxTree for Function {
         assert!(ty.is_unit(), "Function definitions cannot return anything");
         assert!(scope.is_topmost(), "Items live in the topmost scope only");
 
-        if from.name().as_inner() != "main" {
+        // Unit tests are compiled like entry points, so each test can be selected as the main function
+        if !from.is_entry_point() && !from.is_test() {
             let params = from
                 .params()
                 .iter()
                 .map(|param| {
                     let identifier = param.identifier().clone();
                     let ty = scope.resolve(param.ty())?;
-                    Ok(FunctionParam { identifier, ty })
+                    let declared = param.ty().clone();
+                    Ok(FunctionParam {
+                        identifier,
+                        ty,
+                        declared,
+                    })
                 })
                 .collect::<Result<Arc<[FunctionParam]>, Error>>()
                 .with_span(from)?;
@@ -813,10 +1054,14 @@ impl AbstractSyntaxTree for Function {
             }
         }
 
-        scope.push_main_scope();
+        scope
+            .push_entry_point_scope(from.name().clone())
+            .with_span(from)?;
         let body = Expression::analyze(from.body(), ty, scope)?;
-        scope.pop_main_scope();
-        Ok(Self::Main(body))
+        match scope.pop_entry_point_scope() {
+            true => Ok(Self::Main(body)),
+            false => Ok(Self::EntryPoint),
+        }
     }
 }
 
@@ -846,6 +1091,7 @@ impl AbstractSyntaxTree for Assignment {
         // However, the expression evaluated in the assignment does have a type,
         // namely the type specified in the assignment.
         let ty_expr = scope.resolve(from.ty()).with_span(from)?;
+        scope.declare(from.ty().clone());
         let expression = Expression::analyze(from.expression(), &ty_expr, scope)?;
         let typed_variables = from.pattern().is_of_type(&ty_expr).with_span(from)?;
         for (identifier, ty) in typed_variables {
@@ -873,14 +1119,37 @@ impl Expression {
         let mut empty_scope = Scope::default();
         Self::analyze(from, ty, &mut empty_scope)
     }
+
+    /// Analyze an expression from the parse tree that is evaluated on its own,
+    /// outside of any program.
+    ///
+    /// Check if the expression is of the given type.
+    ///
+    /// The expression may call jets, but it may use neither parameters nor witnesses.
+    pub fn analyze_standalone(
+        from: &parse::Expression,
+        ty: &ResolvedType,
+    ) -> Result<Self, RichError> {
+        let mut empty_scope = Scope::default();
+        let expression = Self::analyze(from, ty, &mut empty_scope)?;
+        if let Some((name, ty)) = empty_scope.parameters.into_iter().next() {
+            return Err(Error::ArgumentMissing(name, ty)).with_span(from);
+        }
+        Ok(expression)
+    }
 }
 
 impl AbstractSyntaxTree for Expression {
     type From = parse::Expression;
 
     fn analyze(from: &Self::From, ty: &ResolvedType, scope: &mut Scope) -> Result<Self, RichError> {
+        // Only the value of the expression has the declared type
+        let declared = scope.take_declared();
         match from.inner() {
             parse::ExpressionInner::Single(single) => {
+                if let Some(declared) = declared {
+                    scope.declare(declared);
+                }
                 let ast_single = SingleExpression::analyze(single, ty, scope)?;
                 Ok(Self {
                     ty: ty.clone(),
@@ -895,9 +1164,14 @@ impl AbstractSyntaxTree for Expression {
                     .map(|s| Statement::analyze(s, &ResolvedType::unit(), scope))
                     .collect::<Result<Arc<[Statement]>, RichError>>()?;
                 let ast_expression = match expression {
-                    Some(expression) => Expression::analyze(expression, ty, scope)
-                        .map(Arc::new)
-                        .map(Some),
+                    Some(expression) => {
+                        if let Some(declared) = declared {
+                            scope.declare(declared);
+                        }
+                        Expression::analyze(expression, ty, scope)
+                            .map(Arc::new)
+                            .map(Some)
+                    }
                     None if ty.is_unit() => Ok(None),
                     None => Err(Error::ExpressionTypeMismatch(
                         ty.clone(),
@@ -921,6 +1195,7 @@ impl AbstractSyntaxTree for SingleExpression {
     type From = parse::SingleExpression;
 
     fn analyze(from: &Self::From, ty: &ResolvedType, scope: &mut Scope) -> Result<Self, RichError> {
+        let declared = scope.take_declared();
         let inner = match from.inner() {
             parse::SingleExpressionInner::Boolean(bit) => {
                 if !ty.is_boolean() {
@@ -958,12 +1233,18 @@ impl AbstractSyntaxTree for SingleExpression {
                 scope
                     .insert_witness(name.clone(), ty.clone())
                     .with_span(from)?;
+                if let Some(declared) = declared {
+                    scope.insert_declared_witness(name.clone(), declared);
+                }
                 SingleExpressionInner::Witness(name.clone())
             }
             parse::SingleExpressionInner::Parameter(name) => {
                 scope
                     .insert_parameter(name.shallow_clone(), ty.clone())
                     .with_span(from)?;
+                if let Some(declared) = declared {
+                    scope.insert_declared_parameter(name.shallow_clone(), declared);
+                }
                 SingleExpressionInner::Parameter(name.shallow_clone())
             }
             parse::SingleExpressionInner::Variable(identifier) => {
@@ -979,6 +1260,9 @@ impl AbstractSyntaxTree for SingleExpression {
                 SingleExpressionInner::Variable(identifier.clone())
             }
             parse::SingleExpressionInner::Expression(parse) => {
+                if let Some(declared) = declared {
+                    scope.declare(declared);
+                }
                 Expression::analyze(parse, ty, scope)
                     .map(Arc::new)
                     .map(SingleExpressionInner::Expression)?
@@ -991,10 +1275,20 @@ impl AbstractSyntaxTree for SingleExpression {
                 if tuple.len() != types.len() {
                     return Err(Error::ExpressionUnexpectedType(ty.clone())).with_span(from);
                 }
+                let declared_types = declared
+                    .as_ref()
+                    .and_then(AliasedType::as_tuple)
+                    .filter(|declared_types| declared_types.len() == tuple.len());
                 tuple
                     .iter()
                     .zip(types.iter())
-                    .map(|(el_parse, el_ty)| Expression::analyze(el_parse, el_ty, scope))
+                    .enumerate()
+                    .map(|(index, (el_parse, el_ty))| {
+                        if let Some(declared_types) = declared_types {
+                            scope.declare(declared_types[index].as_ref().clone());
+                        }
+                        Expression::analyze(el_parse, el_ty, scope)
+                    })
                     .collect::<Result<Arc<[Expression]>, RichError>>()
                     .map(SingleExpressionInner::Tuple)?
             }
@@ -1114,10 +1408,28 @@ impl AbstractSyntaxTree for Call {
             Ok(args)
         }
 
+        fn analyze_declared_arguments(
+            parse_args: &[parse::Expression],
+            args_tys: &[ResolvedType],
+            declared_tys: &[AliasedType],
+            scope: &mut Scope,
+        ) -> Result<Arc<[Expression]>, RichError> {
+            let args = parse_args
+                .iter()
+                .zip(args_tys.iter().zip(declared_tys.iter()))
+                .map(|(arg_parse, (arg_ty, declared_ty))| {
+                    scope.declare(declared_ty.clone());
+                    Expression::analyze(arg_parse, arg_ty, scope)
+                })
+                .collect::<Result<Arc<[Expression]>, RichError>>()?;
+            Ok(args)
+        }
+
         let name = CallName::analyze(from, ty, scope)?;
         let args = match name.clone() {
             CallName::Jet(jet) => {
-                let args_tys = crate::jet::source_type(jet)
+                let declared_tys = crate::jet::source_type(jet);
+                let args_tys = declared_tys
                     .iter()
                     .map(AliasedType::resolve_builtin)
                     .collect::<Result<Vec<ResolvedType>, AliasName>>()
@@ -1130,7 +1442,7 @@ impl AbstractSyntaxTree for Call {
                     .with_span(from)?;
                 check_output_type(&out_ty, ty).with_span(from)?;
                 scope.track_call(from, TrackedCallName::Jet);
-                analyze_arguments(from.args(), &args_tys, scope)?
+                analyze_declared_arguments(from.args(), &args_tys, &declared_tys, scope)?
             }
             CallName::UnwrapLeft(right_ty) => {
                 let args_tys = [ResolvedType::either(ty.clone(), right_ty)];
@@ -1200,10 +1512,16 @@ impl AbstractSyntaxTree for Call {
                     .map(FunctionParam::ty)
                     .cloned()
                     .collect::<Vec<ResolvedType>>();
+                let declared_tys = function
+                    .params()
+                    .iter()
+                    .map(FunctionParam::declared)
+                    .cloned()
+                    .collect::<Vec<AliasedType>>();
                 check_argument_types(from.args(), &args_ty).with_span(from)?;
                 let out_ty = function.body().ty();
                 check_output_type(out_ty, ty).with_span(from)?;
-                analyze_arguments(from.args(), &args_ty, scope)?
+                analyze_declared_arguments(from.args(), &args_ty, &declared_tys, scope)?
             }
             CallName::Fold(function, bound) => {
                 // A list fold has the signature:
@@ -1391,8 +1709,9 @@ impl AbstractSyntaxTree for Match {
     type From = parse::Match;
 
     fn analyze(from: &Self::From, ty: &ResolvedType, scope: &mut Scope) -> Result<Self, RichError> {
-        let scrutinee_ty = from.scrutinee_type();
-        let scrutinee_ty = scope.resolve(&scrutinee_ty).with_span(from)?;
+        let declared_ty = from.scrutinee_type();
+        let scrutinee_ty = scope.resolve(&declared_ty).with_span(from)?;
+        scope.declare(declared_ty);
         let scrutinee =
             Expression::analyze(from.scrutinee(), &scrutinee_ty, scope).map(Arc::new)?;
 
@@ -1402,6 +1721,7 @@ impl AbstractSyntaxTree for Match {
             scope.insert_variable(id_l.clone(), ty_l);
         }
         let ast_l = Expression::analyze(from.left().expression(), ty, scope).map(Arc::new)?;
+        scope.track_call(from.left(), TrackedCallName::MatchArm);
         scope.pop_scope();
         scope.push_scope();
         if let Some((id_r, ty_r)) = from.right().pattern().as_typed_variable() {
@@ -1409,6 +1729,7 @@ impl AbstractSyntaxTree for Match {
             scope.insert_variable(id_r.clone(), ty_r);
         }
         let ast_r = Expression::analyze(from.right().expression(), ty, scope).map(Arc::new)?;
+        scope.track_call(from.right(), TrackedCallName::MatchArm);
         scope.pop_scope();
 
         Ok(Self {
@@ -1416,10 +1737,12 @@ impl AbstractSyntaxTree for Match {
             left: MatchArm {
                 pattern: from.left().pattern().clone(),
                 expression: ast_l,
+                span: *from.left().as_ref(),
             },
             right: MatchArm {
                 pattern: from.right().pattern().clone(),
                 expression: ast_r,
+                span: *from.right().as_ref(),
             },
             span: *from.as_ref(),
         })
@@ -1559,6 +1882,12 @@ impl AsRef<Span> for Match {
     }
 }
 
+impl AsRef<Span> for MatchArm {
+    fn as_ref(&self) -> &Span {
+        &self.span
+    }
+}
+
 impl AsRef<Span> for Module {
     fn as_ref(&self) -> &Span {
         &self.span
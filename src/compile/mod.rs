@@ -1,7 +1,11 @@
 //! Compile the parsed ast into a simplicity program
 
 mod builtins;
+mod peephole;
 
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::sync::Arc;
 
 use either::Either;
@@ -28,6 +32,9 @@ use crate::Value;
 
 type ProgNode<'brand> = Arc<named::ConstructNode<'brand, Elements>>;
 
+/// Layout of the input value at each call expression that carries a debug symbol.
+pub(crate) type Layouts = HashMap<Span, BasePattern>;
+
 /// Each SimplicityHL expression expects an _input value_.
 /// A SimplicityHL expression is translated into a Simplicity expression
 /// that similarly expects an _input value_.
@@ -72,6 +79,8 @@ struct Scope<'brand> {
     /// Values for parameters inside the SimplicityHL program.
     arguments: Arguments,
     include_debug_symbols: bool,
+    /// Layouts that are shared between the main scope and all child scopes.
+    layouts: Rc<RefCell<Layouts>>,
 }
 
 impl<'brand> Scope<'brand> {
@@ -95,6 +104,7 @@ impl<'brand> Scope<'brand> {
             call_tracker,
             arguments,
             include_debug_symbols,
+            layouts: Rc::default(),
         }
     }
 
@@ -106,6 +116,7 @@ impl<'brand> Scope<'brand> {
             call_tracker: Arc::clone(&self.call_tracker),
             arguments: self.arguments.clone(),
             include_debug_symbols: self.include_debug_symbols,
+            layouts: Rc::clone(&self.layouts),
         }
     }
 
@@ -207,6 +218,9 @@ impl<'brand> Scope<'brand> {
     ) -> Result<PairBuilder<ProgNode<'brand>>, RichError> {
         match self.call_tracker.get_cmr(span.as_ref()) {
             Some(cmr) if self.include_debug_symbols => {
+                self.layouts
+                    .borrow_mut()
+                    .insert(*span.as_ref(), BasePattern::from(&self.get_input_pattern()));
                 let false_and_args = ProgNode::bit(self.ctx(), false).pair(args);
                 let nop_assert = ProgNode::assertl_drop(body, cmr);
                 false_and_args.comp(&nop_assert).with_span(span)
@@ -215,6 +229,22 @@ impl<'brand> Scope<'brand> {
         }
     }
 
+    /// Attach a debug symbol to the match arm.
+    ///
+    /// With debug symbols, the arm is wrapped in an assertion, so executions and pruning
+    /// can be traced back to the arm. This changes the CMR of debug builds.
+    /// Without debug symbols, the arm is returned unchanged.
+    pub fn with_arm_debug_symbol<S: AsRef<Span>>(
+        &mut self,
+        arm: PairBuilder<ProgNode<'brand>>,
+        span: &S,
+    ) -> Result<PairBuilder<ProgNode<'brand>>, RichError> {
+        if !self.include_debug_symbols {
+            return Ok(arm);
+        }
+        self.with_debug_symbol(PairBuilder::iden(self.ctx()), arm.as_ref(), span)
+    }
+
     pub fn get_argument(&self, name: &WitnessName) -> &Value {
         self.arguments
             .get(name)
@@ -264,6 +294,28 @@ impl Program {
         arguments: Arguments,
         include_debug_symbols: bool,
     ) -> Result<Arc<named::CommitNode<Elements>>, RichError> {
+        self.compile_with_layouts(arguments, include_debug_symbols, false)
+            .map(|(commit, _)| commit)
+    }
+
+    /// Compile the SimplicityHL source code to Simplicity target code.
+    ///
+    /// Also return the layout of the variable environment at each call expression
+    /// that carries a debug symbol. The map is empty without debug symbols.
+    ///
+    /// If `optimize` is set, then the target code is simplified by the peephole optimizer.
+    /// The optimizer changes the CMR of the program.
+    ///
+    /// ## Precondition
+    ///
+    /// The supplied `arguments` are consistent with the program's parameters.
+    /// Call [`Arguments::is_consistent`] before calling this method!
+    pub(crate) fn compile_with_layouts(
+        &self,
+        arguments: Arguments,
+        include_debug_symbols: bool,
+        optimize: bool,
+    ) -> Result<(Arc<named::CommitNode<Elements>>, Layouts), RichError> {
         types::Context::with_context(|ctx| {
             let mut scope = Scope::new(
                 ctx,
@@ -273,15 +325,47 @@ impl Program {
             );
 
             let main = self.main();
-            let construct = main.compile(&mut scope).map(PairBuilder::build)?;
+            let mut construct = main.compile(&mut scope).map(PairBuilder::build)?;
+            if optimize {
+                construct = peephole::optimize(&construct).with_span(main)?;
+            }
             // SimplicityHL types should be correct by construction. If not, assign the
             // whole main function as the span for them, which is as sensible as anything.
-            named::finalize_types(&construct).with_span(main)
+            let commit = named::finalize_types(&construct).with_span(main)?;
+            Ok((commit, scope.layouts.take()))
         })
     }
 }
 
 impl Expression {
+    /// Compile a standalone expression to Simplicity target code
+    /// that takes no input (unit) and that outputs the value of the expression.
+    ///
+    /// If `optimize` is set, then the target code is simplified by the peephole optimizer.
+    ///
+    /// ## Precondition
+    ///
+    /// The expression uses no parameters.
+    /// Call [`Expression::analyze_standalone`] to obtain such an expression!
+    pub fn compile_standalone(
+        &self,
+        optimize: bool,
+    ) -> Result<Arc<named::CommitNode<Elements>>, RichError> {
+        types::Context::with_context(|ctx| {
+            let mut scope = Scope::new(
+                ctx,
+                Arc::new(CallTracker::default()),
+                Arguments::default(),
+                false,
+            );
+            let mut construct = self.compile(&mut scope).map(PairBuilder::build)?;
+            if optimize {
+                construct = peephole::optimize(&construct).with_span(self)?;
+            }
+            named::finalize_types(&construct).with_span(self)
+        })
+    }
+
     fn compile<'brand>(
         &self,
         scope: &mut Scope<'brand>,
@@ -661,6 +745,7 @@ impl Match {
                 .map_or(Pattern::Ignore, Pattern::Identifier),
         );
         let left = self.left().expression().compile(scope)?;
+        let left = scope.with_arm_debug_symbol(left, self.left())?;
         scope.pop_scope();
 
         scope.push_scope();
@@ -672,6 +757,7 @@ impl Match {
                 .map_or(Pattern::Ignore, Pattern::Identifier),
         );
         let right = self.right().expression().compile(scope)?;
+        let right = scope.with_arm_debug_symbol(right, self.right())?;
         scope.pop_scope();
 
         let scrutinee = self.scrutinee().compile(scope)?;
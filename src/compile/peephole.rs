@@ -0,0 +1,490 @@
+//! Peephole optimization of the generated Simplicity target code
+//!
+//! The compiler translates SimplicityHL into Simplicity in a syntax-directed way.
+//! This leaves behind local patterns that can be simplified without changing the
+//! result of the program, such as compositions with `iden` or projections out of
+//! freshly constructed pairs.
+//!
+//! The optimizer rebuilds the DAG bottom-up inside the original type inference context
+//! and applies the following rewrites:
+//!
+//! ```text
+//! comp iden t                 ⇝ t
+//! comp s iden                 ⇝ s
+//! comp s c                    ⇝ c             if s cannot fail and c ignores its input
+//! comp (pair s t) (take u)    ⇝ comp s u      if t cannot fail
+//! comp (pair s t) (drop u)    ⇝ comp t u      if s cannot fail
+//! take c                      ⇝ c             if c ignores its input
+//! drop c                      ⇝ c             if c ignores its input
+//! pair (take iden) (drop iden) ⇝ iden
+//! ```
+//!
+//! Expressions that may fail (jets, assertions and `fail`) are never removed,
+//! so a rewritten program fails on exactly the same inputs as the original.
+//! Assertions are left untouched, which preserves debug symbols.
+//!
+//! Rewriting the target code changes the CMR of the program, and therefore its address.
+//! This is why the optimizer only runs if it is turned on
+//! with [`TemplateProgram::with_optimization`].
+//!
+//! [`TemplateProgram::with_optimization`]: crate::TemplateProgram::with_optimization
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use simplicity::dag::{DagLike, InternalSharing};
+use simplicity::node::{CoreConstructible as _, Inner};
+use simplicity::types;
+
+use super::ProgNode;
+
+/// Properties of an expression that guard the rewrite rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Flags {
+    /// The expression never fails.
+    total: bool,
+    /// The expression never fails, ignores its input and accepts inputs of any type.
+    constant: bool,
+}
+
+impl Flags {
+    const NONE: Self = Self {
+        total: false,
+        constant: false,
+    };
+
+    const TOTAL: Self = Self {
+        total: true,
+        constant: false,
+    };
+
+    const CONSTANT: Self = Self {
+        total: true,
+        constant: true,
+    };
+}
+
+/// State of the optimizer.
+struct Peephole<'brand> {
+    ctx: types::Context<'brand>,
+    /// Cached flags of each visited node, indexed by pointer address.
+    ///
+    /// The visited nodes are kept alive in `nodes`, so addresses are never reused.
+    flags: HashMap<usize, Flags>,
+    nodes: Vec<ProgNode<'brand>>,
+}
+
+/// Optimize the given Simplicity expression using local rewrites.
+///
+/// The returned expression produces the same output as the given expression for each input,
+/// and it fails on exactly the same inputs.
+pub fn optimize<'brand>(root: &ProgNode<'brand>) -> Result<ProgNode<'brand>, types::Error> {
+    let mut peephole = Peephole {
+        ctx: root.inference_context().shallow_clone(),
+        flags: HashMap::new(),
+        nodes: Vec::new(),
+    };
+    let mut converted: Vec<ProgNode<'brand>> = Vec::new();
+
+    for data in Arc::clone(root).post_order_iter::<InternalSharing>() {
+        let left = data.left_index.map(|index| Arc::clone(&converted[index]));
+        let right = data.right_index.map(|index| Arc::clone(&converted[index]));
+        let node = match data.node.inner() {
+            Inner::Iden
+            | Inner::Unit
+            | Inner::Witness(_)
+            | Inner::Fail(_)
+            | Inner::Jet(_)
+            | Inner::Word(_) => Arc::clone(&data.node),
+            Inner::InjL(_) => ProgNode::injl(&left.unwrap()),
+            Inner::InjR(_) => ProgNode::injr(&left.unwrap()),
+            Inner::Take(_) => peephole.take(&left.unwrap())?,
+            Inner::Drop(_) => peephole.drop_(&left.unwrap())?,
+            Inner::Comp(..) => peephole.comp(&left.unwrap(), &right.unwrap())?,
+            Inner::Case(..) => ProgNode::case(&left.unwrap(), &right.unwrap())?,
+            Inner::AssertL(_, cmr) => ProgNode::assertl(&left.unwrap(), *cmr)?,
+            Inner::AssertR(cmr, _) => ProgNode::assertr(*cmr, &left.unwrap())?,
+            Inner::Pair(..) => peephole.pair(&left.unwrap(), &right.unwrap())?,
+            Inner::Disconnect(..) => unreachable!("SimplicityHL does not use disconnect right now"),
+        };
+        peephole.flags(&node);
+        converted.push(node);
+    }
+
+    // Rewritten expressions may have more general types than the original expressions.
+    // Keep the source and target types of the root.
+    let optimized = converted
+        .pop()
+        .expect("post-order iterator yields the root last");
+    let (old_arrow, new_arrow) = (root.cached_data().arrow(), optimized.cached_data().arrow());
+    peephole.ctx.unify(
+        &new_arrow.source,
+        &old_arrow.source,
+        "peephole: root source",
+    )?;
+    peephole.ctx.unify(
+        &new_arrow.target,
+        &old_arrow.target,
+        "peephole: root target",
+    )?;
+    Ok(optimized)
+}
+
+impl<'brand> Peephole<'brand> {
+    /// Compute the flags of the given expression.
+    fn flags(&mut self, node: &ProgNode<'brand>) -> Flags {
+        let key = Arc::as_ptr(node) as usize;
+        if let Some(flags) = self.flags.get(&key) {
+            return *flags;
+        }
+
+        let flags = match node.inner() {
+            Inner::Unit => Flags::CONSTANT,
+            Inner::Iden | Inner::Witness(_) | Inner::Word(_) => Flags::TOTAL,
+            Inner::Fail(_)
+            | Inner::Jet(_)
+            | Inner::AssertL(..)
+            | Inner::AssertR(..)
+            | Inner::Disconnect(..) => Flags::NONE,
+            Inner::InjL(child) | Inner::InjR(child) => self.flags(child),
+            Inner::Take(child) | Inner::Drop(child) => Flags {
+                total: self.flags(child).total,
+                constant: false,
+            },
+            Inner::Comp(s, t) => {
+                let (s, t) = (self.flags(s), self.flags(t));
+                Flags {
+                    total: s.total && t.total,
+                    constant: s.constant && t.total,
+                }
+            }
+            Inner::Pair(s, t) => {
+                let (s, t) = (self.flags(s), self.flags(t));
+                Flags {
+                    total: s.total && t.total,
+                    constant: s.constant && t.constant,
+                }
+            }
+            Inner::Case(s, t) => Flags {
+                total: self.flags(s).total && self.flags(t).total,
+                constant: false,
+            },
+        };
+
+        self.flags.insert(key, flags);
+        self.nodes.push(Arc::clone(node));
+        flags
+    }
+
+    /// Copy the given constant expression with fresh type variables.
+    ///
+    /// The source type of the original expression has already been unified
+    /// with its surrounding, so the original cannot be moved to a different place.
+    ///
+    /// ## Panics
+    ///
+    /// The expression is not constant.
+    fn fresh(&mut self, node: &ProgNode<'brand>) -> Result<ProgNode<'brand>, types::Error> {
+        let fresh = match node.inner() {
+            Inner::Unit => ProgNode::unit(&self.ctx),
+            Inner::InjL(child) => ProgNode::injl(&self.fresh(child)?),
+            Inner::InjR(child) => ProgNode::injr(&self.fresh(child)?),
+            Inner::Pair(s, t) => {
+                let (s, t) = (self.fresh(s)?, self.fresh(t)?);
+                ProgNode::pair(&s, &t)?
+            }
+            Inner::Comp(s, t) => ProgNode::comp(&self.fresh(s)?, t)?,
+            _ => unreachable!("Only constant expressions are copied"),
+        };
+        debug_assert!(self.flags(&fresh).constant);
+        Ok(fresh)
+    }
+
+    fn comp(
+        &mut self,
+        s: &ProgNode<'brand>,
+        t: &ProgNode<'brand>,
+    ) -> Result<ProgNode<'brand>, types::Error> {
+        if matches!(s.inner(), Inner::Iden) {
+            return Ok(Arc::clone(t));
+        }
+        if matches!(t.inner(), Inner::Iden) {
+            return Ok(Arc::clone(s));
+        }
+        if self.flags(t).constant && self.flags(s).total {
+            return self.fresh(t);
+        }
+        if let Inner::Pair(s0, s1) = s.inner() {
+            match t.inner() {
+                Inner::Take(u) if self.flags(s1).total => return self.comp(s0, u),
+                Inner::Drop(u) if self.flags(s0).total => return self.comp(s1, u),
+                _ => {}
+            }
+        }
+        ProgNode::comp(s, t)
+    }
+
+    fn take(&mut self, t: &ProgNode<'brand>) -> Result<ProgNode<'brand>, types::Error> {
+        if self.flags(t).constant {
+            return self.fresh(t);
+        }
+        Ok(ProgNode::take(t))
+    }
+
+    fn drop_(&mut self, t: &ProgNode<'brand>) -> Result<ProgNode<'brand>, types::Error> {
+        if self.flags(t).constant {
+            return self.fresh(t);
+        }
+        Ok(ProgNode::drop_(t))
+    }
+
+    fn pair(
+        &mut self,
+        s: &ProgNode<'brand>,
+        t: &ProgNode<'brand>,
+    ) -> Result<ProgNode<'brand>, types::Error> {
+        match (s.inner(), t.inner()) {
+            (Inner::Take(s), Inner::Drop(t))
+                if matches!(s.inner(), Inner::Iden) && matches!(t.inner(), Inner::Iden) =>
+            {
+                Ok(ProgNode::iden(&self.ctx))
+            }
+            _ => ProgNode::pair(s, t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use simplicity::jet::Elements;
+    use simplicity::node::JetConstructible as _;
+    use simplicity::{BitMachine, Value as SimValue};
+
+    use super::*;
+    use crate::named::{self, CoreExt as _};
+    use crate::WitnessValues;
+
+    /// Deterministic pseudo-random number generator (xorshift64).
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+    }
+
+    /// Shape of the types that the generated expressions operate on.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum Shape {
+        Unit,
+        Bit,
+        U8,
+        Product(Box<Shape>, Box<Shape>),
+    }
+
+    impl Shape {
+        fn arbitrary(rng: &mut Rng, budget: usize) -> Self {
+            match rng.below(if budget == 0 { 2 } else { 4 }) {
+                0 => Self::U8,
+                1 => Self::Bit,
+                _ => Self::Product(
+                    Box::new(Self::arbitrary(rng, budget - 1)),
+                    Box::new(Self::arbitrary(rng, budget - 1)),
+                ),
+            }
+        }
+
+        fn arbitrary_value(&self, rng: &mut Rng) -> SimValue {
+            match self {
+                Self::Unit => SimValue::unit(),
+                Self::Bit => SimValue::u1(rng.below(2) as u8),
+                Self::U8 => SimValue::u8(rng.below(256) as u8),
+                Self::Product(l, r) => {
+                    SimValue::product(l.arbitrary_value(rng), r.arbitrary_value(rng))
+                }
+            }
+        }
+    }
+
+    /// Generate an expression that takes inputs of the given `source` shape.
+    /// Return the expression and the shape of its outputs.
+    ///
+    /// The expressions contain the patterns that the optimizer rewrites,
+    /// along with jets that may fail.
+    fn arbitrary_expr<'brand>(
+        ctx: &types::Context<'brand>,
+        rng: &mut Rng,
+        source: &Shape,
+        budget: usize,
+    ) -> (ProgNode<'brand>, Shape) {
+        let choice = if budget == 0 { 0 } else { rng.below(10) };
+        match (choice, source) {
+            (1, Shape::Product(l, _)) => {
+                let (t, target) = arbitrary_expr(ctx, rng, l, budget - 1);
+                (ProgNode::take(&t), target)
+            }
+            (2, Shape::Product(_, r)) => {
+                let (t, target) = arbitrary_expr(ctx, rng, r, budget - 1);
+                (ProgNode::drop_(&t), target)
+            }
+            (3, _) => {
+                let (s, s_target) = arbitrary_expr(ctx, rng, source, budget - 1);
+                let (t, t_target) = arbitrary_expr(ctx, rng, source, budget - 1);
+                let target = Shape::Product(Box::new(s_target), Box::new(t_target));
+                (ProgNode::pair(&s, &t).unwrap(), target)
+            }
+            (4, _) => {
+                let (s, middle) = arbitrary_expr(ctx, rng, source, budget - 1);
+                let (t, target) = arbitrary_expr(ctx, rng, &middle, budget - 1);
+                (ProgNode::comp(&s, &t).unwrap(), target)
+            }
+            (5, _) => {
+                let (t, target) = arbitrary_expr(ctx, rng, source, budget - 1);
+                let iden = ProgNode::iden(ctx);
+                match rng.below(2) {
+                    0 => (ProgNode::comp(&iden, &t).unwrap(), target),
+                    _ => (ProgNode::comp(&t, &iden).unwrap(), target),
+                }
+            }
+            (6, _) => {
+                let (s, _) = arbitrary_expr(ctx, rng, source, budget - 1);
+                let bit = ProgNode::bit(ctx, rng.below(2) == 1).build();
+                (ProgNode::comp(&s, &bit).unwrap(), Shape::Bit)
+            }
+            (7, Shape::Product(l, r)) if **l == Shape::U8 && **r == Shape::U8 => {
+                let jet = ProgNode::jet(ctx, Elements::Eq8);
+                (jet, Shape::Bit)
+            }
+            (8, Shape::Bit) => {
+                let jet = ProgNode::jet(ctx, Elements::Verify);
+                (jet, Shape::Unit)
+            }
+            (9, Shape::Product(..)) => {
+                let take_iden = ProgNode::take(&ProgNode::iden(ctx));
+                let drop_iden = ProgNode::drop_(&ProgNode::iden(ctx));
+                let pair = ProgNode::pair(&take_iden, &drop_iden).unwrap();
+                (pair, source.clone())
+            }
+            _ => (ProgNode::iden(ctx), source.clone()),
+        }
+    }
+
+    fn run(node: &ProgNode, input: &SimValue) -> Result<SimValue, String> {
+        let commit = named::finalize_types(node).expect("finalize types");
+        let redeem = named::populate_witnesses(&commit, WitnessValues::default())
+            .expect("populate witnesses");
+        let mut mac = BitMachine::for_program(&redeem).expect("reasonable bounds");
+        mac.input(input).map_err(|e| e.to_string())?;
+        mac.exec(&redeem, &crate::dummy_env::dummy())
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn rewrites() {
+        types::Context::with_context(|ctx| {
+            let comp_iden_jet = ProgNode::comp(
+                &ProgNode::iden(&ctx),
+                &ProgNode::jet(&ctx, Elements::Verify),
+            )
+            .unwrap();
+            let expected = ProgNode::jet(&ctx, Elements::Verify);
+            assert_eq!(optimize(&comp_iden_jet).unwrap().cmr(), expected.cmr());
+        });
+
+        types::Context::with_context(|ctx| {
+            let pair = ProgNode::pair(
+                &ProgNode::take(&ProgNode::iden(&ctx)),
+                &ProgNode::drop_(&ProgNode::iden(&ctx)),
+            )
+            .unwrap();
+            let expected = ProgNode::iden(&ctx);
+            assert_eq!(optimize(&pair).unwrap().cmr(), expected.cmr());
+        });
+
+        types::Context::with_context(|ctx| {
+            let projection = ProgNode::comp(
+                &ProgNode::pair(
+                    &ProgNode::jet(&ctx, Elements::Verify),
+                    &ProgNode::unit(&ctx),
+                )
+                .unwrap(),
+                &ProgNode::take(&ProgNode::iden(&ctx)),
+            )
+            .unwrap();
+            let expected = ProgNode::jet(&ctx, Elements::Verify);
+            assert_eq!(optimize(&projection).unwrap().cmr(), expected.cmr());
+        });
+
+        types::Context::with_context(|ctx| {
+            // The failing left component must not be projected away.
+            let projection = ProgNode::comp(
+                &ProgNode::pair(
+                    &ProgNode::jet(&ctx, Elements::Verify),
+                    &ProgNode::unit(&ctx),
+                )
+                .unwrap(),
+                &ProgNode::drop_(&ProgNode::iden(&ctx)),
+            )
+            .unwrap();
+            assert_eq!(optimize(&projection).unwrap().cmr(), projection.cmr());
+        });
+    }
+
+    #[test]
+    fn rewrites_preserve_semantics() {
+        let mut rng = Rng(0x5eed_cafe_f00d_beef);
+        for _ in 0..200 {
+            let source = Shape::arbitrary(&mut rng, 3);
+            let inputs: Vec<SimValue> = (0..8).map(|_| source.arbitrary_value(&mut rng)).collect();
+
+            types::Context::with_context(|ctx| {
+                let (expr, _) = arbitrary_expr(&ctx, &mut rng, &source, 6);
+                let source_ty = types::Type::complete(&ctx, Arc::new(inputs[0].ty().clone()));
+                ctx.unify(&expr.cached_data().arrow().source, &source_ty, "")
+                    .unwrap();
+                let optimized = optimize(&expr).unwrap();
+
+                for input in &inputs {
+                    assert_eq!(
+                        run(&expr, input),
+                        run(&optimized, input),
+                        "Rewrite changed the semantics on input {input}"
+                    );
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn optimization_is_opt_in() {
+        let text = std::fs::read_to_string("./examples/p2pkh.simf").unwrap();
+        let compile = |optimize: bool| {
+            crate::TemplateProgram::new(text.as_str())
+                .unwrap()
+                .with_optimization(optimize)
+                .instantiate(crate::Arguments::default(), false)
+                .unwrap()
+                .commit()
+        };
+        let default = crate::TemplateProgram::new(text.as_str())
+            .unwrap()
+            .instantiate(crate::Arguments::default(), false)
+            .unwrap()
+            .commit();
+        let unoptimized = compile(false);
+        let optimized = compile(true);
+        assert_eq!(default.cmr(), unoptimized.cmr());
+        assert_ne!(unoptimized.cmr(), optimized.cmr());
+        assert!(
+            optimized.pre_order_iter::<InternalSharing>().count()
+                < unoptimized.pre_order_iter::<InternalSharing>().count()
+        );
+    }
+}
@@ -0,0 +1,794 @@
+//! Static analysis of the execution cost of Simplicity target code.
+//!
+//! The analysis runs on a compiled program, before any witness data is known.
+//!
+//! Every `case` combinator is a branching point of the program.
+//! A _spend path_ picks one branch of each `case` combinator that it reaches.
+//! This corresponds to the program that remains after pruning,
+//! if a transaction takes this spend path.
+//!
+//! The bounds of each spend path are computed on the pruned program,
+//! populated with placeholder witness data, so the numbers match what consensus computes
+//! for the program that ends up on the blockchain.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+use simplicity::dag::{DagLike, InternalSharing, PostOrderIterItem};
+use simplicity::jet::Elements;
+use simplicity::node::{self, Constructible, Converter, Hide, Inner, NoDisconnect, NoWitness};
+use simplicity::types;
+use simplicity::{BitIter, Cost, NodeBounds};
+
+use crate::debug::{DebugSymbols, TrackedCall, TrackedCallName};
+use crate::named::{CommitNode, WithNames};
+use crate::str::WitnessName;
+
+/// Maximum number of spend paths that are analyzed.
+///
+/// The number of spend paths grows exponentially in the number of `case` combinators.
+/// If there are too many, then only the most expensive spend paths are kept.
+const MAX_SPEND_PATHS: usize = 64;
+
+/// Branch of a `case` combinator.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum Branch {
+    /// The left branch is taken.
+    Left,
+    /// The right branch is taken.
+    Right,
+}
+
+/// Report about the static cost of a compiled program.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CostReport {
+    bounds: NodeBounds,
+    spend_paths: Vec<SpendPath>,
+    truncated: bool,
+    calls: Vec<CallCost>,
+}
+
+/// Cost of a single spend path through a compiled program.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SpendPath {
+    branches: Vec<Branch>,
+    witnesses: Vec<WitnessName>,
+    bounds: NodeBounds,
+    program_size: usize,
+    max_witness_size: usize,
+}
+
+/// Report about the program that remains after pruning a satisfied program.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PruneReport {
+    pruned_arms: Vec<TrackedCall>,
+    pruned_branches: usize,
+    bounds: NodeBounds,
+    program_size: usize,
+    witness_size: usize,
+}
+
+/// Cost of a single evaluation of a tracked call expression.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CallCost {
+    call: TrackedCall,
+    cost: Cost,
+}
+
+impl CostReport {
+    /// Analyze the given Simplicity target code.
+    ///
+    /// Use the `debug_symbols` to attribute cost to SimplicityHL call expressions.
+    pub(crate) fn new(program: &CommitNode<Elements>, debug_symbols: &DebugSymbols) -> Self {
+        let mut summaries: Vec<Summary> = vec![];
+        let mut truncated = false;
+        let mut calls = HashMap::new();
+
+        for data in program.post_order_iter::<InternalSharing>() {
+            let left = data.left_index.map(|i| &summaries[i]);
+            let right = data.right_index.map(|i| &summaries[i]);
+            let summary = Summary::new(&data, left, right, &mut truncated);
+
+            if let Inner::AssertL(_, cmr) = data.node.inner() {
+                if let Some(call) = debug_symbols.get(cmr) {
+                    let cost = left.expect("assertl has a child").bounds.cost;
+                    calls.insert(*cmr, CallCost::new(call.clone(), cost));
+                }
+            }
+            summaries.push(summary);
+        }
+
+        let root = summaries.pop().expect("program has at least one node");
+        let mut spend_paths: Vec<_> = root
+            .paths
+            .iter()
+            .map(|path| SpendPath::new(program, path))
+            .collect();
+        spend_paths.sort_by_key(|path| Reverse(path.bounds.cost));
+        let mut calls: Vec<_> = calls.into_values().collect();
+        calls.sort_by(|a, b| {
+            b.cost
+                .cmp(&a.cost)
+                .then_with(|| a.call.text().cmp(b.call.text()))
+        });
+
+        Self {
+            bounds: root.bounds,
+            spend_paths,
+            truncated,
+            calls,
+        }
+    }
+
+    /// Access the bounds of the program, maximized over all spend paths.
+    pub fn bounds(&self) -> NodeBounds {
+        self.bounds
+    }
+
+    /// Access the analyzed spend paths, from most to least expensive.
+    pub fn spend_paths(&self) -> &[SpendPath] {
+        &self.spend_paths
+    }
+
+    /// Check if the program has more spend paths than were analyzed.
+    ///
+    /// In this case, [`CostReport::spend_paths`] contains the most expensive spend paths.
+    /// The bounds of [`CostReport::bounds`] are computed over all spend paths nevertheless.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Access the cost of the tracked call expressions, from most to least expensive.
+    ///
+    /// The list is empty if the program was compiled without debug symbols.
+    pub fn calls(&self) -> &[CallCost] {
+        &self.calls
+    }
+}
+
+impl SpendPath {
+    fn new(program: &CommitNode<Elements>, path: &PartialPath) -> Self {
+        let redeem = types::Context::with_context(|inference_context| {
+            let mut pruner = PathPruner {
+                inference_context: &inference_context,
+                branches: &path.branches,
+            };
+            let pruned = match program.convert::<InternalSharing, _, _>(&mut pruner) {
+                Ok(pruned) => pruned,
+                Err(inf) => match inf {},
+            };
+            let commit = pruned
+                .finalize_types()
+                .expect("pruned types check out if unpruned types check out");
+            match commit.convert::<InternalSharing, _, _>(&mut Placeholders { next_id: 0 }) {
+                Ok(redeem) => redeem,
+                Err(inf) => match inf {},
+            }
+        });
+        let witness_bits = path.witnesses.values().sum::<usize>();
+
+        Self {
+            branches: path.branches.values().copied().collect(),
+            witnesses: path.witnesses.keys().cloned().collect(),
+            bounds: redeem.bounds(),
+            program_size: redeem.to_vec_without_witness().len(),
+            max_witness_size: witness_bits.div_ceil(8),
+        }
+    }
+
+    /// Access the branches that the spend path takes,
+    /// in post order of the `case` combinators of the program.
+    pub fn branches(&self) -> &[Branch] {
+        &self.branches
+    }
+
+    /// Access the names of the witness values that the spend path uses.
+    pub fn witnesses(&self) -> &[WitnessName] {
+        &self.witnesses
+    }
+
+    /// Access the bounds of the spend path.
+    ///
+    /// The cost is measured in milliweight units.
+    /// The memory of the Bit Machine is measured in cells and frames.
+    pub fn bounds(&self) -> NodeBounds {
+        self.bounds
+    }
+
+    /// Access the size of the pruned program in bytes, without witness data.
+    pub fn program_size(&self) -> usize {
+        self.program_size
+    }
+
+    /// Access the maximum size of the witness data of the spend path in bytes.
+    pub fn max_witness_size(&self) -> usize {
+        self.max_witness_size
+    }
+}
+
+impl PruneReport {
+    /// Compare the `pruned` program with the `unpruned` program from which it was derived.
+    ///
+    /// Use the `debug_symbols` to identify the match arms that were pruned.
+    pub(crate) fn new(
+        unpruned: &node::RedeemNode<Elements>,
+        pruned: &node::RedeemNode<Elements>,
+        debug_symbols: &DebugSymbols,
+    ) -> Self {
+        let remaining_arms: HashSet<_> = pruned
+            .post_order_iter::<InternalSharing>()
+            .filter_map(|data| match data.node.inner() {
+                Inner::AssertL(_, cmr) => Some(*cmr),
+                _ => None,
+            })
+            .collect();
+        let mut visited = HashSet::new();
+        let pruned_arms = unpruned
+            .post_order_iter::<InternalSharing>()
+            .filter_map(|data| match data.node.inner() {
+                Inner::AssertL(_, cmr) => Some(*cmr),
+                _ => None,
+            })
+            .filter(|cmr| !remaining_arms.contains(cmr) && visited.insert(*cmr))
+            .filter_map(|cmr| debug_symbols.get(&cmr))
+            .filter(|call| matches!(call.name(), TrackedCallName::MatchArm))
+            .cloned()
+            .collect();
+
+        // Pruning turns a `case` combinator into an assertion with the same CMR.
+        let cases: HashSet<_> = unpruned
+            .post_order_iter::<InternalSharing>()
+            .filter(|data| matches!(data.node.inner(), Inner::Case(..)))
+            .map(|data| data.node.cmr())
+            .collect();
+        let pruned_branches = pruned
+            .post_order_iter::<InternalSharing>()
+            .filter(|data| matches!(data.node.inner(), Inner::AssertL(..) | Inner::AssertR(..)))
+            .filter(|data| cases.contains(&data.node.cmr()))
+            .count();
+
+        let (program, witness) = pruned.to_vec_with_witness();
+        Self {
+            pruned_arms,
+            pruned_branches,
+            bounds: pruned.bounds(),
+            program_size: program.len(),
+            witness_size: witness.len(),
+        }
+    }
+
+    /// Access the match arms that were pruned, in post order.
+    ///
+    /// The list is empty if the program was compiled without debug symbols.
+    pub fn pruned_arms(&self) -> &[TrackedCall] {
+        &self.pruned_arms
+    }
+
+    /// Access the number of `case` combinators of which one branch was pruned.
+    ///
+    /// Branches nested inside pruned branches are not counted.
+    pub fn pruned_branches(&self) -> usize {
+        self.pruned_branches
+    }
+
+    /// Access the bounds of the pruned program.
+    pub fn bounds(&self) -> NodeBounds {
+        self.bounds
+    }
+
+    /// Access the size of the pruned program in bytes, without witness data.
+    ///
+    /// Each byte of the witness stack weighs one weight unit.
+    pub fn program_size(&self) -> usize {
+        self.program_size
+    }
+
+    /// Access the size of the witness data of the pruned program in bytes.
+    ///
+    /// Each byte of the witness stack weighs one weight unit.
+    pub fn witness_size(&self) -> usize {
+        self.witness_size
+    }
+}
+
+impl CallCost {
+    fn new(call: TrackedCall, cost: Cost) -> Self {
+        Self { call, cost }
+    }
+
+    /// Access the tracked call expression.
+    pub fn call(&self) -> &TrackedCall {
+        &self.call
+    }
+
+    /// Access the cost of a single evaluation of the call expression.
+    pub fn cost(&self) -> Cost {
+        self.cost
+    }
+}
+
+/// Analysis of a subexpression of the program.
+struct Summary {
+    /// Bounds of the subexpression, maximized over all spend paths.
+    bounds: NodeBounds,
+    /// Spend paths through the subexpression.
+    paths: Vec<PartialPath>,
+}
+
+/// Spend path through a subexpression of the program.
+#[derive(Clone)]
+struct PartialPath {
+    /// Branches taken, indexed by the post-order index of the `case` combinator.
+    branches: BTreeMap<usize, Branch>,
+    /// Witness nodes reached, along with the bit width of their values.
+    witnesses: BTreeMap<WitnessName, usize>,
+    /// Bounds of the subexpression on this spend path.
+    bounds: NodeBounds,
+}
+
+impl Summary {
+    fn new(
+        data: &PostOrderIterItem<&CommitNode<Elements>>,
+        left: Option<&Self>,
+        right: Option<&Self>,
+        truncated: &mut bool,
+    ) -> Self {
+        let node = data.node;
+        let bounds = node_bounds(node, left.map(|s| s.bounds), right.map(|s| s.bounds));
+
+        let mut paths = match (node.inner(), left, right) {
+            (Inner::Case(..), Some(left), Some(right)) => {
+                let take_branch = |path: &PartialPath, branch: Branch| {
+                    let mut path = path.clone();
+                    path.branches.insert(data.index, branch);
+                    path.bounds = match branch {
+                        Branch::Left => NodeBounds::assertl(path.bounds),
+                        Branch::Right => NodeBounds::assertr(path.bounds),
+                    };
+                    path
+                };
+                left.paths
+                    .iter()
+                    .map(|path| take_branch(path, Branch::Left))
+                    .chain(
+                        right
+                            .paths
+                            .iter()
+                            .map(|path| take_branch(path, Branch::Right)),
+                    )
+                    .collect()
+            }
+            (_, Some(left), Some(right)) => {
+                let mut paths = Vec::with_capacity(left.paths.len() * right.paths.len());
+                for l in &left.paths {
+                    for r in &right.paths {
+                        if let Some(path) = l.merge(r, node) {
+                            paths.push(path);
+                        }
+                    }
+                }
+                paths
+            }
+            (_, Some(child), None) => child
+                .paths
+                .iter()
+                .map(|path| PartialPath {
+                    bounds: node_bounds(node, Some(path.bounds), None),
+                    ..path.clone()
+                })
+                .collect(),
+            (Inner::Witness(name), None, None) => vec![PartialPath {
+                branches: BTreeMap::new(),
+                witnesses: BTreeMap::from([(
+                    name.clone(),
+                    node.cached_data().arrow().target.bit_width(),
+                )]),
+                bounds,
+            }],
+            (_, None, None) => vec![PartialPath {
+                branches: BTreeMap::new(),
+                witnesses: BTreeMap::new(),
+                bounds,
+            }],
+            (_, None, Some(_)) => unreachable!("unary nodes have a left child"),
+        };
+
+        if MAX_SPEND_PATHS < paths.len() {
+            paths.sort_by_key(|path| Reverse(path.bounds.cost));
+            paths.truncate(MAX_SPEND_PATHS);
+            *truncated = true;
+        }
+
+        Self { bounds, paths }
+    }
+}
+
+impl PartialPath {
+    /// Merge the spend paths through the children of the given binary `node`.
+    ///
+    /// Return `None` if the paths take different branches of a shared `case` combinator.
+    fn merge(&self, right: &Self, node: &CommitNode<Elements>) -> Option<Self> {
+        let mut branches = self.branches.clone();
+        for (index, branch) in &right.branches {
+            if *branches.entry(*index).or_insert(*branch) != *branch {
+                return None;
+            }
+        }
+        let mut witnesses = self.witnesses.clone();
+        witnesses.extend(right.witnesses.iter().map(|(k, v)| (k.clone(), *v)));
+
+        Some(Self {
+            branches,
+            witnesses,
+            bounds: node_bounds(node, Some(self.bounds), Some(right.bounds)),
+        })
+    }
+}
+
+/// Compute the bounds of the given `node` from the bounds of its children.
+///
+/// This follows the computation of bounds in [`node::RedeemData`].
+fn node_bounds(
+    node: &CommitNode<Elements>,
+    left: Option<NodeBounds>,
+    right: Option<NodeBounds>,
+) -> NodeBounds {
+    let arrow = node.cached_data().arrow();
+    match node.inner() {
+        Inner::Iden => NodeBounds::iden(arrow.source.bit_width()),
+        Inner::Unit => NodeBounds::unit(),
+        Inner::InjL(_) => NodeBounds::injl(left.unwrap()),
+        Inner::InjR(_) => NodeBounds::injr(left.unwrap()),
+        Inner::Take(_) => NodeBounds::take(left.unwrap()),
+        Inner::Drop(_) => NodeBounds::drop(left.unwrap()),
+        Inner::Comp(mid, _) => NodeBounds::comp(
+            left.unwrap(),
+            right.unwrap(),
+            mid.cached_data().arrow().target.bit_width(),
+        ),
+        Inner::Case(..) => NodeBounds::case(left.unwrap(), right.unwrap()),
+        Inner::AssertL(..) => NodeBounds::assertl(left.unwrap()),
+        Inner::AssertR(..) => NodeBounds::assertr(left.unwrap()),
+        Inner::Pair(..) => NodeBounds::pair(left.unwrap(), right.unwrap()),
+        Inner::Disconnect(..) => unreachable!("SimplicityHL does not use disconnect right now"),
+        Inner::Witness(_) => NodeBounds::witness(arrow.target.bit_width()),
+        Inner::Fail(_) => NodeBounds::fail(),
+        Inner::Jet(jet) => NodeBounds::jet(*jet),
+        Inner::Word(word) => NodeBounds::const_word(word),
+    }
+}
+
+/// Prune the `case` combinators of a program according to the branches of a spend path.
+///
+/// Pruning may shrink the types of the remaining program,
+/// so the pruned program is typed from scratch.
+struct PathPruner<'a, 'brand> {
+    inference_context: &'a types::Context<'brand>,
+    branches: &'a BTreeMap<usize, Branch>,
+}
+
+impl<'brand> Converter<WithNames<node::Commit<Elements>>, node::Construct<'brand, Elements>>
+    for PathPruner<'_, 'brand>
+{
+    type Error = std::convert::Infallible;
+
+    fn convert_witness(
+        &mut self,
+        _: &PostOrderIterItem<&CommitNode<Elements>>,
+        _: &WitnessName,
+    ) -> Result<Option<simplicity::Value>, Self::Error> {
+        Ok(None)
+    }
+
+    fn convert_disconnect(
+        &mut self,
+        _: &PostOrderIterItem<&CommitNode<Elements>>,
+        _: Option<&Arc<node::ConstructNode<'brand, Elements>>>,
+        _: &NoDisconnect,
+    ) -> Result<Option<Arc<node::ConstructNode<'brand, Elements>>>, Self::Error> {
+        unreachable!("SimplicityHL does not use disconnect right now")
+    }
+
+    fn prune_case(
+        &mut self,
+        data: &PostOrderIterItem<&CommitNode<Elements>>,
+        _: &Arc<node::ConstructNode<'brand, Elements>>,
+        _: &Arc<node::ConstructNode<'brand, Elements>>,
+    ) -> Result<Hide, Self::Error> {
+        match self.branches.get(&data.index) {
+            Some(Branch::Left) => Ok(Hide::Right),
+            Some(Branch::Right) => Ok(Hide::Left),
+            None => Ok(Hide::Neither),
+        }
+    }
+
+    fn convert_data(
+        &mut self,
+        _: &PostOrderIterItem<&CommitNode<Elements>>,
+        inner: Inner<
+            &Arc<node::ConstructNode<'brand, Elements>>,
+            Elements,
+            &Option<Arc<node::ConstructNode<'brand, Elements>>>,
+            &Option<simplicity::Value>,
+        >,
+    ) -> Result<node::ConstructData<'brand, Elements>, Self::Error> {
+        let inner = inner
+            .map(|node| node.cached_data())
+            .map_witness(Option::<simplicity::Value>::clone);
+        Ok(
+            node::ConstructData::from_inner(self.inference_context, inner)
+                .expect("pruned types check out if unpruned types check out"),
+        )
+    }
+}
+
+/// Populate the witness nodes of a pruned program with placeholder values.
+///
+/// Each witness node receives a different value where its type allows it,
+/// so placeholder values don't introduce sharing that real witness data would not have.
+struct Placeholders {
+    next_id: u64,
+}
+
+impl Converter<node::Commit<Elements>, node::Redeem<Elements>> for Placeholders {
+    type Error = std::convert::Infallible;
+
+    fn convert_witness(
+        &mut self,
+        data: &PostOrderIterItem<&node::CommitNode<Elements>>,
+        _: &NoWitness,
+    ) -> Result<simplicity::Value, Self::Error> {
+        let bytes = self
+            .next_id
+            .to_be_bytes()
+            .into_iter()
+            .chain(std::iter::repeat(0));
+        self.next_id += 1;
+        let ty = &data.node.arrow().target;
+        Ok(
+            simplicity::Value::from_compact_bits(&mut BitIter::new(bytes), ty)
+                .expect("bit iterator is infinite"),
+        )
+    }
+
+    fn convert_disconnect(
+        &mut self,
+        _: &PostOrderIterItem<&node::CommitNode<Elements>>,
+        _: Option<&Arc<node::RedeemNode<Elements>>>,
+        _: &NoDisconnect,
+    ) -> Result<Arc<node::RedeemNode<Elements>>, Self::Error> {
+        unreachable!("SimplicityHL does not use disconnect right now")
+    }
+
+    fn convert_data(
+        &mut self,
+        data: &PostOrderIterItem<&node::CommitNode<Elements>>,
+        inner: Inner<
+            &Arc<node::RedeemNode<Elements>>,
+            Elements,
+            &Arc<node::RedeemNode<Elements>>,
+            &simplicity::Value,
+        >,
+    ) -> Result<Arc<node::RedeemData<Elements>>, Self::Error> {
+        let inner = inner
+            .map(|node| node.cached_data())
+            .map_disconnect(|node| node.cached_data())
+            .map_witness(simplicity::Value::shallow_clone);
+        Ok(Arc::new(node::RedeemData::new(
+            data.node.arrow().shallow_clone(),
+            inner,
+        )))
+    }
+}
+
+impl fmt::Display for Branch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Left => write!(f, "L"),
+            Self::Right => write!(f, "R"),
+        }
+    }
+}
+
+impl fmt::Display for CostReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Worst case: cost {} mWU, {} cells, {} frames",
+            self.bounds.cost, self.bounds.extra_cells, self.bounds.extra_frames
+        )?;
+        for (i, path) in self.spend_paths.iter().enumerate() {
+            write!(f, "Spend path {}: ", i + 1)?;
+            for branch in path.branches() {
+                write!(f, "{branch}")?;
+            }
+            if !path.branches().is_empty() {
+                write!(f, " ")?;
+            }
+            writeln!(
+                f,
+                "cost {} mWU, {} cells, {} frames, program {} bytes, witness at most {} bytes",
+                path.bounds.cost,
+                path.bounds.extra_cells,
+                path.bounds.extra_frames,
+                path.program_size,
+                path.max_witness_size
+            )?;
+            for witness in path.witnesses() {
+                writeln!(f, "    uses witness::{witness}")?;
+            }
+        }
+        if self.truncated {
+            writeln!(f, "More spend paths were omitted")?;
+        }
+        for call in &self.calls {
+            let start = call.call.span().start;
+            writeln!(
+                f,
+                "{}:{}: cost {} mWU: {}",
+                start.line,
+                start.col,
+                call.cost,
+                call.call.text()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PruneReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Cost {} mWU, {} cells, {} frames, program {} bytes, witness {} bytes",
+            self.bounds.cost,
+            self.bounds.extra_cells,
+            self.bounds.extra_frames,
+            self.program_size,
+            self.witness_size
+        )?;
+        writeln!(f, "Pruned {} branches", self.pruned_branches)?;
+        for arm in &self.pruned_arms {
+            let start = arm.span().start;
+            writeln!(f, "{}:{}: pruned {}", start.line, start.col, arm.text())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simplicity::elements;
+
+    use crate::{dummy_env, Arguments, CompiledProgram, TemplateProgram, WitnessValues};
+
+    fn compile(path: &str) -> CompiledProgram {
+        let text = std::fs::read_to_string(path).unwrap();
+        TemplateProgram::new(text)
+            .and_then(|template| template.instantiate(Arguments::default(), true))
+            .unwrap()
+    }
+
+    #[test]
+    fn straight_line_program() {
+        let program = compile("./examples/ctv.simf");
+        let report = program.cost_report();
+        assert!(!report.is_truncated());
+
+        let satisfied = program.satisfy(WitnessValues::default()).unwrap();
+        assert_eq!(report.bounds(), satisfied.redeem().bounds());
+        let path = &report.spend_paths()[0];
+        assert_eq!(path.bounds(), satisfied.redeem().bounds());
+        assert_eq!(
+            path.program_size(),
+            satisfied.redeem().to_vec_without_witness().len()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn spend_paths_match_pruned_programs() {
+        let program = compile("./examples/last_will.simf");
+        let report = program.cost_report();
+        assert!(!report.is_truncated());
+        assert_eq!(3, report.spend_paths().len());
+        assert_eq!(report.bounds().cost, report.spend_paths()[0].bounds().cost);
+
+        let text = std::fs::read_to_string("./examples/last_will.inherit.wit").unwrap();
+        let witness = WitnessValues::from_json_with_types(&text, program.witness_types()).unwrap();
+        let env = dummy_env::dummy_with(
+            elements::LockTime::ZERO,
+            elements::Sequence::from_height(25920),
+            false,
+        );
+        let satisfied = program.satisfy_with_env(witness, Some(&env)).unwrap();
+        let pruned = satisfied.redeem();
+        let path = report
+            .spend_paths()
+            .iter()
+            .find(|path| path.branches() == [Branch::Left])
+            .expect("inherit path exists");
+        assert_eq!(path.bounds(), pruned.bounds());
+        assert_eq!(path.program_size(), pruned.to_vec_without_witness().len());
+        assert_eq!(
+            path.max_witness_size(),
+            pruned.to_vec_with_witness().1.len()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn pruned_match_arms() {
+        let program = compile("./examples/escrow_with_delay.simf");
+        let text = std::fs::read_to_string("./examples/escrow_with_delay.timeout.wit").unwrap();
+        let witness = WitnessValues::from_json_with_types(&text, program.witness_types()).unwrap();
+        let env = dummy_env::dummy_with(
+            elements::LockTime::ZERO,
+            elements::Sequence::from_height(1000),
+            false,
+        );
+        let unpruned = program.satisfy(witness.clone()).unwrap();
+        let (satisfied, report) = program.satisfy_with_report(witness, Some(&env)).unwrap();
+
+        assert!(report
+            .pruned_arms()
+            .iter()
+            .any(|arm| arm.text().starts_with("Left(maybe_sigs")));
+        assert!(!report
+            .pruned_arms()
+            .iter()
+            .any(|arm| arm.text().starts_with("Right(sender_sig")));
+        assert!(0 < report.pruned_branches());
+        assert_eq!(report.bounds(), satisfied.redeem().bounds());
+        let (program_bytes, witness_bytes) = satisfied.redeem().to_vec_with_witness();
+        assert_eq!(report.program_size(), program_bytes.len());
+        assert_eq!(report.witness_size(), witness_bytes.len());
+        assert!(report.program_size() < unpruned.redeem().to_vec_without_witness().len());
+    }
+
+    #[test]
+    fn match_arms_are_wrapped_in_debug_builds() {
+        let text = "fn main() {
+    let x: Either<u8, u16> = witness::X;
+    match x {
+        Left(a: u8) => assert!(jet::eq_8(a, 1)),
+        Right(b: u16) => assert!(jet::eq_16(b, 2)),
+    };
+}";
+        let template = TemplateProgram::new(text).unwrap();
+        let release = template.instantiate(Arguments::default(), false).unwrap();
+        let debug = template.instantiate(Arguments::default(), true).unwrap();
+        let arms = debug
+            .commit()
+            .post_order_iter::<InternalSharing>()
+            .filter_map(|data| match data.node.inner() {
+                Inner::AssertL(_, cmr) => debug.debug_symbols().get(cmr),
+                _ => None,
+            })
+            .filter(|call| matches!(call.name(), TrackedCallName::MatchArm))
+            .count();
+        assert_eq!(2, arms);
+        assert_ne!(release.commit().cmr(), debug.commit().cmr());
+        // The release CMR is the same as before match arms had debug symbols
+        assert_eq!(
+            "5f90919f2a4665cea588d5d64f79eeb39fea5ee1546fe6c6fccf52a27c8f844c",
+            release.commit().cmr().to_string()
+        );
+        assert_eq!(
+            "d4e90020c17d7e81bd1f4b835d695da02e3eae56e4a91b57ccdd90e9cb9520fa",
+            debug.commit().cmr().to_string()
+        );
+    }
+
+    #[test]
+    fn most_expensive_call() {
+        let program = compile("./examples/p2pkh.simf");
+        let report = program.cost_report();
+        let call = &report.calls()[0];
+        assert!(call.call().text().starts_with("jet::bip_0340_verify"));
+        assert!(report.calls().windows(2).all(|w| w[1].cost <= w[0].cost));
+    }
+}
@@ -0,0 +1,450 @@
+//! Code coverage of SimplicityHL programs.
+//!
+//! Coverage is measured at the level of call expressions that carry a debug symbol,
+//! such as jet calls, assertions and match arms.
+//! A call expression is covered if the Bit Machine executed it in at least one run.
+//! The two arms of each match expression form a branch.
+//!
+//! The program must be compiled with debug symbols.
+//! Coverage can be aggregated over multiple runs of the same program
+//! with different witness data, and it can be exported as lcov tracefile
+//! or as annotated HTML source.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use simplicity::bit_machine::{ExecTracker, FrameIter, NodeOutput, PruneTracker, SetTracker};
+use simplicity::dag::{DagLike, NoSharing};
+use simplicity::elements;
+use simplicity::jet::elements::ElementsEnv;
+use simplicity::jet::Elements;
+use simplicity::node::Inner;
+use simplicity::{Cmr, CommitNode, Ihr, RedeemNode};
+
+use crate::debug::{TrackedCall, TrackedCallName};
+use crate::{CompiledProgram, SatisfiedProgram};
+
+/// Coverage of the call expressions of a SimplicityHL program, aggregated over runs.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut coverage = Coverage::new(&compiled);
+/// for witness in witnesses {
+///     let satisfied = compiled.satisfy(witness)?;
+///     coverage.record(&satisfied, &env)?;
+/// }
+/// std::fs::write("lcov.info", coverage.to_lcov("program.simf"))?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct Coverage {
+    cmr: Cmr,
+    calls: Vec<CallCoverage>,
+    branches: Vec<(usize, usize)>,
+    runs: usize,
+}
+
+/// Coverage of a single call expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallCoverage {
+    cmr: Cmr,
+    call: TrackedCall,
+    hits: usize,
+}
+
+impl CallCoverage {
+    /// Access the call expression.
+    pub fn call(&self) -> &TrackedCall {
+        &self.call
+    }
+
+    /// Return the number of times that the call expression was executed, over all runs.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Check if the call expression was executed in at least one run.
+    pub fn is_covered(&self) -> bool {
+        0 < self.hits
+    }
+
+    /// Return the line where the call expression starts.
+    pub fn line(&self) -> usize {
+        self.call.span().start.line.get()
+    }
+}
+
+impl Coverage {
+    /// Create an empty coverage report for the given program.
+    ///
+    /// The program should be compiled with debug symbols.
+    /// Without debug symbols, there is nothing to cover.
+    pub fn new(program: &CompiledProgram) -> Self {
+        let debug_symbols = program.debug_symbols();
+        let commit = program.commit();
+        let mut calls = Vec::new();
+        let mut seen = HashSet::new();
+        let mut arm_pairs = Vec::new();
+
+        for data in commit.as_ref().post_order_iter::<NoSharing>() {
+            match data.node.inner() {
+                Inner::AssertL(_, cmr) => {
+                    if let Some(call) = debug_symbols.get(cmr) {
+                        if seen.insert(*cmr) {
+                            calls.push(CallCoverage {
+                                cmr: *cmr,
+                                call: call.clone(),
+                                hits: 0,
+                            });
+                        }
+                    }
+                }
+                Inner::Case(left, right) => {
+                    // Tracked match arms compile to `comp (pair (bit false) iden) (assertl (drop arm) cmr)`
+                    let arm = |child: &CommitNode<Elements>| match tracked_assertion(child) {
+                        Some(cmr)
+                            if matches!(
+                                debug_symbols.get(&cmr).map(TrackedCall::name),
+                                Some(TrackedCallName::MatchArm)
+                            ) =>
+                        {
+                            Some(cmr)
+                        }
+                        _ => None,
+                    };
+                    if let (Some(left), Some(right)) = (arm(left), arm(right)) {
+                        arm_pairs.push((left, right));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        calls.sort_by_key(|call| {
+            let span = call.call.span();
+            (span.start.line, span.start.col, span.end.line, span.end.col)
+        });
+        let index = calls
+            .iter()
+            .enumerate()
+            .map(|(i, call)| (call.cmr, i))
+            .collect::<HashMap<Cmr, usize>>();
+
+        let mut branches = arm_pairs
+            .into_iter()
+            .map(|(left, right)| (index[&left], index[&right]))
+            .collect::<Vec<(usize, usize)>>();
+        branches.sort_unstable();
+        branches.dedup();
+
+        Self {
+            cmr: commit.cmr(),
+            calls,
+            branches,
+            runs: 0,
+        }
+    }
+
+    /// Execute the given program in the given environment
+    /// and add the executed call expressions to the coverage.
+    ///
+    /// The executed call expressions are recorded even if execution fails,
+    /// so the coverage of failing runs counts, too.
+    ///
+    /// ## Errors
+    ///
+    /// - The program differs from the program of the coverage report.
+    /// - The program fails to execute.
+    pub fn record(
+        &mut self,
+        program: &SatisfiedProgram,
+        env: &ElementsEnv<Arc<elements::Transaction>>,
+    ) -> Result<(), String> {
+        if program.redeem().cmr() != self.cmr {
+            return Err("The program differs from the program of the coverage report".to_string());
+        }
+        let mut tracker = CoverageTracker::default();
+        let result = program.redeem().prune_with_tracker(env, &mut tracker);
+
+        self.runs += 1;
+        for call in &mut self.calls {
+            call.hits += tracker.hits.get(&call.cmr).copied().unwrap_or(0);
+        }
+        result
+            .map(|_| ())
+            .map_err(|e| format!("Execution failed: {e}"))
+    }
+
+    /// Return the number of recorded runs.
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+
+    /// Access the coverage of the call expressions, in order of their position in the source file.
+    pub fn calls(&self) -> &[CallCoverage] {
+        &self.calls
+    }
+
+    /// Iterate over the branches of the program: the left and right arms of each match expression.
+    pub fn branches(&self) -> impl Iterator<Item = (&CallCoverage, &CallCoverage)> {
+        self.branches
+            .iter()
+            .map(|&(left, right)| (&self.calls[left], &self.calls[right]))
+    }
+
+    /// Iterate over the call expressions that were not executed in any run.
+    pub fn uncovered(&self) -> impl Iterator<Item = &CallCoverage> {
+        self.calls.iter().filter(|call| !call.is_covered())
+    }
+
+    /// Return the number of covered call expressions.
+    fn covered_calls(&self) -> usize {
+        self.calls.iter().filter(|call| call.is_covered()).count()
+    }
+
+    /// Return the number of covered branches, counting each match arm as one branch.
+    fn covered_branches(&self) -> usize {
+        self.branches()
+            .map(|(left, right)| usize::from(left.is_covered()) + usize::from(right.is_covered()))
+            .sum()
+    }
+
+    /// Return the hit count of each line that contains a call expression.
+    ///
+    /// The hit count of a line is the maximum hit count of the call expressions
+    /// that start on the line. The line is partially covered if some of these calls
+    /// were not executed.
+    fn lines(&self) -> BTreeMap<usize, (usize, bool)> {
+        let mut lines = BTreeMap::<usize, (usize, bool)>::new();
+        for call in &self.calls {
+            let (hits, partial) = lines.entry(call.line()).or_insert((0, false));
+            *partial |= !call.is_covered();
+            *hits = (*hits).max(call.hits);
+        }
+        lines
+    }
+
+    /// Export the coverage as [lcov tracefile](https://ltp.sourceforge.net/coverage/lcov/geninfo.1.php)
+    /// for the SimplicityHL source file at the given path.
+    pub fn to_lcov(&self, source_path: &str) -> String {
+        let mut lcov = String::new();
+        writeln!(lcov, "TN:").unwrap();
+        writeln!(lcov, "SF:{source_path}").unwrap();
+
+        for (block, (left, right)) in self.branches().enumerate() {
+            let executed = left.is_covered() || right.is_covered();
+            for (branch, arm) in [left, right].into_iter().enumerate() {
+                let taken = match executed {
+                    true => arm.hits.to_string(),
+                    false => "-".to_string(),
+                };
+                writeln!(lcov, "BRDA:{},{block},{branch},{taken}", left.line()).unwrap();
+            }
+        }
+        writeln!(lcov, "BRF:{}", 2 * self.branches.len()).unwrap();
+        writeln!(lcov, "BRH:{}", self.covered_branches()).unwrap();
+
+        let lines = self.lines();
+        for (line, (hits, _)) in &lines {
+            writeln!(lcov, "DA:{line},{hits}").unwrap();
+        }
+        writeln!(lcov, "LF:{}", lines.len()).unwrap();
+        let hit_lines = lines.values().filter(|(hits, _)| 0 < *hits).count();
+        writeln!(lcov, "LH:{hit_lines}").unwrap();
+        writeln!(lcov, "end_of_record").unwrap();
+        lcov
+    }
+
+    /// Export the coverage as standalone HTML page that shows the given SimplicityHL source
+    /// with covered, partially covered and uncovered lines highlighted.
+    pub fn to_html(&self, source: &str) -> String {
+        let lines = self.lines();
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str("<title>SimplicityHL coverage</title>\n<style>\n");
+        html.push_str("pre { font-family: monospace; }\n");
+        html.push_str(".hits { display: inline-block; width: 6em; color: #888; }\n");
+        html.push_str(".covered { background-color: #cfc; }\n");
+        html.push_str(".partial { background-color: #ffc; }\n");
+        html.push_str(".uncovered { background-color: #fcc; }\n");
+        html.push_str("</style>\n</head>\n<body>\n");
+        writeln!(html, "<p>{}</p>", escape_html(&self.summary())).unwrap();
+        html.push_str("<pre>\n");
+
+        for (i, text) in source.lines().enumerate() {
+            let line = i + 1;
+            let (class, hits) = match lines.get(&line) {
+                Some((0, _)) => ("uncovered", "0".to_string()),
+                Some((hits, true)) => ("partial", hits.to_string()),
+                Some((hits, false)) => ("covered", hits.to_string()),
+                None => ("", String::new()),
+            };
+            writeln!(
+                html,
+                "<span class=\"{class}\"><span class=\"hits\">{line:>4} {hits:>5}</span> {}</span>",
+                escape_html(text)
+            )
+            .unwrap();
+        }
+
+        html.push_str("</pre>\n</body>\n</html>\n");
+        html
+    }
+
+    /// Return a one-line summary of the coverage.
+    fn summary(&self) -> String {
+        format!(
+            "Calls: {}/{} covered, branches: {}/{} covered, runs: {}",
+            self.covered_calls(),
+            self.calls.len(),
+            self.covered_branches(),
+            2 * self.branches.len(),
+            self.runs,
+        )
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.summary())?;
+        for call in self.uncovered() {
+            writeln!(f, "    line {}: {}", call.line(), call.call.text())?;
+        }
+        Ok(())
+    }
+}
+
+/// Return the CMR of the assertion of a tracked call expression of the form
+/// `comp _ (assertl _ cmr)`.
+fn tracked_assertion(node: &CommitNode<Elements>) -> Option<Cmr> {
+    match node.inner() {
+        Inner::Comp(_, right) => match right.inner() {
+            Inner::AssertL(_, cmr) => Some(*cmr),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Tracker that counts how often the assertions of tracked call expressions are executed.
+#[derive(Default)]
+struct CoverageTracker {
+    hits: HashMap<Cmr, usize>,
+    inner: SetTracker,
+}
+
+impl PruneTracker<Elements> for CoverageTracker {
+    fn contains_left(&self, ihr: Ihr) -> bool {
+        PruneTracker::<Elements>::contains_left(&self.inner, ihr)
+    }
+
+    fn contains_right(&self, ihr: Ihr) -> bool {
+        PruneTracker::<Elements>::contains_right(&self.inner, ihr)
+    }
+}
+
+impl ExecTracker<Elements> for CoverageTracker {
+    fn visit_node(&mut self, node: &RedeemNode<Elements>, input: FrameIter, output: NodeOutput) {
+        if let Inner::AssertL(_, cmr) = node.inner() {
+            *self.hits.entry(*cmr).or_default() += 1;
+        }
+        self.inner.visit_node(node, input, output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::ParseFromStr;
+    use crate::{dummy_env, Arguments, TemplateProgram, WitnessValues};
+
+    const PROGRAM: &str = r#"fn check(x: Either<u32, bool>) -> bool {
+    match x {
+        Left(n: u32) => jet::eq_32(n, 0),
+        Right(b: bool) => b,
+    }
+}
+
+fn main() {
+    let x: Either<u32, bool> = witness::X;
+    assert!(check(x));
+}"#;
+
+    fn compile() -> CompiledProgram {
+        TemplateProgram::new(PROGRAM)
+            .unwrap()
+            .instantiate(Arguments::default(), true)
+            .unwrap()
+    }
+
+    fn run(coverage: &mut Coverage, compiled: &CompiledProgram, x: &str) {
+        let witness = WitnessValues::parse_from_str(&format!(
+            "mod witness {{ const X: Either<u32, bool> = {x}; }}"
+        ))
+        .unwrap();
+        let satisfied = compiled.satisfy(witness).unwrap();
+        coverage.record(&satisfied, &dummy_env::dummy()).unwrap();
+    }
+
+    #[test]
+    fn aggregate_runs() {
+        let compiled = compile();
+        let mut coverage = Coverage::new(&compiled);
+        assert_eq!(0, coverage.runs());
+        assert_eq!(1, coverage.branches().count());
+        assert!(coverage.calls().iter().all(|call| !call.is_covered()));
+
+        run(&mut coverage, &compiled, "Left(0)");
+        let uncovered = coverage
+            .uncovered()
+            .map(CallCoverage::line)
+            .collect::<Vec<usize>>();
+        assert_eq!(vec![4], uncovered);
+        let lcov = coverage.to_lcov("check.simf");
+        assert!(lcov.contains("BRDA:3,0,0,1\nBRDA:3,0,1,0\n"));
+        assert!(lcov.contains("BRF:2\nBRH:1\n"));
+        assert!(lcov.contains("DA:3,1\nDA:4,0\n"));
+
+        run(&mut coverage, &compiled, "Right(true)");
+        assert_eq!(2, coverage.runs());
+        assert_eq!(0, coverage.uncovered().count());
+        let lcov = coverage.to_lcov("check.simf");
+        assert!(lcov.contains("BRH:2\n"));
+        assert!(lcov.contains("DA:10,2\n"));
+        assert!(lcov.ends_with("end_of_record\n"));
+    }
+
+    #[test]
+    fn failing_run_counts() {
+        let compiled = compile();
+        let mut coverage = Coverage::new(&compiled);
+        let witness = WitnessValues::parse_from_str(
+            "mod witness { const X: Either<u32, bool> = Right(false); }",
+        )
+        .unwrap();
+        let satisfied = compiled.satisfy(witness).unwrap();
+        assert!(coverage.record(&satisfied, &dummy_env::dummy()).is_err());
+        assert_eq!(1, coverage.runs());
+
+        let html = coverage.to_html(PROGRAM);
+        assert!(html.contains("<span class=\"uncovered\"><span class=\"hits\">   3     0</span>"));
+        assert!(html.contains("<span class=\"covered\"><span class=\"hits\">   4     1</span>"));
+        assert!(html.contains("Either&lt;u32, bool&gt;"));
+    }
+}
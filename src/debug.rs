@@ -3,9 +3,11 @@ use std::sync::Arc;
 
 use either::Either;
 use hashes::{sha256, Hash, HashEngine};
-use simplicity::{hashes, Cmr};
+use simplicity::{hashes, Cmr, Value as SimValue};
 
 use crate::error::Span;
+use crate::pattern::BasePattern;
+use crate::str::Identifier;
 use crate::types::ResolvedType;
 use crate::value::{StructuralValue, Value};
 
@@ -23,6 +25,21 @@ pub struct DebugSymbols(HashMap<Cmr, TrackedCall>);
 pub(crate) struct CallTracker {
     next_id: u32,
     map: HashMap<Span, (Cmr, TrackedCallName)>,
+    variables: HashMap<Span, VisibleVariables>,
+}
+
+/// Variables that are visible at a point of the program, as a persistent list of bindings.
+///
+/// Capturing the list at a call expression is cheap because the bindings are shared.
+/// The list is resolved into the visible variables only when debug symbols are created.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub(crate) struct VisibleVariables(Option<Arc<Binding>>);
+
+#[derive(Debug, Eq, PartialEq)]
+struct Binding {
+    identifier: Identifier,
+    ty: ResolvedType,
+    previous: VisibleVariables,
 }
 
 /// Call expression with a debug symbol.
@@ -30,6 +47,9 @@ pub(crate) struct CallTracker {
 pub struct TrackedCall {
     text: Arc<str>,
     name: TrackedCallName,
+    span: Span,
+    variables: Arc<[(Identifier, ResolvedType)]>,
+    layout: Option<BasePattern>,
 }
 
 /// Name of a call expression with a debug symbol.
@@ -42,6 +62,7 @@ pub enum TrackedCallName {
     UnwrapRight(ResolvedType),
     Unwrap,
     Debug(ResolvedType),
+    MatchArm,
 }
 
 /// Fallible call expression with runtime input value.
@@ -72,7 +93,14 @@ pub struct DebugValue {
 impl DebugSymbols {
     /// Insert a tracked call expression.
     /// Use the SimplicityHL source `file` to extract the SimplicityHL text of the expression.
-    pub(crate) fn insert(&mut self, span: Span, cmr: Cmr, name: TrackedCallName, file: &str) {
+    pub(crate) fn insert(
+        &mut self,
+        span: Span,
+        cmr: Cmr,
+        name: TrackedCallName,
+        variables: Arc<[(Identifier, ResolvedType)]>,
+        file: &str,
+    ) {
         let text = remove_excess_whitespace(span.to_slice(file).unwrap_or(""));
         let text = text
             .strip_prefix("dbg!(")
@@ -84,15 +112,37 @@ impl DebugSymbols {
             TrackedCall {
                 text: Arc::from(text),
                 name,
+                span,
+                variables,
+                layout: None,
             },
         );
     }
 
+    /// Attach the layouts of the variables that are in scope at each call expression,
+    /// as produced by the compiler.
+    pub(crate) fn with_layouts(mut self, layouts: &HashMap<Span, BasePattern>) -> Self {
+        for call in self.0.values_mut() {
+            call.layout = layouts.get(&call.span).cloned();
+        }
+        self
+    }
+
     /// Check if the given CMR tracks any call expressions.
     pub fn contains_key(&self, cmr: &Cmr) -> bool {
         self.0.contains_key(cmr)
     }
 
+    /// Return the number of tracked call expressions.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check if no call expressions are tracked.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     /// Get the call expression that is tracked by the given CMR.
     pub fn get(&self, cmr: &Cmr) -> Option<&TrackedCall> {
         self.0.get(cmr)
@@ -121,9 +171,12 @@ impl CallTracker {
     ///
     /// This holds true when the method is called on a real source file.
     /// The precondition might be broken when this method is called on random input.
-    pub fn track_call(&mut self, span: Span, name: TrackedCallName) {
+    ///
+    /// The `variables` are in scope at the call expression.
+    pub fn track_call(&mut self, span: Span, name: TrackedCallName, variables: VisibleVariables) {
         let cmr = self.next_id_cmr();
         let _replaced = self.map.insert(span, (cmr, name));
+        self.variables.insert(span, variables);
         self.next_id += 1;
     }
 
@@ -145,12 +198,44 @@ impl CallTracker {
     pub fn with_file(&self, file: &str) -> DebugSymbols {
         let mut debug_symbols = DebugSymbols::default();
         for (span, (cmr, name)) in &self.map {
-            debug_symbols.insert(*span, *cmr, name.clone(), file);
+            let variables = self
+                .variables
+                .get(span)
+                .map(VisibleVariables::resolve)
+                .unwrap_or_default();
+            debug_symbols.insert(*span, *cmr, name.clone(), variables, file);
         }
         debug_symbols
     }
 }
 
+impl VisibleVariables {
+    /// Bind the variable to the given type, shadowing previous bindings of the same name.
+    pub fn insert(&mut self, identifier: Identifier, ty: ResolvedType) {
+        let previous = std::mem::take(self);
+        self.0 = Some(Arc::new(Binding {
+            identifier,
+            ty,
+            previous,
+        }));
+    }
+
+    /// Return the visible variables and their types, ordered by name.
+    pub fn resolve(&self) -> Arc<[(Identifier, ResolvedType)]> {
+        let mut visible = HashMap::new();
+        let mut current = self.0.as_ref();
+        while let Some(binding) = current {
+            visible
+                .entry(binding.identifier.clone())
+                .or_insert_with(|| binding.ty.clone());
+            current = binding.previous.0.as_ref();
+        }
+        let mut variables: Vec<(Identifier, ResolvedType)> = visible.into_iter().collect();
+        variables.sort_by(|(x, _), (y, _)| x.cmp(y));
+        Arc::from(variables)
+    }
+}
+
 impl TrackedCall {
     /// Access the text of the SimplicityHL call expression.
     pub fn text(&self) -> &str {
@@ -162,12 +247,60 @@ impl TrackedCall {
         &self.name
     }
 
+    /// Access the span of the call expression inside the SimplicityHL source file.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Access the variables that are in scope at the call expression, with their types.
+    pub fn variables(&self) -> &[(Identifier, ResolvedType)] {
+        &self.variables
+    }
+
+    /// Supply the Simplicity input value of the environment of the call expression at runtime.
+    /// Decode the values of the variables that are in scope at the call expression.
+    ///
+    /// Shadowed variables are skipped. The most recently bound variable comes first.
+    ///
+    /// Return `None` if the layout of the environment is unknown,
+    /// because the program was compiled without debug symbols,
+    /// or if the environment value is of the wrong type.
+    pub fn decode_variables(&self, environment: &SimValue) -> Option<Vec<(Identifier, Value)>> {
+        let layout = self.layout.as_ref()?;
+        let mut decoded: Vec<(Identifier, Value)> = Vec::new();
+        // Traverse the layout in pre-order, left before right,
+        // because the compiler binds newer variables further left
+        let mut stack = vec![(layout, environment.as_ref())];
+        while let Some((pattern, value)) = stack.pop() {
+            match pattern {
+                BasePattern::Ignore => {}
+                BasePattern::Identifier(identifier) => {
+                    if decoded.iter().any(|(bound, _)| bound == identifier) {
+                        continue;
+                    }
+                    let Some((_, ty)) = self.variables.iter().find(|(x, _)| x == identifier) else {
+                        continue;
+                    };
+                    let value = Value::reconstruct(&StructuralValue::from(value.to_value()), ty)?;
+                    decoded.push((identifier.clone(), value));
+                }
+                BasePattern::Product(left, right) => {
+                    let (value_l, value_r) = value.as_product()?;
+                    stack.push((right, value_r));
+                    stack.push((left, value_l));
+                }
+            }
+        }
+        Some(decoded)
+    }
+
     /// Supply the Simplicity input value of the call expression at runtime.
     /// Convert the debug call into a fallible call or into a debug value,
     /// depending on the kind of debug symbol.
     ///
     /// Return `None` if the Simplicity input value is of the wrong type,
-    /// according to the debug symbol.
+    /// according to the debug symbol, or if the debug symbol tracks a match arm,
+    /// which can neither fail nor be debugged.
     pub fn map_value(&self, value: &StructuralValue) -> Option<Either<FallibleCall, DebugValue>> {
         let name = match self.name() {
             TrackedCallName::Assert => FallibleCallName::Assert,
@@ -180,6 +313,7 @@ impl TrackedCall {
                 Value::reconstruct(value, ty).map(FallibleCallName::UnwrapRight)?
             }
             TrackedCallName::Unwrap => FallibleCallName::Unwrap,
+            TrackedCallName::MatchArm => return None,
             TrackedCallName::Debug(ty) => {
                 return Value::reconstruct(value, ty)
                     .map(|value| DebugValue {
@@ -0,0 +1,531 @@
+//! Step debugger for SimplicityHL programs.
+//!
+//! The debugger observes the execution of the Bit Machine and stops at _steps_.
+//! A step is a call expression that carries a debug symbol,
+//! such as a jet call, an assertion or a match arm.
+//! The program must be compiled with debug symbols.
+//!
+//! Custom functions are inlined by the compiler, so there is no call stack at runtime.
+//! The debugger approximates stepping over and out of functions using the static call graph:
+//! a step belongs to a callee of the current function if the current function (transitively) calls
+//! the function of the step.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use miniscript::iter::TreeLike;
+use simplicity::bit_machine::{ExecTracker, FrameIter, NodeOutput, PruneTracker, SetTracker};
+use simplicity::jet::Elements;
+use simplicity::node::Inner;
+use simplicity::{Ihr, RedeemNode};
+
+use crate::debug::{DebugSymbols, TrackedCall};
+use crate::error::Span;
+use crate::parse::{self, CallName, ParseFromStr};
+use crate::str::{FunctionName, Identifier};
+use crate::tracker::{read_value, FrameOffsets};
+use crate::Value;
+
+/// Location where the debugger stops execution.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Breakpoint {
+    /// Stop when execution enters the given line (starting at 1) from a different line.
+    Line(usize),
+    /// Stop when execution enters the given function from a different function.
+    Function(FunctionName),
+}
+
+/// Instruction of the front end how to resume execution.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Command {
+    /// Stop at the next step.
+    Step,
+    /// Stop at the next step on a different line of the current function,
+    /// or after returning from the current function.
+    /// Do not stop inside functions that the current function calls.
+    StepOver,
+    /// Stop at the next step after returning from the current function.
+    StepOut,
+    /// Stop at the next breakpoint.
+    Continue,
+}
+
+/// Step of the program where the debugger stopped.
+#[derive(Clone, Debug)]
+pub struct Step<'a> {
+    call: &'a TrackedCall,
+    function: FunctionName,
+    variables: Option<Vec<(Identifier, Value)>>,
+}
+
+impl Step<'_> {
+    /// Access the call expression that is about to be evaluated.
+    pub fn call(&self) -> &TrackedCall {
+        self.call
+    }
+
+    /// Access the name of the function that contains the call expression.
+    pub fn function(&self) -> &FunctionName {
+        &self.function
+    }
+
+    /// Return the line of the call expression (starting at 1).
+    pub fn line(&self) -> usize {
+        self.call.span().start.line.get()
+    }
+
+    /// Access the variables that are in scope at the call expression, with their current values.
+    ///
+    /// Return `None` if the values could not be decoded.
+    pub fn variables(&self) -> Option<&[(Identifier, Value)]> {
+        self.variables.as_deref()
+    }
+}
+
+/// Internal state of where to stop next.
+#[derive(Clone, Debug)]
+enum Mode {
+    Step,
+    StepOver(FunctionName, usize),
+    StepOut(FunctionName),
+    Continue,
+}
+
+/// Tracker that stops the execution of a SimplicityHL program at steps
+/// and hands control to a front end.
+///
+/// The front end inspects the [`Step`], may edit the list of breakpoints,
+/// and returns the [`Command`] how to resume execution.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut debugger = Debugger::new(source, satisfied.debug_symbols(), |step, _breakpoints| {
+///     println!("{}:{}", step.line(), step.call().text());
+///     Command::Step
+/// })?;
+///
+/// let pruned = program.prune_with_tracker(&env, &mut debugger)?;
+/// ```
+pub struct Debugger<'a, F> {
+    debug_symbols: &'a DebugSymbols,
+    call_graph: CallGraph,
+    breakpoints: Vec<Breakpoint>,
+    mode: Mode,
+    previous: Option<(FunctionName, usize)>,
+    front_end: F,
+    offsets: FrameOffsets,
+    inner: SetTracker,
+}
+
+impl<'a, F> Debugger<'a, F>
+where
+    F: FnMut(&Step, &mut Vec<Breakpoint>) -> Command,
+{
+    /// Create a debugger for the program that is compiled from the given source
+    /// with the given debug symbols.
+    ///
+    /// The debugger stops at the first step.
+    ///
+    /// ## Errors
+    ///
+    /// The source is not a valid SimplicityHL program.
+    pub fn new(
+        source: &str,
+        debug_symbols: &'a DebugSymbols,
+        front_end: F,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            debug_symbols,
+            call_graph: CallGraph::new(source)?,
+            breakpoints: Vec::new(),
+            mode: Mode::Step,
+            previous: None,
+            front_end,
+            offsets: FrameOffsets::default(),
+            inner: SetTracker::default(),
+        })
+    }
+
+    /// Set the breakpoints of the debugger.
+    ///
+    /// If there are breakpoints, then the debugger no longer stops at the first step,
+    /// but it runs until the first breakpoint.
+    pub fn with_breakpoints(mut self, breakpoints: Vec<Breakpoint>) -> Self {
+        if !breakpoints.is_empty() {
+            self.mode = Mode::Continue;
+        }
+        self.breakpoints = breakpoints;
+        self
+    }
+
+    /// Access the current breakpoints.
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Check if execution stops at a step in the given function on the given line.
+    fn stops_at(&self, function: &FunctionName, line: usize) -> bool {
+        let by_mode = match &self.mode {
+            Mode::Step => true,
+            Mode::StepOver(current, current_line) => match current == function {
+                true => *current_line != line,
+                false => !self.call_graph.calls(current, function),
+            },
+            Mode::StepOut(current) => {
+                current != function && !self.call_graph.calls(current, function)
+            }
+            Mode::Continue => false,
+        };
+        let previous = self.previous.as_ref();
+        let by_breakpoint = self.breakpoints.iter().any(|breakpoint| match breakpoint {
+            Breakpoint::Line(n) => *n == line && previous != Some(&(function.clone(), line)),
+            Breakpoint::Function(name) => {
+                name == function && !matches!(previous, Some((previous, _)) if previous == function)
+            }
+        });
+        by_mode || by_breakpoint
+    }
+
+    fn handle_call(
+        &mut self,
+        node: &RedeemNode<Elements>,
+        input: &FrameIter,
+        read: usize,
+        call: &'a TrackedCall,
+    ) {
+        let function = self.call_graph.function_of(call.span());
+        let line = call.span().start.line.get();
+        let stops = self.stops_at(&function, line);
+        self.previous = Some((function.clone(), line));
+        if !stops {
+            return;
+        }
+
+        // The input of the call is the variable environment at the call expression
+        let variables = read_value(input, read, &node.arrow().source)
+            .and_then(|environment| call.decode_variables(&environment));
+        let step = Step {
+            call,
+            function,
+            variables,
+        };
+        let command = (self.front_end)(&step, &mut self.breakpoints);
+        self.mode = match command {
+            Command::Step => Mode::Step,
+            Command::StepOver => Mode::StepOver(step.function, line),
+            Command::StepOut => Mode::StepOut(step.function),
+            Command::Continue => Mode::Continue,
+        };
+    }
+}
+
+/// Static call graph of the functions of a SimplicityHL program.
+///
+/// Custom functions are inlined by the compiler,
+/// so the call graph relates call expressions at runtime back to the functions that contain them.
+#[derive(Clone, Debug)]
+pub(crate) struct CallGraph {
+    functions: Vec<(FunctionName, Span)>,
+    call_sites: HashMap<FunctionName, Vec<(Span, FunctionName)>>,
+    callees: HashMap<FunctionName, HashSet<FunctionName>>,
+}
+
+impl CallGraph {
+    /// Create the call graph of the given source.
+    ///
+    /// ## Errors
+    ///
+    /// The source is not a valid SimplicityHL program.
+    pub(crate) fn new(source: &str) -> Result<Self, String> {
+        let program = parse::Program::parse_from_str(source)?;
+        let mut functions = Vec::new();
+        let mut call_sites = HashMap::new();
+        let mut direct_callees = HashMap::new();
+        for item in program.items() {
+            let parse::Item::Function(function) = item else {
+                continue;
+            };
+            functions.push((function.name().clone(), *function.span()));
+            let sites = parse::ExprTree::Expression(function.body())
+                .pre_order_iter()
+                .filter_map(|node| match node {
+                    parse::ExprTree::Call(call) => match call.name() {
+                        CallName::Custom(name)
+                        | CallName::Fold(name, _)
+                        | CallName::ArrayFold(name, _)
+                        | CallName::ForWhile(name) => Some((*call.span(), name.clone())),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect::<Vec<(Span, FunctionName)>>();
+            let callees = sites.iter().map(|(_, name)| name.clone()).collect();
+            direct_callees.insert(function.name().clone(), callees);
+            call_sites.insert(function.name().clone(), sites);
+        }
+
+        let callees = direct_callees
+            .keys()
+            .map(|name| (name.clone(), transitive_callees(name, &direct_callees)))
+            .collect();
+
+        Ok(Self {
+            functions,
+            call_sites,
+            callees,
+        })
+    }
+
+    /// Return the name of the function whose body contains the given span.
+    pub(crate) fn function_of(&self, span: &Span) -> FunctionName {
+        self.functions
+            .iter()
+            .find(|(_, function_span)| contains(function_span, span))
+            .map_or_else(FunctionName::main, |(name, _)| name.clone())
+    }
+
+    /// Check if `caller` (transitively) calls `callee`.
+    pub(crate) fn calls(&self, caller: &FunctionName, callee: &FunctionName) -> bool {
+        self.callees
+            .get(caller)
+            .is_some_and(|callees| callees.contains(callee))
+    }
+
+    /// Return a shortest chain of calls from `caller` to `callee`,
+    /// excluding `caller` and including `callee`.
+    ///
+    /// If `within` is given, then the chain starts at a call of `caller` inside this span.
+    /// Chains are explored in order of the calls in the source file.
+    ///
+    /// Return only `callee` if there is no such chain,
+    /// and return nothing if both are the same function.
+    pub(crate) fn call_chain(
+        &self,
+        caller: &FunctionName,
+        within: Option<&Span>,
+        callee: &FunctionName,
+    ) -> Vec<FunctionName> {
+        if caller == callee {
+            return Vec::new();
+        }
+        let mut previous = HashMap::<&FunctionName, &FunctionName>::new();
+        let mut queue = VecDeque::from([caller]);
+        while let Some(function) = queue.pop_front() {
+            if function == callee {
+                let mut chain = vec![callee.clone()];
+                let mut current = callee;
+                while let Some(&next) = previous.get(current) {
+                    if next == caller {
+                        break;
+                    }
+                    chain.push(next.clone());
+                    current = next;
+                }
+                chain.reverse();
+                return chain;
+            }
+            for (span, next) in self.call_sites.get(function).into_iter().flatten() {
+                let inside = function != caller || within.map_or(true, |w| contains(w, span));
+                if inside && next != caller && !previous.contains_key(next) {
+                    previous.insert(next, function);
+                    queue.push_back(next);
+                }
+            }
+        }
+        vec![callee.clone()]
+    }
+}
+
+/// Return the functions that the given function transitively calls.
+fn transitive_callees(
+    name: &FunctionName,
+    direct_callees: &HashMap<FunctionName, HashSet<FunctionName>>,
+) -> HashSet<FunctionName> {
+    let mut visited = HashSet::new();
+    let mut stack = vec![name];
+    while let Some(caller) = stack.pop() {
+        for callee in direct_callees.get(caller).into_iter().flatten() {
+            if visited.insert(callee.clone()) {
+                stack.push(callee);
+            }
+        }
+    }
+    visited
+}
+
+/// Check if the `outer` span contains the `inner` span.
+fn contains(outer: &Span, inner: &Span) -> bool {
+    let start = |span: &Span| (span.start.line, span.start.col);
+    let end = |span: &Span| (span.end.line, span.end.col);
+    start(outer) <= start(inner) && end(inner) <= end(outer)
+}
+
+impl<F> PruneTracker<Elements> for Debugger<'_, F>
+where
+    F: FnMut(&Step, &mut Vec<Breakpoint>) -> Command,
+{
+    fn contains_left(&self, ihr: Ihr) -> bool {
+        PruneTracker::<Elements>::contains_left(&self.inner, ihr)
+    }
+
+    fn contains_right(&self, ihr: Ihr) -> bool {
+        PruneTracker::<Elements>::contains_right(&self.inner, ihr)
+    }
+}
+
+impl<F> ExecTracker<Elements> for Debugger<'_, F>
+where
+    F: FnMut(&Step, &mut Vec<Breakpoint>) -> Command,
+{
+    fn visit_node(&mut self, node: &RedeemNode<Elements>, input: FrameIter, output: NodeOutput) {
+        let (read, _) = self.offsets.visit(node, &input);
+        // Call expressions with debug symbols are compiled to `comp (pair false args) (assertl body cmr)`
+        if let Inner::Comp(_, right) = node.inner() {
+            if let Inner::AssertL(_, cmr) = right.inner() {
+                if let Some(call) = self.debug_symbols.get(cmr) {
+                    self.handle_call(node, &input, read, call);
+                }
+            }
+        }
+
+        self.inner.visit_node(node, input, output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dummy_env;
+    use crate::{Arguments, TemplateProgram, WitnessValues};
+
+    const PROGRAM: &str = r#"fn add(a: u32, b: u32) -> u32 {
+    let (_, sum): (bool, u32) = jet::add_32(a, b);
+    sum
+}
+
+fn main() {
+    let (_, x): (bool, u32) = jet::add_32(0, 1);
+    let y: u32 = add(x, 2);
+    assert!(jet::eq_32(y, 3));
+}"#;
+
+    /// Function, line and variables of a step where the debugger stopped.
+    type Stop = (String, usize, Vec<(String, String)>);
+
+    /// Run the program in the debugger with the given breakpoints
+    /// and return the steps where the debugger stopped.
+    fn debug<F>(breakpoints: Vec<Breakpoint>, mut commands: F) -> Vec<Stop>
+    where
+        F: FnMut(&Step) -> Command,
+    {
+        let template = TemplateProgram::new(PROGRAM).unwrap();
+        let compiled = template.instantiate(Arguments::default(), true).unwrap();
+        let satisfied = compiled.satisfy(WitnessValues::default()).unwrap();
+        let mut stops = Vec::new();
+        let mut debugger = Debugger::new(PROGRAM, satisfied.debug_symbols(), |step, _| {
+            let variables = step
+                .variables()
+                .unwrap()
+                .iter()
+                .map(|(x, value)| (x.to_string(), value.to_string()))
+                .collect();
+            stops.push((step.function().to_string(), step.line(), variables));
+            commands(step)
+        })
+        .unwrap()
+        .with_breakpoints(breakpoints);
+        satisfied
+            .redeem()
+            .prune_with_tracker(&dummy_env::dummy(), &mut debugger)
+            .unwrap();
+        drop(debugger);
+        stops
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(x, value)| (x.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn step_into_functions() {
+        let stops = debug(vec![], |_| Command::Step);
+        let expected = vec![
+            ("main".to_string(), 7, vars(&[])),
+            ("add".to_string(), 2, vars(&[("a", "1"), ("b", "2")])),
+            ("main".to_string(), 9, vars(&[("y", "3"), ("x", "1")])),
+            ("main".to_string(), 9, vars(&[("y", "3"), ("x", "1")])),
+        ];
+        assert_eq!(expected, stops);
+    }
+
+    #[test]
+    fn step_over_and_out() {
+        let stops = debug(vec![], |_| Command::StepOver);
+        let lines: Vec<usize> = stops.iter().map(|(_, line, _)| *line).collect();
+        assert_eq!(vec![7, 9], lines);
+
+        let stops = debug(
+            vec![Breakpoint::Function(FunctionName::from_str_unchecked(
+                "add",
+            ))],
+            |_| Command::StepOut,
+        );
+        let locations: Vec<(&str, usize)> = stops
+            .iter()
+            .map(|(function, line, _)| (function.as_str(), *line))
+            .collect();
+        assert_eq!(vec![("add", 2), ("main", 9)], locations);
+    }
+
+    #[test]
+    fn variables_in_match_arm() {
+        let program = r#"fn main() {
+    let x: u8 = 5;
+    match Some(x) {
+        Some(y: u8) => assert!(jet::eq_8(y, x)),
+        None => panic!(),
+    };
+}"#;
+        let compiled = TemplateProgram::new(program)
+            .unwrap()
+            .instantiate(Arguments::default(), true)
+            .unwrap();
+        let satisfied = compiled.satisfy(WitnessValues::default()).unwrap();
+        let mut variables = Vec::new();
+        let mut debugger = Debugger::new(program, satisfied.debug_symbols(), |step, _| {
+            variables.push(
+                step.variables()
+                    .unwrap()
+                    .iter()
+                    .map(|(x, value)| format!("{x} = {value}"))
+                    .collect::<Vec<String>>(),
+            );
+            Command::Step
+        })
+        .unwrap();
+        satisfied
+            .redeem()
+            .prune_with_tracker(&dummy_env::dummy(), &mut debugger)
+            .unwrap();
+        drop(debugger);
+        // Match arm, assertion and jet
+        let expected = vec!["y = 5".to_string(), "x = 5".to_string()];
+        assert_eq!(
+            vec![expected.clone(), expected.clone(), expected],
+            variables
+        );
+    }
+
+    #[test]
+    fn breakpoints() {
+        let stops = debug(vec![Breakpoint::Line(9)], |_| Command::Continue);
+        let lines: Vec<usize> = stops.iter().map(|(_, line, _)| *line).collect();
+        assert_eq!(vec![9], lines);
+
+        let stops = debug(vec![Breakpoint::Line(3)], |_| Command::Continue);
+        assert!(stops.is_empty());
+    }
+}
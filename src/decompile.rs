@@ -0,0 +1,1483 @@
+//! Decompiler from Simplicity target code back to SimplicityHL.
+//!
+//! The decompiler evaluates the program symbolically and recognizes the shapes that
+//! the SimplicityHL compiler emits:
+//!
+//! - The output of `s` in `comp s t` is bound by `let` statements before `t` is applied.
+//!   In particular, `comp (pair s iden) t` becomes a `let` statement that adds a variable
+//!   to the environment, which is how the compiler translates `let` statements.
+//! - `case` becomes a `match` expression.
+//! - `assertl` and `assertr` become `unwrap_left`, `unwrap_right`, `unwrap` or `assert!`.
+//! - Jets become jet calls.
+//! - Loop skeletons become `for_while`, `array_fold` and `fold` calls.
+//! - Large expressions that are shared inside the program become functions.
+//!
+//! The output is best effort. Names of variables, functions and witnesses are lost during
+//! compilation, so fresh names are generated. Types are reconstructed from the structure of
+//! Simplicity types, so type aliases, arrays and lists show up as tuples.
+//! Straight-line programs of `let` statements and jet calls compile back to the same CMR.
+//!
+//! Fragments that the decompiler does not recognize are written in inline combinator syntax
+//! `simplicity!("combinators", input)`, which is not valid SimplicityHL.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use simplicity::dag::{DagLike, MaxSharing};
+use simplicity::jet::Elements;
+use simplicity::node::{Inner, Marker};
+use simplicity::types::arrow::FinalArrow;
+use simplicity::types::Final;
+use simplicity::{BitIter, Cmr, CommitNode, RedeemNode};
+
+use crate::debug::DebugSymbols;
+use crate::disassemble::Encoding;
+use crate::num::{NonZeroPow2Usize, Pow2Usize};
+use crate::types::{AliasedType, ResolvedType, TypeConstructible, TypeDeconstructible, UIntType};
+use crate::value::{StructuralValue, Value};
+
+/// Shared expressions with at least this many combinators become functions.
+const FUNCTION_SIZE: usize = 32;
+
+/// Maximum number of steps to take when looking for the body of a loop.
+const MAX_LOOP_DEPTH: usize = 64;
+
+/// Decompiled Simplicity program.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Decompilation {
+    cmr: Cmr,
+    source: String,
+}
+
+impl Decompilation {
+    /// Decode and decompile the given serialized `program`.
+    ///
+    /// If the serialized `witness` data is given, then the values of witness nodes are listed.
+    /// The `debug_symbols` of the SimplicityHL program are used to annotate the output.
+    ///
+    /// The program and witness data are both in the given text `encoding`.
+    ///
+    /// ## Errors
+    ///
+    /// - The strings are not in the given encoding.
+    /// - The program or witness data fail to decode.
+    pub fn decode(
+        program: &str,
+        witness: Option<&str>,
+        encoding: Encoding,
+        debug_symbols: Option<&DebugSymbols>,
+    ) -> Result<Self, String> {
+        let program_bytes = encoding.decode(program)?;
+        let program_bits = BitIter::new(program_bytes.iter().copied());
+        match witness {
+            Some(witness) => {
+                let witness_bytes = encoding.decode(witness)?;
+                let witness_bits = BitIter::new(witness_bytes.iter().copied());
+                let redeem = RedeemNode::<Elements>::decode(program_bits, witness_bits)
+                    .map_err(|e| e.to_string())?;
+                Ok(Self::from_redeem(&redeem, debug_symbols))
+            }
+            None => {
+                let commit =
+                    CommitNode::<Elements>::decode(program_bits).map_err(|e| e.to_string())?;
+                Ok(Self::from_commit(&commit, debug_symbols))
+            }
+        }
+    }
+
+    /// Decompile the given program without witness data.
+    pub fn from_commit(
+        program: &CommitNode<Elements>,
+        debug_symbols: Option<&DebugSymbols>,
+    ) -> Self {
+        let (nodes, witnesses) = arena(program, |node| node.arrow().shallow_clone(), |_| None);
+        Self {
+            cmr: program.cmr(),
+            source: Decompiler::new(&nodes, &witnesses, debug_symbols).run(program.cmr()),
+        }
+    }
+
+    /// Decompile the given program with witness data.
+    pub fn from_redeem(
+        program: &RedeemNode<Elements>,
+        debug_symbols: Option<&DebugSymbols>,
+    ) -> Self {
+        let (nodes, witnesses) = arena(
+            program,
+            |node| node.arrow().shallow_clone(),
+            |value| Some(value.shallow_clone()),
+        );
+        Self {
+            cmr: program.cmr(),
+            source: Decompiler::new(&nodes, &witnesses, debug_symbols).run(program.cmr()),
+        }
+    }
+
+    /// Access the CMR of the program.
+    pub fn cmr(&self) -> Cmr {
+        self.cmr
+    }
+
+    /// Access the reconstructed SimplicityHL source code.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
+
+impl fmt::Display for Decompilation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Combinator of a node, with references to the children by index.
+#[derive(Clone, Debug)]
+enum Combinator {
+    Iden,
+    Unit,
+    InjL(usize),
+    InjR(usize),
+    Take(usize),
+    Drop(usize),
+    Comp(usize, usize),
+    Case(usize, usize),
+    AssertL(usize, Cmr),
+    AssertR(Cmr, usize),
+    Pair(usize, usize),
+    Disconnect(usize, usize),
+    Witness(usize),
+    Fail,
+    Jet(Elements),
+    Word(simplicity::Value),
+}
+
+/// Node of the program, shared maximally.
+#[derive(Clone, Debug)]
+struct Node {
+    combinator: Combinator,
+    source: Arc<Final>,
+    target: Arc<Final>,
+    cmr: Cmr,
+    references: usize,
+    /// Number of combinators when the node is expanded into a tree.
+    size: usize,
+}
+
+/// Witness node of the program.
+#[derive(Clone, Debug)]
+struct Witness {
+    ty: Arc<Final>,
+    value: Option<simplicity::Value>,
+}
+
+/// List the nodes of the program in post order, so the root comes last.
+fn arena<N, A, W>(
+    program: &simplicity::node::Node<N>,
+    arrow: A,
+    witness: W,
+) -> (Vec<Node>, Vec<Witness>)
+where
+    N: Marker<Jet = Elements>,
+    A: Fn(&simplicity::node::Node<N>) -> FinalArrow,
+    W: Fn(&N::Witness) -> Option<simplicity::Value>,
+{
+    let mut nodes: Vec<Node> = vec![];
+    let mut witnesses = vec![];
+    for data in program.post_order_iter::<MaxSharing<N>>() {
+        let l = data.left_index.unwrap_or_default();
+        let r = data.right_index.unwrap_or_default();
+        let arrow = arrow(data.node);
+        let combinator = match data.node.inner() {
+            Inner::Iden => Combinator::Iden,
+            Inner::Unit => Combinator::Unit,
+            Inner::InjL(_) => Combinator::InjL(l),
+            Inner::InjR(_) => Combinator::InjR(l),
+            Inner::Take(_) => Combinator::Take(l),
+            Inner::Drop(_) => Combinator::Drop(l),
+            Inner::Comp(..) => Combinator::Comp(l, r),
+            Inner::Case(..) => Combinator::Case(l, r),
+            Inner::AssertL(_, cmr) => Combinator::AssertL(l, *cmr),
+            Inner::AssertR(cmr, _) => Combinator::AssertR(*cmr, l),
+            Inner::Pair(..) => Combinator::Pair(l, r),
+            Inner::Disconnect(..) => Combinator::Disconnect(l, r),
+            Inner::Witness(value) => {
+                witnesses.push(Witness {
+                    ty: arrow.target.clone(),
+                    value: witness(value),
+                });
+                Combinator::Witness(witnesses.len() - 1)
+            }
+            Inner::Fail(_) => Combinator::Fail,
+            Inner::Jet(jet) => Combinator::Jet(*jet),
+            Inner::Word(word) => Combinator::Word(word.as_value().shallow_clone()),
+        };
+        let mut size = 1usize;
+        for child in [data.left_index, data.right_index].into_iter().flatten() {
+            nodes[child].references += 1;
+            size = size.saturating_add(nodes[child].size);
+        }
+        nodes.push(Node {
+            combinator,
+            source: arrow.source,
+            target: arrow.target,
+            cmr: data.node.cmr(),
+            references: 0,
+            size,
+        });
+    }
+    (nodes, witnesses)
+}
+
+/// Symbolic value that flows through the program.
+///
+/// Variables and expressions may have a declared type, such as the output type of a jet.
+/// Otherwise, their type is reconstructed from the Simplicity type.
+#[derive(Clone, Debug)]
+enum Sym {
+    Unit,
+    Var(String, Option<AliasedType>),
+    /// Expression in SimplicityHL syntax.
+    ///
+    /// Atomic expressions can be duplicated without evaluating anything twice.
+    Expr(String, bool, Option<AliasedType>),
+    Const(simplicity::Value),
+    Pair(Box<Sym>, Box<Sym>),
+    Left(Box<Sym>),
+    Right(Box<Sym>),
+}
+
+impl Sym {
+    fn pair(left: Self, right: Self) -> Self {
+        Self::Pair(Box::new(left), Box::new(right))
+    }
+
+    fn var(name: String) -> Self {
+        Self::Var(name, None)
+    }
+
+    fn expr<S: Into<String>>(text: S) -> Self {
+        Self::Expr(text.into(), false, None)
+    }
+
+    /// Access the declared type of the value, if any.
+    fn declared(&self) -> Option<&AliasedType> {
+        match self {
+            Sym::Var(_, ty) | Sym::Expr(_, _, ty) => ty.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn is_atomic(&self) -> bool {
+        match self {
+            Sym::Unit | Sym::Var(..) | Sym::Const(_) => true,
+            Sym::Expr(_, atomic, _) => *atomic,
+            Sym::Pair(left, right) => left.is_atomic() && right.is_atomic(),
+            Sym::Left(inner) | Sym::Right(inner) => inner.is_atomic(),
+        }
+    }
+
+    /// Return the value of type `ty`, if it is fully known.
+    fn to_const(&self, ty: &Final) -> Option<simplicity::Value> {
+        match self {
+            Sym::Unit => Some(simplicity::Value::unit()),
+            Sym::Const(value) => Some(value.shallow_clone()),
+            Sym::Pair(left, right) => {
+                let (ty_l, ty_r) = ty.as_product()?;
+                Some(simplicity::Value::product(
+                    left.to_const(ty_l)?,
+                    right.to_const(ty_r)?,
+                ))
+            }
+            Sym::Left(inner) => {
+                let (ty_l, ty_r) = ty.as_sum()?;
+                Some(simplicity::Value::left(inner.to_const(ty_l)?, ty_r.clone()))
+            }
+            Sym::Right(inner) => {
+                let (ty_l, ty_r) = ty.as_sum()?;
+                Some(simplicity::Value::right(
+                    ty_l.clone(),
+                    inner.to_const(ty_r)?,
+                ))
+            }
+            Sym::Var(..) | Sym::Expr(..) => None,
+        }
+    }
+
+    /// Return the branch of a value of sum type, if it is known.
+    ///
+    /// The branch is `true` for right values.
+    fn branch(&self) -> Option<(bool, Sym)> {
+        match self {
+            Sym::Left(inner) => Some((false, inner.as_ref().clone())),
+            Sym::Right(inner) => Some((true, inner.as_ref().clone())),
+            Sym::Const(value) => match (value.as_left(), value.as_right()) {
+                (Some(inner), _) => Some((false, Sym::Const(inner.to_value()))),
+                (_, Some(inner)) => Some((true, Sym::Const(inner.to_value()))),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Statements of a block expression.
+#[derive(Clone, Debug, Default)]
+struct Block {
+    statements: Vec<String>,
+    /// Variables that have already been destructured into tuples, by their expression.
+    splits: HashMap<String, (Sym, Sym)>,
+}
+
+impl Block {
+    /// Create an empty block that can use the destructured variables of `self`.
+    fn nested(&self) -> Self {
+        Self {
+            statements: vec![],
+            splits: self.splits.clone(),
+        }
+    }
+
+    /// Render the block with its final expression.
+    fn render(&self, result: Option<String>) -> String {
+        let mut text = "{\n".to_string();
+        for line in self.statements.iter().chain(result.as_ref()) {
+            text.push_str("    ");
+            text.push_str(&indent(line));
+            text.push('\n');
+        }
+        text.push('}');
+        text
+    }
+}
+
+fn indent(text: &str) -> String {
+    text.replace('\n', "\n    ")
+}
+
+/// Role of a function in the decompiled program.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+enum Role {
+    /// Shared expression that is called directly.
+    Plain,
+    /// Body of `array_fold` or `fold`.
+    Fold,
+    /// Body of `for_while`.
+    ForWhile,
+}
+
+/// Loop skeleton that was recognized in the program.
+#[derive(Copy, Clone, Debug)]
+enum Loop {
+    ForWhile { f: usize },
+    ArrayFold { f: usize, size: usize },
+    ListFold { f: usize, bound: NonZeroPow2Usize },
+}
+
+struct Decompiler<'a> {
+    nodes: &'a [Node],
+    witnesses: &'a [Witness],
+    debug_symbols: Option<&'a DebugSymbols>,
+    next_variable: usize,
+    functions: Vec<String>,
+    function_names: HashMap<(usize, Role), String>,
+    loops: HashMap<usize, Option<Loop>>,
+}
+
+impl<'a> Decompiler<'a> {
+    fn new(
+        nodes: &'a [Node],
+        witnesses: &'a [Witness],
+        debug_symbols: Option<&'a DebugSymbols>,
+    ) -> Self {
+        Self {
+            nodes,
+            witnesses,
+            debug_symbols,
+            next_variable: 0,
+            functions: vec![],
+            function_names: HashMap::new(),
+            loops: HashMap::new(),
+        }
+    }
+
+    fn run(mut self, cmr: Cmr) -> String {
+        let root = self.nodes.len() - 1;
+        let mut main = Block::default();
+        let output = self.eval(root, Sym::Unit, &mut main);
+        let result = Self::result(&output, &self.nodes[root].target);
+
+        let mut text = format!("// cmr {cmr}\n");
+        for (index, witness) in self.witnesses.iter().enumerate() {
+            let ty = type_of(&witness.ty);
+            match &witness.value {
+                Some(value) => {
+                    let value = render_const(value, &witness.ty);
+                    text.push_str(&format!("// witness::W{index}: {ty} = {value}\n"));
+                }
+                None => text.push_str(&format!("// witness::W{index}: {ty}\n")),
+            }
+        }
+        for function in &self.functions {
+            text.push('\n');
+            text.push_str(function);
+            text.push('\n');
+        }
+        text.push_str(&format!("\nfn main() {}\n", main.render(result)));
+        text
+    }
+
+    fn fresh(&mut self) -> String {
+        let name = format!("v{}", self.next_variable);
+        self.next_variable += 1;
+        name
+    }
+
+    /// Evaluate node `index` on the `input` value.
+    ///
+    /// Statements that the output depends on are appended to `block`.
+    fn eval(&mut self, index: usize, input: Sym, block: &mut Block) -> Sym {
+        if let Some(skeleton) = self.recognize(index) {
+            return self.eval_loop(index, skeleton, input, block);
+        }
+        let node = &self.nodes[index];
+        if 1 < node.references && FUNCTION_SIZE <= node.size {
+            let source = node.source.clone();
+            let name = self.function(index, Role::Plain);
+            let args = self.arguments(input, &source, block);
+            return Sym::expr(format!("{name}({args})"));
+        }
+        self.eval_inner(index, input, block)
+    }
+
+    fn eval_inner(&mut self, index: usize, input: Sym, block: &mut Block) -> Sym {
+        let node = &self.nodes[index];
+        let source = node.source.clone();
+        match node.combinator.clone() {
+            Combinator::Iden => input,
+            Combinator::Unit => Sym::Unit,
+            Combinator::InjL(c) => Sym::Left(Box::new(self.eval(c, input, block))),
+            Combinator::InjR(c) => Sym::Right(Box::new(self.eval(c, input, block))),
+            Combinator::Take(c) => {
+                let (left, _) = self.split(input, &source, block);
+                self.eval(c, left, block)
+            }
+            Combinator::Drop(c) => {
+                let (_, right) = self.split(input, &source, block);
+                self.eval(c, right, block)
+            }
+            Combinator::Comp(s, t) => {
+                let middle = self.nodes[s].target.clone();
+                if let Some(value) = self.let_value(s, t) {
+                    // `let` statements extend the environment by a variable
+                    let ty = self.nodes[value].target.clone();
+                    let output = self.eval(value, input.clone(), block);
+                    let output = self.bind_variable(output, &ty, block);
+                    return self.eval(t, Sym::pair(output, input), block);
+                }
+                let output = self.eval(s, input, block);
+                // Jets consume their arguments exactly once, from left to right
+                let output = match self.nodes[t].combinator {
+                    Combinator::Jet(_) => output,
+                    _ => self.bind(output, &middle, block),
+                };
+                self.eval(t, output, block)
+            }
+            Combinator::Pair(s, t) => {
+                // Bind the left expression first, so statements appear in evaluation order
+                let left = self.eval(s, input.clone(), block);
+                let left = match left.is_atomic() {
+                    true => left,
+                    false => self.bind(left, &self.nodes[s].target.clone(), block),
+                };
+                let right = self.eval(t, input, block);
+                match (left, right) {
+                    (Sym::Const(left), Sym::Const(right)) => {
+                        Sym::Const(simplicity::Value::product(left, right))
+                    }
+                    (left, right) => Sym::pair(left, right),
+                }
+            }
+            Combinator::Case(l, r) => self.eval_case(index, l, r, input, block),
+            Combinator::AssertL(c, cmr) => self.eval_assert(index, false, c, cmr, input, block),
+            Combinator::AssertR(cmr, c) => self.eval_assert(index, true, c, cmr, input, block),
+            // Witnesses are not atomic because each witness may be used only once
+            Combinator::Witness(i) => Sym::expr(format!("witness::W{i}")),
+            Combinator::Fail => Sym::expr("panic!()"),
+            Combinator::Jet(jet) => {
+                let params = crate::jet::source_type(jet);
+                let args = self.unfold(input, &source, params.len(), block);
+                let args = args
+                    .iter()
+                    .zip(&params)
+                    .map(|((arg, ty), param)| match param.resolve_builtin() {
+                        Ok(param) => render_as(arg, ty, &param),
+                        Err(_) => render(arg, ty),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                match jet {
+                    Elements::Verify => Sym::expr(format!("assert!({args})")),
+                    _ => Sym::Expr(
+                        format!("jet::{jet}({args})"),
+                        false,
+                        Some(crate::jet::target_type(jet)),
+                    ),
+                }
+            }
+            Combinator::Word(value) => Sym::Const(value),
+            Combinator::Disconnect(..) => {
+                let input = render(&input, &source);
+                Sym::expr(format!(
+                    "simplicity!(\"{}\", {input})",
+                    self.combinators(index)
+                ))
+            }
+        }
+    }
+
+    fn eval_case(
+        &mut self,
+        index: usize,
+        l: usize,
+        r: usize,
+        input: Sym,
+        block: &mut Block,
+    ) -> Sym {
+        let node = &self.nodes[index];
+        let (source, target) = (node.source.clone(), node.target.clone());
+        let (sum, env) = self.split(input, &source, block);
+        if let Some((is_right, inner)) = sum.branch() {
+            let child = if is_right { r } else { l };
+            return self.eval(child, Sym::pair(inner, env), block);
+        }
+
+        let sum_ty = source.as_product().expect("case input is a product").0;
+        let (ty_l, ty_r) = sum_ty.as_sum().expect("case input is a sum");
+        let scrutinee = render_as(&sum, sum_ty, &type_of(sum_ty));
+        let mut arms = vec![];
+        for (child, is_right) in [(l, false), (r, true)] {
+            let ty = if is_right { ty_r } else { ty_l };
+            let (pattern, bound) = match (ty_l.is_unit() && ty_r.is_unit(), ty_l.is_unit()) {
+                (true, _) => (is_right.to_string(), Sym::Unit),
+                (false, true) if !is_right => ("None".to_string(), Sym::Unit),
+                (false, true) => {
+                    let name = self.fresh();
+                    (format!("Some({name}: {})", type_of(ty)), Sym::var(name))
+                }
+                (false, false) => {
+                    let name = self.fresh();
+                    let constructor = if is_right { "Right" } else { "Left" };
+                    (
+                        format!("{constructor}({name}: {})", type_of(ty)),
+                        Sym::var(name),
+                    )
+                }
+            };
+            let mut arm = block.nested();
+            let output = self.eval(child, Sym::pair(bound, env.clone()), &mut arm);
+            let result = Self::result(&output, &target);
+            let body = match arm.statements.is_empty() {
+                true => result.unwrap_or_else(|| "()".to_string()),
+                false => arm.render(result),
+            };
+            arms.push(format!("{pattern} => {body},"));
+        }
+        Sym::expr(format!(
+            "match {scrutinee} {{\n    {}\n    {}\n}}",
+            indent(&arms[0]),
+            indent(&arms[1])
+        ))
+    }
+
+    fn eval_assert(
+        &mut self,
+        index: usize,
+        is_right: bool,
+        child: usize,
+        cmr: Cmr,
+        input: Sym,
+        block: &mut Block,
+    ) -> Sym {
+        let source = self.nodes[index].source.clone();
+        let (sum, env) = self.split(input, &source, block);
+        let inner = match sum.branch() {
+            Some((branch, inner)) if branch == is_right => {
+                // Debug symbols are attached to assertions that never fail
+                if let Some(call) = self.debug_symbols.and_then(|symbols| symbols.get(&cmr)) {
+                    let span = call.span();
+                    let text = call.text().lines().next().unwrap_or_default();
+                    block
+                        .statements
+                        .push(format!("// {}:{} {text}", span.start.line, span.start.col));
+                }
+                inner
+            }
+            Some(_) => return Sym::expr("panic!()"),
+            None => {
+                let sum_ty = source.as_product().expect("assertion input is a product").0;
+                let (ty_l, ty_r) = sum_ty.as_sum().expect("assertion input is a sum");
+                let scrutinee = render_as(&sum, sum_ty, &type_of(sum_ty));
+                let inner_ty = if is_right { ty_r } else { ty_l };
+                let expr = match (ty_l.is_unit(), ty_r.is_unit(), is_right) {
+                    (true, true, false) => {
+                        format!("assert!(match {scrutinee} {{ false => true, true => false, }})")
+                    }
+                    (true, true, true) => format!("assert!({scrutinee})"),
+                    (true, false, false) => {
+                        format!("assert!(is_none::<{}>({scrutinee}))", type_of(ty_r))
+                    }
+                    (true, false, true) => format!("unwrap({scrutinee})"),
+                    (false, _, false) => format!("unwrap_left::<{}>({scrutinee})", type_of(ty_r)),
+                    (false, _, true) => format!("unwrap_right::<{}>({scrutinee})", type_of(ty_l)),
+                };
+                match inner_ty.is_unit() {
+                    true => {
+                        block.statements.push(format!("{expr};"));
+                        Sym::Unit
+                    }
+                    false => self.bind(Sym::expr(expr), inner_ty, block),
+                }
+            }
+        };
+        self.eval(child, Sym::pair(inner, env), block)
+    }
+
+    fn eval_loop(&mut self, index: usize, skeleton: Loop, input: Sym, block: &mut Block) -> Sym {
+        let source = self.nodes[index].source.clone();
+        let (first, second) = self.split(input, &source, block);
+        let (ty_first, ty_second) = source.as_product().expect("loop input is a product");
+        // Folds take an array or list, which cannot be reconstructed from the Simplicity type
+        let expected_first = match skeleton {
+            Loop::ForWhile { .. } => type_of(ty_first),
+            Loop::ArrayFold { f, size } => {
+                let element = self.element_type(f);
+                ResolvedType::array(element, size)
+            }
+            Loop::ListFold { f, bound } => {
+                let element = self.element_type(f);
+                ResolvedType::list(element, bound)
+            }
+        };
+        let args = format!(
+            "{}, {}",
+            render_as(&first, ty_first, &expected_first),
+            render_as(&second, ty_second, &type_of(ty_second))
+        );
+        match skeleton {
+            Loop::ForWhile { f } => {
+                let name = self.function(f, Role::ForWhile);
+                Sym::expr(format!("for_while::<{name}>({args})"))
+            }
+            Loop::ArrayFold { f, size } => {
+                let name = self.function(f, Role::Fold);
+                Sym::expr(format!("array_fold::<{name}, {size}>({args})"))
+            }
+            Loop::ListFold { f, bound } => {
+                let name = self.function(f, Role::Fold);
+                Sym::expr(format!("fold::<{name}, {bound}>({args})"))
+            }
+        }
+    }
+
+    /// Return the element type of the fold with body `f`.
+    fn element_type(&self, f: usize) -> ResolvedType {
+        let (element, _) = self.nodes[f]
+            .source
+            .as_product()
+            .expect("fold body takes an element and an accumulator");
+        type_of(element)
+    }
+
+    /// Return the name of the function that computes node `index`.
+    ///
+    /// The function is defined if it does not exist yet.
+    fn function(&mut self, index: usize, role: Role) -> String {
+        if let Some(name) = self.function_names.get(&(index, role)) {
+            return name.clone();
+        }
+        let name = format!("f{}", self.function_names.len());
+        self.function_names.insert((index, role), name.clone());
+
+        let node = &self.nodes[index];
+        let (source, target) = (node.source.clone(), node.target.clone());
+        let mut params = vec![];
+        let mut param = |ty: &Arc<Final>| {
+            let name = self.fresh();
+            params.push(format!("{name}: {}", type_of(ty)));
+            Sym::var(name)
+        };
+        let input = match (role, source.as_product()) {
+            (Role::ForWhile, Some((acc, context_counter))) => {
+                let (context, counter) = context_counter
+                    .as_product()
+                    .expect("loop body takes a context and a counter");
+                let acc = param(acc);
+                let context = param(context);
+                let counter = param(counter);
+                Sym::pair(acc, Sym::pair(context, counter))
+            }
+            (_, Some((left, right))) => {
+                let left = param(left);
+                let right = param(right);
+                Sym::pair(left, right)
+            }
+            (_, None) if source.is_unit() => Sym::Unit,
+            (_, None) => param(&source),
+        };
+
+        let mut body = Block::default();
+        let output = self.eval_inner(index, input, &mut body);
+        let result = Self::result(&output, &target);
+        let output_ty = match target.is_unit() {
+            true => String::new(),
+            false => format!(" -> {}", type_of(&target)),
+        };
+        self.functions.push(format!(
+            "fn {name}({}){output_ty} {}",
+            params.join(", "),
+            body.render(result)
+        ));
+        name
+    }
+
+    /// Render the arguments of a call to a function of [`Role::Plain`].
+    fn arguments(&mut self, input: Sym, ty: &Arc<Final>, block: &mut Block) -> String {
+        match ty.as_product() {
+            Some((ty_l, ty_r)) => {
+                let (left, right) = self.split(input, ty, block);
+                format!(
+                    "{}, {}",
+                    render_as(&left, ty_l, &type_of(ty_l)),
+                    render_as(&right, ty_r, &type_of(ty_r))
+                )
+            }
+            None if ty.is_unit() => String::new(),
+            None => render_as(&input, ty, &type_of(ty)),
+        }
+    }
+
+    /// Render the final expression of a block, if it is not the unit value.
+    fn result(output: &Sym, ty: &Final) -> Option<String> {
+        match output {
+            Sym::Unit => None,
+            _ => Some(render_as(output, ty, &type_of(ty))),
+        }
+    }
+
+    /// Split a value of product type into its components.
+    ///
+    /// Opaque values are destructured by a `let` statement.
+    fn split(&mut self, sym: Sym, ty: &Final, block: &mut Block) -> (Sym, Sym) {
+        match sym {
+            Sym::Pair(left, right) => (*left, *right),
+            Sym::Const(value) => {
+                let (left, right) = value.as_product().expect("value is a product");
+                (Sym::Const(left.to_value()), Sym::Const(right.to_value()))
+            }
+            sym => {
+                let text = render(&sym, ty);
+                if let Some(split) = block.splits.get(&text) {
+                    return split.clone();
+                }
+                let (ty_l, ty_r) = ty.as_product().expect("value is a product");
+                let (left, right) = (self.fresh(), self.fresh());
+                let split = match sym.declared().and_then(|ty| ty.as_tuple()) {
+                    Some([declared_l, declared_r]) => {
+                        let tuple = sym.declared().expect("declared type is a tuple");
+                        block
+                            .statements
+                            .push(format!("let ({left}, {right}): {tuple} = {text};"));
+                        (
+                            Sym::Var(left, Some(declared_l.as_ref().clone())),
+                            Sym::Var(right, Some(declared_r.as_ref().clone())),
+                        )
+                    }
+                    _ => {
+                        let tuple = ResolvedType::tuple([type_of(ty_l), type_of(ty_r)]);
+                        let value = render_as(&sym, ty, &tuple);
+                        block
+                            .statements
+                            .push(format!("let ({left}, {right}): {tuple} = {value};"));
+                        (Sym::var(left), Sym::var(right))
+                    }
+                };
+                block.splits.insert(text, split.clone());
+                split
+            }
+        }
+    }
+
+    /// Split a value into `n` arguments, which form a balanced binary tree.
+    fn unfold(
+        &mut self,
+        sym: Sym,
+        ty: &Arc<Final>,
+        n: usize,
+        block: &mut Block,
+    ) -> Vec<(Sym, Arc<Final>)> {
+        match n {
+            0 => vec![],
+            1 => vec![(sym, ty.clone())],
+            n => {
+                let half = n - n.next_power_of_two() / 2;
+                let (ty_l, ty_r) = ty.as_product().expect("arguments are a product");
+                let (left, right) = self.split(sym, ty, block);
+                let mut args = self.unfold(left, ty_l, half, block);
+                args.extend(self.unfold(right, ty_r, n - half, block));
+                args
+            }
+        }
+    }
+
+    /// Bind the expressions inside the given value to variables,
+    /// so they can be used multiple times without being evaluated multiple times.
+    fn bind(&mut self, sym: Sym, ty: &Final, block: &mut Block) -> Sym {
+        match sym {
+            Sym::Pair(left, right) => {
+                let (ty_l, ty_r) = ty.as_product().expect("value is a product");
+                let left = self.bind(*left, ty_l, block);
+                let right = self.bind(*right, ty_r, block);
+                Sym::pair(left, right)
+            }
+            sym if sym.is_atomic() => sym,
+            sym if ty.is_unit() => {
+                block.statements.push(format!("{};", render(&sym, ty)));
+                Sym::Unit
+            }
+            sym => self.bind_fresh(sym, ty, block),
+        }
+    }
+
+    /// Return the value of the `let` statement if `comp s t` is the shape that
+    /// the compiler emits for `let` statements: `comp (pair value iden) t`.
+    ///
+    /// Match expressions have the same shape, so `t` must not branch on its input.
+    fn let_value(&self, s: usize, t: usize) -> Option<usize> {
+        match (&self.nodes[s].combinator, &self.nodes[t].combinator) {
+            (_, Combinator::Case(..) | Combinator::AssertL(..) | Combinator::AssertR(..)) => None,
+            (Combinator::Pair(value, iden), _)
+                if matches!(self.nodes[*iden].combinator, Combinator::Iden) =>
+            {
+                Some(*value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Bind the value to a variable, like [`Decompiler::bind`],
+    /// but also bind variables and integer constants to a new variable.
+    ///
+    /// Other constants stay inline, because their type is ambiguous:
+    /// arrays and tuples have the same Simplicity type.
+    fn bind_variable(&mut self, sym: Sym, ty: &Final, block: &mut Block) -> Sym {
+        match sym {
+            Sym::Var(..) => self.bind_fresh(sym, ty, block),
+            Sym::Const(_) if ty.as_word().is_some() => self.bind_fresh(sym, ty, block),
+            sym => self.bind(sym, ty, block),
+        }
+    }
+
+    /// Bind the value to a new variable.
+    fn bind_fresh(&mut self, sym: Sym, ty: &Final, block: &mut Block) -> Sym {
+        let name = self.fresh();
+        let (annotation, declared) = match sym.declared() {
+            Some(declared) => (format!("{declared}"), Some(declared.clone())),
+            None => (type_of(ty).to_string(), None),
+        };
+        let text = match declared {
+            Some(_) => render(&sym, ty),
+            None => render_as(&sym, ty, &type_of(ty)),
+        };
+        block
+            .statements
+            .push(format!("let {name}: {annotation} = {text};"));
+        Sym::Var(name, declared)
+    }
+
+    /// Render node `index` in inline combinator syntax.
+    fn combinators(&self, index: usize) -> String {
+        const MAX_SIZE: usize = 64;
+        let node = &self.nodes[index];
+        if MAX_SIZE < node.size {
+            return format!("#{}", node.cmr);
+        }
+        let child = |i: usize| match self.nodes[i].size {
+            1 => self.combinators(i),
+            _ => format!("({})", self.combinators(i)),
+        };
+        match &node.combinator {
+            Combinator::Iden => "iden".to_string(),
+            Combinator::Unit => "unit".to_string(),
+            Combinator::InjL(c) => format!("injl {}", child(*c)),
+            Combinator::InjR(c) => format!("injr {}", child(*c)),
+            Combinator::Take(c) => format!("take {}", child(*c)),
+            Combinator::Drop(c) => format!("drop {}", child(*c)),
+            Combinator::Comp(l, r) => format!("comp {} {}", child(*l), child(*r)),
+            Combinator::Case(l, r) => format!("case {} {}", child(*l), child(*r)),
+            Combinator::AssertL(c, cmr) => format!("assertl {} #{cmr}", child(*c)),
+            Combinator::AssertR(cmr, c) => format!("assertr #{cmr} {}", child(*c)),
+            Combinator::Pair(l, r) => format!("pair {} {}", child(*l), child(*r)),
+            Combinator::Disconnect(l, r) => format!("disconnect {} {}", child(*l), child(*r)),
+            Combinator::Witness(i) => format!("witness W{i}"),
+            Combinator::Fail => "fail".to_string(),
+            Combinator::Jet(jet) => format!("jet {jet}"),
+            Combinator::Word(value) => format!("const {value}"),
+        }
+    }
+
+    /// Check if node `index` is the skeleton of a loop.
+    fn recognize(&mut self, index: usize) -> Option<Loop> {
+        if !matches!(
+            self.nodes[index].combinator,
+            Combinator::Comp(..) | Combinator::Case(..)
+        ) {
+            return None;
+        }
+        if let Some(skeleton) = self.loops.get(&index) {
+            return *skeleton;
+        }
+        let skeleton = self
+            .find_list_fold(index)
+            .or_else(|| self.find_array_fold(index))
+            .or_else(|| self.find_for_while(index));
+        self.loops.insert(index, skeleton);
+        skeleton
+    }
+
+    fn find_list_fold(&self, index: usize) -> Option<Loop> {
+        let mut current = index;
+        let mut bound = NonZeroPow2Usize::TWO;
+        let f = loop {
+            match &self.nodes[current].combinator {
+                Combinator::Case(l, f) if self.is_selector(*l, &[true]) => break *f,
+                Combinator::Comp(_, r) => match &self.nodes[*r].combinator {
+                    Combinator::Case(l, _) => match &self.nodes[*l].combinator {
+                        Combinator::Drop(fold) => current = *fold,
+                        _ => return None,
+                    },
+                    _ => return None,
+                },
+                _ => return None,
+            }
+            bound = bound.mul2();
+            if MAX_LOOP_DEPTH < bound.get().ilog2() as usize {
+                return None;
+            }
+        };
+        // Folds over lists of at most one element are indistinguishable from match expressions
+        if bound == NonZeroPow2Usize::TWO {
+            return None;
+        }
+        (skeleton::list_fold(bound, self.nodes[f].cmr) == self.nodes[index].cmr)
+            .then_some(Loop::ListFold { f, bound })
+    }
+
+    fn find_array_fold(&self, index: usize) -> Option<Loop> {
+        let (array, _) = self.nodes[index].source.as_product()?;
+        let mut current = index;
+        let mut found = None;
+        // Keep going to find the innermost fold body
+        for _ in 0..MAX_LOOP_DEPTH {
+            let Combinator::Comp(_, f) = self.nodes[current].combinator else {
+                break;
+            };
+            current = f;
+            let Some((element, _)) = self.nodes[f].source.as_product() else {
+                continue;
+            };
+            match array_len(array, element) {
+                Some(size)
+                    if 2 <= size
+                        && skeleton::array_fold(size, self.nodes[f].cmr)
+                            == self.nodes[index].cmr =>
+                {
+                    found = Some(Loop::ArrayFold { f, size });
+                }
+                _ => {}
+            }
+        }
+        found
+    }
+
+    fn find_for_while(&self, index: usize) -> Option<Loop> {
+        let mut current = index;
+        let mut found = None;
+        // Keep going to find the innermost loop body
+        for _ in 0..MAX_LOOP_DEPTH {
+            let Combinator::Comp(l, r) = self.nodes[current].combinator else {
+                break;
+            };
+            current = match (&self.nodes[l].combinator, &self.nodes[r].combinator) {
+                (Combinator::Pair(p, _), Combinator::Case(..)) => match self.nodes[*p].combinator {
+                    Combinator::Comp(_, f) => f,
+                    _ => r,
+                },
+                _ => r,
+            };
+            let Some(bit_width) = counter_bit_width(&self.nodes[current].source) else {
+                continue;
+            };
+            if skeleton::for_while(bit_width, self.nodes[current].cmr) == self.nodes[index].cmr {
+                found = Some(Loop::ForWhile { f: current });
+            }
+        }
+        found
+    }
+
+    /// Check if node `index` is a selector of `take`, `drop` and `iden`.
+    ///
+    /// The `path` lists `true` for `drop` and `false` for `take`.
+    fn is_selector(&self, index: usize, path: &[bool]) -> bool {
+        match (path.split_first(), &self.nodes[index].combinator) {
+            (None, Combinator::Iden) => true,
+            (Some((false, rest)), Combinator::Take(c)) => self.is_selector(*c, rest),
+            (Some((true, rest)), Combinator::Drop(c)) => self.is_selector(*c, rest),
+            _ => false,
+        }
+    }
+}
+
+/// Return the number of `element`s in the `array` type.
+fn array_len(array: &Final, element: &Final) -> Option<usize> {
+    if array.tmr() == element.tmr() {
+        return Some(1);
+    }
+    let (left, right) = array.as_product()?;
+    Some(array_len(left, element)? + array_len(right, element)?)
+}
+
+/// Return the bit width of the counter of a `for_while` loop body.
+///
+/// The body has the type `A × (C × 2^(2^n)) → B + A`, where `n ≤ 4`.
+fn counter_bit_width(source: &Final) -> Option<Pow2Usize> {
+    let (_, context_counter) = source.as_product()?;
+    let (_, counter) = context_counter.as_product()?;
+    let n = counter.as_word()?;
+    (n <= 4).then(|| Pow2Usize::new_unchecked(1 << n))
+}
+
+/// Reconstruct a SimplicityHL type from the given Simplicity type.
+fn type_of(ty: &Final) -> ResolvedType {
+    if ty.is_unit() {
+        return ResolvedType::unit();
+    }
+    if let Some(n) = ty.as_word() {
+        return match (n, UIntType::two_n(n)) {
+            (0, _) => ResolvedType::boolean(),
+            (_, Some(int)) => ResolvedType::from(int),
+            (_, None) => ResolvedType::byte_array((1 << n) / 8),
+        };
+    }
+    if let Some((left, right)) = ty.as_sum() {
+        return match left.is_unit() {
+            true => ResolvedType::option(type_of(right)),
+            false => ResolvedType::either(type_of(left), type_of(right)),
+        };
+    }
+    let (left, right) = ty.as_product().expect("type is a product");
+    ResolvedType::tuple([type_of(left), type_of(right)])
+}
+
+fn render_const(value: &simplicity::Value, ty: &Final) -> String {
+    Value::reconstruct(&StructuralValue::from(value.shallow_clone()), &type_of(ty))
+        .map_or_else(|| value.to_string(), |value| value.to_string())
+}
+
+/// Render the given value of type `ty` in SimplicityHL syntax.
+fn render(sym: &Sym, ty: &Final) -> String {
+    match sym {
+        Sym::Unit => "()".to_string(),
+        Sym::Var(name, _) => name.clone(),
+        Sym::Expr(text, ..) => text.clone(),
+        Sym::Const(value) => render_const(value, ty),
+        Sym::Pair(left, right) => {
+            let (ty_l, ty_r) = ty.as_product().expect("value is a product");
+            format!("({}, {})", render(left, ty_l), render(right, ty_r))
+        }
+        Sym::Left(inner) | Sym::Right(inner) => {
+            let is_right = matches!(sym, Sym::Right(_));
+            let (ty_l, ty_r) = ty.as_sum().expect("value is a sum");
+            match (ty_l.is_unit() && ty_r.is_unit(), ty_l.is_unit(), is_right) {
+                (true, _, _) => is_right.to_string(),
+                (false, true, false) => "None".to_string(),
+                (false, true, true) => format!("Some({})", render(inner, ty_r)),
+                (false, false, false) => format!("Left({})", render(inner, ty_l)),
+                (false, false, true) => format!("Right({})", render(inner, ty_r)),
+            }
+        }
+    }
+}
+
+/// Render the given value of type `ty` such that it has the `expected` type.
+///
+/// Values whose declared type differs from the `expected` type are cast.
+fn render_as(sym: &Sym, ty: &Final, expected: &ResolvedType) -> String {
+    let cast = |sym: &Sym| {
+        let (actual, text) = match sym.declared() {
+            Some(declared) => match declared.resolve_builtin() {
+                Ok(resolved) => (resolved, declared.to_string()),
+                Err(_) => (type_of(ty), type_of(ty).to_string()),
+            },
+            None => (type_of(ty), type_of(ty).to_string()),
+        };
+        match &actual == expected {
+            true => render(sym, ty),
+            false => format!("<{text}>::into({})", render(sym, ty)),
+        }
+    };
+    if let Some(value) = sym.to_const(ty).filter(|_| !matches!(sym, Sym::Const(_))) {
+        return render_as(&Sym::Const(value), ty, expected);
+    }
+    match sym {
+        Sym::Const(value) => {
+            Value::reconstruct(&StructuralValue::from(value.shallow_clone()), expected)
+                .map_or_else(|| cast(sym), |value| value.to_string())
+        }
+        Sym::Pair(left, right) => {
+            let (ty_l, ty_r) = ty.as_product().expect("value is a product");
+            match expected.as_tuple() {
+                Some([expected_l, expected_r]) => format!(
+                    "({}, {})",
+                    render_as(left, ty_l, expected_l),
+                    render_as(right, ty_r, expected_r)
+                ),
+                _ => {
+                    let (expected_l, expected_r) = (type_of(ty_l), type_of(ty_r));
+                    let tuple = ResolvedType::tuple([expected_l.clone(), expected_r.clone()]);
+                    format!(
+                        "<{tuple}>::into(({}, {}))",
+                        render_as(left, ty_l, &expected_l),
+                        render_as(right, ty_r, &expected_r)
+                    )
+                }
+            }
+        }
+        Sym::Left(inner) | Sym::Right(inner) => {
+            let is_right = matches!(sym, Sym::Right(_));
+            let (ty_l, ty_r) = ty.as_sum().expect("value is a sum");
+            match (expected.as_either(), expected.as_option(), is_right) {
+                (Some((expected_l, _)), _, false) => {
+                    format!("Left({})", render_as(inner, ty_l, expected_l))
+                }
+                (Some((_, expected_r)), _, true) => {
+                    format!("Right({})", render_as(inner, ty_r, expected_r))
+                }
+                (_, Some(_), false) => "None".to_string(),
+                (_, Some(expected_r), true) => {
+                    format!("Some({})", render_as(inner, ty_r, expected_r))
+                }
+                _ if expected.is_boolean() => is_right.to_string(),
+                _ => cast(sym),
+            }
+        }
+        Sym::Unit => render(sym, ty),
+        Sym::Var(..) | Sym::Expr(..) => cast(sym),
+    }
+}
+
+/// CMRs of the loop skeletons that the compiler emits.
+///
+/// The skeletons mirror the construction in [`crate::compile`].
+mod skeleton {
+    use simplicity::Cmr;
+
+    use crate::num::{NonZeroPow2Usize, Pow2Usize};
+
+    /// Selector of `take`, `drop` and `iden`, where `o` is `take` and `i` is `drop`.
+    fn sel(path: &str) -> Cmr {
+        path.chars().rev().fold(Cmr::iden(), |cmr, c| match c {
+            'o' => Cmr::take(cmr),
+            _ => Cmr::drop(cmr),
+        })
+    }
+
+    pub fn list_fold(bound: NonZeroPow2Usize, f: Cmr) -> Cmr {
+        let mut f_array = f;
+        let mut f_fold = Cmr::case(sel("i"), f_array);
+        let mut i = NonZeroPow2Usize::TWO;
+        while i < bound {
+            let half1_acc = Cmr::pair(sel("oo"), sel("i"));
+            f_array = Cmr::comp(Cmr::pair(sel("oi"), Cmr::comp(half1_acc, f_array)), f_array);
+            let case_input = Cmr::pair(sel("oo"), Cmr::pair(sel("oi"), sel("i")));
+            let f_n_output = Cmr::comp(Cmr::pair(sel("o"), sel("ii")), f_array);
+            let case_right = Cmr::comp(Cmr::pair(sel("io"), f_n_output), f_fold);
+            f_fold = Cmr::comp(case_input, Cmr::case(Cmr::drop(f_fold), case_right));
+            i = i.mul2();
+        }
+        f_fold
+    }
+
+    pub fn array_fold(size: usize, f: Cmr) -> Cmr {
+        fn f_array_fold(f_left: Cmr, f_right: Cmr) -> Cmr {
+            let left_res = Cmr::comp(Cmr::pair(sel("oo"), sel("i")), f_left);
+            Cmr::comp(Cmr::pair(sel("oi"), left_res), f_right)
+        }
+        fn tree_fold(n: usize, f_powers_of_two: &[Cmr]) -> Cmr {
+            let max_pow2 = n.ilog2() as usize;
+            let f_right = f_powers_of_two[max_pow2];
+            let size_right = 1 << max_pow2;
+            if n == size_right {
+                return f_right;
+            }
+            f_array_fold(tree_fold(n - size_right, f_powers_of_two), f_right)
+        }
+
+        let mut f_powers_of_two = vec![f];
+        let mut i = 1;
+        while i < size {
+            let f_prev = *f_powers_of_two.last().unwrap();
+            f_powers_of_two.push(f_array_fold(f_prev, f_prev));
+            i *= 2;
+        }
+        tree_fold(size, &f_powers_of_two)
+    }
+
+    pub fn for_while(bit_width: Pow2Usize, f: Cmr) -> Cmr {
+        fn for_while_0(f: Cmr) -> Cmr {
+            let bit = |b: Cmr| Cmr::pair(sel("o"), Cmr::pair(sel("i"), b));
+            let f_output = Cmr::comp(bit(Cmr::injl(Cmr::unit())), f);
+            let case_input = Cmr::pair(f_output, sel("i"));
+            let x = Cmr::injl(sel("o"));
+            let f_output = Cmr::comp(bit(Cmr::injr(Cmr::unit())), f);
+            Cmr::comp(case_input, Cmr::case(x, f_output))
+        }
+        fn adapt_f(f: Cmr) -> Cmr {
+            let f_input = Cmr::pair(
+                sel("o"),
+                Cmr::pair(sel("ioo"), Cmr::pair(sel("ioi"), sel("ii"))),
+            );
+            Cmr::comp(f_input, f)
+        }
+
+        // See the construction in the compiler: false is for_while_0 and true is adapt_f
+        let max_stack = bit_width.mul2().get() - 1;
+        let mut stack = vec![false; max_stack];
+        let mut i = Pow2Usize::ONE.mul2();
+        while i <= bit_width {
+            let index = i.get() - 1;
+            let (prefix, tail) = stack.as_mut_slice().split_at_mut(index);
+            tail[..index].copy_from_slice(prefix);
+            tail[index] = true;
+            i = i.mul2();
+        }
+
+        let mut for_while_f = f;
+        while let Some(adapt) = stack.pop() {
+            for_while_f = match adapt {
+                false => for_while_0(for_while_f),
+                true => adapt_f(for_while_f),
+            };
+        }
+        for_while_f
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equivalence::{Equivalence, EquivalenceCheck};
+    use crate::symbolic::depends_on_cmr;
+    use crate::{Arguments, CompiledProgram};
+
+    fn decompile(text: &str, include_debug_symbols: bool) -> Decompilation {
+        let program = CompiledProgram::new(text, Arguments::default(), include_debug_symbols)
+            .expect("program compiles");
+        let debug_symbols = include_debug_symbols.then_some(program.debug_symbols());
+        let decompilation = Decompilation::from_commit(&program.commit(), debug_symbols);
+        if let Err(error) =
+            CompiledProgram::new(decompilation.source(), Arguments::default(), false)
+        {
+            panic!("{error}\n{decompilation}");
+        }
+        decompilation
+    }
+
+    fn decompile_file(path: &str) -> Decompilation {
+        decompile(&std::fs::read_to_string(path).unwrap(), false)
+    }
+
+    #[test]
+    fn let_bindings_and_jets() {
+        let decompilation = decompile(
+            r#"fn main() {
+    let a: u32 = witness::A;
+    let b: u32 = witness::B;
+    let (carry, sum): (bool, u32) = jet::add_32(a, b);
+    assert!(jet::eq_32(sum, 10));
+}"#,
+            false,
+        );
+        let source = decompilation.source();
+        assert!(source.contains("// witness::W0: u32"), "{source}");
+        assert!(source.contains(": u32 = witness::W1;"), "{source}");
+        assert!(source.contains(": (bool, u32) = jet::add_32("), "{source}");
+        assert!(source.contains("assert!(jet::eq_32("), "{source}");
+    }
+
+    #[test]
+    fn match_expressions() {
+        let decompilation = decompile_file("./examples/escrow_with_delay.simf");
+        let source = decompilation.source();
+        assert!(source.contains("Left(v"), "{source}");
+        assert!(source.contains("None => "), "{source}");
+        assert!(source.contains("Some(v"), "{source}");
+        assert!(source.contains("jet::bip_0340_verify"), "{source}");
+        assert!(
+            source.contains("jet::complement_1(<bool>::into("),
+            "{source}"
+        );
+    }
+
+    #[test]
+    fn loops() {
+        let array_fold = decompile_file("./examples/array_fold.simf");
+        let source = array_fold.source();
+        assert!(
+            source.contains("array_fold::<f0, 7>([1, 2, 3, 4, 5, 6, 7], 0)"),
+            "{source}"
+        );
+        let array_fold_2n = decompile_file("./examples/array_fold_2n.simf");
+        let source = array_fold_2n.source();
+        assert!(source.contains("array_fold::<f0, 8>("), "{source}");
+
+        let for_while = decompile_file("./examples/hash_loop.simf");
+        let source = for_while.source();
+        assert!(source.contains("for_while::<f0>("), "{source}");
+        assert!(
+            source.contains("v2: (), v3: u8) -> Either<u256,"),
+            "{source}"
+        );
+
+        let list_fold = decompile(
+            r#"fn sum(elt: u32, acc: u32) -> u32 {
+    let (_, acc): (bool, u32) = jet::add_32(elt, acc);
+    acc
+}
+
+fn main() {
+    let list: List<u32, 8> = list![1, 2, 3];
+    let sum: u32 = fold::<sum, 8>(list, 0);
+    assert!(jet::eq_32(sum, 6));
+}"#,
+            false,
+        );
+        let source = list_fold.source();
+        assert!(
+            source.contains("fold::<f0, 8>(list![1, 2, 3], 0)"),
+            "{source}"
+        );
+    }
+
+    #[test]
+    fn shared_expressions_become_functions() {
+        let decompilation = decompile_file("./examples/escrow_with_delay.simf");
+        let source = decompilation.source();
+        assert!(source.contains("fn f0(v"), "{source}");
+        assert_eq!(3, source.matches(": u8 = f0(").count(), "{source}");
+    }
+
+    #[test]
+    fn annotate_with_debug_symbols() {
+        let text = std::fs::read_to_string("./examples/p2pkh.simf").unwrap();
+        let decompilation = decompile(&text, true);
+        let source = decompilation.source();
+        assert!(
+            source.contains("// 19:5 assert!(jet::eq_256(pk_hash, expected_pk_hash))"),
+            "{source}"
+        );
+        let without_symbols = decompile(&text, false);
+        assert!(!without_symbols.source().contains("// 19:5"));
+    }
+
+    #[test]
+    fn decompiled_examples_compile() {
+        for entry in std::fs::read_dir("./examples").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|extension| extension != "simf") {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path).unwrap();
+            // Skip examples with parameters
+            if CompiledProgram::new(text.as_str(), Arguments::default(), false).is_ok() {
+                decompile(&text, true);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_to_same_cmr() {
+        let text = std::fs::read_to_string("./examples/ctv.simf").unwrap();
+        let program = CompiledProgram::new(text.as_str(), Arguments::default(), false).unwrap();
+        let decompilation = decompile(&text, false);
+        let recompiled =
+            CompiledProgram::new(decompilation.source(), Arguments::default(), false).unwrap();
+        assert_eq!(program.commit().cmr(), recompiled.commit().cmr());
+    }
+
+    #[test]
+    fn decompiled_examples_are_equivalent() {
+        let mut checked = 0;
+        for entry in std::fs::read_dir("./examples").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|extension| extension != "simf") {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path).unwrap();
+            // Skip examples with parameters
+            let Ok(program) = CompiledProgram::new(text.as_str(), Arguments::default(), false)
+            else {
+                continue;
+            };
+            // Skip examples that hash their own CMR, which changes during the round trip
+            let hashes_cmr = program
+                .commit()
+                .post_order_iter::<MaxSharing<_>>()
+                .any(|data| matches!(data.node.inner(), Inner::Jet(jet) if depends_on_cmr(*jet)));
+            if hashes_cmr {
+                continue;
+            }
+
+            // Witness names are lost during compilation, so restore them
+            let mut source = decompile(&text, false).source().to_string();
+            let names: Vec<_> = program
+                .simplicity
+                .as_ref()
+                .post_order_iter::<MaxSharing<_>>()
+                .filter_map(|data| match data.node.inner() {
+                    Inner::Witness(name) => Some(name.clone()),
+                    _ => None,
+                })
+                .collect();
+            for (index, name) in names.iter().enumerate().rev() {
+                source = source.replace(&format!("witness::W{index}"), &format!("witness::{name}"));
+            }
+            let recompiled = CompiledProgram::new(source.as_str(), Arguments::default(), false)
+                .unwrap_or_else(|error| panic!("{}: {error}\n{source}", path.display()));
+            let equivalence = EquivalenceCheck::new(&program, &recompiled)
+                .check()
+                .unwrap_or_else(|error| panic!("{}: {error}\n{source}", path.display()));
+            assert!(
+                matches!(
+                    equivalence,
+                    Equivalence::Identical | Equivalence::Equivalent
+                ),
+                "{}: {equivalence}\n{source}",
+                path.display()
+            );
+            checked += 1;
+        }
+        assert!(5 <= checked);
+    }
+
+    #[test]
+    fn decode_hex() {
+        let text = std::fs::read_to_string("./examples/cat.simf").unwrap();
+        let program = CompiledProgram::new(text, Arguments::default(), false).unwrap();
+        let bytes = program.commit().to_vec_without_witness();
+        let hex = simplicity::hex::DisplayHex::as_hex(&bytes[..]).to_string();
+        let decompilation = Decompilation::decode(&hex, None, Encoding::Hex, None).unwrap();
+        assert_eq!(program.commit().cmr(), decompilation.cmr());
+        assert!(decompilation.source().contains("fn main()"));
+    }
+}
@@ -0,0 +1,364 @@
+//! Disassembler for Simplicity target code.
+//!
+//! The disassembler lists the combinators of a program in post order, one node per line.
+//! Nodes are shared maximally, as in the serialized program.
+//! Each node is listed once and it is referenced by its index `%i`.
+//! Children are always listed before their parents, so the root comes last.
+//!
+//! When debug symbols are available, nodes that carry a debug symbol are annotated
+//! with the SimplicityHL source text and span that they originate from.
+
+use std::fmt;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use simplicity::dag::{DagLike, MaxSharing};
+use simplicity::hex::FromHex;
+use simplicity::jet::Elements;
+use simplicity::node::{Inner, Marker};
+use simplicity::types::arrow::FinalArrow;
+use simplicity::{BitIter, Cmr, CommitNode, RedeemNode};
+
+use crate::debug::{DebugSymbols, TrackedCall};
+
+/// Disassembled Simplicity program.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Disassembly {
+    nodes: Vec<DisassembledNode>,
+    program_size: usize,
+    witness_size: Option<usize>,
+}
+
+/// Node of a disassembled Simplicity program.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DisassembledNode {
+    combinator: String,
+    arrow: FinalArrow,
+    cmr: Cmr,
+    references: usize,
+    call: Option<TrackedCall>,
+}
+
+impl Disassembly {
+    /// Decode and disassemble the given serialized `program`.
+    ///
+    /// If the serialized `witness` data is given, then the witness nodes display their values.
+    /// The `debug_symbols` of the SimplicityHL program are used to annotate the nodes.
+    ///
+    /// The program and witness data are both in the given text `encoding`.
+    ///
+    /// ## Errors
+    ///
+    /// - The strings are not in the given encoding.
+    /// - The program or witness data fail to decode.
+    pub fn decode(
+        program: &str,
+        witness: Option<&str>,
+        encoding: Encoding,
+        debug_symbols: Option<&DebugSymbols>,
+    ) -> Result<Self, String> {
+        let program_bytes = encoding.decode(program)?;
+        let program_bits = BitIter::new(program_bytes.iter().copied());
+        match witness {
+            Some(witness) => {
+                let witness_bytes = encoding.decode(witness)?;
+                let witness_bits = BitIter::new(witness_bytes.iter().copied());
+                let redeem = RedeemNode::<Elements>::decode(program_bits, witness_bits)
+                    .map_err(|e| e.to_string())?;
+                let mut disassembly = Self::from_redeem(&redeem, debug_symbols);
+                disassembly.program_size = program_bytes.len();
+                disassembly.witness_size = Some(witness_bytes.len());
+                Ok(disassembly)
+            }
+            None => {
+                let commit =
+                    CommitNode::<Elements>::decode(program_bits).map_err(|e| e.to_string())?;
+                let mut disassembly = Self::from_commit(&commit, debug_symbols);
+                disassembly.program_size = program_bytes.len();
+                Ok(disassembly)
+            }
+        }
+    }
+
+    /// Disassemble the given program without witness data.
+    pub fn from_commit(
+        program: &CommitNode<Elements>,
+        debug_symbols: Option<&DebugSymbols>,
+    ) -> Self {
+        let nodes = disassemble(
+            program,
+            |node| node.arrow().shallow_clone(),
+            |_| None,
+            debug_symbols,
+        );
+        Self {
+            nodes,
+            program_size: program.to_vec_without_witness().len(),
+            witness_size: None,
+        }
+    }
+
+    /// Disassemble the given program with witness data.
+    pub fn from_redeem(
+        program: &RedeemNode<Elements>,
+        debug_symbols: Option<&DebugSymbols>,
+    ) -> Self {
+        let nodes = disassemble(
+            program,
+            |node| node.arrow().shallow_clone(),
+            |value| Some(value.to_string()),
+            debug_symbols,
+        );
+        let (program_bytes, witness_bytes) = program.to_vec_with_witness();
+        Self {
+            nodes,
+            program_size: program_bytes.len(),
+            witness_size: Some(witness_bytes.len()),
+        }
+    }
+
+    /// Access the nodes of the program in post order.
+    ///
+    /// The root node comes last.
+    pub fn nodes(&self) -> &[DisassembledNode] {
+        &self.nodes
+    }
+
+    /// Access the CMR of the program.
+    pub fn cmr(&self) -> Cmr {
+        self.nodes
+            .last()
+            .expect("program has at least one node")
+            .cmr
+    }
+
+    /// Access the size of the serialized program in bytes, without witness data.
+    pub fn program_size(&self) -> usize {
+        self.program_size
+    }
+
+    /// Access the size of the serialized witness data in bytes, if any.
+    pub fn witness_size(&self) -> Option<usize> {
+        self.witness_size
+    }
+}
+
+impl DisassembledNode {
+    /// Access the combinator of the node, including references to its children.
+    pub fn combinator(&self) -> &str {
+        &self.combinator
+    }
+
+    /// Access the type of the node.
+    pub fn arrow(&self) -> &FinalArrow {
+        &self.arrow
+    }
+
+    /// Access the CMR of the node.
+    pub fn cmr(&self) -> Cmr {
+        self.cmr
+    }
+
+    /// Access the number of parent nodes that reference the node.
+    ///
+    /// Nodes with more than one reference are shared.
+    pub fn references(&self) -> usize {
+        self.references
+    }
+
+    /// Access the SimplicityHL call expression that the node tracks, if any.
+    pub fn call(&self) -> Option<&TrackedCall> {
+        self.call.as_ref()
+    }
+}
+
+/// Text encoding of serialized programs and witness data.
+///
+/// The encodings cannot be told apart reliably,
+/// because many hex strings are also valid base64.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum Encoding {
+    /// Base64 with padding, as printed by `simc build`.
+    #[default]
+    Base64,
+    /// Hexadecimal digits.
+    Hex,
+}
+
+impl Encoding {
+    /// Decode the bytes from the given string, ignoring surrounding whitespace.
+    pub(crate) fn decode(self, s: &str) -> Result<Vec<u8>, String> {
+        let s = s.trim();
+        match self {
+            Self::Base64 => STANDARD
+                .decode(s)
+                .map_err(|e| format!("Invalid base64: {e}")),
+            Self::Hex => Vec::<u8>::from_hex(s).map_err(|e| format!("Invalid hex: {e}")),
+        }
+    }
+}
+
+fn disassemble<N, A, W>(
+    program: &simplicity::node::Node<N>,
+    arrow: A,
+    witness: W,
+    debug_symbols: Option<&DebugSymbols>,
+) -> Vec<DisassembledNode>
+where
+    N: Marker<Jet = Elements>,
+    A: Fn(&simplicity::node::Node<N>) -> FinalArrow,
+    W: Fn(&N::Witness) -> Option<String>,
+{
+    let mut nodes: Vec<DisassembledNode> = vec![];
+    for data in program.post_order_iter::<MaxSharing<N>>() {
+        let l = data.left_index.map(|i| format!("%{i}")).unwrap_or_default();
+        let r = data
+            .right_index
+            .map(|i| format!("%{i}"))
+            .unwrap_or_default();
+        let mut call = None;
+        let combinator = match data.node.inner() {
+            Inner::Iden => "iden".to_string(),
+            Inner::Unit => "unit".to_string(),
+            Inner::InjL(_) => format!("injl {l}"),
+            Inner::InjR(_) => format!("injr {l}"),
+            Inner::Take(_) => format!("take {l}"),
+            Inner::Drop(_) => format!("drop {l}"),
+            Inner::Comp(..) => format!("comp {l} {r}"),
+            Inner::Case(..) => format!("case {l} {r}"),
+            Inner::AssertL(_, cmr) => {
+                call = debug_symbols.and_then(|symbols| symbols.get(cmr)).cloned();
+                format!("assertl {l} #{cmr}")
+            }
+            Inner::AssertR(cmr, _) => format!("assertr #{cmr} {l}"),
+            Inner::Pair(..) => format!("pair {l} {r}"),
+            Inner::Disconnect(..) => format!("disconnect {l} {r}"),
+            Inner::Witness(value) => match witness(value) {
+                Some(value) => format!("witness {value}"),
+                None => "witness".to_string(),
+            },
+            Inner::Fail(entropy) => format!("fail {entropy}"),
+            Inner::Jet(jet) => format!("jet {jet}"),
+            Inner::Word(word) => format!("const {word}"),
+        };
+        for child in [data.left_index, data.right_index].into_iter().flatten() {
+            nodes[child].references += 1;
+        }
+        nodes.push(DisassembledNode {
+            combinator,
+            arrow: arrow(data.node),
+            cmr: data.node.cmr(),
+            references: 0,
+            call,
+        });
+    }
+    nodes
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "; cmr {}", self.cmr())?;
+        write!(f, "; program {} bytes", self.program_size)?;
+        if let Some(witness_size) = self.witness_size {
+            write!(f, ", witness {witness_size} bytes")?;
+        }
+        writeln!(f, ", {} nodes", self.nodes.len())?;
+        for (index, node) in self.nodes.iter().enumerate() {
+            write!(f, "%{index} = {node}")?;
+            if 1 < node.references {
+                write!(f, " ; shared by {}", node.references)?;
+            }
+            writeln!(f)?;
+            if let Some(call) = &node.call {
+                let span = call.span();
+                writeln!(
+                    f,
+                    "    ; {}:{}-{}:{} {}",
+                    span.start.line,
+                    span.start.col,
+                    span.end.line,
+                    span.end.col,
+                    call.text()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DisassembledNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} : {} ; cmr {}", self.combinator, self.arrow, self.cmr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::display::Base64Display;
+    use simplicity::hex::DisplayHex;
+
+    use super::*;
+    use crate::{Arguments, CompiledProgram, WitnessValues};
+
+    fn compile(path: &str) -> CompiledProgram {
+        let text = std::fs::read_to_string(path).unwrap();
+        CompiledProgram::new(text, Arguments::default(), true).unwrap()
+    }
+
+    #[test]
+    fn decode_base64_and_hex() {
+        let program = compile("./examples/cat.simf");
+        let satisfied = program.satisfy(WitnessValues::default()).unwrap();
+        let (program_bytes, witness_bytes) = satisfied.redeem().to_vec_with_witness();
+
+        let base64 = Base64Display::new(&program_bytes, &STANDARD).to_string();
+        let hex = program_bytes.as_hex().to_string();
+        let from_base64 = Disassembly::decode(&base64, None, Encoding::Base64, None).unwrap();
+        let from_hex = Disassembly::decode(&hex, None, Encoding::Hex, None).unwrap();
+        assert_eq!(from_base64, from_hex);
+        assert_eq!(program.commit().cmr(), from_base64.cmr());
+        assert_eq!(program_bytes.len(), from_base64.program_size());
+
+        let witness = Base64Display::new(&witness_bytes, &STANDARD).to_string();
+        let with_witness =
+            Disassembly::decode(&base64, Some(&witness), Encoding::Base64, None).unwrap();
+        assert_eq!(Some(witness_bytes.len()), with_witness.witness_size());
+        assert_eq!(from_base64.nodes().len(), with_witness.nodes().len());
+    }
+
+    #[test]
+    fn annotate_with_debug_symbols() {
+        let program = compile("./examples/p2pkh.simf");
+        let disassembly =
+            Disassembly::from_commit(&program.commit(), Some(program.debug_symbols()));
+        let calls: Vec<_> = disassembly
+            .nodes()
+            .iter()
+            .filter_map(DisassembledNode::call)
+            .collect();
+        assert!(calls
+            .iter()
+            .any(|call| call.text().starts_with("jet::bip_0340_verify")));
+        assert!(disassembly.nodes().iter().any(|node| 1 < node.references()));
+        assert_eq!(
+            0,
+            disassembly.nodes().last().unwrap().references(),
+            "root is not referenced"
+        );
+    }
+
+    #[test]
+    fn reject_garbage() {
+        assert!(Disassembly::decode("not a program!", None, Encoding::Base64, None).is_err());
+        assert!(Disassembly::decode("00", None, Encoding::Hex, None).is_err());
+        // Hex that happens to be valid base64 is not mistaken for base64
+        let program = compile("./examples/cat.simf");
+        let hex = program
+            .commit()
+            .to_vec_without_witness()
+            .as_hex()
+            .to_string();
+        assert!(Disassembly::decode(&hex, None, Encoding::Hex, None).is_ok());
+        assert!(Disassembly::decode(&hex, None, Encoding::Base64, None).is_err());
+    }
+}
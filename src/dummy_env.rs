@@ -46,13 +46,18 @@ fn create_default_transaction(
     tx
 }
 
-/// Returns a dummy Elements environment with a provided transaction.
-pub fn dummy_with_tx(tx: elements::Transaction) -> ElementsEnv<Arc<elements::Transaction>> {
+/// Returns a dummy control block.
+pub(crate) fn dummy_control_block() -> ControlBlock {
     let ctrl_blk: [u8; 33] = [
         0xc0, 0xeb, 0x04, 0xb6, 0x8e, 0x9a, 0x26, 0xd1, 0x16, 0x04, 0x6c, 0x76, 0xe8, 0xff, 0x47,
         0x33, 0x2f, 0xb7, 0x1d, 0xda, 0x90, 0xff, 0x4b, 0xef, 0x53, 0x70, 0xf2, 0x52, 0x26, 0xd3,
         0xbc, 0x09, 0xfc,
     ];
+    ControlBlock::from_slice(&ctrl_blk).unwrap()
+}
+
+/// Returns a dummy Elements environment with a provided transaction.
+pub fn dummy_with_tx(tx: elements::Transaction) -> ElementsEnv<Arc<elements::Transaction>> {
     let num_inputs = tx.input.len();
 
     ElementsEnv::new(
@@ -67,7 +72,7 @@ pub fn dummy_with_tx(tx: elements::Transaction) -> ElementsEnv<Arc<elements::Tra
         ],
         0,
         Cmr::from_byte_array([0; 32]),
-        ControlBlock::from_slice(&ctrl_blk).unwrap(),
+        dummy_control_block(),
         None,
         elements::BlockHash::all_zeros(),
     )
@@ -0,0 +1,393 @@
+//! Transactions that are described by the user.
+//!
+//! A [`TransactionDescription`] lists the inputs, the UTXOs that they spend, the outputs
+//! and the remaining fields of the transaction that a Simplicity program can introspect.
+//! The description is written in JSON or TOML. Byte strings are hex encoded.
+//! Amounts are either explicit integers or hex encoded confidential commitments.
+//!
+//! ```toml
+//! lock_time = 100
+//! input_index = 0
+//!
+//! [[inputs]]
+//! previous_output = "0000000000000000000000000000000000000000000000000000000000000000:0"
+//! sequence = 4294967294
+//! utxo = { script_pubkey = "5120…", value = 100000 }
+//!
+//! [[outputs]]
+//! script_pubkey = "0014…"
+//! value = 99000
+//!
+//! [[outputs]]
+//! value = 1000
+//! ```
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use elements::hex::FromHex;
+use elements::secp256k1_zkp::Tweak;
+use elements::{confidential, taproot::ControlBlock, AssetId, AssetIssuance, BlockHash};
+use hashes::Hash;
+use serde::Deserialize;
+
+use super::parse_bytes;
+use simplicity::jet::elements::{ElementsEnv, ElementsUtxo};
+use simplicity::Cmr;
+use simplicity::{elements, hashes};
+
+/// Description of a transaction and the UTXOs that it spends,
+/// from which an Elements environment is built.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransactionDescription {
+    #[serde(default = "default_version")]
+    version: u32,
+    #[serde(default)]
+    lock_time: u32,
+    inputs: Vec<InputDescription>,
+    #[serde(default)]
+    outputs: Vec<OutputDescription>,
+    #[serde(default)]
+    input_index: u32,
+    #[serde(default)]
+    annex: Option<String>,
+    #[serde(default)]
+    genesis_hash: Option<String>,
+    #[serde(default)]
+    control_block: Option<String>,
+}
+
+/// Description of a transaction input.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InputDescription {
+    #[serde(default)]
+    previous_output: Option<String>,
+    #[serde(default = "default_sequence")]
+    sequence: u32,
+    #[serde(default)]
+    issuance: Option<IssuanceDescription>,
+    utxo: UtxoDescription,
+}
+
+/// Description of the UTXO that is spent by a transaction input.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UtxoDescription {
+    #[serde(default)]
+    script_pubkey: String,
+    #[serde(default)]
+    asset: Option<String>,
+    value: Amount,
+}
+
+/// Description of a transaction output.
+///
+/// An output without a script pubkey is a fee output.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OutputDescription {
+    #[serde(default)]
+    script_pubkey: String,
+    #[serde(default)]
+    asset: Option<String>,
+    value: Amount,
+    #[serde(default)]
+    nonce: Option<String>,
+}
+
+/// Description of an asset issuance of a transaction input.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IssuanceDescription {
+    #[serde(default)]
+    asset_blinding_nonce: Option<String>,
+    #[serde(default)]
+    asset_entropy: Option<String>,
+    #[serde(default)]
+    amount: Option<Amount>,
+    #[serde(default)]
+    inflation_keys: Option<Amount>,
+}
+
+/// Explicit amount or hex encoded confidential commitment.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum Amount {
+    Explicit(u64),
+    Confidential(String),
+}
+
+fn default_version() -> u32 {
+    2
+}
+
+fn default_sequence() -> u32 {
+    elements::Sequence::MAX.to_consensus_u32()
+}
+
+impl TransactionDescription {
+    /// Parse a transaction description from JSON.
+    ///
+    /// ## Errors
+    ///
+    /// The string is not a valid JSON transaction description.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid transaction description: {e}"))
+    }
+
+    /// Parse a transaction description from TOML.
+    ///
+    /// ## Errors
+    ///
+    /// The string is not a valid TOML transaction description.
+    pub fn from_toml(toml: &str) -> Result<Self, String> {
+        toml::from_str(toml).map_err(|e| format!("Invalid transaction description: {e}"))
+    }
+
+    /// Build the Elements environment in which the input at the given index
+    /// spends a Simplicity program with the given `script_cmr`.
+    ///
+    /// If the description has no control block, then a dummy control block is used.
+    ///
+    /// ## Errors
+    ///
+    /// - The input index is out of range.
+    /// - A field is malformed.
+    pub fn to_env(
+        &self,
+        script_cmr: Cmr,
+    ) -> Result<ElementsEnv<Arc<elements::Transaction>>, String> {
+        if self.input_index as usize >= self.inputs.len() {
+            return Err(format!(
+                "Input index {} is out of range for {} inputs",
+                self.input_index,
+                self.inputs.len()
+            ));
+        }
+
+        let mut input = Vec::with_capacity(self.inputs.len());
+        let mut utxos = Vec::with_capacity(self.inputs.len());
+        for (index, description) in self.inputs.iter().enumerate() {
+            let (txin, utxo) = description
+                .to_input()
+                .map_err(|e| format!("Input {index}: {e}"))?;
+            input.push(txin);
+            utxos.push(utxo);
+        }
+        let output = self
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(index, description)| {
+                description
+                    .to_output()
+                    .map_err(|e| format!("Output {index}: {e}"))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let tx = elements::Transaction {
+            version: self.version,
+            lock_time: elements::LockTime::from_consensus(self.lock_time),
+            input,
+            output,
+        };
+
+        let annex = self
+            .annex
+            .as_deref()
+            .map(|annex| parse_bytes(annex, "annex"))
+            .transpose()?;
+        let genesis_hash = match &self.genesis_hash {
+            Some(hash) => {
+                BlockHash::from_str(hash).map_err(|e| format!("Invalid genesis hash: {e}"))?
+            }
+            None => BlockHash::all_zeros(),
+        };
+        let control_block = match &self.control_block {
+            Some(bytes) => ControlBlock::from_slice(&parse_bytes(bytes, "control block")?)
+                .map_err(|e| format!("Invalid control block: {e}"))?,
+            None => crate::dummy_env::dummy_control_block(),
+        };
+
+        Ok(ElementsEnv::new(
+            Arc::new(tx),
+            utxos,
+            self.input_index,
+            script_cmr,
+            control_block,
+            annex,
+            genesis_hash,
+        ))
+    }
+}
+
+impl InputDescription {
+    fn to_input(&self) -> Result<(elements::TxIn, ElementsUtxo), String> {
+        let previous_output = match &self.previous_output {
+            Some(outpoint) => elements::OutPoint::from_str(outpoint)
+                .map_err(|e| format!("Invalid previous output: {e}"))?,
+            None => elements::OutPoint::default(),
+        };
+        let asset_issuance = match &self.issuance {
+            Some(issuance) => issuance.to_issuance()?,
+            None => AssetIssuance::default(),
+        };
+        let txin = elements::TxIn {
+            previous_output,
+            is_pegin: false,
+            script_sig: elements::Script::new(),
+            sequence: elements::Sequence::from_consensus(self.sequence),
+            asset_issuance,
+            witness: elements::TxInWitness::default(),
+        };
+        let utxo = ElementsUtxo {
+            script_pubkey: parse_script(&self.utxo.script_pubkey)?,
+            asset: parse_asset(self.utxo.asset.as_deref())?,
+            value: self.utxo.value.to_value()?,
+        };
+        Ok((txin, utxo))
+    }
+}
+
+impl OutputDescription {
+    fn to_output(&self) -> Result<elements::TxOut, String> {
+        let nonce = match &self.nonce {
+            Some(nonce) => confidential::Nonce::from_commitment(&parse_bytes(nonce, "nonce")?)
+                .map_err(|e| format!("Invalid nonce: {e}"))?,
+            None => confidential::Nonce::Null,
+        };
+        Ok(elements::TxOut {
+            asset: parse_asset(self.asset.as_deref())?,
+            value: self.value.to_value()?,
+            nonce,
+            script_pubkey: parse_script(&self.script_pubkey)?,
+            witness: elements::TxOutWitness::default(),
+        })
+    }
+}
+
+impl IssuanceDescription {
+    fn to_issuance(&self) -> Result<AssetIssuance, String> {
+        let asset_blinding_nonce = match &self.asset_blinding_nonce {
+            Some(nonce) => Tweak::from_slice(&parse_bytes(nonce, "asset blinding nonce")?)
+                .map_err(|e| format!("Invalid asset blinding nonce: {e}"))?,
+            None => Tweak::from_inner([0; 32]).expect("zero is a valid tweak"),
+        };
+        let asset_entropy = match &self.asset_entropy {
+            Some(entropy) => {
+                <[u8; 32]>::from_hex(entropy).map_err(|e| format!("Invalid asset entropy: {e}"))?
+            }
+            None => [0; 32],
+        };
+        let amount = |amount: Option<&Amount>| {
+            amount.map_or(Ok(confidential::Value::Null), Amount::to_value)
+        };
+        Ok(AssetIssuance {
+            asset_blinding_nonce,
+            asset_entropy,
+            amount: amount(self.amount.as_ref())?,
+            inflation_keys: amount(self.inflation_keys.as_ref())?,
+        })
+    }
+}
+
+impl Amount {
+    fn to_value(&self) -> Result<confidential::Value, String> {
+        match self {
+            Amount::Explicit(value) => Ok(confidential::Value::Explicit(*value)),
+            Amount::Confidential(commitment) => {
+                confidential::Value::from_commitment(&parse_bytes(commitment, "value")?)
+                    .map_err(|e| format!("Invalid value commitment: {e}"))
+            }
+        }
+    }
+}
+
+/// Parse an explicit asset ID or a hex encoded confidential commitment.
+///
+/// The default asset is the all-zeros asset ID.
+fn parse_asset(asset: Option<&str>) -> Result<confidential::Asset, String> {
+    match asset {
+        None => Ok(confidential::Asset::Explicit(AssetId::default())),
+        Some(asset) if asset.len() == 64 => AssetId::from_str(asset)
+            .map(confidential::Asset::Explicit)
+            .map_err(|e| format!("Invalid asset: {e}")),
+        Some(asset) => confidential::Asset::from_commitment(&parse_bytes(asset, "asset")?)
+            .map_err(|e| format!("Invalid asset commitment: {e}")),
+    }
+}
+
+fn parse_script(script: &str) -> Result<elements::Script, String> {
+    parse_bytes(script, "script pubkey").map(elements::Script::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use simplicity::bit_machine::BitMachine;
+
+    use super::*;
+    use crate::{Arguments, CompiledProgram, WitnessValues};
+
+    const TOML: &str = r#"
+lock_time = 1000
+
+[[inputs]]
+previous_output = "0101010101010101010101010101010101010101010101010101010101010101:1"
+sequence = 4294967294
+utxo = { script_pubkey = "51", value = 100000 }
+
+[[inputs]]
+utxo = { value = 50000, asset = "0000000000000000000000000000000000000000000000000000000000000001" }
+
+[[outputs]]
+script_pubkey = "0014000102030405060708090a0b0c0d0e0f10111213"
+value = 149000
+
+[[outputs]]
+value = 1000
+"#;
+
+    #[test]
+    fn parse_toml() {
+        let description = TransactionDescription::from_toml(TOML).unwrap();
+        let env = description.to_env(Cmr::unit()).unwrap();
+        let tx = env.tx();
+        assert_eq!(2, tx.input.len());
+        assert_eq!(2, tx.output.len());
+        assert_eq!(1, tx.input[0].previous_output.vout);
+        assert_eq!(elements::LockTime::from_consensus(1000), tx.lock_time);
+        assert_eq!(Some(1000), tx.output[1].value.explicit());
+        assert!(tx.output[1].is_fee());
+    }
+
+    #[test]
+    fn parse_json() {
+        let json = r#"{
+            "inputs": [{ "utxo": { "value": 1 } }],
+            "input_index": 1
+        }"#;
+        let description = TransactionDescription::from_json(json).unwrap();
+        assert!(description.to_env(Cmr::unit()).is_err());
+
+        let json = r#"{ "inputs": [{ "utxo": { "value": 1, "colour": "red" } }] }"#;
+        assert!(TransactionDescription::from_json(json).is_err());
+    }
+
+    #[test]
+    fn execute_against_description() {
+        let prog_text = r#"fn main() {
+    assert!(jet::eq_32(jet::num_inputs(), 2));
+    assert!(jet::eq_32(jet::tx_lock_height(), 1000));
+    let (_, amount): (Asset1, Amount1) = unwrap(jet::input_amount(0));
+    let amount: u64 = unwrap_right::<(u1, u256)>(amount);
+    assert!(jet::eq_64(amount, 100000));
+}"#;
+        let compiled = CompiledProgram::new(prog_text, Arguments::default(), false).unwrap();
+        let satisfied = compiled.satisfy(WitnessValues::default()).unwrap();
+        let description = TransactionDescription::from_toml(TOML).unwrap();
+        let env = description.to_env(satisfied.redeem().cmr()).unwrap();
+        let mut mac = BitMachine::for_program(satisfied.redeem()).unwrap();
+        mac.exec(satisfied.redeem(), &env).unwrap();
+    }
+}
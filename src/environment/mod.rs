@@ -0,0 +1,368 @@
+//! Elements environments of real transactions.
+//!
+//! The environment of a Simplicity program is derived from the transaction that spends it:
+//! either from a serialized transaction plus the UTXOs that it spends,
+//! or from a PSET that includes the UTXOs and the Taproot script paths of its inputs.
+//! The control block is checked against the spent UTXO,
+//! so the program is executed against exactly the transaction that will be broadcast.
+
+#[cfg(feature = "serde")]
+mod description;
+
+use std::sync::Arc;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use elements::hex::FromHex;
+use elements::pset::PartiallySignedTransaction;
+use elements::schnorr::TweakedPublicKey;
+use elements::secp256k1_zkp::{Secp256k1, XOnlyPublicKey};
+use elements::taproot::ControlBlock;
+use elements::{encode, BlockHash};
+use simplicity::elements;
+use simplicity::jet::elements::{ElementsEnv, ElementsUtxo};
+use simplicity::Cmr;
+
+use crate::taproot;
+
+#[cfg(feature = "serde")]
+pub use description::TransactionDescription;
+
+/// Tag of the Taproot annex, which is the first byte of the annex.
+const ANNEX_TAG: u8 = 0x50;
+
+/// Build the Elements environment in which input `input_index` of the transaction
+/// spends the Simplicity program with the given `script_cmr`.
+///
+/// The `utxos` are the outputs that are spent by the transaction inputs, in order.
+///
+/// If no `control_block` is given, then it is read from the witness stack of the input,
+/// which must be a Simplicity Taproot script-path spend of the program.
+/// The annex is read from the witness stack, too.
+///
+/// ## Errors
+///
+/// - The input index is out of range.
+/// - There are not as many UTXOs as inputs.
+/// - The control block is unknown.
+/// - The control block does not commit to the program in the spent UTXO.
+pub fn from_transaction(
+    tx: elements::Transaction,
+    utxos: Vec<ElementsUtxo>,
+    input_index: u32,
+    script_cmr: Cmr,
+    control_block: Option<ControlBlock>,
+    genesis_hash: BlockHash,
+) -> Result<ElementsEnv<Arc<elements::Transaction>>, String> {
+    let input = tx.input.get(input_index as usize).ok_or_else(|| {
+        format!(
+            "Input index {input_index} is out of range for {} inputs",
+            tx.input.len()
+        )
+    })?;
+    if utxos.len() != tx.input.len() {
+        return Err(format!(
+            "Transaction has {} inputs but {} UTXOs were given",
+            tx.input.len(),
+            utxos.len()
+        ));
+    }
+
+    let spend = read_script_witness(&input.witness.script_witness, script_cmr)?;
+    let annex = spend.as_ref().and_then(|(_, annex)| annex.clone());
+    let Some(control_block) = control_block.or(spend.map(|(control_block, _)| control_block))
+    else {
+        return Err(format!("Control block of input {input_index} is unknown"));
+    };
+    verify_control_block(
+        &control_block,
+        &utxos[input_index as usize].script_pubkey,
+        script_cmr,
+    )?;
+
+    Ok(ElementsEnv::new(
+        Arc::new(tx),
+        utxos,
+        input_index,
+        script_cmr,
+        control_block,
+        annex,
+        genesis_hash,
+    ))
+}
+
+/// Build the Elements environment in which input `input_index` of the PSET
+/// spends the Simplicity program with the given `script_cmr`.
+///
+/// Each PSET input must include its UTXO.
+/// The control block is taken from the Taproot scripts of the input
+/// or from its final witness stack.
+///
+/// ## Errors
+///
+/// - The transaction cannot be extracted from the PSET.
+/// - A PSET input is missing its UTXO.
+/// - See [`from_transaction`].
+pub fn from_pset(
+    pset: &PartiallySignedTransaction,
+    input_index: u32,
+    script_cmr: Cmr,
+    genesis_hash: BlockHash,
+) -> Result<ElementsEnv<Arc<elements::Transaction>>, String> {
+    let tx = pset
+        .extract_tx()
+        .map_err(|e| format!("Cannot extract transaction from PSET: {e}"))?;
+    let utxos = pset
+        .inputs()
+        .iter()
+        .enumerate()
+        .map(|(index, input)| {
+            let txout = match (&input.witness_utxo, &input.non_witness_utxo) {
+                (Some(txout), _) => Some(txout.clone()),
+                (None, Some(prev_tx)) => prev_tx
+                    .output
+                    .get(input.previous_output_index as usize)
+                    .cloned(),
+                (None, None) => None,
+            };
+            txout
+                .map(ElementsUtxo::from)
+                .ok_or_else(|| format!("Input {index} of the PSET is missing its UTXO"))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let leaf = taproot::leaf(script_cmr);
+    let control_block = pset
+        .inputs()
+        .get(input_index as usize)
+        .and_then(|input| {
+            input
+                .tap_scripts
+                .iter()
+                .find(|(_, script_ver)| **script_ver == leaf)
+        })
+        .map(|(control_block, _)| control_block.clone());
+
+    from_transaction(
+        tx,
+        utxos,
+        input_index,
+        script_cmr,
+        control_block,
+        genesis_hash,
+    )
+}
+
+/// Decode a hex encoded Elements transaction.
+///
+/// ## Errors
+///
+/// The string is not a hex encoded transaction.
+pub fn decode_transaction(hex: &str) -> Result<elements::Transaction, String> {
+    let bytes = parse_bytes(hex.trim(), "transaction")?;
+    encode::deserialize(&bytes).map_err(|e| format!("Invalid transaction: {e}"))
+}
+
+/// Decode a hex encoded transaction output that is spent as a UTXO.
+///
+/// ## Errors
+///
+/// The string is not a hex encoded transaction output.
+pub fn decode_utxo(hex: &str) -> Result<ElementsUtxo, String> {
+    let bytes = parse_bytes(hex.trim(), "UTXO")?;
+    encode::deserialize::<elements::TxOut>(&bytes)
+        .map(ElementsUtxo::from)
+        .map_err(|e| format!("Invalid UTXO: {e}"))
+}
+
+/// Decode a base64 or hex encoded PSET.
+///
+/// ## Errors
+///
+/// The string is neither a base64 nor a hex encoded PSET.
+pub fn decode_pset(s: &str) -> Result<PartiallySignedTransaction, String> {
+    let s = s.trim();
+    let bytes = Vec::<u8>::from_hex(s)
+        .or_else(|_| STANDARD.decode(s))
+        .map_err(|_| "PSET is neither base64 nor hex encoded".to_string())?;
+    encode::deserialize(&bytes).map_err(|e| format!("Invalid PSET: {e}"))
+}
+
+/// Control block and optional annex of a Taproot script-path spend.
+type ScriptPathSpend = (ControlBlock, Option<Vec<u8>>);
+
+/// Read the control block and annex from the witness stack of a Simplicity
+/// Taproot script-path spend: witness, program, leaf script, control block and optional annex.
+///
+/// Return `None` if the witness stack is empty.
+fn read_script_witness(
+    stack: &[Vec<u8>],
+    script_cmr: Cmr,
+) -> Result<Option<ScriptPathSpend>, String> {
+    let (stack, annex) = match stack.split_last() {
+        None => return Ok(None),
+        Some((last, rest)) if !rest.is_empty() && last.first() == Some(&ANNEX_TAG) => {
+            (rest, Some(last.clone()))
+        }
+        Some(_) => (stack, None),
+    };
+    let [.., script, control_block] = stack else {
+        return Err("Witness stack is not a Taproot script-path spend".to_string());
+    };
+    if script.as_slice() != script_cmr.as_ref() {
+        return Err(format!(
+            "Witness stack does not spend the program with CMR {script_cmr}"
+        ));
+    }
+    let control_block = ControlBlock::from_slice(control_block)
+        .map_err(|e| format!("Invalid control block: {e}"))?;
+    Ok(Some((control_block, annex)))
+}
+
+/// Check that the control block proves that the Simplicity program
+/// is a Taproot leaf of the output key in the `script_pubkey`.
+///
+/// Script pubkeys that are not Taproot outputs are not checked.
+fn verify_control_block(
+    control_block: &ControlBlock,
+    script_pubkey: &elements::Script,
+    script_cmr: Cmr,
+) -> Result<(), String> {
+    if control_block.leaf_version != simplicity::leaf_version() {
+        return Err("Control block does not have the Simplicity leaf version".to_string());
+    }
+    if !script_pubkey.is_v1_p2tr() {
+        return Ok(());
+    }
+    let output_key = XOnlyPublicKey::from_slice(&script_pubkey.as_bytes()[2..])
+        .map(TweakedPublicKey::new)
+        .map_err(|e| format!("Invalid Taproot output key: {e}"))?;
+    let secp = Secp256k1::verification_only();
+    match control_block.verify_taproot_commitment(
+        &secp,
+        &output_key,
+        &taproot::leaf_script(script_cmr),
+    ) {
+        true => Ok(()),
+        false => Err(format!(
+            "Control block does not commit to the program with CMR {script_cmr} in the spent UTXO"
+        )),
+    }
+}
+
+fn parse_bytes(bytes: &str, what: &str) -> Result<Vec<u8>, String> {
+    Vec::<u8>::from_hex(bytes).map_err(|e| format!("Invalid {what}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use elements::hashes::Hash;
+    use elements::pset::Input;
+    use elements::{confidential, AssetId, OutPoint, Txid};
+    use simplicity::bit_machine::BitMachine;
+
+    use super::*;
+    use crate::{Arguments, CompiledProgram, SatisfiedProgram, WitnessValues};
+
+    const PROGRAM: &str = r#"fn main() {
+    assert!(jet::eq_32(jet::current_index(), 0));
+    assert!(jet::eq_32(jet::num_inputs(), 1));
+}"#;
+
+    fn satisfied() -> SatisfiedProgram {
+        let compiled = CompiledProgram::new(PROGRAM, Arguments::default(), false).unwrap();
+        compiled.satisfy(WitnessValues::default()).unwrap()
+    }
+
+    fn pset(script_cmr: Cmr) -> PartiallySignedTransaction {
+        let info = taproot::spend_info(script_cmr, taproot::nums_key());
+        let script_pubkey = elements::Script::new_v1_p2tr_tweaked(info.output_key());
+        let control_block = info.control_block(&taproot::leaf(script_cmr)).unwrap();
+
+        let mut pset = PartiallySignedTransaction::new_v2();
+        let mut input = Input::from_prevout(OutPoint::new(Txid::all_zeros(), 1));
+        input.witness_utxo = Some(elements::TxOut {
+            asset: confidential::Asset::Explicit(AssetId::LIQUID_BTC),
+            value: confidential::Value::Explicit(1000),
+            nonce: confidential::Nonce::Null,
+            script_pubkey,
+            witness: elements::TxOutWitness::default(),
+        });
+        input
+            .tap_scripts
+            .insert(control_block, taproot::leaf(script_cmr));
+        pset.add_input(input);
+        pset
+    }
+
+    #[test]
+    fn execute_against_pset() {
+        let satisfied = satisfied();
+        let cmr = satisfied.redeem().cmr();
+        let pset = decode_pset(&STANDARD.encode(encode::serialize(&pset(cmr)))).unwrap();
+        let env = from_pset(&pset, 0, cmr, BlockHash::all_zeros()).unwrap();
+        let mut mac = BitMachine::for_program(satisfied.redeem()).unwrap();
+        mac.exec(satisfied.redeem(), &env).unwrap();
+
+        assert!(from_pset(&pset, 1, cmr, BlockHash::all_zeros()).is_err());
+        assert!(from_pset(&pset, 0, Cmr::unit(), BlockHash::all_zeros()).is_err());
+    }
+
+    #[test]
+    fn control_block_from_witness() {
+        let satisfied = satisfied();
+        let cmr = satisfied.redeem().cmr();
+        let pset = pset(cmr);
+        let (control_block, _) = pset.inputs()[0].tap_scripts.iter().next().unwrap();
+        let mut tx = pset.extract_tx().unwrap();
+        let utxo = ElementsUtxo::from(pset.inputs()[0].witness_utxo.clone().unwrap());
+        assert!(from_transaction(
+            tx.clone(),
+            vec![utxo.clone()],
+            0,
+            cmr,
+            None,
+            BlockHash::all_zeros()
+        )
+        .is_err());
+
+        let (program_bytes, witness_bytes) = satisfied.redeem().to_vec_with_witness();
+        tx.input[0].witness.script_witness = vec![
+            witness_bytes,
+            program_bytes,
+            cmr.as_ref().to_vec(),
+            control_block.serialize(),
+        ];
+        let tx = decode_transaction(&encode::serialize_hex(&tx)).unwrap();
+        let env = from_transaction(tx, vec![utxo], 0, cmr, None, BlockHash::all_zeros()).unwrap();
+        assert_eq!(control_block, env.control_block());
+    }
+
+    #[test]
+    fn finalize_pset() {
+        let satisfied = satisfied();
+        let cmr = satisfied.redeem().cmr();
+        let mut pset = pset(cmr);
+        satisfied
+            .finalize_pset(&mut pset, 0, BlockHash::all_zeros())
+            .unwrap();
+        let input = &pset.inputs()[0];
+        assert!(input.tap_scripts.is_empty());
+        let stack = input.final_script_witness.as_ref().unwrap();
+        assert_eq!(4, stack.len());
+        assert_eq!(cmr.as_ref(), stack[2].as_slice());
+        let tx = pset.extract_tx().unwrap();
+        assert_eq!(stack, &tx.input[0].witness.script_witness);
+
+        let failing = CompiledProgram::new("fn main() { panic!() }", Arguments::default(), false)
+            .unwrap()
+            .satisfy(WitnessValues::default())
+            .unwrap();
+        let mut pset = self::pset(failing.redeem().cmr());
+        let unchanged = pset.clone();
+        assert!(failing
+            .finalize_pset(&mut pset, 0, BlockHash::all_zeros())
+            .is_err());
+        assert_eq!(unchanged, pset);
+    }
+}
@@ -0,0 +1,303 @@
+//! Equivalence checking of SimplicityHL programs.
+//!
+//! Two programs are equivalent if they accept exactly the same witness data in every environment.
+//! This is what matters when a contract is refactored: the new version must still accept
+//! the same spends and reject the same spends as the old version.
+//!
+//! Programs with the same CMR are identical.
+//! Otherwise, both programs are [executed symbolically](crate::symbolic) on the same witness data,
+//! and a SAT solver searches for witness data that one program accepts and the other rejects.
+//! Core jets are modeled precisely, while all other jets are uninterpreted functions
+//! that both programs share: calls of the same jet on the same input return the same output.
+//! So if the solver finds no such witness data, then the programs are equivalent.
+//!
+//! Witness data that distinguishes the programs might rely on uninterpreted jets
+//! returning values that they never return, for example when the programs compute
+//! the same hash in different ways. Therefore, the witness data is confirmed by executing
+//! both programs in a concrete environment.
+//!
+//! The search is bounded by a maximum number of conflicts of the SAT solver,
+//! because the equivalence of arithmetic, such as multiplication, can be hard to decide.
+
+use std::fmt;
+use std::sync::Arc;
+
+use simplicity::elements;
+use simplicity::jet::elements::ElementsEnv;
+
+use crate::sat::Outcome;
+use crate::symbolic::Executor;
+use crate::{dummy_env, CompiledProgram, WitnessValues};
+
+/// Equivalence check between two versions of a SimplicityHL program.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let old = CompiledProgram::new(old_source, Arguments::default(), false)?;
+/// let new = CompiledProgram::new(new_source, Arguments::default(), false)?;
+/// match EquivalenceCheck::new(&old, &new).check()? {
+///     Equivalence::Identical | Equivalence::Equivalent => println!("Refactoring is safe"),
+///     result => println!("{result}"),
+/// }
+/// ```
+pub struct EquivalenceCheck<'a> {
+    first: &'a CompiledProgram,
+    second: &'a CompiledProgram,
+    env: ElementsEnv<Arc<elements::Transaction>>,
+    max_conflicts: usize,
+}
+
+/// Result of an equivalence check.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Equivalence {
+    /// The programs have the same CMR.
+    Identical,
+    /// The programs accept the same witness data in every environment.
+    Equivalent,
+    /// The programs disagree on some witness data.
+    Different(Difference),
+    /// The SAT solver gave up after the maximum number of conflicts.
+    Unknown,
+}
+
+/// Witness data that one program accepts and the other program rejects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Difference {
+    witness_values: WitnessValues,
+    accepted_by_first: bool,
+    confirmed: bool,
+}
+
+impl Difference {
+    /// Access the witness values that distinguish the programs.
+    pub fn witness_values(&self) -> &WitnessValues {
+        &self.witness_values
+    }
+
+    /// Check if the first program accepts the witness values and the second program rejects them.
+    ///
+    /// Otherwise, the second program accepts them and the first program rejects them.
+    pub fn accepted_by_first(&self) -> bool {
+        self.accepted_by_first
+    }
+
+    /// Check if the programs disagree when they are executed in the environment of the check.
+    ///
+    /// If not, then the witness values distinguish the programs only if
+    /// the uninterpreted jets return particular values, which might never happen.
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed
+    }
+}
+
+impl fmt::Display for Equivalence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Equivalence::Identical => writeln!(f, "Programs are identical"),
+            Equivalence::Equivalent => writeln!(f, "Programs are equivalent"),
+            Equivalence::Different(difference) => {
+                let (accepting, rejecting) = match difference.accepted_by_first {
+                    true => ("first", "second"),
+                    false => ("second", "first"),
+                };
+                writeln!(
+                    f,
+                    "Programs are not equivalent: the {accepting} program accepts this witness and the {rejecting} program rejects it"
+                )?;
+                if !difference.confirmed {
+                    writeln!(
+                        f,
+                        "(not confirmed by execution: the programs differ only if jets return particular values)"
+                    )?;
+                }
+                writeln!(f, "{}", difference.witness_values)
+            }
+            Equivalence::Unknown => writeln!(
+                f,
+                "Equivalence is unknown: the solver gave up after the maximum number of conflicts"
+            ),
+        }
+    }
+}
+
+impl<'a> EquivalenceCheck<'a> {
+    /// Create an equivalence check between the given programs.
+    ///
+    /// By default, the check confirms differences in the dummy environment,
+    /// and the SAT solver gives up after 100 000 conflicts.
+    pub fn new(first: &'a CompiledProgram, second: &'a CompiledProgram) -> Self {
+        Self {
+            first,
+            second,
+            env: dummy_env::dummy(),
+            max_conflicts: 100_000,
+        }
+    }
+
+    /// Confirm differences by executing the programs in the given environment.
+    pub fn with_environment(mut self, env: ElementsEnv<Arc<elements::Transaction>>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Give up after the given number of conflicts of the SAT solver.
+    pub fn with_max_conflicts(mut self, max_conflicts: usize) -> Self {
+        self.max_conflicts = max_conflicts;
+        self
+    }
+
+    /// Check if the programs are equivalent.
+    ///
+    /// ## Errors
+    ///
+    /// The programs have different witness types.
+    pub fn check(&self) -> Result<Equivalence, String> {
+        if self.first.witness_types() != self.second.witness_types() {
+            return Err("Programs have different witness types".to_string());
+        }
+        if self.first.commit().cmr() == self.second.commit().cmr() {
+            return Ok(Equivalence::Identical);
+        }
+
+        let mut executor = Executor::new(self.first);
+        let first_succeeds = executor.run(self.first);
+        let second_succeeds = executor.run(self.second);
+        let differ = executor.circuit.xor(first_succeeds, second_succeeds);
+
+        let model = match executor.solve(differ, self.max_conflicts) {
+            Outcome::Satisfiable(model) => model,
+            Outcome::Unsatisfiable => return Ok(Equivalence::Equivalent),
+            Outcome::Unknown => return Ok(Equivalence::Unknown),
+        };
+        let witness_values = executor.witness_values(&model, self.first.witness_types());
+        let accepted_by_first = first_succeeds.evaluate(&model);
+        let confirmed = self.accepts(self.first, &witness_values) == accepted_by_first
+            && self.accepts(self.second, &witness_values) != accepted_by_first;
+
+        Ok(Equivalence::Different(Difference {
+            witness_values,
+            accepted_by_first,
+            confirmed,
+        }))
+    }
+
+    /// Check if the program succeeds with the given witness values in the environment of the check.
+    fn accepts(&self, program: &CompiledProgram, witness_values: &WitnessValues) -> bool {
+        program
+            .satisfy(witness_values.clone())
+            .and_then(|satisfied| {
+                satisfied
+                    .redeem()
+                    .prune(&self.env)
+                    .map_err(|error| error.to_string())
+            })
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::ParseFromStr;
+    use crate::Arguments;
+
+    fn check(first: &str, second: &str) -> Equivalence {
+        let first = CompiledProgram::new(first, Arguments::default(), false).unwrap();
+        let second = CompiledProgram::new(second, Arguments::default(), false).unwrap();
+        EquivalenceCheck::new(&first, &second).check().unwrap()
+    }
+
+    #[test]
+    fn identical_programs() {
+        let program = "fn main() { assert!(jet::eq_32(witness::X, 7)); }";
+        let renamed = "fn main() { let x: u32 = witness::X; assert!(jet::eq_32(x, 7)); }";
+        assert_eq!(Equivalence::Identical, check(program, program));
+        // Let bindings change the CMR, so the programs are only equivalent
+        assert_eq!(Equivalence::Equivalent, check(program, renamed));
+    }
+
+    #[test]
+    fn equivalent_programs() {
+        let first = r#"fn main() {
+    let x: u32 = witness::X;
+    assert!(jet::lt_32(x, 10));
+}"#;
+        let second = r#"fn main() {
+    let x: u32 = witness::X;
+    match jet::le_32(10, x) {
+        true => panic!(),
+        false => {},
+    };
+}"#;
+        assert_eq!(Equivalence::Equivalent, check(first, second));
+
+        // Both programs hash the witness in the same way
+        let first = r#"fn main() {
+    let ctx: Ctx8 = jet::sha_256_ctx_8_add_32(jet::sha_256_ctx_8_init(), witness::X);
+    assert!(jet::eq_256(jet::sha_256_ctx_8_finalize(ctx), 0x0000000000000000000000000000000000000000000000000000000000000001));
+}"#;
+        let second = r#"fn main() {
+    let x: u256 = witness::X;
+    let hash: u256 = jet::sha_256_ctx_8_finalize(jet::sha_256_ctx_8_add_32(jet::sha_256_ctx_8_init(), x));
+    match jet::eq_256(hash, 0x0000000000000000000000000000000000000000000000000000000000000001) {
+        true => {},
+        false => panic!(),
+    };
+}"#;
+        assert_eq!(Equivalence::Equivalent, check(first, second));
+    }
+
+    #[test]
+    fn signatures_depend_on_cmr() {
+        // The signature covers the CMR of the program via `jet::sig_all_hash`,
+        // so a signature for one program is invalid for the other
+        let first = r#"fn main() {
+    let pk: Pubkey = 0x79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798;
+    jet::bip_0340_verify((pk, jet::sig_all_hash()), witness::SIG);
+}"#;
+        let second = r#"fn main() {
+    let pk: Pubkey = 0x79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798;
+    let msg: u256 = jet::sig_all_hash();
+    let sig: Signature = witness::SIG;
+    jet::bip_0340_verify((pk, msg), sig);
+}"#;
+        assert!(matches!(check(first, second), Equivalence::Different(..)));
+    }
+
+    #[test]
+    fn different_programs() {
+        let first = r#"fn main() {
+    let x: u32 = witness::X;
+    assert!(jet::lt_32(x, 10));
+}"#;
+        let second = r#"fn main() {
+    let x: u32 = witness::X;
+    assert!(jet::le_32(x, 10));
+}"#;
+        let Equivalence::Different(difference) = check(first, second) else {
+            panic!("Programs should be different");
+        };
+        assert!(!difference.accepted_by_first());
+        assert!(difference.is_confirmed());
+        let expected = WitnessValues::parse_from_str("mod witness { const X: u32 = 10; }").unwrap();
+        assert_eq!(&expected, difference.witness_values());
+    }
+
+    #[test]
+    fn different_witness_types() {
+        let first = CompiledProgram::new(
+            "fn main() { assert!(jet::eq_32(witness::X, 7)); }",
+            Arguments::default(),
+            false,
+        )
+        .unwrap();
+        let second = CompiledProgram::new(
+            "fn main() { assert!(jet::eq_16(witness::X, 7)); }",
+            Arguments::default(),
+            false,
+        )
+        .unwrap();
+        assert!(EquivalenceCheck::new(&first, &second).check().is_err());
+    }
+}
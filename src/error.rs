@@ -83,6 +83,17 @@ impl Span {
         Self { start, end }
     }
 
+    /// Check if the span covers the `other` span.
+    pub const fn contains(&self, other: &Span) -> bool {
+        let after_start = self.start.line.get() < other.start.line.get()
+            || (self.start.line.get() == other.start.line.get()
+                && self.start.col.get() <= other.start.col.get());
+        let before_end = other.end.line.get() < self.end.line.get()
+            || (other.end.line.get() == self.end.line.get()
+                && other.end.col.get() <= self.end.col.get());
+        after_start && before_end
+    }
+
     /// Check if the span covers more than one line.
     pub const fn is_multiline(&self) -> bool {
         self.start.line.get() < self.end.line.get()
@@ -235,29 +246,7 @@ impl fmt::Display for RichError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.file {
             Some(ref file) if !file.is_empty() => {
-                let start_line_index = self.span.start.line.get() - 1;
-                let n_spanned_lines = self.span.end.line.get() - start_line_index;
-                let line_num_width = self.span.end.line.get().to_string().len();
-                writeln!(f, "{:width$} |", " ", width = line_num_width)?;
-
-                let mut lines = file.lines().skip(start_line_index).peekable();
-                let start_line_len = lines.peek().map_or(0, |l| l.len());
-
-                for (relative_line_index, line_str) in lines.take(n_spanned_lines).enumerate() {
-                    let line_num = start_line_index + relative_line_index + 1;
-                    writeln!(f, "{line_num:line_num_width$} | {line_str}")?;
-                }
-
-                let (underline_start, underline_length) = match self.span.is_multiline() {
-                    true => (0, start_line_len),
-                    false => (
-                        self.span.start.col.get(),
-                        self.span.end.col.get() - self.span.start.col.get(),
-                    ),
-                };
-                write!(f, "{:width$} |", " ", width = line_num_width)?;
-                write!(f, "{:width$}", " ", width = underline_start)?;
-                write!(f, "{:^<width$} ", "", width = underline_length)?;
+                write_snippet(f, file, &self.span)?;
                 write!(f, "{}", self.error)
             }
             _ => {
@@ -267,6 +256,36 @@ impl fmt::Display for RichError {
     }
 }
 
+/// Write the lines of the `file` that the `span` covers,
+/// followed by a line that underlines the span.
+///
+/// The caller writes the message after the underline.
+pub(crate) fn write_snippet(f: &mut fmt::Formatter<'_>, file: &str, span: &Span) -> fmt::Result {
+    let start_line_index = span.start.line.get() - 1;
+    let n_spanned_lines = span.end.line.get() - start_line_index;
+    let line_num_width = span.end.line.get().to_string().len();
+    writeln!(f, "{:width$} |", " ", width = line_num_width)?;
+
+    let mut lines = file.lines().skip(start_line_index).peekable();
+    let start_line_len = lines.peek().map_or(0, |l| l.len());
+
+    for (relative_line_index, line_str) in lines.take(n_spanned_lines).enumerate() {
+        let line_num = start_line_index + relative_line_index + 1;
+        writeln!(f, "{line_num:line_num_width$} | {line_str}")?;
+    }
+
+    let (underline_start, underline_length) = match span.is_multiline() {
+        true => (0, start_line_len),
+        false => (
+            span.start.col.get(),
+            span.end.col.get() - span.start.col.get(),
+        ),
+    };
+    write!(f, "{:width$} |", " ", width = line_num_width)?;
+    write!(f, "{:width$}", " ", width = underline_start)?;
+    write!(f, "{:^<width$} ", "", width = underline_length)
+}
+
 impl std::error::Error for RichError {}
 
 impl From<RichError> for Error {
@@ -319,6 +338,7 @@ pub enum Error {
     MainNoInputs,
     MainNoOutput,
     MainRequired,
+    EntryPointUndefined(FunctionName),
     FunctionRedefined(FunctionName),
     FunctionUndefined(FunctionName),
     InvalidNumberOfArguments(usize, usize),
@@ -337,13 +357,15 @@ pub enum Error {
     WitnessOutsideMain,
     ModuleRequired(ModuleName),
     ModuleRedefined(ModuleName),
-    ArgumentMissing(WitnessName),
+    ArgumentMissing(WitnessName, ResolvedType),
     ArgumentTypeMismatch(WitnessName, ResolvedType, ResolvedType),
     InvalidJsonFormat(String),
     UndefinedWitness(WitnessName),
     UndefinedParameter(WitnessName),
     WitnessMultipleAssignments(WitnessName),
     ArgumentMultipleAssignments(WitnessName),
+    LintDoesNotExist(String),
+    AttributeRequiresTest(String),
 }
 
 #[rustfmt::skip]
@@ -406,6 +428,10 @@ impl fmt::Display for Error {
                 f,
                 "Main function is required"
             ),
+            Error::EntryPointUndefined(name) => write!(
+                f,
+                "Entry point `{name}` is not defined"
+            ),
             Error::FunctionRedefined(name) => write!(
                 f,
                 "Function `{name}` was defined multiple times"
@@ -478,9 +504,9 @@ impl fmt::Display for Error {
                 f,
                 "Module `{name}` is defined twice"
             ),
-            Error::ArgumentMissing(name) => write!(
+            Error::ArgumentMissing(name, declared) => write!(
                 f,
-                "Parameter `{name}` is missing an argument"
+                "Parameter `{name}` of type `{declared}` is missing an argument"
             ),
             Error::ArgumentTypeMismatch(name, declared, assigned) => write!(
                 f,
@@ -506,6 +532,14 @@ impl fmt::Display for Error {
                 f,
                 "Argument `{name}` is assigned multiple times in JSON"
             ),
+            Error::AttributeRequiresTest(attribute) => write!(
+                f,
+                "Attribute `{attribute}` is only allowed on unit tests with `#[test]`"
+            ),
+            Error::LintDoesNotExist(name) => write!(
+                f,
+                "Lint `{name}` does not exist"
+            ),
         }
     }
 }
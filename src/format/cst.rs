@@ -0,0 +1,478 @@
+//! Lossless concrete syntax tree.
+//!
+//! The tree consists of tokens and of groups of tokens between matching delimiters.
+//! Each token keeps the whitespace and comments in front of it,
+//! so the tree reproduces its source text exactly.
+//!
+//! The tree does not know the grammar of SimplicityHL.
+//! It is meant for tools such as the formatter which must not lose comments.
+
+use std::fmt;
+
+use crate::error::{Error, Position, RichError, Span};
+
+/// Lossless syntax tree of a source file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxTree {
+    nodes: Vec<Node>,
+    end: Token,
+}
+
+/// Node of a syntax tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Node {
+    /// Single token.
+    Token(Token),
+    /// Group of nodes between matching delimiters.
+    Group(Group),
+}
+
+/// Group of nodes between matching delimiters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Group {
+    delimiter: Delimiter,
+    open: Token,
+    nodes: Vec<Node>,
+    close: Token,
+}
+
+/// Delimiter of a group.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Delimiter {
+    /// `( ... )`
+    Parenthesis,
+    /// `[ ... ]`
+    Bracket,
+    /// `{ ... }`
+    Brace,
+    /// `< ... >`
+    Angle,
+}
+
+/// Token including the trivia in front of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    trivia: Vec<Trivia>,
+    kind: TokenKind,
+    text: String,
+    span: Span,
+}
+
+/// Kind of a token.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// Identifier or keyword.
+    Identifier,
+    /// Decimal, binary or hexadecimal literal.
+    Number,
+    /// String literal.
+    String,
+    /// Punctuation such as `:`, `::` or `=>`.
+    Punctuation,
+    /// Opening delimiter.
+    Open(Delimiter),
+    /// Closing delimiter.
+    Close(Delimiter),
+    /// End of the file.
+    ///
+    /// The text of this token is empty.
+    End,
+}
+
+/// Whitespace or comment in front of a token.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Trivia {
+    /// Whitespace, including line breaks.
+    Whitespace(String),
+    /// Comment from `//` until the end of the line, exclusively.
+    LineComment(String),
+    /// Comment between `/*` and `*/`.
+    BlockComment(String),
+}
+
+impl SyntaxTree {
+    /// Parse the lossless syntax tree of the given source text.
+    ///
+    /// ## Errors
+    ///
+    /// The text contains an unterminated comment or string literal, or unmatched delimiters.
+    pub fn parse(source: &str) -> Result<Self, RichError> {
+        let mut lexer = Lexer::new(source);
+        let mut stack: Vec<(Token, Vec<Node>)> = Vec::new();
+        let mut nodes = Vec::new();
+
+        loop {
+            let token = lexer.next_token()?;
+            match token.kind {
+                TokenKind::Open(_) => stack.push((token, std::mem::take(&mut nodes))),
+                TokenKind::Close(delimiter) => match stack.pop() {
+                    Some((open, outer)) if open.kind == TokenKind::Open(delimiter) => {
+                        let inner = std::mem::replace(&mut nodes, outer);
+                        nodes.push(Node::Group(Group {
+                            delimiter,
+                            open,
+                            nodes: inner,
+                            close: token,
+                        }));
+                    }
+                    _ => {
+                        let error = Error::CannotParse(format!("Unmatched `{}`", token.text));
+                        return Err(RichError::new(error, token.span));
+                    }
+                },
+                TokenKind::End => {
+                    if let Some((open, _)) = stack.pop() {
+                        let error = Error::CannotParse(format!("Unclosed `{}`", open.text));
+                        return Err(RichError::new(error, open.span));
+                    }
+                    return Ok(Self { nodes, end: token });
+                }
+                _ => nodes.push(Node::Token(token)),
+            }
+        }
+    }
+
+    /// Access the top-level nodes of the tree.
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Access the end of the file, which holds the trivia after the last node.
+    pub fn end(&self) -> &Token {
+        &self.end
+    }
+
+    /// Iterate over all tokens of the tree in source order, including the end of the file.
+    pub fn tokens(&self) -> impl Iterator<Item = &Token> {
+        let mut tokens = Vec::new();
+        for node in &self.nodes {
+            node.collect_tokens(&mut tokens);
+        }
+        tokens.push(&self.end);
+        tokens.into_iter()
+    }
+}
+
+impl Node {
+    /// Access the first token of the node.
+    pub fn first_token(&self) -> &Token {
+        match self {
+            Node::Token(token) => token,
+            Node::Group(group) => &group.open,
+        }
+    }
+
+    /// Access the last token of the node.
+    pub fn last_token(&self) -> &Token {
+        match self {
+            Node::Token(token) => token,
+            Node::Group(group) => &group.close,
+        }
+    }
+
+    /// Check if the node is the punctuation of the given text.
+    pub fn is_punctuation(&self, text: &str) -> bool {
+        matches!(self, Node::Token(token) if token.kind == TokenKind::Punctuation && token.text == text)
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a Token>) {
+        match self {
+            Node::Token(token) => tokens.push(token),
+            Node::Group(group) => {
+                tokens.push(&group.open);
+                for node in &group.nodes {
+                    node.collect_tokens(tokens);
+                }
+                tokens.push(&group.close);
+            }
+        }
+    }
+}
+
+impl Group {
+    /// Access the delimiter of the group.
+    pub fn delimiter(&self) -> Delimiter {
+        self.delimiter
+    }
+
+    /// Access the opening delimiter.
+    pub fn open(&self) -> &Token {
+        &self.open
+    }
+
+    /// Access the nodes between the delimiters.
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Access the closing delimiter.
+    pub fn close(&self) -> &Token {
+        &self.close
+    }
+}
+
+impl Token {
+    /// Access the whitespace and comments in front of the token.
+    pub fn trivia(&self) -> &[Trivia] {
+        &self.trivia
+    }
+
+    /// Access the kind of the token.
+    pub fn kind(&self) -> TokenKind {
+        self.kind
+    }
+
+    /// Access the text of the token.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Access the span of the token in the source text.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Trivia {
+    /// Check if the trivia is a comment.
+    pub fn is_comment(&self) -> bool {
+        !matches!(self, Trivia::Whitespace(..))
+    }
+
+    /// Access the text of the trivia.
+    pub fn text(&self) -> &str {
+        match self {
+            Trivia::Whitespace(text) | Trivia::LineComment(text) | Trivia::BlockComment(text) => {
+                text
+            }
+        }
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in &self.nodes {
+            write!(f, "{node}")?;
+        }
+        write!(f, "{}", self.end)
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Token(token) => write!(f, "{token}"),
+            Node::Group(group) => {
+                write!(f, "{}", group.open)?;
+                for node in &group.nodes {
+                    write!(f, "{node}")?;
+                }
+                write!(f, "{}", group.close)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for trivia in &self.trivia {
+            write!(f, "{}", trivia.text())?;
+        }
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Lexer that keeps whitespace and comments.
+struct Lexer<'a> {
+    source: &'a str,
+    offset: usize,
+    line: usize,
+    col: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            offset: 0,
+            line: 1,
+            col: 1,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.offset..]
+    }
+
+    fn position(&self) -> Position {
+        Position::new(self.line, self.col)
+    }
+
+    /// Consume the given number of bytes and return the consumed text.
+    fn bump(&mut self, len: usize) -> &'a str {
+        let text = &self.rest()[..len];
+        for c in text.chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.col = 1;
+            } else {
+                self.col += 1;
+            }
+        }
+        self.offset += len;
+        text
+    }
+
+    /// Consume the longest prefix whose characters satisfy the predicate.
+    fn bump_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let len = self
+            .rest()
+            .find(|c| !predicate(c))
+            .unwrap_or(self.rest().len());
+        self.bump(len)
+    }
+
+    fn next_token(&mut self) -> Result<Token, RichError> {
+        let trivia = self.trivia()?;
+        let start = self.position();
+        let rest = self.rest();
+        let Some(c) = rest.chars().next() else {
+            return Ok(Token {
+                trivia,
+                kind: TokenKind::End,
+                text: String::new(),
+                span: Span::new(start, start),
+            });
+        };
+
+        let (kind, text) = if c.is_ascii_alphabetic() || c == '_' {
+            let text = self.bump_while(|c| c.is_ascii_alphanumeric() || c == '_');
+            (TokenKind::Identifier, text)
+        } else if c.is_ascii_digit() {
+            let text = self.bump_while(|c| c.is_ascii_alphanumeric() || c == '_');
+            (TokenKind::Number, text)
+        } else if c == '"' {
+            match rest[1..].find(['"', '\n']) {
+                Some(len) if rest[1 + len..].starts_with('"') => {
+                    (TokenKind::String, self.bump(len + 2))
+                }
+                _ => {
+                    let error = Error::CannotParse("Unterminated string literal".to_string());
+                    return Err(RichError::new(error, Span::new(start, start)));
+                }
+            }
+        } else if ["::", "->", "=>"].iter().any(|p| rest.starts_with(p)) {
+            (TokenKind::Punctuation, self.bump(2))
+        } else {
+            let kind = match c {
+                '(' => TokenKind::Open(Delimiter::Parenthesis),
+                '[' => TokenKind::Open(Delimiter::Bracket),
+                '{' => TokenKind::Open(Delimiter::Brace),
+                '<' => TokenKind::Open(Delimiter::Angle),
+                ')' => TokenKind::Close(Delimiter::Parenthesis),
+                ']' => TokenKind::Close(Delimiter::Bracket),
+                '}' => TokenKind::Close(Delimiter::Brace),
+                '>' => TokenKind::Close(Delimiter::Angle),
+                _ => TokenKind::Punctuation,
+            };
+            (kind, self.bump(c.len_utf8()))
+        };
+
+        Ok(Token {
+            trivia,
+            kind,
+            text: text.to_string(),
+            span: Span::new(start, self.position()),
+        })
+    }
+
+    fn trivia(&mut self) -> Result<Vec<Trivia>, RichError> {
+        let mut trivia = Vec::new();
+        loop {
+            let rest = self.rest();
+            if rest.starts_with(char::is_whitespace) {
+                let text = self.bump_while(char::is_whitespace);
+                trivia.push(Trivia::Whitespace(text.to_string()));
+            } else if rest.starts_with("//") {
+                let text = self.bump_while(|c| c != '\n');
+                trivia.push(Trivia::LineComment(text.to_string()));
+            } else if let Some(body) = rest.strip_prefix("/*") {
+                let start = self.position();
+                let Some(len) = body.find("*/") else {
+                    let error = Error::CannotParse("Unterminated block comment".to_string());
+                    return Err(RichError::new(error, Span::new(start, start)));
+                };
+                let text = self.bump(len + 4);
+                trivia.push(Trivia::BlockComment(text.to_string()));
+            } else {
+                return Ok(trivia);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lossless() {
+        let source = r#"/* header */
+#[witness("x.wit")]
+fn main() { // start
+    let (a, b): (u8, u16) = (0x1_f, <u8>::into(witness::B)); /* inline */
+    assert!(jet::eq_8(a, unwrap_left::<u8>(Left(1))));
+}
+// end
+"#;
+        let tree = SyntaxTree::parse(source).unwrap();
+        assert_eq!(source, tree.to_string());
+        assert_eq!(
+            3,
+            tree.nodes()
+                .iter()
+                .filter(|node| matches!(node, Node::Group(..)))
+                .count()
+        );
+
+        let comments = tree
+            .tokens()
+            .flat_map(Token::trivia)
+            .filter(|trivia| trivia.is_comment())
+            .map(Trivia::text)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec!["/* header */", "// start", "/* inline */", "// end"],
+            comments
+        );
+    }
+
+    #[test]
+    fn tokens() {
+        let tree = SyntaxTree::parse("x => jet::add_8 -> 0b01").unwrap();
+        let tokens = tree
+            .tokens()
+            .map(|token| (token.kind(), token.text()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (TokenKind::Identifier, "x"),
+                (TokenKind::Punctuation, "=>"),
+                (TokenKind::Identifier, "jet"),
+                (TokenKind::Punctuation, "::"),
+                (TokenKind::Identifier, "add_8"),
+                (TokenKind::Punctuation, "->"),
+                (TokenKind::Number, "0b01"),
+                (TokenKind::End, ""),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn invalid_trees() {
+        for source in ["fn main() {", "(]", "/* open", "\"open"] {
+            assert!(SyntaxTree::parse(source).is_err(), "{source}");
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Pretty printer in the style of Wadler's "A prettier printer".
+//!
+//! A document is laid out within a maximum line width.
+//! Each group is printed flat if it fits on the rest of the line
+//! up to the next point where a later group could break.
+//! Otherwise, its line breaks are printed as newlines.
+
+/// Number of spaces per indentation level.
+const INDENT: usize = 4;
+
+#[derive(Clone, Debug)]
+pub(crate) enum Doc {
+    /// Text without newlines, except inside block comments.
+    Text(String),
+    /// Space if the group is flat, newline otherwise.
+    Line,
+    /// Nothing if the group is flat, newline otherwise.
+    SoftLine,
+    /// Newline that breaks the enclosing groups.
+    ///
+    /// Nothing at the start of a line.
+    HardLine,
+    /// Empty line that breaks the enclosing groups.
+    ///
+    /// At most one empty line is printed in a row.
+    BlankLine,
+    /// Text that is printed only if the group is broken.
+    IfBreak(&'static str),
+    /// Sequence of documents.
+    Concat(Vec<Doc>),
+    /// Sequence of documents that is indented after newlines.
+    Indent(Vec<Doc>),
+    /// Sequence of documents that is printed flat or broken as a whole.
+    Group(Vec<Doc>),
+}
+
+impl Doc {
+    pub fn text<S: Into<String>>(text: S) -> Self {
+        Doc::Text(text.into())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// Print the document within the given line width.
+pub(crate) fn render(doc: &Doc, width: usize) -> String {
+    let mut printer = Printer {
+        output: String::new(),
+        column: 0,
+        line_start: true,
+        blank_line: true,
+    };
+    let mut stack = vec![(0, Mode::Break, doc)];
+
+    while let Some((indent, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => printer.text(indent, text),
+            Doc::Line | Doc::SoftLine if mode == Mode::Break => printer.newline(),
+            Doc::Line => printer.text(indent, " "),
+            Doc::SoftLine => {}
+            Doc::HardLine => printer.newline(),
+            Doc::BlankLine => printer.blank_line(),
+            Doc::IfBreak(text) => {
+                if mode == Mode::Break {
+                    printer.text(indent, text);
+                }
+            }
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc))),
+            Doc::Indent(docs) => {
+                stack.extend(docs.iter().rev().map(|doc| (indent + INDENT, mode, doc)));
+            }
+            Doc::Group(docs) => {
+                let column = if printer.line_start {
+                    indent
+                } else {
+                    printer.column
+                };
+                let remaining = width as isize - column as isize;
+                let mode = match mode {
+                    Mode::Flat => Mode::Flat,
+                    Mode::Break if fits(docs, &stack, remaining) => Mode::Flat,
+                    Mode::Break => Mode::Break,
+                };
+                stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+            }
+        }
+    }
+
+    printer.output
+}
+
+/// Check if the flat group fits on the rest of the line.
+///
+/// The group is followed by the documents on the stack.
+/// These are measured up to their next line break, assuming that their groups break.
+fn fits(group: &[Doc], rest: &[(usize, Mode, &Doc)], mut remaining: isize) -> bool {
+    let mut stack: Vec<_> = group.iter().rev().map(|doc| (Mode::Flat, doc)).collect();
+    let mut rest = rest.iter().rev();
+
+    while remaining >= 0 {
+        let (mode, doc) = match stack.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some((_, mode, doc)) => (*mode, *doc),
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(text) => remaining -= text.chars().count() as isize,
+            Doc::Line | Doc::SoftLine if mode == Mode::Break => return true,
+            Doc::Line => remaining -= 1,
+            Doc::SoftLine => {}
+            Doc::HardLine | Doc::BlankLine => return mode == Mode::Break,
+            Doc::IfBreak(text) => {
+                if mode == Mode::Break {
+                    remaining -= text.len() as isize;
+                }
+            }
+            Doc::Concat(docs) | Doc::Indent(docs) | Doc::Group(docs) => {
+                stack.extend(docs.iter().rev().map(|doc| (mode, doc)));
+            }
+        }
+    }
+
+    false
+}
+
+struct Printer {
+    output: String,
+    column: usize,
+    line_start: bool,
+    blank_line: bool,
+}
+
+impl Printer {
+    fn text(&mut self, indent: usize, text: &str) {
+        if self.line_start {
+            if text.trim().is_empty() {
+                return;
+            }
+            self.output.push_str(&" ".repeat(indent));
+            self.column = indent;
+            self.line_start = false;
+            self.blank_line = false;
+        }
+        self.output.push_str(text);
+        self.column = match text.rfind('\n') {
+            Some(index) => text[index + 1..].chars().count(),
+            None => self.column + text.chars().count(),
+        };
+    }
+
+    fn newline(&mut self) {
+        if !self.line_start {
+            let len = self.output.trim_end_matches(' ').len();
+            self.output.truncate(len);
+            self.output.push('\n');
+            self.column = 0;
+            self.line_start = true;
+        }
+    }
+
+    fn blank_line(&mut self) {
+        self.newline();
+        if !self.blank_line {
+            self.output.push('\n');
+            self.blank_line = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(items: &[&str]) -> Doc {
+        let mut inner = vec![Doc::SoftLine];
+        for (index, item) in items.iter().enumerate() {
+            if 0 < index {
+                inner.extend([Doc::text(","), Doc::Line]);
+            }
+            inner.push(Doc::text(*item));
+        }
+        inner.push(Doc::IfBreak(","));
+        Doc::Group(vec![
+            Doc::text("("),
+            Doc::Indent(inner),
+            Doc::SoftLine,
+            Doc::text(")"),
+        ])
+    }
+
+    #[test]
+    fn break_groups_that_do_not_fit() {
+        let doc = Doc::Concat(vec![Doc::text("f"), list(&["a", "b"]), Doc::text(";")]);
+        assert_eq!("f(a, b);", render(&doc, 8));
+        assert_eq!("f(\n    a,\n    b,\n);", render(&doc, 7));
+    }
+
+    #[test]
+    fn break_last_group_first() {
+        let doc = Doc::Concat(vec![
+            list(&["a", "b"]),
+            Doc::text(" = f"),
+            list(&["c", "d"]),
+        ]);
+        assert_eq!("(a, b) = f(\n    c,\n    d,\n)", render(&doc, 12));
+    }
+
+    #[test]
+    fn hard_lines_break_groups() {
+        let doc = Doc::Group(vec![
+            Doc::text("a"),
+            Doc::Line,
+            Doc::text("// b"),
+            Doc::HardLine,
+            Doc::BlankLine,
+            Doc::BlankLine,
+            Doc::text("c"),
+        ]);
+        assert_eq!("a\n// b\n\nc", render(&doc, 80));
+    }
+}
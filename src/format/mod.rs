@@ -0,0 +1,579 @@
+//! Source code formatter.
+//!
+//! The formatter lays out the [lossless syntax tree](cst) of a program,
+//! so comments survive formatting.
+//!
+//! Items, statements and match arms go on separate lines,
+//! and blocks are indented by four spaces.
+//! Lists in parentheses, brackets and angle brackets stay on one line if they fit
+//! within the line width. Otherwise, each element goes on its own line.
+//! At most one empty line is kept between statements.
+//!
+//! Formatting is stable: formatting the output again does not change it.
+//! The output is checked to parse to the same program as the input.
+
+pub mod cst;
+mod doc;
+
+use std::sync::Arc;
+
+use crate::parse::{self, ParseFromStr};
+use cst::{Delimiter, Group, Node, SyntaxTree, Token, TokenKind, Trivia};
+use doc::Doc;
+
+/// Formatter of SimplicityHL source code.
+#[derive(Clone, Debug)]
+pub struct Formatter {
+    line_width: usize,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Formatter {
+    /// Create a formatter with a line width of 100 characters.
+    pub fn new() -> Self {
+        Self { line_width: 100 }
+    }
+
+    /// Lay out the code within the given number of characters per line, where possible.
+    pub fn with_line_width(mut self, line_width: usize) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    /// Format the source code of a program.
+    ///
+    /// ## Errors
+    ///
+    /// The source code is not a valid SimplicityHL program.
+    pub fn format(&self, source: &str) -> Result<String, String> {
+        let file: Arc<str> = Arc::from(source);
+        let program = parse::Program::parse_from_str(source)
+            .map_err(|error| error.with_file(file.clone()))?;
+        let tree = SyntaxTree::parse(source).map_err(|error| error.with_file(file))?;
+        let formatted = doc::render(&program_doc(&tree), self.line_width);
+
+        match parse::Program::parse_from_str(&formatted) {
+            Ok(formatted_program) if formatted_program == program => Ok(formatted),
+            _ => Err("Formatting would change the program; this is a bug".to_string()),
+        }
+    }
+
+    /// Check if the source code of a program is formatted.
+    ///
+    /// ## Errors
+    ///
+    /// The source code is not a valid SimplicityHL program.
+    pub fn is_formatted(&self, source: &str) -> Result<bool, String> {
+        self.format(source).map(|formatted| formatted == source)
+    }
+}
+
+/// Comment in front of a token.
+struct Comment<'a> {
+    text: &'a str,
+    /// Number of line breaks between the previous comment or token and the comment.
+    newlines: usize,
+    /// Whether the comment runs until the end of the line.
+    line: bool,
+}
+
+/// Return the comments in front of the token,
+/// and the number of line breaks between the last comment and the token.
+fn comments(token: &Token) -> (Vec<Comment<'_>>, usize) {
+    let mut comments = Vec::new();
+    let mut newlines = 0;
+    for trivia in token.trivia() {
+        match trivia {
+            Trivia::Whitespace(text) => newlines += text.matches('\n').count(),
+            Trivia::LineComment(text) | Trivia::BlockComment(text) => {
+                comments.push(Comment {
+                    text: text.trim_end(),
+                    newlines,
+                    line: matches!(trivia, Trivia::LineComment(..)),
+                });
+                newlines = 0;
+            }
+        }
+    }
+    (comments, newlines)
+}
+
+/// Return the number of comments at the start that are on the same line as the previous token.
+fn same_line(comments: &[Comment]) -> usize {
+    comments
+        .iter()
+        .position(|comment| 0 < comment.newlines)
+        .unwrap_or(comments.len())
+}
+
+/// Lay out comments at the end of the previous line.
+fn trailing_comments(comments: &[Comment], docs: &mut Vec<Doc>) {
+    for comment in comments {
+        docs.extend([Doc::text(" "), Doc::text(comment.text)]);
+        if comment.line {
+            docs.push(Doc::HardLine);
+        }
+    }
+}
+
+/// Lay out comments and the separator to the following token,
+/// which is the given number of line breaks after the last comment.
+fn comment_lines(comments: &[Comment], newlines: usize, docs: &mut Vec<Doc>) {
+    for (index, comment) in comments.iter().enumerate() {
+        docs.push(Doc::text(comment.text));
+        let newlines = comments
+            .get(index + 1)
+            .map_or(newlines, |next| next.newlines);
+        docs.push(match newlines {
+            0 if !comment.line => Doc::text(" "),
+            0 | 1 => Doc::HardLine,
+            _ => Doc::BlankLine,
+        });
+    }
+}
+
+/// Lay out the token after skipping the given number of its comments.
+fn token(token: &Token, skip: usize) -> Doc {
+    let (comments, newlines) = comments(token);
+    let mut docs = Vec::new();
+    if let Some(first) = comments.get(skip) {
+        if 0 < first.newlines {
+            docs.push(Doc::HardLine);
+        }
+        comment_lines(&comments[skip..], newlines, &mut docs);
+    }
+    docs.push(Doc::text(token.text()));
+    Doc::Concat(docs)
+}
+
+fn program_doc(tree: &SyntaxTree) -> Doc {
+    let mut docs = entries(tree.nodes(), tree.end(), true);
+    docs.push(Doc::HardLine);
+    Doc::Concat(docs)
+}
+
+/// Split the nodes into items, statements or match arms.
+fn split_entries(nodes: &[Node]) -> Vec<&[Node]> {
+    let mut entries = Vec::new();
+    let mut start = 0;
+    for (index, node) in nodes.iter().enumerate() {
+        let is_end = match node {
+            Node::Token(..) => node.is_punctuation(";") || node.is_punctuation(","),
+            Node::Group(group) => {
+                group.delimiter() == Delimiter::Brace
+                    && !nodes
+                        .get(index + 1)
+                        .is_some_and(|next| next.is_punctuation(";") || next.is_punctuation(","))
+            }
+        };
+        if is_end {
+            entries.push(&nodes[start..=index]);
+            start = index + 1;
+        }
+    }
+    if start < nodes.len() {
+        entries.push(&nodes[start..]);
+    }
+    entries
+}
+
+/// Check if the entry has a body in braces, such as a function.
+fn has_body(entry: &[Node]) -> bool {
+    entry
+        .iter()
+        .any(|node| matches!(node, Node::Group(group) if group.delimiter() == Delimiter::Brace))
+}
+
+/// Lay out the entries on separate lines, followed by the comments in front of the closing token.
+///
+/// Items on the top level are separated by empty lines if one of them has a body.
+fn entries(nodes: &[Node], close: &Token, top_level: bool) -> Vec<Doc> {
+    let mut docs = Vec::new();
+    let mut previous: Option<&[Node]> = None;
+    // Comments at the start of the file are not on the same line as a previous token
+    let has_previous_line = |previous: Option<&[Node]>| previous.is_some() || !top_level;
+
+    for entry in split_entries(nodes) {
+        let first = entry[0].first_token();
+        let (comments, newlines) = comments(first);
+        let trailing = match has_previous_line(previous) {
+            true => same_line(&comments),
+            false => 0,
+        };
+        trailing_comments(&comments[..trailing], &mut docs);
+
+        let own_line = &comments[trailing..];
+        let blank = previous.is_some_and(|previous| {
+            let newlines = own_line
+                .first()
+                .map_or(newlines, |comment| comment.newlines);
+            1 < newlines || top_level && (has_body(previous) || has_body(entry))
+        });
+        docs.push(if blank { Doc::BlankLine } else { Doc::HardLine });
+        comment_lines(own_line, newlines, &mut docs);
+        docs.extend(sequence(entry, comments.len()));
+        previous = Some(entry);
+    }
+
+    let (comments, newlines) = comments(close);
+    let trailing = match has_previous_line(previous) {
+        true => same_line(&comments),
+        false => 0,
+    };
+    trailing_comments(&comments[..trailing], &mut docs);
+    let own_line = &comments[trailing..];
+    if let Some(first) = own_line.first() {
+        let blank = previous.is_some() && 1 < first.newlines;
+        docs.push(if blank { Doc::BlankLine } else { Doc::HardLine });
+        comment_lines(own_line, newlines, &mut docs);
+    }
+    docs
+}
+
+/// Lay out the nodes of an entry or list element on one line, if possible.
+///
+/// The given number of comments in front of the first node are skipped.
+fn sequence(nodes: &[Node], skip: usize) -> Vec<Doc> {
+    let mut docs = Vec::new();
+    let mut previous: Option<&Token> = None;
+    for (index, node) in nodes.iter().enumerate() {
+        if let Some(previous) = previous {
+            if is_attribute(&nodes[..index]) {
+                docs.push(Doc::HardLine);
+            } else if space_between(previous, node.first_token()) {
+                docs.push(Doc::text(" "));
+            }
+        }
+        let skip = if index == 0 { skip } else { 0 };
+        docs.push(match node {
+            Node::Token(token) => self::token(token, skip),
+            Node::Group(group) if group.delimiter() == Delimiter::Brace => block(group, skip),
+            Node::Group(group) => list(group, previous, skip),
+        });
+        previous = Some(node.last_token());
+    }
+    docs
+}
+
+/// Check if the nodes end with an attribute such as `#[test]`.
+fn is_attribute(nodes: &[Node]) -> bool {
+    match nodes {
+        [.., hash, Node::Group(group)] => {
+            hash.is_punctuation("#") && group.delimiter() == Delimiter::Bracket
+        }
+        _ => false,
+    }
+}
+
+fn is_keyword(text: &str) -> bool {
+    matches!(text, "let" | "match" | "fn" | "type" | "mod" | "const")
+}
+
+fn space_between(previous: &Token, next: &Token) -> bool {
+    match (previous.kind(), next.kind()) {
+        (TokenKind::Open(..), _) | (_, TokenKind::Close(..)) => false,
+        (_, TokenKind::Punctuation) if matches!(next.text(), "," | ";" | ":" | "::" | "!") => false,
+        (TokenKind::Punctuation, _) if matches!(previous.text(), "::" | "!" | "#") => false,
+        (_, TokenKind::Open(Delimiter::Brace)) => true,
+        (TokenKind::Identifier, TokenKind::Open(..)) => is_keyword(previous.text()),
+        (TokenKind::Close(..), TokenKind::Open(..)) => false,
+        _ => true,
+    }
+}
+
+/// Check if the token is followed by the arguments of a call, rather than by a tuple.
+fn is_callee(token: &Token) -> bool {
+    match token.kind() {
+        TokenKind::Identifier => !is_keyword(token.text()),
+        TokenKind::Close(Delimiter::Angle) => true,
+        _ => token.text() == "!",
+    }
+}
+
+fn block(group: &Group, skip: usize) -> Doc {
+    let open = token(group.open(), skip);
+    let close = token(group.close(), usize::MAX);
+    if group.nodes().is_empty() && comments(group.close()).0.is_empty() {
+        return Doc::Concat(vec![open, close]);
+    }
+    let inner = entries(group.nodes(), group.close(), false);
+    Doc::Concat(vec![open, Doc::Indent(inner), Doc::HardLine, close])
+}
+
+/// Element of a comma-separated list.
+struct Element<'a> {
+    nodes: &'a [Node],
+    comma: Option<&'a Token>,
+}
+
+fn split_elements(nodes: &[Node]) -> Vec<Element<'_>> {
+    let mut elements = Vec::new();
+    let mut start = 0;
+    for (index, node) in nodes.iter().enumerate() {
+        if let (true, Node::Token(comma)) = (node.is_punctuation(","), node) {
+            elements.push(Element {
+                nodes: &nodes[start..index],
+                comma: Some(comma),
+            });
+            start = index + 1;
+        }
+    }
+    if start < nodes.len() {
+        elements.push(Element {
+            nodes: &nodes[start..],
+            comma: None,
+        });
+    }
+    elements
+}
+
+/// Where a list has a comma after its last element.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum TrailingComma {
+    /// Never, as in call arguments.
+    Never,
+    /// If the list is broken over multiple lines.
+    Broken,
+    /// Always, as in tuples with one element.
+    Always,
+}
+
+/// Lay out the list on one line if it fits, and one element per line otherwise.
+fn list(group: &Group, previous: Option<&Token>, skip: usize) -> Doc {
+    let elements = split_elements(group.nodes());
+    let has_trailing_comma = elements.last().is_some_and(|last| last.comma.is_some());
+    let trailing_comma = match group.delimiter() {
+        Delimiter::Parenthesis if previous.is_some_and(is_callee) => TrailingComma::Never,
+        Delimiter::Parenthesis if elements.len() == 1 => match has_trailing_comma {
+            true => TrailingComma::Always,
+            false => TrailingComma::Never,
+        },
+        Delimiter::Bracket
+            if previous.is_some_and(|token| token.text() == "#")
+                || group.nodes().iter().any(|node| node.is_punctuation(";")) =>
+        {
+            TrailingComma::Never
+        }
+        Delimiter::Parenthesis | Delimiter::Bracket => TrailingComma::Broken,
+        Delimiter::Angle | Delimiter::Brace => TrailingComma::Never,
+    };
+
+    let (close_comments, close_newlines) = comments(group.close());
+    if elements.is_empty() && close_comments.is_empty() {
+        return Doc::Concat(vec![
+            token(group.open(), skip),
+            Doc::text(group.close().text()),
+        ]);
+    }
+
+    let mut inner = Vec::new();
+    for (index, element) in elements.iter().enumerate() {
+        // Comments after the opening delimiter or comma stay on that line
+        let (comments, _) = comments(element.nodes[0].first_token());
+        let trailing = same_line(&comments);
+        trailing_comments(&comments[..trailing], &mut inner);
+        inner.push(if index == 0 { Doc::SoftLine } else { Doc::Line });
+        inner.extend(sequence(element.nodes, trailing));
+
+        match (element.comma, index + 1 == elements.len()) {
+            (Some(comma), false) => inner.push(token(comma, 0)),
+            (comma, true) => {
+                let (comments, newlines) = comma.map(self::comments).unwrap_or_default();
+                if !comments.is_empty() {
+                    inner.push(Doc::text(" "));
+                    comment_lines(&comments, newlines, &mut inner);
+                }
+                inner.push(match trailing_comma {
+                    TrailingComma::Never => Doc::text(""),
+                    TrailingComma::Broken => Doc::IfBreak(","),
+                    TrailingComma::Always => Doc::text(","),
+                });
+            }
+            (None, false) => unreachable!("Elements are separated by commas"),
+        }
+    }
+
+    let trailing = same_line(&close_comments);
+    trailing_comments(&close_comments[..trailing], &mut inner);
+    if trailing < close_comments.len() {
+        inner.push(Doc::HardLine);
+        comment_lines(&close_comments[trailing..], close_newlines, &mut inner);
+    }
+
+    Doc::Group(vec![
+        token(group.open(), skip),
+        Doc::Indent(inner),
+        Doc::SoftLine,
+        Doc::text(group.close().text()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(source: &str) -> String {
+        Formatter::new().format(source).unwrap()
+    }
+
+    #[test]
+    fn layout() {
+        let source = r#"type   Pair=(u8,u16);
+fn add(a:u32,b:u32)->u32{let (_, sum):(bool,u32)=jet::add_32(a,b);sum}
+#[test] fn test_add(){assert!(jet::eq_32(add(1,2),3));}
+fn main(){let x:Option<u8>=Some(1);match x{Some(y:u8)=>{dbg!(y);},None=>panic!(),};let t:(u8,)=(1,);
+let c: u16 = <u8>::into(unwrap_left::<u8>(Left(2)));let a:[u8;2]=[1,2];let l:List<u8,4>=list![1];}"#;
+        let expected = r#"type Pair = (u8, u16);
+
+fn add(a: u32, b: u32) -> u32 {
+    let (_, sum): (bool, u32) = jet::add_32(a, b);
+    sum
+}
+
+#[test]
+fn test_add() {
+    assert!(jet::eq_32(add(1, 2), 3));
+}
+
+fn main() {
+    let x: Option<u8> = Some(1);
+    match x {
+        Some(y: u8) => {
+            dbg!(y);
+        },
+        None => panic!(),
+    };
+    let t: (u8,) = (1,);
+    let c: u16 = <u8>::into(unwrap_left::<u8>(Left(2)));
+    let a: [u8; 2] = [1, 2];
+    let l: List<u8, 4> = list![1];
+}
+"#;
+        assert_eq!(expected, format(source));
+    }
+
+    #[test]
+    fn line_width() {
+        let source =
+            "fn main() { let (a, b): (u32, u32) = jet::full_multiply_32(witness::A, witness::B); }";
+        let expected = r#"fn main() {
+    let (a, b): (u32, u32) = jet::full_multiply_32(
+        witness::A,
+        witness::B
+    );
+}
+"#;
+        assert_eq!(
+            expected,
+            Formatter::new().with_line_width(60).format(source).unwrap()
+        );
+
+        let expected = r#"fn main() {
+    let (
+        a,
+        b,
+    ): (
+        u32,
+        u32,
+    ) = jet::full_multiply_32(
+        witness::A,
+        witness::B
+    );
+}
+"#;
+        assert_eq!(
+            expected,
+            Formatter::new().with_line_width(10).format(source).unwrap()
+        );
+    }
+
+    #[test]
+    fn preserve_comments() {
+        let source = r#"/*
+ * Header
+ */
+
+// Alias
+type A = u8; // trailing
+
+
+fn main() { // start
+    // before
+    let a: A = 1; /* after */
+
+    let b: (u8, u8) = (a, // first
+        2);
+    // end
+}
+// eof
+"#;
+        let expected = r#"/*
+ * Header
+ */
+
+// Alias
+type A = u8; // trailing
+
+fn main() { // start
+    // before
+    let a: A = 1; /* after */
+
+    let b: (u8, u8) = (
+        a, // first
+        2,
+    );
+    // end
+}
+// eof
+"#;
+        assert_eq!(expected, format(source));
+    }
+
+    #[test]
+    fn stable_output() {
+        for entry in std::fs::read_dir("./examples").unwrap() {
+            let path = entry.unwrap().path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "simf")
+            {
+                let source = std::fs::read_to_string(&path).unwrap();
+                for line_width in [20, 100] {
+                    let formatter = Formatter::new().with_line_width(line_width);
+                    let formatted = formatter.format(&source).unwrap();
+                    assert!(
+                        formatter.is_formatted(&formatted).unwrap(),
+                        "{}",
+                        path.display()
+                    );
+                }
+                let formatted = format(&source);
+                let tree = SyntaxTree::parse(&source).unwrap();
+                let comments = |tree: &SyntaxTree| -> Vec<String> {
+                    tree.tokens()
+                        .flat_map(Token::trivia)
+                        .filter(|trivia| trivia.is_comment())
+                        .map(|trivia| trivia.text().trim_end().to_string())
+                        .collect()
+                };
+                let formatted_tree = SyntaxTree::parse(&formatted).unwrap();
+                assert_eq!(
+                    comments(&tree),
+                    comments(&formatted_tree),
+                    "{}",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_program() {
+        assert!(Formatter::new().format("fn main() {").is_err());
+    }
+}
@@ -3,17 +3,36 @@
 pub mod array;
 pub mod ast;
 pub mod compile;
+pub mod cost;
+pub mod coverage;
 pub mod debug;
+pub mod debugger;
+pub mod decompile;
+pub mod disassemble;
 pub mod dummy_env;
+pub mod environment;
+pub mod equivalence;
 pub mod error;
+pub mod format;
 pub mod jet;
+pub mod lint;
 pub mod named;
 pub mod num;
 pub mod parse;
 pub mod pattern;
+pub mod profiler;
+#[cfg(feature = "arbitrary")]
+pub mod property;
+pub mod repl;
+mod sat;
 #[cfg(feature = "serde")]
 mod serde;
+pub mod sighash;
+pub mod signer;
 pub mod str;
+pub mod symbolic;
+pub mod taproot;
+pub mod testing;
 pub mod tracker;
 pub mod types;
 pub mod value;
@@ -21,16 +40,22 @@ mod witness;
 
 use std::sync::Arc;
 
+use simplicity::elements::pset::PartiallySignedTransaction;
+use simplicity::elements::secp256k1_zkp::XOnlyPublicKey;
+use simplicity::elements::taproot::{ControlBlock, TaprootSpendInfo};
 use simplicity::jet::elements::ElementsEnv;
+use simplicity::BitMachine;
 use simplicity::{jet::Elements, CommitNode, RedeemNode};
 
 pub extern crate either;
 pub extern crate simplicity;
 pub use simplicity::elements;
 
+use crate::cost::{CostReport, PruneReport};
 use crate::debug::DebugSymbols;
 use crate::error::WithFile;
 use crate::parse::ParseFromStr;
+use crate::str::FunctionName;
 pub use crate::types::ResolvedType;
 pub use crate::value::Value;
 pub use crate::witness::{Arguments, Parameters, WitnessTypes, WitnessValues};
@@ -41,7 +66,9 @@ pub use crate::witness::{Arguments, Parameters, WitnessTypes, WitnessValues};
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct TemplateProgram {
     simfony: ast::Program,
+    entry_point: FunctionName,
     file: Arc<str>,
+    optimize: bool,
 }
 
 impl TemplateProgram {
@@ -51,22 +78,123 @@ impl TemplateProgram {
     ///
     /// The string is not a valid SimplicityHL program.
     pub fn new<Str: Into<Arc<str>>>(s: Str) -> Result<Self, String> {
+        Self::with_entry_point(s, &FunctionName::main())
+    }
+
+    /// Parse the template of a SimplicityHL program
+    /// whose main function is the entry point of the given name.
+    ///
+    /// ## Errors
+    ///
+    /// - The string is not a valid SimplicityHL program.
+    /// - The program has no entry point of the given name.
+    pub fn with_entry_point<Str: Into<Arc<str>>>(
+        s: Str,
+        entry_point: &FunctionName,
+    ) -> Result<Self, String> {
+        let file = s.into();
+        let parse_program = parse::Program::parse_from_str(&file)?;
+        Self::analyze(&parse_program, entry_point, file)
+    }
+
+    /// Parse and type-check a SimplicityHL program and return all errors that it contains.
+    ///
+    /// Analysis continues after an item with errors, so independent errors are reported together.
+    /// Syntax errors stop the parser, so they are reported alone.
+    pub fn check<Str: Into<Arc<str>>>(s: Str) -> Vec<String> {
+        let file = s.into();
+        match parse::Program::parse_from_str(&file) {
+            Ok(parse_program) => ast::Program::diagnose(&parse_program)
+                .into_iter()
+                .map(|error| error.with_file(Arc::clone(&file)).to_string())
+                .collect(),
+            Err(error) => vec![error.to_string()],
+        }
+    }
+
+    /// Parse the templates of all entry points of a SimplicityHL program,
+    /// in order of declaration.
+    ///
+    /// Entry points are the main function and functions with the `#[entry_point]` attribute.
+    ///
+    /// ## Errors
+    ///
+    /// - The string is not a valid SimplicityHL program.
+    /// - The program has no entry points.
+    pub fn entry_points<Str: Into<Arc<str>>>(s: Str) -> Result<Vec<Self>, String> {
         let file = s.into();
         let parse_program = parse::Program::parse_from_str(&file)?;
-        let ast_program = ast::Program::analyze(&parse_program).with_file(Arc::clone(&file))?;
+        let mut names = parse_program
+            .items()
+            .iter()
+            .filter_map(|item| match item {
+                parse::Item::Function(function) if function.is_entry_point() => {
+                    Some(function.name().clone())
+                }
+                _ => None,
+            })
+            .collect::<Vec<FunctionName>>();
+        if names.is_empty() {
+            // Analysis reports the missing main function
+            names.push(FunctionName::main());
+        }
+        names
+            .iter()
+            .map(|name| Self::analyze(&parse_program, name, Arc::clone(&file)))
+            .collect()
+    }
+
+    fn analyze(
+        parse_program: &parse::Program,
+        entry_point: &FunctionName,
+        file: Arc<str>,
+    ) -> Result<Self, String> {
+        let ast_program = ast::Program::analyze_entry_point(parse_program, entry_point)
+            .with_file(Arc::clone(&file))?;
         Ok(Self {
             simfony: ast_program,
+            entry_point: entry_point.clone(),
             file,
+            optimize: false,
         })
     }
 
+    /// Set whether the compiled Simplicity code is simplified by the peephole optimizer.
+    ///
+    /// The optimizer is disabled by default. It changes the CMR and therefore the address
+    /// of the program, so enable it only for programs whose coins are not locked yet.
+    pub fn with_optimization(mut self, optimize: bool) -> Self {
+        self.optimize = optimize;
+        self
+    }
+
+    /// Access the name of the entry point that is the main function of the program.
+    pub fn entry_point(&self) -> &FunctionName {
+        &self.entry_point
+    }
+
     /// Access the parameters of the program.
     pub fn parameters(&self) -> &Parameters {
         self.simfony.parameters()
     }
 
+    /// Access the witness types declared in the program.
+    pub fn witness_types(&self) -> &WitnessTypes {
+        self.simfony.witness_types()
+    }
+
+    /// Access the types of the parameters and witnesses as they are written in the program,
+    /// including the names of type aliases.
+    pub fn declared_types(&self) -> &ast::DeclaredTypes {
+        self.simfony.declared_types()
+    }
+
     /// Instantiate the template program with the given `arguments`.
     ///
+    /// If `include_debug_symbols` is set, then each call expression and each match arm
+    /// is wrapped in an assertion that carries its debug symbol. The wrappers change
+    /// the CMR and the cost of the program, so only deploy programs without debug symbols.
+    ///
     /// ## Errors
     ///
     /// The arguments are not consistent with the parameters of the program.
@@ -80,13 +208,16 @@ impl TemplateProgram {
             .is_consistent(self.simfony.parameters())
             .map_err(|error| error.to_string())?;
 
-        let commit = self
+        let (commit, layouts) = self
             .simfony
-            .compile(arguments, include_debug_symbols)
+            .compile_with_layouts(arguments, include_debug_symbols, self.optimize)
             .with_file(Arc::clone(&self.file))?;
 
         Ok(CompiledProgram {
-            debug_symbols: self.simfony.debug_symbols(self.file.as_ref()),
+            debug_symbols: self
+                .simfony
+                .debug_symbols(self.file.as_ref())
+                .with_layouts(&layouts),
             simplicity: commit,
             witness_types: self.simfony.witness_types().shallow_clone(),
         })
@@ -117,14 +248,12 @@ impl CompiledProgram {
             .and_then(|template| template.instantiate(arguments, include_debug_symbols))
     }
 
-       /// Access the debug symbols for the Simplicity target code.
+    /// Access the debug symbols for the Simplicity target code.
     pub fn debug_symbols(&self) -> &DebugSymbols {
         &self.debug_symbols
     }
 
     /// Access the witness types declared in the SimplicityHL program.
-    ///
-    /// [NEW METHOD - INSERT HERE]
     pub fn witness_types(&self) -> &WitnessTypes {
         &self.witness_types
     }
@@ -134,6 +263,95 @@ impl CompiledProgram {
         named::forget_names(&self.simplicity)
     }
 
+    /// Return the Taproot spend info of an output with the given internal key
+    /// that is locked by the program.
+    ///
+    /// Use [`taproot::nums_key`] if the output should only be spendable via the program.
+    pub fn taproot_spend_info(&self, internal_key: XOnlyPublicKey) -> TaprootSpendInfo {
+        taproot::spend_info(self.commit().cmr(), internal_key)
+    }
+
+    /// Return the control block that proves that the program is a leaf of
+    /// the output with the given internal key.
+    pub fn control_block(&self, internal_key: XOnlyPublicKey) -> ControlBlock {
+        self.taproot_spend_info(internal_key)
+            .control_block(&taproot::leaf(self.commit().cmr()))
+            .expect("program is a leaf of its Taproot tree")
+    }
+
+    /// Return the script pubkey of the Taproot output that is locked by the program,
+    /// with the NUMS internal key.
+    pub fn script_pubkey(&self) -> elements::Script {
+        let info = self.taproot_spend_info(taproot::nums_key());
+        elements::Script::new_v1_p2tr_tweaked(info.output_key())
+    }
+
+    /// Return the unconfidential address of the Taproot output that is locked by the program,
+    /// with the NUMS internal key.
+    pub fn address(&self, params: &'static elements::AddressParams) -> elements::Address {
+        let info = self.taproot_spend_info(taproot::nums_key());
+        elements::Address::p2tr_tweaked(info.output_key(), None, params)
+    }
+
+    /// Compute the message that the program signs when it is executed in the given environment.
+    ///
+    /// The message is the value of the given SimplicityHL expression of type `u256`,
+    /// such as `jet::sig_all_hash()`. See [`sighash::evaluate`].
+    ///
+    /// ## Errors
+    ///
+    /// - The environment was created for a different program.
+    ///   The script CMR of the environment is part of messages such as `jet::sig_all_hash()`.
+    /// - The expression cannot be evaluated.
+    pub fn sighash(
+        &self,
+        expression: &str,
+        env: &ElementsEnv<Arc<elements::Transaction>>,
+    ) -> Result<[u8; 32], String> {
+        let cmr = self.commit().cmr();
+        if sighash::evaluate("jet::script_cmr()", env)? != cmr.to_byte_array() {
+            return Err(format!(
+                "Environment was created for a different program: expected script CMR {cmr}"
+            ));
+        }
+        sighash::evaluate(expression, env)
+    }
+
+    /// Sign the signature witnesses of the program that are missing from the given `witness_values`,
+    /// and return the completed witness values.
+    ///
+    /// A signature witness is a witness of type `Signature` that the program verifies
+    /// via `jet::bip_0340_verify`. The program is executed in the given environment
+    /// to find the public key and the message of each verification.
+    /// Signature witnesses that the program does not verify in this environment stay missing.
+    ///
+    /// Signatures inside witnesses of tuple or array types are signed like signature witnesses.
+    /// Signatures inside `Either` or `Option` depend on the spend path,
+    /// so the given value of the witness must mark each signature to sign with the zero signature.
+    ///
+    /// ## Errors
+    ///
+    /// - Witness values have a different type than declared in the SimplicityHL program.
+    /// - A witness that contains signatures is missing, but it cannot be filled with signatures alone.
+    /// - The signer fails to sign a message.
+    pub fn sign<S: signer::Signer + ?Sized>(
+        &self,
+        witness_values: WitnessValues,
+        signer: &mut S,
+        env: &ElementsEnv<Arc<elements::Transaction>>,
+    ) -> Result<WitnessValues, String> {
+        signer::fill_signatures(self, witness_values, signer, env)
+    }
+
+    /// Statically analyze the execution cost of the program,
+    /// for every spend path through the program.
+    ///
+    /// Compile the program with debug symbols
+    /// to attribute cost to SimplicityHL call expressions.
+    pub fn cost_report(&self) -> CostReport {
+        CostReport::new(&self.simplicity, &self.debug_symbols)
+    }
+
     /// Satisfy the SimplicityHL program with the given `witness_values`.
     ///
     /// ## Errors
@@ -156,11 +374,7 @@ impl CompiledProgram {
         witness_values: WitnessValues,
         env: Option<&ElementsEnv<Arc<elements::Transaction>>>,
     ) -> Result<SatisfiedProgram, String> {
-        witness_values
-            .is_consistent(&self.witness_types)
-            .map_err(|e| e.to_string())?;
-
-        let mut simplicity_redeem = named::populate_witnesses(&self.simplicity, witness_values)?;
+        let mut simplicity_redeem = self.populate_witnesses(witness_values)?;
         if let Some(env) = env {
             simplicity_redeem = simplicity_redeem.prune(env).map_err(|e| e.to_string())?;
         }
@@ -169,6 +383,44 @@ impl CompiledProgram {
             debug_symbols: self.debug_symbols.clone(),
         })
     }
+
+    /// Satisfy the SimplicityHL program with the given `witness_values`,
+    /// like [`CompiledProgram::satisfy_with_env`].
+    ///
+    /// Return a report about the pruned program alongside the satisfied program.
+    /// Compile the program with debug symbols to see which match arms were pruned.
+    ///
+    /// ## Errors
+    ///
+    /// - Witness values have a different type than declared in the SimplicityHL program.
+    /// - There are missing witness values.
+    pub fn satisfy_with_report(
+        &self,
+        witness_values: WitnessValues,
+        env: Option<&ElementsEnv<Arc<elements::Transaction>>>,
+    ) -> Result<(SatisfiedProgram, PruneReport), String> {
+        let unpruned = self.populate_witnesses(witness_values)?;
+        let pruned = match env {
+            Some(env) => unpruned.prune(env).map_err(|e| e.to_string())?,
+            None => Arc::clone(&unpruned),
+        };
+        let report = PruneReport::new(&unpruned, &pruned, &self.debug_symbols);
+        let satisfied = SatisfiedProgram {
+            simplicity: pruned,
+            debug_symbols: self.debug_symbols.clone(),
+        };
+        Ok((satisfied, report))
+    }
+
+    fn populate_witnesses(
+        &self,
+        witness_values: WitnessValues,
+    ) -> Result<Arc<RedeemNode<Elements>>, String> {
+        witness_values
+            .is_consistent(&self.witness_types)
+            .map_err(|e| e.to_string())?;
+        named::populate_witnesses(&self.simplicity, witness_values)
+    }
 }
 
 /// A SimplicityHL program, compiled to Simplicity and satisfied with witness data.
@@ -205,6 +457,54 @@ impl SatisfiedProgram {
     pub fn debug_symbols(&self) -> &DebugSymbols {
         &self.debug_symbols
     }
+
+    /// Finalize input `input_index` of the PSET with the Simplicity Taproot script-path witness:
+    /// witness data, program, leaf script and control block.
+    ///
+    /// The program is pruned and executed in the environment that is derived from the PSET,
+    /// so the finalized input is known to be valid. The PSET is unchanged if there is an error.
+    ///
+    /// ## Errors
+    ///
+    /// - The environment cannot be derived from the PSET. See [`environment::from_pset`].
+    /// - The program fails to execute.
+    pub fn finalize_pset(
+        &self,
+        pset: &mut PartiallySignedTransaction,
+        input_index: u32,
+        genesis_hash: elements::BlockHash,
+    ) -> Result<(), String> {
+        let script_cmr = self.simplicity.cmr();
+        let env = environment::from_pset(pset, input_index, script_cmr, genesis_hash)?;
+        let pruned = self
+            .simplicity
+            .prune(&env)
+            .map_err(|e| format!("Execution failed: {e}"))?;
+        let (program_bytes, witness_bytes) = pruned.to_vec_with_witness();
+
+        let mut finalized = pset.clone();
+        let input = &mut finalized.inputs_mut()[input_index as usize];
+        input.final_script_witness = Some(vec![
+            witness_bytes,
+            program_bytes,
+            taproot::leaf_script(script_cmr).into_bytes(),
+            env.control_block().serialize(),
+        ]);
+        // Finalized inputs keep only their UTXOs and final witness
+        input.tap_key_sig = None;
+        input.tap_script_sigs.clear();
+        input.tap_scripts.clear();
+        input.tap_key_origins.clear();
+        input.tap_internal_key = None;
+        input.tap_merkle_root = None;
+
+        let env = environment::from_pset(&finalized, input_index, script_cmr, genesis_hash)?;
+        let mut mac = BitMachine::for_program(&pruned).map_err(|e| e.to_string())?;
+        mac.exec(&pruned, &env)
+            .map_err(|e| format!("Execution failed: {e}"))?;
+        *pset = finalized;
+        Ok(())
+    }
 }
 
 /// Recursively implement [`PartialEq`], [`Eq`] and [`std::hash::Hash`]
@@ -312,10 +612,11 @@ pub(crate) mod tests {
             arguments_file_path: P,
         ) -> TestCase<CompiledProgram> {
             let arguments_text = std::fs::read_to_string(arguments_file_path).unwrap();
-            let arguments = match serde_json::from_str::<Arguments>(&arguments_text) {
-                Ok(x) => x,
-                Err(error) => panic!("{error}"),
-            };
+            let arguments =
+                match Arguments::from_json_with_types(&arguments_text, self.program.parameters()) {
+                    Ok(x) => x,
+                    Err(error) => panic!("{error}"),
+                };
             self.with_arguments(arguments)
         }
 
@@ -350,7 +651,10 @@ pub(crate) mod tests {
             witness_file_path: P,
         ) -> TestCase<SatisfiedProgram> {
             let witness_text = std::fs::read_to_string(witness_file_path).unwrap();
-            let witness_values = match serde_json::from_str::<WitnessValues>(&witness_text) {
+            let witness_values = match WitnessValues::from_json_with_types(
+                &witness_text,
+                self.program.witness_types(),
+            ) {
                 Ok(x) => x,
                 Err(error) => panic!("{error}"),
             };
@@ -633,6 +937,29 @@ fn main() {
         }
     }
 
+    #[test]
+    fn check_reports_all_errors() {
+        let prog_text = r#"fn add(a: u32, b: u32) -> u32 {
+    let (_, c): (bool, u16) = jet::add_32(a, b);
+    c
+}
+
+fn is_ok() -> bool {
+    undefined
+}
+
+fn main() {
+    let x: u16 = add(1, 2);
+    assert!(is_ok());
+}
+"#;
+        let errors = TemplateProgram::check(prog_text);
+        assert_eq!(2, errors.len(), "Unexpected errors: {errors:?}");
+        assert!(errors[0].contains("Expected expression of type `(bool, u16)`"));
+        assert!(errors[1].contains("Variable `undefined` is not defined"));
+        assert!(TemplateProgram::check("fn main() {}").is_empty());
+    }
+
     #[test]
     fn fuzz_regression_2() {
         parse::Program::parse_from_str("fn dbggscas(h: bool, asyxhaaaa: a) {\nfalse}\n\n").unwrap();
@@ -657,6 +984,29 @@ fn main() {
             .assert_run_success();
     }
 
+    #[test]
+    fn declared_types_keep_aliases() {
+        let prog_text = r#"type Sig = Signature;
+type Amount = u64;
+
+fn main() {
+    let amount: Amount = param::AMOUNT;
+    let sig: Sig = witness::SIG;
+    let (carry, _): (bool, u64) = jet::subtract_64(amount, 1);
+    assert!(jet::eq_1(<bool>::into(carry), 0));
+}"#;
+        let template = TemplateProgram::new(prog_text).unwrap();
+        let declared = template.declared_types();
+        let amount = str::WitnessName::from_str_unchecked("AMOUNT");
+        let sig = str::WitnessName::from_str_unchecked("SIG");
+        assert_eq!("Amount", declared.parameter(&amount).unwrap().to_string());
+        assert_eq!("Sig", declared.witness(&sig).unwrap().to_string());
+        assert_eq!(
+            "u64",
+            template.parameters().get(&amount).unwrap().to_string()
+        );
+    }
+
     #[test]
     fn type_error_regression() {
         let prog_text = r#"fn main() {
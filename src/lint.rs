@@ -0,0 +1,1234 @@
+//! Lints that warn about valid but suspicious SimplicityHL code.
+//!
+//! The compiler rejects programs that are wrong. The linter goes further
+//! and reports code that compiles, but that is likely a mistake or that should not be deployed:
+//!
+//! - `unused_variables`: A variable, function parameter or match binding is never read.
+//!   Parameters of unit type are exempt.
+//! - `unused_functions`: A function is never called from any entry point or unit test.
+//! - `unused_witnesses`: A witness value is ignored or bound to a variable that is never read.
+//! - `shadowed_bindings`: A binding in an inner scope shadows a binding of the same name
+//!   from an outer scope without using it. The outer value is back in scope after the inner scope,
+//!   which is easy to miss. Rebinding a name in the same scope is fine.
+//! - `dbg_macro`: A `dbg!` call is left in the code.
+//! - `unreachable_code`: Code follows an expression that always fails, such as `panic!()`.
+//! - `constant_assertions`: The condition of `assert!` does not depend on any variable,
+//!   witness, parameter or the transaction, so the assertion always passes or always fails.
+//! - `redundant_casts`: A value is cast into the type that it already has.
+//!
+//! Every lint warns by default. The level of each lint can be changed to `allow`, `warn` or `deny`
+//! in a [`LintConfig`], which can be read from a TOML file:
+//!
+//! ```toml
+//! [lints]
+//! dbg_macro = "deny"
+//! shadowed_bindings = "allow"
+//! ```
+//!
+//! Attributes change the level of lints inside a single function,
+//! overriding the configuration:
+//!
+//! ```text
+//! #[allow(unused_variables, redundant_casts)]
+//! fn main() {
+//!     let x: u8 = <u8>::into(1);
+//! }
+//! ```
+//!
+//! The linter analyzes each entry point and each unit test of the program.
+//! Functions are linted where they are called,
+//! so the bodies of unused functions are not linted beyond being reported as unused.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use either::Either;
+use miniscript::iter::TreeLike;
+
+use crate::ast::{
+    self, CallName, ExprTree, Expression, ExpressionInner, SingleExpression, SingleExpressionInner,
+    Statement,
+};
+use crate::error::{Error, Position, RichError, Span, WithFile, WithSpan};
+use crate::parse::{self, Attribute, ParseFromStr};
+use crate::pattern::Pattern;
+use crate::str::{FunctionName, Identifier, WitnessName};
+use crate::types::TypeDeconstructible;
+use crate::value::Value;
+
+/// Kind of suspicious code that the linter reports.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Lint {
+    /// A variable, function parameter or match binding is never read.
+    UnusedVariables,
+    /// A function is never called.
+    UnusedFunctions,
+    /// A witness value is never used.
+    UnusedWitnesses,
+    /// A binding shadows a binding of an outer scope without using it.
+    ShadowedBindings,
+    /// A `dbg!` call is left in the code.
+    DebugCalls,
+    /// Code follows an expression that always fails.
+    UnreachableCode,
+    /// The condition of `assert!` is constant.
+    ConstantAssertions,
+    /// A value is cast into its own type.
+    RedundantCasts,
+}
+
+impl Lint {
+    /// All lints, in order of declaration.
+    pub const ALL: [Self; 8] = [
+        Self::UnusedVariables,
+        Self::UnusedFunctions,
+        Self::UnusedWitnesses,
+        Self::ShadowedBindings,
+        Self::DebugCalls,
+        Self::UnreachableCode,
+        Self::ConstantAssertions,
+        Self::RedundantCasts,
+    ];
+
+    /// Return the name of the lint, as used in attributes and configuration files.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::UnusedVariables => "unused_variables",
+            Self::UnusedFunctions => "unused_functions",
+            Self::UnusedWitnesses => "unused_witnesses",
+            Self::ShadowedBindings => "shadowed_bindings",
+            Self::DebugCalls => "dbg_macro",
+            Self::UnreachableCode => "unreachable_code",
+            Self::ConstantAssertions => "constant_assertions",
+            Self::RedundantCasts => "redundant_casts",
+        }
+    }
+}
+
+impl FromStr for Lint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|lint| lint.name() == s)
+            .ok_or_else(|| format!("Lint `{s}` does not exist"))
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Level at which a lint is reported.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Level {
+    /// The lint is not reported.
+    Allow,
+    /// The lint is reported as a warning.
+    Warn,
+    /// The lint is reported as an error.
+    Deny,
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(Self::Allow),
+            "warn" => Ok(Self::Warn),
+            "deny" => Ok(Self::Deny),
+            _ => Err(format!("Lint level `{s}` does not exist")),
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Allow => write!(f, "allow"),
+            Self::Warn => write!(f, "warn"),
+            Self::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+/// Levels of the lints for a whole program.
+///
+/// Lints that are not configured warn.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LintConfig {
+    levels: HashMap<Lint, Level>,
+}
+
+impl LintConfig {
+    /// Create a configuration where every lint warns.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report the given lint at the given level.
+    pub fn with_level(mut self, lint: Lint, level: Level) -> Self {
+        self.levels.insert(lint, level);
+        self
+    }
+
+    /// Return the level at which the given lint is reported.
+    pub fn level(&self, lint: Lint) -> Level {
+        self.levels.get(&lint).copied().unwrap_or(Level::Warn)
+    }
+
+    /// Parse the configuration from a TOML string.
+    ///
+    /// The levels are listed in a `[lints]` table that maps lint names to levels.
+    #[cfg(feature = "serde")]
+    pub fn from_toml(s: &str) -> Result<Self, String> {
+        #[derive(serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct ConfigFile {
+            #[serde(default)]
+            lints: HashMap<String, Level>,
+        }
+
+        let file: ConfigFile =
+            toml::from_str(s).map_err(|e| format!("Invalid lint configuration: {e}"))?;
+        let mut config = Self::new();
+        for (name, level) in file.lints {
+            let lint =
+                Lint::from_str(&name).map_err(|e| format!("Invalid lint configuration: {e}"))?;
+            config = config.with_level(lint, level);
+        }
+        Ok(config)
+    }
+}
+
+/// Warning about suspicious code.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Warning {
+    lint: Lint,
+    level: Level,
+    message: String,
+    span: Span,
+    file: Option<Arc<str>>,
+}
+
+impl Warning {
+    /// Access the lint that produced the warning.
+    pub fn lint(&self) -> Lint {
+        self.lint
+    }
+
+    /// Access the level of the warning.
+    ///
+    /// The level is never [`Level::Allow`].
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    /// Access the message of the warning.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Access the span of the suspicious code.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = self.file.as_ref().filter(|file| !file.is_empty()) {
+            crate::error::write_snippet(f, file, &self.span)?;
+        }
+        let kind = match self.level {
+            Level::Deny => "error",
+            Level::Allow | Level::Warn => "warning",
+        };
+        write!(f, "{kind}[{}]: {}", self.lint, self.message)
+    }
+}
+
+/// Linter for SimplicityHL programs.
+#[derive(Clone, Debug, Default)]
+pub struct Linter {
+    config: LintConfig,
+}
+
+impl Linter {
+    /// Create a linter where every lint warns.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report the lints at the levels of the given configuration.
+    pub fn with_config(mut self, config: LintConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Lint the given SimplicityHL program.
+    ///
+    /// Return the warnings in order of their position in the program.
+    /// Warnings of allowed lints are omitted.
+    ///
+    /// ## Errors
+    ///
+    /// The string is not a valid SimplicityHL program.
+    pub fn lint(&self, source: &str) -> Result<Vec<Warning>, String> {
+        let file: Arc<str> = Arc::from(source);
+        let program = parse::Program::parse_from_str(source)?;
+        let functions: Vec<&parse::Function> = program
+            .items()
+            .iter()
+            .filter_map(|item| match item {
+                parse::Item::Function(function) => Some(function),
+                _ => None,
+            })
+            .collect();
+
+        let mut entry_points: Vec<FunctionName> = functions
+            .iter()
+            .filter(|function| function.is_entry_point() || function.is_test())
+            .map(|function| function.name().clone())
+            .collect();
+        if entry_points.is_empty() {
+            // Analysis reports the missing main function
+            entry_points.push(FunctionName::main());
+        }
+
+        let text = Text::new(source);
+        let attributes = functions
+            .iter()
+            .map(|function| {
+                text.lint_attributes(function)
+                    .map(|levels| (*function, levels))
+                    .with_file(Arc::clone(&file))
+            })
+            .collect::<Result<Vec<_>, RichError>>()?;
+        let mut walker = Walker::new(&text, &functions);
+        for name in &entry_points {
+            let ast_program =
+                ast::Program::analyze_entry_point(&program, name).with_file(Arc::clone(&file))?;
+            walker.visit_function(&[], ast_program.main());
+        }
+        for function in &functions {
+            let is_called = walker.visited.contains(function.body().as_ref());
+            if !function.is_entry_point() && !function.is_test() && !is_called {
+                walker.warn(
+                    Lint::UnusedFunctions,
+                    format!("function `{}` is never called", function.name()),
+                    text.function_header(function),
+                );
+            }
+        }
+
+        let mut warnings: Vec<Warning> = walker
+            .warnings
+            .into_iter()
+            .filter_map(|(lint, message, span)| {
+                let level = self.level(&attributes, lint, &span);
+                (level != Level::Allow).then(|| Warning {
+                    lint,
+                    level,
+                    message,
+                    span,
+                    file: Some(Arc::clone(&file)),
+                })
+            })
+            .collect();
+        warnings.sort_by_key(|warning| {
+            let span = warning.span;
+            (
+                span.start.line,
+                span.start.col,
+                span.end.line,
+                span.end.col,
+                warning.lint,
+            )
+        });
+        warnings.dedup();
+        Ok(warnings)
+    }
+
+    /// Return the level of the lint at the given span.
+    ///
+    /// Attributes of the enclosing function override the configuration.
+    fn level(&self, attributes: &[FunctionLevels], lint: Lint, span: &Span) -> Level {
+        attributes
+            .iter()
+            .find(|(function, _)| function.span().contains(span))
+            .and_then(|(_, levels)| {
+                levels
+                    .iter()
+                    .rev()
+                    .find_map(|(level, other)| (*other == lint).then_some(*level))
+            })
+            .unwrap_or_else(|| self.config.level(lint))
+    }
+}
+
+/// Function with the lint levels of its attributes, in order of appearance.
+type FunctionLevels<'a> = (&'a parse::Function, Vec<(Level, Lint)>);
+
+/// Source text with conversions between byte offsets and positions.
+///
+/// Used to narrow the spans of warnings down to the relevant code.
+struct Text<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> Text<'a> {
+    fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    fn offset(&self, position: Position) -> usize {
+        let line_start = self.line_starts[position.line.get() - 1];
+        self.source[line_start..]
+            .char_indices()
+            .nth(position.col.get() - 1)
+            .map_or(self.source.len(), |(index, _)| line_start + index)
+    }
+
+    fn position(&self, offset: usize) -> Position {
+        let line_index = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let line_start = self.line_starts[line_index];
+        let col = self.source[line_start..offset].chars().count();
+        Position::new(line_index + 1, col + 1)
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span::new(self.position(start), self.position(end))
+    }
+
+    /// Return the span of the source from the start of `span` up to the given delimiter,
+    /// without trailing whitespace.
+    ///
+    /// Return `span` if the delimiter does not occur inside it.
+    fn prefix_until(&self, span: &Span, delimiter: &str) -> Span {
+        let start = self.offset(span.start);
+        let end = self.offset(span.end);
+        match self.source[start..end].find(delimiter) {
+            Some(length) => {
+                let prefix = self.source[start..start + length].trim_end();
+                self.span(start, start + prefix.len())
+            }
+            None => *span,
+        }
+    }
+
+    /// Return the span of the `fn name` part of the function.
+    fn function_header(&self, function: &parse::Function) -> Span {
+        let start = self.offset(function.span().start);
+        let end = self.offset(function.body().as_ref().start);
+        let text = &self.source[start..end];
+        let name = function.name().as_inner();
+        let header = find_word(text, "fn").and_then(|fn_keyword| {
+            find_word(&text[fn_keyword..], name)
+                .map(|index| (start + fn_keyword, start + fn_keyword + index + name.len()))
+        });
+        match header {
+            Some((start, end)) => self.span(start, end),
+            None => *function.span(),
+        }
+    }
+
+    /// Return the lint levels of the attributes of the function, in order of appearance.
+    ///
+    /// ## Errors
+    ///
+    /// An attribute names a level or a lint that does not exist.
+    fn lint_attributes(&self, function: &parse::Function) -> Result<Vec<(Level, Lint)>, RichError> {
+        let mut levels = Vec::new();
+        for attribute in function.attributes() {
+            let Attribute::Lint(level, names) = attribute else {
+                continue;
+            };
+            let level = Level::from_str(level)
+                .map_err(Error::CannotParse)
+                .with_span(self.attribute_word(function, level))?;
+            for name in names.iter() {
+                let lint = Lint::from_str(name)
+                    .map_err(|_| Error::LintDoesNotExist(name.to_string()))
+                    .with_span(self.attribute_word(function, name))?;
+                levels.push((level, lint));
+            }
+        }
+        Ok(levels)
+    }
+
+    /// Return the span of the word in the attributes of the function,
+    /// or the span of the function.
+    fn attribute_word(&self, function: &parse::Function, word: &str) -> Span {
+        let start = self.offset(function.span().start);
+        let end = self.offset(self.function_header(function).start);
+        match find_word(&self.source[start..end], word) {
+            Some(index) => self.span(start + index, start + index + word.len()),
+            None => *function.span(),
+        }
+    }
+
+    /// Return the span of the parameter of the function, or of its header.
+    fn function_param(&self, function: &parse::Function, param: &Identifier) -> Span {
+        let header = self.function_header(function);
+        let start = self.offset(header.end);
+        let end = self.offset(function.body().as_ref().start);
+        match find_word(&self.source[start..end], param.as_inner()) {
+            Some(index) => self.span(start + index, start + index + param.as_inner().len()),
+            None => header,
+        }
+    }
+}
+
+/// Return the byte offset of the first occurrence of `word` in `text`
+/// that is not part of a longer identifier.
+fn find_word(text: &str, word: &str) -> Option<usize> {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    text.match_indices(word)
+        .map(|(index, _)| index)
+        .find(|index| {
+            let before = text[..*index].chars().next_back();
+            let after = text[index + word.len()..].chars().next();
+            !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+        })
+}
+
+/// Variable binding that is in scope.
+struct Binding {
+    identifier: Identifier,
+    kind: BindingKind,
+    span: Span,
+    used: bool,
+}
+
+#[derive(Clone, Debug)]
+enum BindingKind {
+    /// Variable bound by a `let` statement or a match arm.
+    Variable,
+    /// Parameter of a function.
+    Parameter,
+    /// Variable that is assigned a witness value.
+    Witness(WitnessName),
+}
+
+/// Walker over the abstract syntax trees of a program that collects warnings.
+struct Walker<'a> {
+    text: &'a Text<'a>,
+    /// Functions of the program, indexed by the span of their body.
+    functions: HashMap<Span, &'a parse::Function>,
+    /// Spans of the bodies of the functions that have been linted.
+    visited: HashSet<Span>,
+    /// Stack of scopes of the function that is being linted.
+    scopes: Vec<Vec<Binding>>,
+    warnings: Vec<(Lint, String, Span)>,
+}
+
+impl<'a> Walker<'a> {
+    fn new(text: &'a Text<'a>, functions: &[&'a parse::Function]) -> Self {
+        Self {
+            text,
+            functions: functions
+                .iter()
+                .map(|function| (*function.body().as_ref(), *function))
+                .collect(),
+            visited: HashSet::new(),
+            scopes: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, lint: Lint, message: String, span: Span) {
+        self.warnings.push((lint, message, span));
+    }
+
+    /// Lint the body of a function with the given parameters.
+    ///
+    /// Each function is linted once, even if it is called many times.
+    fn visit_function(&mut self, params: &[ast::FunctionParam], body: &Expression) {
+        if !self.visited.insert(*body.span()) {
+            return;
+        }
+        let function = self.functions.get(body.span()).copied();
+        let outer_scopes = std::mem::take(&mut self.scopes);
+        self.scopes.push(Vec::new());
+        for param in params {
+            let span = match function {
+                Some(function) => self.text.function_param(function, param.identifier()),
+                None => *body.span(),
+            };
+            self.bind(param.identifier(), BindingKind::Parameter, span);
+            // Unit parameters carry no data, but the signatures of loops require them
+            if param.ty().is_unit() {
+                self.use_variable(param.identifier());
+            }
+        }
+        self.visit_expression(body);
+        self.pop_scope();
+        self.scopes = outer_scopes;
+    }
+
+    fn bind(&mut self, identifier: &Identifier, kind: BindingKind, span: Span) {
+        self.scopes
+            .last_mut()
+            .expect("bindings live inside a scope")
+            .push(Binding {
+                identifier: identifier.clone(),
+                kind,
+                span,
+                used: false,
+            });
+    }
+
+    /// Bind the variable, warning if it shadows a binding of an outer scope
+    /// that the bound `expression` does not use.
+    fn bind_variable(
+        &mut self,
+        identifier: &Identifier,
+        kind: BindingKind,
+        span: Span,
+        expression: &Expression,
+    ) {
+        if self.is_bound_outside(identifier) && !reads(expression, identifier) {
+            self.warn(
+                Lint::ShadowedBindings,
+                format!("`{identifier}` shadows a binding of an outer scope that it does not use"),
+                span,
+            );
+        }
+        self.bind(identifier, kind, span);
+    }
+
+    /// Check if the variable is bound in a scope other than the innermost scope.
+    fn is_bound_outside(&self, identifier: &Identifier) -> bool {
+        let outer_scopes = &self.scopes[..self.scopes.len().saturating_sub(1)];
+        outer_scopes
+            .iter()
+            .flatten()
+            .any(|binding| &binding.identifier == identifier)
+    }
+
+    fn use_variable(&mut self, identifier: &Identifier) {
+        if let Some(binding) = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|binding| &binding.identifier == identifier)
+        {
+            binding.used = true;
+        }
+    }
+
+    /// Leave the innermost scope and warn about its unused bindings.
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().expect("scope was pushed");
+        for binding in scope.into_iter().filter(|binding| !binding.used) {
+            let identifier = &binding.identifier;
+            let (lint, message) = match binding.kind {
+                BindingKind::Variable => (
+                    Lint::UnusedVariables,
+                    format!("variable `{identifier}` is never used"),
+                ),
+                BindingKind::Parameter => (
+                    Lint::UnusedVariables,
+                    format!("parameter `{identifier}` is never used"),
+                ),
+                BindingKind::Witness(name) => (
+                    Lint::UnusedWitnesses,
+                    format!("witness `{name}` is assigned to `{identifier}`, which is never used"),
+                ),
+            };
+            self.warn(lint, message, binding.span);
+        }
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression.inner() {
+            ExpressionInner::Single(single) => self.visit_single(single),
+            ExpressionInner::Block(statements, maybe_expr) => {
+                self.scopes.push(Vec::new());
+                for statement in statements.iter() {
+                    match statement {
+                        Statement::Assignment(assignment) => self.visit_assignment(assignment),
+                        Statement::Expression(expression) => self.visit_expression(expression),
+                    }
+                }
+                if let Some(expression) = maybe_expr {
+                    self.visit_expression(expression);
+                }
+                self.pop_scope();
+                self.check_unreachable(statements, maybe_expr.as_deref());
+            }
+        }
+    }
+
+    fn visit_assignment(&mut self, assignment: &ast::Assignment) {
+        let expression = assignment.expression();
+        self.visit_expression(expression);
+        let span = self.text.prefix_until(assignment.span(), "=");
+        let witness = as_witness(expression);
+        match (assignment.pattern(), witness) {
+            (Pattern::Ignore, Some(name)) => self.warn(
+                Lint::UnusedWitnesses,
+                format!("witness `{name}` is ignored"),
+                span,
+            ),
+            (Pattern::Identifier(identifier), Some(name)) => {
+                let kind = BindingKind::Witness(name.clone());
+                self.bind_variable(identifier, kind, span, expression);
+            }
+            (pattern, _) => {
+                for node in pattern.pre_order_iter() {
+                    if let Pattern::Identifier(identifier) = node {
+                        self.bind_variable(identifier, BindingKind::Variable, span, expression);
+                    }
+                }
+            }
+        }
+    }
+
+    fn visit_single(&mut self, single: &SingleExpression) {
+        match single.inner() {
+            SingleExpressionInner::Constant(_)
+            | SingleExpressionInner::Witness(_)
+            | SingleExpressionInner::Parameter(_)
+            | SingleExpressionInner::Option(None) => {}
+            SingleExpressionInner::Variable(identifier) => self.use_variable(identifier),
+            SingleExpressionInner::Expression(expression)
+            | SingleExpressionInner::Either(Either::Left(expression))
+            | SingleExpressionInner::Either(Either::Right(expression))
+            | SingleExpressionInner::Option(Some(expression)) => self.visit_expression(expression),
+            SingleExpressionInner::Tuple(elements)
+            | SingleExpressionInner::Array(elements)
+            | SingleExpressionInner::List(elements) => {
+                for element in elements.iter() {
+                    self.visit_expression(element);
+                }
+            }
+            SingleExpressionInner::Call(call) => {
+                for arg in call.args().iter() {
+                    self.visit_expression(arg);
+                }
+                match call.name() {
+                    CallName::Debug => self.warn(
+                        Lint::DebugCalls,
+                        "`dbg!` call is left in the code".to_string(),
+                        *call.span(),
+                    ),
+                    CallName::Assert if is_constant(&call.args()[0]) => self.warn(
+                        Lint::ConstantAssertions,
+                        "condition of `assert!` is constant".to_string(),
+                        *call.span(),
+                    ),
+                    CallName::TypeCast(source) if source == single.ty() => self.warn(
+                        Lint::RedundantCasts,
+                        format!("cast of `{source}` into the same type is redundant"),
+                        *call.span(),
+                    ),
+                    CallName::Custom(function)
+                    | CallName::Fold(function, _)
+                    | CallName::ArrayFold(function, _)
+                    | CallName::ForWhile(function, _) => {
+                        self.visit_function(function.params(), function.body())
+                    }
+                    _ => {}
+                }
+            }
+            SingleExpressionInner::Match(match_) => {
+                let scrutinee = match_.scrutinee();
+                self.visit_expression(scrutinee);
+                for arm in [match_.left(), match_.right()] {
+                    self.scopes.push(Vec::new());
+                    if let Some(identifier) = arm.pattern().as_variable() {
+                        let span = self.text.prefix_until(arm.span(), "=>");
+                        self.bind_variable(identifier, BindingKind::Variable, span, scrutinee);
+                    }
+                    self.visit_expression(arm.expression());
+                    self.pop_scope();
+                }
+            }
+        }
+    }
+
+    /// Warn about the statements of the block that follow a statement that always fails.
+    fn check_unreachable(&mut self, statements: &[Statement], maybe_expr: Option<&Expression>) {
+        let spans: Vec<Span> = statements
+            .iter()
+            .map(|statement| match statement {
+                Statement::Assignment(assignment) => *assignment.span(),
+                Statement::Expression(expression) => *expression.span(),
+            })
+            .chain(maybe_expr.map(|expression| *expression.span()))
+            .collect();
+        let diverging = statements.iter().position(|statement| match statement {
+            Statement::Assignment(assignment) => diverges(assignment.expression()),
+            Statement::Expression(expression) => diverges(expression),
+        });
+        if let Some(index) = diverging.filter(|index| index + 1 < spans.len()) {
+            let span = Span::new(spans[index + 1].start, spans[spans.len() - 1].end);
+            self.warn(
+                Lint::UnreachableCode,
+                "unreachable code after an expression that always fails".to_string(),
+                span,
+            );
+        }
+    }
+}
+
+/// Return the name of the witness if the expression is a witness value.
+fn as_witness(expression: &Expression) -> Option<&WitnessName> {
+    match expression.inner() {
+        ExpressionInner::Single(single) => match single.inner() {
+            SingleExpressionInner::Witness(name) => Some(name),
+            SingleExpressionInner::Expression(inner) => as_witness(inner),
+            _ => None,
+        },
+        ExpressionInner::Block(..) => None,
+    }
+}
+
+/// Check if the expression reads the given variable.
+///
+/// The bodies of called functions are not considered, because they cannot see the variable.
+fn reads(expression: &Expression, identifier: &Identifier) -> bool {
+    ExprTree::Expression(expression)
+        .pre_order_iter()
+        .any(|node| match node {
+            ExprTree::Single(single) => {
+                matches!(single.inner(), SingleExpressionInner::Variable(i) if i == identifier)
+            }
+            _ => false,
+        })
+}
+
+/// Check if the value of the expression is the same in every execution.
+///
+/// The expression reads no variables, witnesses or parameters,
+/// and it calls only builtin functions and core jets that compute on their input.
+/// Jets that introspect the transaction are not constant.
+fn is_constant(expression: &Expression) -> bool {
+    ExprTree::Expression(expression)
+        .pre_order_iter()
+        .all(|node| match node {
+            ExprTree::Single(single) => !matches!(
+                single.inner(),
+                SingleExpressionInner::Variable(_)
+                    | SingleExpressionInner::Witness(_)
+                    | SingleExpressionInner::Parameter(_)
+            ),
+            ExprTree::Call(call) => match call.name() {
+                CallName::Jet(jet) => is_pure_jet(&jet.to_string()),
+                CallName::UnwrapLeft(_)
+                | CallName::UnwrapRight(_)
+                | CallName::IsNone(_)
+                | CallName::Unwrap
+                | CallName::TypeCast(_) => true,
+                _ => false,
+            },
+            _ => true,
+        })
+}
+
+/// Check if the jet of the given name is a core jet
+/// whose output depends only on its input.
+fn is_pure_jet(name: &str) -> bool {
+    let family = match name.rsplit_once('_') {
+        Some((family, n)) if n.parse::<usize>().is_ok() => family,
+        _ => name,
+    };
+    matches!(
+        family,
+        "low"
+            | "high"
+            | "one"
+            | "complement"
+            | "some"
+            | "all"
+            | "is_zero"
+            | "is_one"
+            | "and"
+            | "or"
+            | "xor"
+            | "eq"
+            | "lt"
+            | "le"
+            | "min"
+            | "max"
+            | "add"
+            | "subtract"
+            | "multiply"
+            | "increment"
+            | "decrement"
+            | "negate"
+    )
+}
+
+/// Check if the expression always fails.
+fn diverges(expression: &Expression) -> bool {
+    match expression.inner() {
+        ExpressionInner::Single(single) => single_diverges(single),
+        ExpressionInner::Block(statements, maybe_expr) => {
+            statements.iter().any(|statement| match statement {
+                Statement::Assignment(assignment) => diverges(assignment.expression()),
+                Statement::Expression(expression) => diverges(expression),
+            }) || maybe_expr.as_deref().is_some_and(diverges)
+        }
+    }
+}
+
+fn single_diverges(single: &SingleExpression) -> bool {
+    match single.inner() {
+        SingleExpressionInner::Constant(_)
+        | SingleExpressionInner::Witness(_)
+        | SingleExpressionInner::Parameter(_)
+        | SingleExpressionInner::Variable(_)
+        | SingleExpressionInner::Option(None) => false,
+        SingleExpressionInner::Expression(expression)
+        | SingleExpressionInner::Either(Either::Left(expression))
+        | SingleExpressionInner::Either(Either::Right(expression))
+        | SingleExpressionInner::Option(Some(expression)) => diverges(expression),
+        SingleExpressionInner::Tuple(elements)
+        | SingleExpressionInner::Array(elements)
+        | SingleExpressionInner::List(elements) => elements.iter().any(diverges),
+        SingleExpressionInner::Call(call) => {
+            call.args().iter().any(diverges)
+                || match call.name() {
+                    CallName::Panic => true,
+                    CallName::Assert => is_false(&call.args()[0]),
+                    CallName::Custom(function) => diverges(function.body()),
+                    _ => false,
+                }
+        }
+        SingleExpressionInner::Match(match_) => {
+            diverges(match_.scrutinee())
+                || (diverges(match_.left().expression()) && diverges(match_.right().expression()))
+        }
+    }
+}
+
+/// Check if the expression is the constant `false`.
+fn is_false(expression: &Expression) -> bool {
+    match expression.inner() {
+        ExpressionInner::Single(single) => match single.inner() {
+            SingleExpressionInner::Constant(value) => value == &Value::from(false),
+            SingleExpressionInner::Expression(inner) => is_false(inner),
+            _ => false,
+        },
+        ExpressionInner::Block(..) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lint the program and return the name, line and message of each warning.
+    fn lint(source: &str) -> Vec<(&'static str, usize, String)> {
+        Linter::new()
+            .lint(source)
+            .unwrap()
+            .into_iter()
+            .map(|warning| {
+                let line = warning.span().start.line.get();
+                (warning.lint().name(), line, warning.message().to_string())
+            })
+            .collect()
+    }
+
+    fn lints(source: &str) -> Vec<&'static str> {
+        lint(source).into_iter().map(|(name, ..)| name).collect()
+    }
+
+    #[test]
+    fn lint_examples() {
+        for entry in std::fs::read_dir("./examples").unwrap() {
+            let path = entry.unwrap().path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "simf")
+            {
+                let source = std::fs::read_to_string(&path).unwrap();
+                if let Err(error) = Linter::new().lint(&source) {
+                    panic!("{}: {error}", path.display());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unused_variables() {
+        let source = r#"fn add(a: u32, b: u32) -> u32 {
+    let (carry, sum): (bool, u32) = jet::add_32(a, a);
+    sum
+}
+
+fn main() {
+    let x: u32 = add(1, 2);
+    match Some(x) {
+        Some(y: u32) => {},
+        None => {},
+    };
+}"#;
+        assert_eq!(
+            lint(source),
+            vec![
+                (
+                    "unused_variables",
+                    1,
+                    "parameter `b` is never used".to_string()
+                ),
+                (
+                    "unused_variables",
+                    2,
+                    "variable `carry` is never used".to_string()
+                ),
+                (
+                    "unused_variables",
+                    9,
+                    "variable `y` is never used".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn unused_functions() {
+        let source = r#"fn one() -> u8 {
+    1
+}
+
+fn two() -> u8 {
+    2
+}
+
+fn three() -> u8 {
+    3
+}
+
+#[entry_point]
+fn a() {
+    assert!(jet::eq_8(one(), witness::X));
+}
+
+#[test]
+fn test_two() {
+    assert!(jet::eq_8(two(), witness::X));
+}"#;
+        assert_eq!(
+            lint(source),
+            vec![(
+                "unused_functions",
+                9,
+                "function `three` is never called".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn unused_witnesses() {
+        let source = r#"fn main() {
+    let _: u8 = witness::A;
+    let b: u8 = witness::B;
+    let c: u8 = witness::C;
+    assert!(jet::eq_8(c, 0));
+}"#;
+        assert_eq!(
+            lint(source),
+            vec![
+                ("unused_witnesses", 2, "witness `A` is ignored".to_string()),
+                (
+                    "unused_witnesses",
+                    3,
+                    "witness `B` is assigned to `b`, which is never used".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn shadowed_bindings() {
+        let source = r#"fn main() {
+    let x: u8 = witness::X;
+    let x: u8 = jet::complement_8(x);
+    let y: u8 = {
+        let x: u8 = witness::Y;
+        x
+    };
+    let z: u8 = {
+        let x: u8 = jet::complement_8(x);
+        x
+    };
+    assert!(jet::eq_8(x, jet::max_8(y, z)));
+}"#;
+        assert_eq!(
+            lint(source),
+            vec![(
+                "shadowed_bindings",
+                5,
+                "`x` shadows a binding of an outer scope that it does not use".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn debug_calls_and_redundant_casts() {
+        let source = r#"fn main() {
+    let x: u8 = <u8>::into(witness::X);
+    let y: u16 = <(u8, u8)>::into((x, x));
+    assert!(jet::eq_16(dbg!(y), 0));
+}"#;
+        assert_eq!(lints(source), vec!["redundant_casts", "dbg_macro"]);
+    }
+
+    #[test]
+    fn unreachable_code() {
+        let source = r#"fn fail() {
+    panic!()
+}
+
+fn main() {
+    match witness::X {
+        true => {
+            fail();
+            assert!(jet::is_zero_8(witness::Y));
+        },
+        false => {
+            assert!(false);
+            let x: u8 = 1;
+            assert!(jet::is_zero_8(x))
+        },
+    };
+}"#;
+        assert_eq!(
+            lint(source),
+            vec![
+                (
+                    "unreachable_code",
+                    9,
+                    "unreachable code after an expression that always fails".to_string()
+                ),
+                (
+                    "constant_assertions",
+                    12,
+                    "condition of `assert!` is constant".to_string()
+                ),
+                (
+                    "unreachable_code",
+                    13,
+                    "unreachable code after an expression that always fails".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn constant_assertions() {
+        let source = r#"fn main() {
+    assert!(true);
+    assert!(jet::eq_32(jet::max_32(1, 2), 2));
+    assert!(jet::eq_32(jet::current_index(), 0));
+    assert!(jet::eq_8(witness::X, 0));
+}"#;
+        assert_eq!(
+            lints(source),
+            vec!["constant_assertions", "constant_assertions"]
+        );
+    }
+
+    #[test]
+    fn attributes_override_config() {
+        let source = r#"#[allow(dbg_macro)]
+fn debug(x: u8) -> u8 {
+    dbg!(x)
+}
+
+#[deny(unused_variables)]
+fn main() {
+    let x: u8 = debug(1);
+    let y: u8 = dbg!(x);
+}"#;
+        let warnings = Linter::new().lint(source).unwrap();
+        let levels: Vec<_> = warnings
+            .iter()
+            .map(|warning| (warning.lint(), warning.level()))
+            .collect();
+        assert_eq!(
+            levels,
+            vec![
+                (Lint::UnusedVariables, Level::Deny),
+                (Lint::DebugCalls, Level::Warn),
+            ]
+        );
+
+        let config = LintConfig::new()
+            .with_level(Lint::DebugCalls, Level::Deny)
+            .with_level(Lint::UnusedVariables, Level::Allow);
+        let warnings = Linter::new().with_config(config).lint(source).unwrap();
+        let levels: Vec<_> = warnings
+            .iter()
+            .map(|warning| (warning.lint(), warning.level()))
+            .collect();
+        assert_eq!(
+            levels,
+            vec![
+                (Lint::UnusedVariables, Level::Deny),
+                (Lint::DebugCalls, Level::Deny),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_lint_attribute() {
+        let error = Linter::new()
+            .lint("#[allow(unused_things)]\nfn main() {}")
+            .unwrap_err();
+        assert!(error.contains("Lint `unused_things` does not exist"));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn config_from_toml() {
+        let config = LintConfig::from_toml(
+            r#"[lints]
+dbg_macro = "deny"
+shadowed_bindings = "allow"
+"#,
+        )
+        .unwrap();
+        assert_eq!(Level::Deny, config.level(Lint::DebugCalls));
+        assert_eq!(Level::Allow, config.level(Lint::ShadowedBindings));
+        assert_eq!(Level::Warn, config.level(Lint::UnusedVariables));
+
+        let error = LintConfig::from_toml("[lints]\nunused = \"warn\"").unwrap_err();
+        assert!(error.contains("Lint `unused` does not exist"));
+        assert!(LintConfig::from_toml("[lints]\ndbg_macro = \"forbid\"").is_err());
+    }
+
+    #[test]
+    fn display_warning() {
+        let source = "fn main() {\n    let x: u8 = 1;\n}";
+        let warnings = Linter::new().lint(source).unwrap();
+        let expected = "  |
+2 |     let x: u8 = 1;
+  |     ^^^^^^^^^ warning[unused_variables]: variable `x` is never used";
+        assert_eq!(expected, warnings[0].to_string());
+    }
+}
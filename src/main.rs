@@ -1,8 +1,40 @@
 use base64::display::Base64Display;
 use base64::engine::general_purpose::STANDARD;
-use clap::{Arg, ArgAction, Command};
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
 
-use simplicityhl::{Arguments, CompiledProgram};
+use simplicityhl::coverage::Coverage;
+use simplicityhl::debugger::{Breakpoint, Command as DebugCommand, Debugger, Step};
+use simplicityhl::disassemble::{Disassembly, Encoding};
+use simplicityhl::elements::hashes::Hash;
+use simplicityhl::elements::hex::FromHex;
+use simplicityhl::elements::taproot::ControlBlock;
+use simplicityhl::environment;
+#[cfg(feature = "serde")]
+use simplicityhl::environment::TransactionDescription;
+use simplicityhl::equivalence::{Equivalence, EquivalenceCheck};
+use simplicityhl::format::Formatter;
+use simplicityhl::lint::{Level, LintConfig, Linter};
+use simplicityhl::profiler::Profiler;
+use simplicityhl::repl::Repl;
+#[cfg(feature = "serde")]
+use simplicityhl::signer::KeyStore;
+use simplicityhl::simplicity::dag::{DagLike, MaxSharing};
+use simplicityhl::simplicity::jet::elements::ElementsEnv;
+use simplicityhl::simplicity::node::Inner;
+use simplicityhl::simplicity::Cmr;
+use simplicityhl::str::{FunctionName, WitnessName};
+use simplicityhl::symbolic::SymbolicExecution;
+use simplicityhl::testing::{unit_tests, TestOutcome, UnitTest};
+use simplicityhl::tracker::{DefaultTracker, TraceRecorder, TrackerLogLevel};
+use simplicityhl::{dummy_env, elements};
+use simplicityhl::{Arguments, CompiledProgram, Parameters, TemplateProgram, Value, WitnessValues};
+#[cfg(feature = "serde")]
+use std::collections::BTreeMap;
+use std::collections::{BTreeSet, HashMap};
+use std::io::BufRead;
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::sync::Arc;
 use std::{env, fmt};
 
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
@@ -24,79 +56,579 @@ impl fmt::Display for Output {
     }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let command = {
-        Command::new(env!("CARGO_BIN_NAME"))
-            .about(
-                "\
-                Compile the given SimplicityHL program and print the resulting Simplicity base64 string.\n\
-                If a SimplicityHL witness is provided, then use it to satisfy the program (requires \
-                feature 'serde' to be enabled).\
-                ",
-            )
-            .arg(
-                Arg::new("prog_file")
-                    .required(true)
-                    .value_name("PROGRAM_FILE")
-                    .action(ArgAction::Set)
-                    .help("SimplicityHL program file to build"),
-            )
-            .arg(
-                Arg::new("wit_file")
-                    .value_name("WITNESS_FILE")
-                    .action(ArgAction::Set)
-                    .help("File containing the witness data"),
-            )
-            .arg(
-                Arg::new("debug")
-                    .long("debug")
-                    .action(ArgAction::SetTrue)
-                    .help("Include debug symbols in the output"),
-            )
-            .arg(
-                Arg::new("json")
-                    .long("json")
-                    .action(ArgAction::SetTrue)
-                    .help("Output in JSON"),
-            )
-    };
+fn prog_file_arg() -> Arg {
+    Arg::new("prog_file")
+        .required(true)
+        .value_name("PROGRAM_FILE")
+        .action(ArgAction::Set)
+        .help("SimplicityHL program file")
+}
+
+fn wit_file_arg() -> Arg {
+    Arg::new("wit_file")
+        .value_name("WITNESS_FILE")
+        .action(ArgAction::Set)
+        .help("File containing the witness data")
+}
+
+fn args_file_arg() -> Arg {
+    Arg::new("args_file")
+        .long("args")
+        .value_name("FILE")
+        .action(ArgAction::Set)
+        .help("File containing the arguments for the program parameters")
+}
+
+fn arg_arg() -> Arg {
+    Arg::new("arg")
+        .long("arg")
+        .value_name("NAME=VALUE")
+        .action(ArgAction::Append)
+        .help("Argument for a program parameter, overriding the arguments file")
+}
+
+fn debug_arg() -> Arg {
+    Arg::new("debug")
+        .long("debug")
+        .action(ArgAction::SetTrue)
+        .help("Include debug symbols in the output")
+}
+
+fn optimize_arg() -> Arg {
+    Arg::new("optimize")
+        .long("optimize")
+        .action(ArgAction::SetTrue)
+        .help("Simplify the compiled code with the peephole optimizer, which changes the CMR of the program")
+}
+
+fn build_args() -> [Arg; 8] {
+    [
+        prog_file_arg(),
+        wit_file_arg(),
+        args_file_arg(),
+        arg_arg(),
+        debug_arg(),
+        optimize_arg(),
+        Arg::new("disassemble")
+            .long("disassemble")
+            .action(ArgAction::SetTrue)
+            .help("Print a disassembly of the Simplicity program instead of base64"),
+        Arg::new("json")
+            .long("json")
+            .action(ArgAction::SetTrue)
+            .help("Output in JSON"),
+    ]
+}
+
+fn environment_args() -> [Arg; 9] {
+    [
+        Arg::new("tx_file")
+            .long("tx")
+            .value_name("FILE")
+            .action(ArgAction::Set)
+            .help("JSON or TOML file describing the transaction environment"),
+        Arg::new("pset_file")
+            .long("pset")
+            .value_name("FILE")
+            .action(ArgAction::Set)
+            .help("File containing a base64 or hex encoded PSET that spends the program"),
+        Arg::new("raw_tx_file")
+            .long("raw-tx")
+            .value_name("FILE")
+            .action(ArgAction::Set)
+            .requires("utxo")
+            .help("File containing a hex encoded transaction that spends the program"),
+        Arg::new("utxo")
+            .long("utxo")
+            .value_name("HEX")
+            .action(ArgAction::Append)
+            .requires("raw_tx_file")
+            .help("Hex encoded output that is spent by the raw transaction, once per input"),
+        Arg::new("control_block")
+            .long("control-block")
+            .value_name("HEX")
+            .action(ArgAction::Set)
+            .requires("raw_tx_file")
+            .help("Control block of the spent input of the raw transaction"),
+        Arg::new("input_index")
+            .long("input-index")
+            .value_name("INDEX")
+            .value_parser(clap::value_parser!(u32))
+            .default_value("0")
+            .help("Index of the input of the PSET or raw transaction that spends the program"),
+        Arg::new("genesis_hash")
+            .long("genesis-hash")
+            .value_name("HEX")
+            .action(ArgAction::Set)
+            .help("Genesis block hash of the chain of the PSET or raw transaction"),
+        Arg::new("lock_time")
+            .long("lock-time")
+            .value_name("HEIGHT")
+            .value_parser(clap::value_parser!(u32))
+            .conflicts_with("environment")
+            .help("Block height of the lock time of the dummy transaction"),
+        Arg::new("sequence")
+            .long("sequence")
+            .value_name("DISTANCE")
+            .value_parser(clap::value_parser!(u16))
+            .conflicts_with("environment")
+            .help("Relative block height of the input sequence of the dummy transaction"),
+    ]
+}
 
-    let matches = command.get_matches();
+fn environment_group() -> ArgGroup {
+    ArgGroup::new("environment")
+        .args(["tx_file", "pset_file", "raw_tx_file"])
+        .multiple(false)
+}
 
+fn command() -> Command {
+    Command::new(env!("CARGO_BIN_NAME"))
+        .about(
+            "\
+            Compile the given SimplicityHL program and print the resulting Simplicity base64 string.\n\
+            If a SimplicityHL witness is provided, then use it to satisfy the program (requires \
+            feature 'serde' to be enabled).\n\
+            Without a subcommand, behave like `build`.\
+            ",
+        )
+        .args(build_args())
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
+        .subcommand(
+            Command::new("check")
+                .about("Parse and type-check the given SimplicityHL program without compiling it and report all errors")
+                .arg(prog_file_arg()),
+        )
+        .subcommand(
+            Command::new("build")
+                .about("Compile the given SimplicityHL program and print the resulting Simplicity base64 string")
+                .args(build_args()),
+        )
+        .subcommand(
+            Command::new("disassemble")
+                .about("Decode the given Simplicity program and print its disassembly")
+                .arg(
+                    Arg::new("program")
+                        .required(true)
+                        .value_name("PROGRAM")
+                        .action(ArgAction::Set)
+                        .help("Base64 encoded Simplicity program, as printed by `build`"),
+                )
+                .arg(
+                    Arg::new("witness")
+                        .value_name("WITNESS")
+                        .action(ArgAction::Set)
+                        .help("Base64 encoded Simplicity witness data of the program"),
+                )
+                .arg(
+                    Arg::new("hex")
+                        .long("hex")
+                        .action(ArgAction::SetTrue)
+                        .help("Decode the program and witness data as hex instead of base64"),
+                )
+                .arg(
+                    Arg::new("source_file")
+                        .long("source")
+                        .value_name("FILE")
+                        .action(ArgAction::Set)
+                        .help("SimplicityHL program file that the program was compiled from with `--debug`, for debug symbols"),
+                )
+                .arg(args_file_arg())
+                .arg(arg_arg())
+                .arg(optimize_arg()),
+        )
+        .subcommand(
+            Command::new("run")
+                .about("Satisfy the given SimplicityHL program and execute it in a transaction environment")
+                .arg(prog_file_arg())
+                .arg(wit_file_arg())
+                .arg(args_file_arg())
+                .arg(arg_arg())
+                .arg(optimize_arg())
+                .args(environment_args())
+                .group(environment_group())
+                .arg(
+                    Arg::new("log_level")
+                        .long("log-level")
+                        .value_name("LEVEL")
+                        .value_parser(["none", "debug", "warning", "trace"])
+                        .default_value("debug")
+                        .help("Print debug output, pruning warnings or jet calls during execution"),
+                )
+                .arg(
+                    Arg::new("trace_file")
+                        .long("trace")
+                        .value_name("FILE")
+                        .action(ArgAction::Set)
+                        .help("Write a trace of the evaluated calls as JSON Lines to the given file"),
+                ),
+        )
+        .subcommand(
+            Command::new("debug")
+                .about("Execute the given SimplicityHL program step by step in an interactive debugger")
+                .arg(prog_file_arg())
+                .arg(wit_file_arg())
+                .arg(args_file_arg())
+                .arg(arg_arg())
+                .arg(optimize_arg())
+                .args(environment_args())
+                .group(environment_group())
+                .arg(
+                    Arg::new("break")
+                        .long("break")
+                        .value_name("LINE|FUNCTION")
+                        .action(ArgAction::Append)
+                        .help("Stop at the given line or function instead of the first step"),
+                ),
+        )
+        .subcommand(
+            Command::new("coverage")
+                .about("Execute the given SimplicityHL program once per witness file and report which calls and match arms were covered")
+                .arg(prog_file_arg())
+                .arg(
+                    Arg::new("wit_files")
+                        .value_name("WITNESS_FILE")
+                        .action(ArgAction::Append)
+                        .help("Files containing the witness data of each run; without files, run once without witness data"),
+                )
+                .arg(args_file_arg())
+                .arg(arg_arg())
+                .arg(optimize_arg())
+                .args(environment_args())
+                .group(environment_group())
+                .arg(
+                    Arg::new("lcov_file")
+                        .long("lcov")
+                        .value_name("FILE")
+                        .action(ArgAction::Set)
+                        .help("Write the coverage as lcov tracefile to the given file"),
+                )
+                .arg(
+                    Arg::new("html_file")
+                        .long("html")
+                        .value_name("FILE")
+                        .action(ArgAction::Set)
+                        .help("Write the source annotated with coverage as HTML to the given file"),
+                ),
+        )
+        .subcommand(
+            Command::new("profile")
+                .about("Execute the given SimplicityHL program and report how much cost each function and line spends")
+                .arg(prog_file_arg())
+                .arg(wit_file_arg())
+                .arg(args_file_arg())
+                .arg(arg_arg())
+                .arg(optimize_arg())
+                .args(environment_args())
+                .group(environment_group())
+                .arg(
+                    Arg::new("folded_file")
+                        .long("folded")
+                        .value_name("FILE")
+                        .action(ArgAction::Set)
+                        .help("Write the cost as folded stacks for flame graph tools to the given file"),
+                ),
+        )
+        .subcommand(
+            Command::new("test")
+                .about("Run the unit tests of the given SimplicityHL program: the functions with the #[test] attribute")
+                .arg(prog_file_arg())
+                .arg(
+                    Arg::new("filter")
+                        .value_name("FILTER")
+                        .action(ArgAction::Set)
+                        .help("Run only the tests whose name contains the given string"),
+                )
+                .arg(args_file_arg())
+                .arg(arg_arg())
+                .arg(optimize_arg()),
+        )
+        .subcommand(
+            Command::new("reach")
+                .about("Symbolically execute the given SimplicityHL program and report which match arms and panic! calls some witness data reaches")
+                .arg(prog_file_arg())
+                .arg(args_file_arg())
+                .arg(arg_arg())
+                .arg(optimize_arg())
+                .arg(
+                    Arg::new("max_conflicts")
+                        .long("max-conflicts")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Give up on a program point after the SAT solver runs into the given number of conflicts"),
+                ),
+        )
+        .subcommand(
+            Command::new("equiv")
+                .about("Check if two versions of a SimplicityHL program accept the same witness data and print a distinguishing witness if not")
+                .arg(prog_file_arg())
+                .arg(
+                    Arg::new("other_file")
+                        .required(true)
+                        .value_name("OTHER_FILE")
+                        .action(ArgAction::Set)
+                        .help("SimplicityHL program file of the other version"),
+                )
+                .arg(args_file_arg())
+                .arg(arg_arg())
+                .arg(optimize_arg())
+                .args(environment_args())
+                .group(environment_group())
+                .arg(
+                    Arg::new("max_conflicts")
+                        .long("max-conflicts")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Give up after the SAT solver runs into the given number of conflicts"),
+                ),
+        )
+        .subcommand(
+            Command::new("repl")
+                .about("Evaluate SimplicityHL statements and expressions interactively in a transaction environment")
+                .arg(optimize_arg())
+                .args(environment_args())
+                .group(environment_group()),
+        )
+        .subcommand(
+            Command::new("fmt")
+                .about("Format the given SimplicityHL files in place")
+                .arg(
+                    Arg::new("files")
+                        .required(true)
+                        .num_args(1..)
+                        .value_name("FILE")
+                        .action(ArgAction::Append)
+                        .help("SimplicityHL files to format"),
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .action(ArgAction::SetTrue)
+                        .help("List the files that are not formatted without changing them, and fail if there are any"),
+                )
+                .arg(
+                    Arg::new("width")
+                        .long("width")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Maximum number of characters per line (default: 100)"),
+                ),
+        )
+        .subcommand(
+            Command::new("lint")
+                .about("Warn about suspicious code in the given SimplicityHL program, such as unused variables or dbg! calls")
+                .arg(prog_file_arg())
+                .arg(
+                    Arg::new("config_file")
+                        .long("config")
+                        .value_name("FILE")
+                        .action(ArgAction::Set)
+                        .help("TOML file that sets the level of each lint to allow, warn or deny"),
+                ),
+        )
+        .subcommand(
+            Command::new("sign")
+                .about("Sign the signature witnesses of the given SimplicityHL program and print the completed witness file")
+                .arg(prog_file_arg())
+                .arg(wit_file_arg())
+                .arg(
+                    Arg::new("keys_file")
+                        .long("keys")
+                        .value_name("FILE")
+                        .required(true)
+                        .action(ArgAction::Set)
+                        .help("JSON file that maps signature witness names to hex encoded secret keys"),
+                )
+                .arg(args_file_arg())
+                .arg(arg_arg())
+                .arg(optimize_arg())
+                .args(environment_args())
+                .group(environment_group()),
+        )
+        .subcommand(
+            Command::new("cmr")
+                .about("Print the commitment Merkle root of the given SimplicityHL program")
+                .arg(prog_file_arg())
+                .arg(args_file_arg())
+                .arg(arg_arg())
+                .arg(optimize_arg())
+                .arg(debug_arg()),
+        )
+        .subcommand(
+            Command::new("info")
+                .about("List the parameters, witnesses and jets of the given SimplicityHL program")
+                .arg(prog_file_arg())
+                .arg(args_file_arg())
+                .arg(arg_arg())
+                .arg(optimize_arg()),
+        )
+}
+
+fn main() -> ExitCode {
+    let matches = command().get_matches();
+    let result = match matches.subcommand() {
+        Some(("check", matches)) => check(matches),
+        Some(("build", matches)) => build(matches),
+        Some(("disassemble", matches)) => disassemble(matches),
+        Some(("run", matches)) => run(matches),
+        Some(("debug", matches)) => debug(matches),
+        Some(("coverage", matches)) => coverage(matches),
+        Some(("profile", matches)) => profile(matches),
+        Some(("test", matches)) => test(matches),
+        Some(("reach", matches)) => reach(matches),
+        Some(("equiv", matches)) => equiv(matches),
+        Some(("repl", matches)) => repl(matches),
+        Some(("fmt", matches)) => fmt(matches),
+        Some(("lint", matches)) => lint(matches),
+        Some(("sign", matches)) => sign(matches),
+        Some(("cmr", matches)) => cmr(matches),
+        Some(("info", matches)) => info(matches),
+        _ => build(&matches),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn read_program(matches: &ArgMatches) -> Result<String, String> {
     let prog_file = matches.get_one::<String>("prog_file").unwrap();
     let prog_path = std::path::Path::new(prog_file);
-    let prog_text = std::fs::read_to_string(prog_path).map_err(|e| e.to_string())?;
+    std::fs::read_to_string(prog_path).map_err(|e| format!("{prog_file}: {e}"))
+}
+
+/// Read the arguments for the `parameters` from the arguments file,
+/// overridden by individual `--arg NAME=VALUE` assignments.
+fn read_arguments(matches: &ArgMatches, parameters: &Parameters) -> Result<Arguments, String> {
+    let mut map: HashMap<WitnessName, Value> = match matches.get_one::<String>("args_file") {
+        Some(args_file) => read_arguments_file(args_file, parameters)?
+            .iter()
+            .map(|(name, value)| (name.shallow_clone(), value.clone()))
+            .collect(),
+        None => HashMap::new(),
+    };
+    for assignment in matches.get_many::<String>("arg").into_iter().flatten() {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("Argument `{assignment}` is not of the form NAME=VALUE"))?;
+        let name = WitnessName::from_str_unchecked(name.trim());
+        let ty = parameters
+            .get(&name)
+            .ok_or_else(|| format!("Parameter `{name}` is not defined in the program"))?;
+        let value = Value::parse_from_str(value.trim(), ty).map_err(|error| {
+            format!("Argument for parameter `{name}` is not of type `{ty}`:\n{error}")
+        })?;
+        map.insert(name, value);
+    }
+    Ok(Arguments::from(map))
+}
+
+#[cfg(feature = "serde")]
+fn read_arguments_file(args_file: &str, parameters: &Parameters) -> Result<Arguments, String> {
+    let args_text = std::fs::read_to_string(args_file).map_err(|e| format!("{args_file}: {e}"))?;
+    Arguments::from_json_with_types(&args_text, parameters).map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "serde"))]
+fn read_arguments_file(_args_file: &str, _parameters: &Parameters) -> Result<Arguments, String> {
+    Err(
+        "Program was compiled without the 'serde' feature and cannot process .args files."
+            .to_string(),
+    )
+}
+
+/// Check if the compiled code is simplified by the peephole optimizer.
+fn optimize(matches: &ArgMatches) -> bool {
+    matches.get_flag("optimize")
+}
+
+/// Parse the program and instantiate it with the arguments from the command line.
+fn compile(matches: &ArgMatches, include_debug_symbols: bool) -> Result<CompiledProgram, String> {
+    let prog_text = read_program(matches)?;
+    let template = TemplateProgram::new(prog_text)?.with_optimization(optimize(matches));
+    let arguments = read_arguments(matches, template.parameters())?;
+    template.instantiate(arguments, include_debug_symbols)
+}
+
+/// Compute the CMR of the program as it is deployed, without debug symbols.
+///
+/// Debug symbols change the CMR, so commands that execute a debug build derive
+/// their environment from this CMR instead. Otherwise the control block and the
+/// signature hash would not match the transaction that spends the program.
+fn deployed_cmr(matches: &ArgMatches) -> Result<Cmr, String> {
+    compile(matches, false).map(|compiled| compiled.commit().cmr())
+}
+
+fn read_witness(
+    matches: &ArgMatches,
+    compiled: &CompiledProgram,
+) -> Result<Option<WitnessValues>, String> {
+    matches
+        .get_one::<String>("wit_file")
+        .map(|wit_file| read_witness_file(wit_file, compiled))
+        .transpose()
+}
+
+#[cfg(feature = "serde")]
+fn read_witness_file(wit_file: &str, compiled: &CompiledProgram) -> Result<WitnessValues, String> {
+    let wit_path = std::path::Path::new(wit_file);
+    let wit_text = std::fs::read_to_string(wit_path).map_err(|e| e.to_string())?;
+    // Use new context-aware deserialization method
+    // Type information is provided by the compiled program (witness_types)
+    // Users only need to specify values in simplified JSON format
+    WitnessValues::from_json_with_types(&wit_text, compiled.witness_types())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "serde"))]
+fn read_witness_file(
+    _wit_file: &str,
+    _compiled: &CompiledProgram,
+) -> Result<WitnessValues, String> {
+    Err(
+        "Program was compiled without the 'serde' feature and cannot process .wit files."
+            .to_string(),
+    )
+}
+
+fn check(matches: &ArgMatches) -> Result<(), String> {
+    let prog_file = matches.get_one::<String>("prog_file").unwrap();
+    let prog_text = read_program(matches)?;
+    let errors = TemplateProgram::check(prog_text);
+    if errors.is_empty() {
+        println!("{prog_file}: ok");
+        return Ok(());
+    }
+    for error in &errors {
+        eprintln!("{error}\n");
+    }
+    let plural = if errors.len() == 1 { "" } else { "s" };
+    Err(format!("{prog_file}: {} error{plural}", errors.len()))
+}
+
+fn build(matches: &ArgMatches) -> Result<(), String> {
     let include_debug_symbols = matches.get_flag("debug");
     let output_json = matches.get_flag("json");
+    let disassemble = matches.get_flag("disassemble");
 
-    let compiled = CompiledProgram::new(prog_text, Arguments::default(), include_debug_symbols)?;
+    let compiled = compile(matches, include_debug_symbols)?;
+    let witness_opt = read_witness(matches, &compiled)?;
 
-    #[cfg(feature = "serde")]
-    let witness_opt = matches
-        .get_one::<String>("wit_file")
-        .map(|wit_file| -> Result<simplicityhl::WitnessValues, String> {
-            let wit_path = std::path::Path::new(wit_file);
-            let wit_text = std::fs::read_to_string(wit_path).map_err(|e| e.to_string())?;
-            // Use new context-aware deserialization method
-            // Type information is provided by the compiled program (witness_types)
-            // Users only need to specify values in simplified JSON format
-            let witness = simplicityhl::WitnessValues::from_json_with_types(
-                &wit_text,
-                &compiled.witness_types(),
-            )
-            .map_err(|e| e.to_string())?;
-            Ok(witness)
-        })
-        .transpose()?;
-    #[cfg(not(feature = "serde"))]
-    let witness_opt = if matches.contains_id("wit_file") {
-        return Err(
-            "Program was compiled without the 'serde' feature and cannot process .wit files."
-                .into(),
-        );
-    } else {
-        None
-    };
+    if disassemble {
+        let disassembly = match witness_opt {
+            Some(witness) => {
+                let satisfied = compiled.satisfy(witness)?;
+                Disassembly::from_redeem(satisfied.redeem(), Some(satisfied.debug_symbols()))
+            }
+            None => Disassembly::from_commit(&compiled.commit(), Some(compiled.debug_symbols())),
+        };
+        print!("{}", disassembly);
+        return Ok(());
+    }
 
     let (program_bytes, witness_bytes) = match witness_opt {
         Some(witness) => {
@@ -118,13 +650,639 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     if output_json {
         #[cfg(not(feature = "serde"))]
         return Err(
-            "Program was compiled without the 'serde' feature and cannot output JSON.".into(),
+            "Program was compiled without the 'serde' feature and cannot output JSON.".to_string(),
         );
         #[cfg(feature = "serde")]
-        println!("{}", serde_json::to_string(&output)?);
+        println!(
+            "{}",
+            serde_json::to_string(&output).map_err(|e| e.to_string())?
+        );
     } else {
         println!("{}", output);
     }
 
     Ok(())
 }
+
+fn disassemble(matches: &ArgMatches) -> Result<(), String> {
+    let program = matches.get_one::<String>("program").unwrap();
+    let witness = matches.get_one::<String>("witness");
+    let encoding = if matches.get_flag("hex") {
+        Encoding::Hex
+    } else {
+        Encoding::Base64
+    };
+    let compiled = match matches.get_one::<String>("source_file") {
+        Some(source_file) => {
+            let source_text =
+                std::fs::read_to_string(source_file).map_err(|e| format!("{source_file}: {e}"))?;
+            let template = TemplateProgram::new(source_text)?.with_optimization(optimize(matches));
+            let arguments = read_arguments(matches, template.parameters())?;
+            Some(template.instantiate(arguments, true)?)
+        }
+        None => None,
+    };
+
+    let disassembly = Disassembly::decode(
+        program,
+        witness.map(String::as_str),
+        encoding,
+        compiled.as_ref().map(CompiledProgram::debug_symbols),
+    )?;
+    if let Some(compiled) = &compiled {
+        if compiled.commit().cmr() != disassembly.cmr() {
+            eprintln!("warning: program was not compiled from the source file with `--debug`; ignoring its debug symbols");
+        }
+    }
+    print!("{}", disassembly);
+    Ok(())
+}
+
+fn run(matches: &ArgMatches) -> Result<(), String> {
+    let compiled = compile(matches, true)?;
+    let witness = read_witness(matches, &compiled)?.unwrap_or_default();
+    let satisfied = compiled.satisfy(witness)?;
+
+    let env = read_environment(matches, deployed_cmr(matches)?)?;
+
+    let log_level = match matches.get_one::<String>("log_level").map(String::as_str) {
+        Some("none") => TrackerLogLevel::None,
+        Some("warning") => TrackerLogLevel::Warning,
+        Some("trace") => TrackerLogLevel::Trace,
+        _ => TrackerLogLevel::Debug,
+    };
+    let mut tracker = DefaultTracker::new(satisfied.debug_symbols()).with_log_level(log_level);
+    match matches.get_one::<String>("trace_file") {
+        Some(trace_file) => {
+            let mut recorder = TraceRecorder::new(satisfied.debug_symbols(), tracker);
+            let result = satisfied.redeem().prune_with_tracker(&env, &mut recorder);
+            // Write the trace even if execution failed, so the failure can be analyzed
+            write_trace(trace_file, &recorder)?;
+            result
+        }
+        None => satisfied.redeem().prune_with_tracker(&env, &mut tracker),
+    }
+    .map_err(|e| format!("Execution failed: {e}"))?;
+    println!("Program executed successfully");
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+fn write_trace<T>(trace_file: &str, recorder: &TraceRecorder<T>) -> Result<(), String> {
+    std::fs::write(trace_file, recorder.to_json_lines()).map_err(|e| format!("{trace_file}: {e}"))
+}
+
+#[cfg(not(feature = "serde"))]
+fn write_trace<T>(_trace_file: &str, _recorder: &TraceRecorder<T>) -> Result<(), String> {
+    Err("Program was compiled without the 'serde' feature and cannot write traces.".to_string())
+}
+
+fn coverage(matches: &ArgMatches) -> Result<(), String> {
+    let prog_file = matches.get_one::<String>("prog_file").unwrap();
+    let prog_text = read_program(matches)?;
+    let compiled = compile(matches, true)?;
+    let env = read_environment(matches, deployed_cmr(matches)?)?;
+
+    let mut coverage = Coverage::new(&compiled);
+    let wit_files = matches
+        .get_many::<String>("wit_files")
+        .map_or_else(|| vec![None], |files| files.map(Some).collect());
+    for wit_file in wit_files {
+        let witness = match wit_file {
+            Some(wit_file) => read_witness_file(wit_file, &compiled)?,
+            None => WitnessValues::default(),
+        };
+        let satisfied = compiled.satisfy(witness)?;
+        // Failing runs still contribute to the coverage
+        if let Err(error) = coverage.record(&satisfied, &env) {
+            eprintln!(
+                "{}: {error}",
+                wit_file.map_or(prog_file.as_str(), String::as_str)
+            );
+        }
+    }
+
+    if let Some(lcov_file) = matches.get_one::<String>("lcov_file") {
+        std::fs::write(lcov_file, coverage.to_lcov(prog_file))
+            .map_err(|e| format!("{lcov_file}: {e}"))?;
+    }
+    if let Some(html_file) = matches.get_one::<String>("html_file") {
+        std::fs::write(html_file, coverage.to_html(&prog_text))
+            .map_err(|e| format!("{html_file}: {e}"))?;
+    }
+    print!("{coverage}");
+    Ok(())
+}
+
+fn test(matches: &ArgMatches) -> Result<(), String> {
+    let prog_file = matches.get_one::<String>("prog_file").unwrap();
+    let prog_text = read_program(matches)?;
+    let filter = matches.get_one::<String>("filter");
+    let tests = unit_tests(&prog_text)?
+        .into_iter()
+        .filter(|test| {
+            filter.map_or(true, |filter| {
+                test.name().as_inner().contains(filter.as_str())
+            })
+        })
+        .collect::<Vec<UnitTest>>();
+
+    println!("running {} tests", tests.len());
+    let mut failures = Vec::new();
+    for test in &tests {
+        let outcome =
+            run_test(matches, prog_file, &prog_text, test).unwrap_or_else(TestOutcome::Failed);
+        match outcome {
+            TestOutcome::Passed => println!("test {} ... ok", test.name()),
+            TestOutcome::Failed(message) => {
+                println!("test {} ... FAILED", test.name());
+                failures.push((test.name(), message));
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+        for (name, message) in &failures {
+            println!("    {name}: {message}");
+        }
+    }
+    let result = match failures.is_empty() {
+        true => "ok",
+        false => "FAILED",
+    };
+    println!(
+        "\ntest result: {result}. {} passed; {} failed",
+        tests.len() - failures.len(),
+        failures.len()
+    );
+    match failures.is_empty() {
+        true => Ok(()),
+        false => Err(format!("{prog_file}: {} tests failed", failures.len())),
+    }
+}
+
+/// Compile, satisfy and execute a unit test of the program.
+///
+/// The witness file of the test is relative to the program file.
+fn run_test(
+    matches: &ArgMatches,
+    prog_file: &str,
+    prog_text: &str,
+    test: &UnitTest,
+) -> Result<TestOutcome, String> {
+    let template = TemplateProgram::with_entry_point(prog_text, test.name())?
+        .with_optimization(optimize(matches));
+    let arguments = read_arguments(matches, template.parameters())?;
+    let compiled = template.instantiate(arguments, true)?;
+    let witness = match test.witness_file() {
+        Some(wit_file) => {
+            let prog_dir = std::path::Path::new(prog_file)
+                .parent()
+                .unwrap_or(std::path::Path::new(""));
+            let wit_path = prog_dir.join(wit_file);
+            read_witness_file(&wit_path.to_string_lossy(), &compiled)?
+        }
+        None => WitnessValues::default(),
+    };
+    let satisfied = compiled.satisfy(witness)?;
+    Ok(test.execute(&satisfied))
+}
+
+fn profile(matches: &ArgMatches) -> Result<(), String> {
+    let prog_text = read_program(matches)?;
+    let compiled = compile(matches, true)?;
+    let witness = read_witness(matches, &compiled)?.unwrap_or_default();
+    let satisfied = compiled.satisfy(witness)?;
+
+    let env = read_environment(matches, deployed_cmr(matches)?)?;
+
+    let mut profiler = Profiler::new(&prog_text, satisfied.debug_symbols())?;
+    let result = satisfied.redeem().prune_with_tracker(&env, &mut profiler);
+    // Report the profile even if execution failed, so the cost up to the failure can be analyzed
+    if let Some(folded_file) = matches.get_one::<String>("folded_file") {
+        std::fs::write(folded_file, profiler.to_folded_stacks())
+            .map_err(|e| format!("{folded_file}: {e}"))?;
+    }
+    print!("{profiler}");
+    result.map_err(|e| format!("Execution failed: {e}"))?;
+    Ok(())
+}
+
+fn debug(matches: &ArgMatches) -> Result<(), String> {
+    let prog_text = read_program(matches)?;
+    let template = TemplateProgram::new(prog_text.as_str())?.with_optimization(optimize(matches));
+    let arguments = read_arguments(matches, template.parameters())?;
+    let compiled = template.instantiate(arguments, true)?;
+    let witness = read_witness(matches, &compiled)?.unwrap_or_default();
+    let satisfied = compiled.satisfy(witness)?;
+
+    let env = read_environment(matches, deployed_cmr(matches)?)?;
+
+    let breakpoints = matches
+        .get_many::<String>("break")
+        .into_iter()
+        .flatten()
+        .map(|breakpoint| parse_breakpoint(breakpoint))
+        .collect::<Vec<Breakpoint>>();
+    let lines = prog_text.lines().collect::<Vec<&str>>();
+    let mut debugger = Debugger::new(
+        &prog_text,
+        satisfied.debug_symbols(),
+        |step, breakpoints| prompt(step, breakpoints, &lines),
+    )?
+    .with_breakpoints(breakpoints);
+    satisfied
+        .redeem()
+        .prune_with_tracker(&env, &mut debugger)
+        .map_err(|e| format!("Execution failed: {e}"))?;
+    println!("Program executed successfully");
+    Ok(())
+}
+
+/// Parse a breakpoint from a line number or a function name.
+fn parse_breakpoint(breakpoint: &str) -> Breakpoint {
+    match breakpoint.trim().parse::<usize>() {
+        Ok(line) => Breakpoint::Line(line),
+        Err(_) => Breakpoint::Function(FunctionName::from_str_unchecked(breakpoint.trim())),
+    }
+}
+
+/// Print the current step and read debugger commands from stdin until execution resumes.
+fn prompt(step: &Step, breakpoints: &mut Vec<Breakpoint>, lines: &[&str]) -> DebugCommand {
+    let source_line = lines.get(step.line() - 1).map_or("", |line| line.trim());
+    println!("{}:{}: {source_line}", step.function(), step.line());
+    println!("  -> {}", step.call().text());
+
+    let stdin = std::io::stdin();
+    loop {
+        eprint!("(debug) ");
+        let mut input = String::new();
+        match stdin.lock().read_line(&mut input) {
+            // Run to completion once stdin is closed
+            Ok(0) | Err(_) => return DebugCommand::Continue,
+            Ok(_) => {}
+        }
+        let (command, argument) = input.trim().split_once(' ').unwrap_or((input.trim(), ""));
+        match command {
+            "" | "s" | "step" => return DebugCommand::Step,
+            "n" | "next" => return DebugCommand::StepOver,
+            "o" | "out" => return DebugCommand::StepOut,
+            "c" | "continue" => return DebugCommand::Continue,
+            "b" | "break" if !argument.trim().is_empty() => {
+                breakpoints.push(parse_breakpoint(argument));
+                println!("Breakpoint set at {}", argument.trim());
+            }
+            "p" | "print" => match step.variables() {
+                Some([]) => println!("No variables in scope"),
+                Some(variables) => {
+                    for (identifier, value) in variables {
+                        println!("{identifier} = {value}");
+                    }
+                }
+                None => println!("Variables are not available"),
+            },
+            "q" | "quit" => std::process::exit(0),
+            _ => println!(
+                "Commands: step (s), next (n), out (o), continue (c), break (b) LINE|FUNCTION, print (p), quit (q)"
+            ),
+        }
+    }
+}
+
+fn reach(matches: &ArgMatches) -> Result<(), String> {
+    let compiled = compile(matches, true)?;
+    let execution = match matches.get_one::<usize>("max_conflicts") {
+        Some(max_conflicts) => SymbolicExecution::with_max_conflicts(&compiled, *max_conflicts),
+        None => SymbolicExecution::new(&compiled),
+    };
+    print!("{execution}");
+    Ok(())
+}
+
+fn equiv(matches: &ArgMatches) -> Result<(), String> {
+    let first = compile(matches, true)?;
+    let other_file = matches.get_one::<String>("other_file").unwrap();
+    let other_template =
+        TemplateProgram::new(read_file(other_file)?)?.with_optimization(optimize(matches));
+    let other_arguments = read_arguments(matches, other_template.parameters())?;
+    let second = other_template.instantiate(other_arguments, true)?;
+    let env = read_environment(matches, deployed_cmr(matches)?)?;
+
+    let mut check = EquivalenceCheck::new(&first, &second).with_environment(env);
+    if let Some(max_conflicts) = matches.get_one::<usize>("max_conflicts") {
+        check = check.with_max_conflicts(*max_conflicts);
+    }
+    let equivalence = check.check()?;
+    print!("{equivalence}");
+    match equivalence {
+        Equivalence::Identical | Equivalence::Equivalent => Ok(()),
+        Equivalence::Different(..) => Err("Programs are not equivalent".to_string()),
+        Equivalence::Unknown => Err("Equivalence is unknown".to_string()),
+    }
+}
+
+fn fmt(matches: &ArgMatches) -> Result<(), String> {
+    let check = matches.get_flag("check");
+    let mut formatter = Formatter::new();
+    if let Some(width) = matches.get_one::<usize>("width") {
+        formatter = formatter.with_line_width(*width);
+    }
+
+    let mut unformatted = 0;
+    for file in matches.get_many::<String>("files").unwrap() {
+        let source = read_file(file)?;
+        let formatted = formatter
+            .format(&source)
+            .map_err(|error| format!("{file}: {error}"))?;
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{file}: not formatted");
+            unformatted += 1;
+        } else {
+            std::fs::write(file, formatted).map_err(|e| format!("{file}: {e}"))?;
+        }
+    }
+
+    match unformatted {
+        0 => Ok(()),
+        _ => Err(format!("{unformatted} file(s) are not formatted")),
+    }
+}
+
+fn lint(matches: &ArgMatches) -> Result<(), String> {
+    let prog_file = matches.get_one::<String>("prog_file").unwrap();
+    let prog_text = read_program(matches)?;
+    let mut linter = Linter::new();
+    if let Some(config_file) = matches.get_one::<String>("config_file") {
+        linter = linter.with_config(read_lint_config(config_file)?);
+    }
+
+    let warnings = linter.lint(&prog_text)?;
+    for warning in &warnings {
+        println!("{warning}\n");
+    }
+    let errors = warnings
+        .iter()
+        .filter(|warning| warning.level() == Level::Deny)
+        .count();
+    match (warnings.len() - errors, errors) {
+        (0, 0) => {
+            println!("{prog_file}: ok");
+            Ok(())
+        }
+        (warnings, 0) => {
+            println!("{prog_file}: {warnings} warning(s)");
+            Ok(())
+        }
+        (warnings, errors) => Err(format!(
+            "{prog_file}: {warnings} warning(s) and {errors} error(s)"
+        )),
+    }
+}
+
+#[cfg(feature = "serde")]
+fn read_lint_config(config_file: &str) -> Result<LintConfig, String> {
+    LintConfig::from_toml(&read_file(config_file)?)
+}
+
+#[cfg(not(feature = "serde"))]
+fn read_lint_config(_config_file: &str) -> Result<LintConfig, String> {
+    Err(
+        "Program was compiled without the 'serde' feature and cannot process lint configuration files."
+            .to_string(),
+    )
+}
+
+fn repl(matches: &ArgMatches) -> Result<(), String> {
+    // Inputs are not part of any program, so the environment is for the unit program
+    let env = read_environment(matches, Cmr::unit())?;
+    let mut repl = Repl::new()
+        .with_environment(env)
+        .with_optimization(optimize(matches));
+
+    let stdin = std::io::stdin();
+    loop {
+        eprint!("> ");
+        let mut input = String::new();
+        match stdin.lock().read_line(&mut input) {
+            Ok(0) | Err(_) => return Ok(()),
+            Ok(_) => {}
+        }
+        let input = input.trim();
+        let (command, argument) = input.split_once(' ').unwrap_or((input, ""));
+        let result = match command {
+            "" => Ok(String::new()),
+            ":t" | ":type" => repl.type_of(argument).map(|ty| ty.to_string()),
+            ":c" | ":cost" => repl.cost(argument).map(|bounds| {
+                format!(
+                    "cost {} mWU, {} cells, {} frames",
+                    bounds.cost, bounds.extra_cells, bounds.extra_frames
+                )
+            }),
+            ":v" | ":vars" => Ok(repl
+                .variables()
+                .iter()
+                .map(|(identifier, value)| format!("{identifier}: {} = {value}", value.ty()))
+                .collect::<Vec<String>>()
+                .join("\n")),
+            ":q" | ":quit" => return Ok(()),
+            _ if command.starts_with(':') => Ok(
+                "Commands: :type (:t) EXPRESSION, :cost (:c) EXPRESSION, :vars (:v), :quit (:q)"
+                    .to_string(),
+            ),
+            _ => repl
+                .eval(input)
+                .map(|value| value.map(|value| value.to_string()).unwrap_or_default()),
+        };
+        match result {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => println!("{output}"),
+            Err(error) => eprintln!("{error}"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+fn sign(matches: &ArgMatches) -> Result<(), String> {
+    let compiled = compile(matches, false)?;
+    let witness = read_witness(matches, &compiled)?.unwrap_or_default();
+    let keys_file = matches.get_one::<String>("keys_file").unwrap();
+    let mut keys =
+        KeyStore::from_json(&read_file(keys_file)?).map_err(|e| format!("{keys_file}: {e}"))?;
+    let env = read_environment(matches, compiled.commit().cmr())?;
+
+    let signed = compiled.sign(witness, &mut keys, &env)?;
+    let witness_file = signed
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect::<BTreeMap<String, String>>();
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&witness_file).map_err(|e| e.to_string())?
+    );
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn sign(_matches: &ArgMatches) -> Result<(), String> {
+    Err(
+        "Program was compiled without the 'serde' feature and cannot process key files."
+            .to_string(),
+    )
+}
+
+/// Read the transaction environment in which the program with the given CMR is spent.
+///
+/// Without a transaction on the command line, use a dummy environment.
+fn read_environment(
+    matches: &ArgMatches,
+    script_cmr: Cmr,
+) -> Result<ElementsEnv<Arc<elements::Transaction>>, String> {
+    let input_index = *matches.get_one::<u32>("input_index").unwrap();
+    let genesis_hash = match matches.get_one::<String>("genesis_hash") {
+        Some(hash) => {
+            elements::BlockHash::from_str(hash).map_err(|e| format!("Invalid genesis hash: {e}"))?
+        }
+        None => elements::BlockHash::all_zeros(),
+    };
+
+    if let Some(tx_file) = matches.get_one::<String>("tx_file") {
+        read_description(tx_file, script_cmr)
+    } else if let Some(pset_file) = matches.get_one::<String>("pset_file") {
+        let pset = environment::decode_pset(&read_file(pset_file)?)?;
+        environment::from_pset(&pset, input_index, script_cmr, genesis_hash)
+    } else if let Some(raw_tx_file) = matches.get_one::<String>("raw_tx_file") {
+        let tx = environment::decode_transaction(&read_file(raw_tx_file)?)?;
+        let utxos = matches
+            .get_many::<String>("utxo")
+            .into_iter()
+            .flatten()
+            .map(|utxo| environment::decode_utxo(utxo))
+            .collect::<Result<Vec<_>, String>>()?;
+        let control_block = matches
+            .get_one::<String>("control_block")
+            .map(|hex| {
+                Vec::<u8>::from_hex(hex)
+                    .map_err(|e| e.to_string())
+                    .and_then(|bytes| ControlBlock::from_slice(&bytes).map_err(|e| e.to_string()))
+                    .map_err(|e| format!("Invalid control block: {e}"))
+            })
+            .transpose()?;
+        environment::from_transaction(
+            tx,
+            utxos,
+            input_index,
+            script_cmr,
+            control_block,
+            genesis_hash,
+        )
+    } else {
+        dummy_environment(matches)
+    }
+}
+
+fn read_file(file: &str) -> Result<String, String> {
+    std::fs::read_to_string(file).map_err(|e| format!("{file}: {e}"))
+}
+
+fn dummy_environment(
+    matches: &ArgMatches,
+) -> Result<ElementsEnv<Arc<elements::Transaction>>, String> {
+    let mut lock_time = elements::LockTime::ZERO;
+    let mut sequence = elements::Sequence::MAX;
+    if let Some(height) = matches.get_one::<u32>("lock_time") {
+        let height = elements::locktime::Height::from_consensus(*height)
+            .map_err(|e| format!("Invalid lock time: {e}"))?;
+        lock_time = elements::LockTime::Blocks(height);
+        sequence = elements::Sequence::ENABLE_LOCKTIME_NO_RBF;
+    }
+    if let Some(distance) = matches.get_one::<u16>("sequence") {
+        sequence = elements::Sequence::from_height(*distance);
+    }
+    Ok(dummy_env::dummy_with(lock_time, sequence, false))
+}
+
+#[cfg(feature = "serde")]
+fn read_description(
+    tx_file: &str,
+    script_cmr: Cmr,
+) -> Result<ElementsEnv<Arc<elements::Transaction>>, String> {
+    let tx_text = read_file(tx_file)?;
+    let is_toml = std::path::Path::new(tx_file)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"));
+    let description = match is_toml {
+        true => TransactionDescription::from_toml(&tx_text)?,
+        false => TransactionDescription::from_json(&tx_text)?,
+    };
+    description.to_env(script_cmr)
+}
+
+#[cfg(not(feature = "serde"))]
+fn read_description(
+    _tx_file: &str,
+    _script_cmr: Cmr,
+) -> Result<ElementsEnv<Arc<elements::Transaction>>, String> {
+    Err(
+        "Program was compiled without the 'serde' feature and cannot process transaction files."
+            .to_string(),
+    )
+}
+
+fn cmr(matches: &ArgMatches) -> Result<(), String> {
+    let compiled = compile(matches, matches.get_flag("debug"))?;
+    println!("{}", compiled.commit().cmr());
+    Ok(())
+}
+
+fn info(matches: &ArgMatches) -> Result<(), String> {
+    let prog_text = read_program(matches)?;
+    let template = TemplateProgram::new(prog_text)?.with_optimization(optimize(matches));
+
+    let mut parameters: Vec<_> = template.parameters().iter().collect();
+    parameters.sort_by_key(|(name, _)| *name);
+    println!("Parameters:");
+    for (name, ty) in parameters {
+        match template.declared_types().parameter(name) {
+            Some(declared) => println!("    param::{name}: {declared}"),
+            None => println!("    param::{name}: {ty}"),
+        }
+    }
+
+    let mut witnesses: Vec<_> = template.witness_types().iter().collect();
+    witnesses.sort_by_key(|(name, _)| *name);
+    println!("Witnesses:");
+    for (name, ty) in witnesses {
+        match template.declared_types().witness(name) {
+            Some(declared) => println!("    witness::{name}: {declared}"),
+            None => println!("    witness::{name}: {ty}"),
+        }
+    }
+
+    let arguments = read_arguments(matches, template.parameters())?;
+    let compiled = match template.instantiate(arguments, true) {
+        Ok(compiled) => compiled,
+        Err(error) => {
+            println!("Jets and debug symbols are unavailable: {error}");
+            return Ok(());
+        }
+    };
+    let jets: BTreeSet<String> = compiled
+        .commit()
+        .post_order_iter::<MaxSharing<_>>()
+        .filter_map(|data| match data.node.inner() {
+            Inner::Jet(jet) => Some(jet.to_string()),
+            _ => None,
+        })
+        .collect();
+    println!("Jets:");
+    for jet in jets {
+        println!("    jet::{jet}");
+    }
+    println!("Debug symbols: {}", compiled.debug_symbols().len());
+
+    Ok(())
+}
@@ -57,6 +57,7 @@ pub enum Item {
 /// Definition of a function.
 #[derive(Clone, Debug)]
 pub struct Function {
+    attributes: Arc<[Attribute]>,
     name: FunctionName,
     params: Arc<[FunctionParam]>,
     ret: Option<AliasedType>,
@@ -65,6 +66,23 @@ pub struct Function {
 }
 
 impl Function {
+    /// Access the attributes of the function.
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+
+    /// Check if the function is a unit test.
+    pub fn is_test(&self) -> bool {
+        self.attributes.contains(&Attribute::Test)
+    }
+
+    /// Check if the function is an entry point of the program.
+    ///
+    /// Entry points are the main function and functions with the `#[entry_point]` attribute.
+    pub fn is_entry_point(&self) -> bool {
+        self.name.as_inner() == "main" || self.attributes.contains(&Attribute::EntryPoint)
+    }
+
     /// Access the name of the function.
     pub fn name(&self) -> &FunctionName {
         &self.name
@@ -93,7 +111,32 @@ impl Function {
     }
 }
 
-impl_eq_hash!(Function; name, params, ret, body);
+impl_eq_hash!(Function; attributes, name, params, ret, body);
+
+/// Attribute of a function.
+///
+/// Attributes mark unit tests and configure how they are executed.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Attribute {
+    /// `#[entry_point]`: The function is an entry point of the program, like the main function.
+    EntryPoint,
+    /// `#[test]`: The function is a unit test.
+    Test,
+    /// `#[should_fail]`: The unit test passes if its execution fails.
+    ShouldFail,
+    /// `#[witness("path")]`: The unit test takes its witness data from the file at the given path,
+    /// relative to the program file.
+    Witness(Arc<str>),
+    /// `#[lock_time(n)]`: The unit test is executed in a transaction with the given lock time.
+    LockTime(u32),
+    /// `#[sequence(n)]`: The unit test is executed in a transaction whose input has the given sequence number.
+    Sequence(u32),
+    /// `#[allow(lint, ...)]`, `#[warn(lint, ...)]` or `#[deny(lint, ...)]`:
+    /// The lints of the given names are reported at the given level inside the function.
+    ///
+    /// The linter checks that the lints exist.
+    Lint(Arc<str>, Arc<[Arc<str>]>),
+}
 
 /// Parameter of a function.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -395,10 +438,11 @@ impl Match {
 impl_eq_hash!(Match; scrutinee, left, right);
 
 /// Arm of a match expression.
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug)]
 pub struct MatchArm {
     pattern: MatchPattern,
     expression: Arc<Expression>,
+    span: Span,
 }
 
 impl MatchArm {
@@ -413,6 +457,8 @@ impl MatchArm {
     }
 }
 
+impl_eq_hash!(MatchArm; pattern, expression);
+
 /// Pattern of a match arm.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
@@ -560,6 +606,9 @@ impl fmt::Display for TypeAlias {
 
 impl fmt::Display for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for attribute in self.attributes() {
+            writeln!(f, "{attribute}")?;
+        }
         write!(f, "fn {}(", self.name())?;
         for (i, param) in self.params().iter().enumerate() {
             if 0 < i {
@@ -575,6 +624,30 @@ impl fmt::Display for Function {
     }
 }
 
+impl Attribute {
+    /// Check if the attribute configures a unit test, so it requires the `#[test]` attribute.
+    pub fn is_test_only(&self) -> bool {
+        matches!(
+            self,
+            Self::ShouldFail | Self::Witness(_) | Self::LockTime(_) | Self::Sequence(_)
+        )
+    }
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EntryPoint => write!(f, "#[entry_point]"),
+            Self::Test => write!(f, "#[test]"),
+            Self::ShouldFail => write!(f, "#[should_fail]"),
+            Self::Witness(path) => write!(f, "#[witness(\"{path}\")]"),
+            Self::LockTime(n) => write!(f, "#[lock_time({n})]"),
+            Self::Sequence(n) => write!(f, "#[sequence({n})]"),
+            Self::Lint(level, names) => write!(f, "#[{level}({})]", names.iter().join(", ")),
+        }
+    }
+}
+
 impl fmt::Display for FunctionParam {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}: {}", self.identifier(), self.ty())
@@ -916,6 +989,23 @@ impl PestParse for Function {
         assert!(matches!(pair.as_rule(), Self::RULE));
         let span = Span::from(&pair);
         let mut it = pair.into_inner();
+        let mut attributes = Vec::new();
+        let mut test_only = Vec::new();
+        while let Some(Rule::attribute) = it.peek().map(|pair| pair.as_rule()) {
+            let pair = it.next().unwrap();
+            let attribute_span = Span::from(&pair);
+            let attribute = Attribute::parse(pair)?;
+            if attribute.is_test_only() {
+                test_only.push((attribute.clone(), attribute_span));
+            }
+            attributes.push(attribute);
+        }
+        if !attributes.contains(&Attribute::Test) {
+            if let Some((attribute, attribute_span)) = test_only.into_iter().next() {
+                return Err(Error::AttributeRequiresTest(attribute.to_string()))
+                    .with_span(attribute_span);
+            }
+        }
         let _fn_keyword = it.next().unwrap();
         let name = FunctionName::parse(it.next().unwrap())?;
         let params = {
@@ -938,6 +1028,7 @@ impl PestParse for Function {
         let body = Expression::parse(it.next().unwrap())?;
 
         Ok(Self {
+            attributes: Arc::from(attributes),
             name,
             params,
             ret,
@@ -947,6 +1038,43 @@ impl PestParse for Function {
     }
 }
 
+impl PestParse for Attribute {
+    const RULE: Rule = Rule::attribute;
+
+    fn parse(pair: pest::iterators::Pair<Rule>) -> Result<Self, RichError> {
+        assert!(matches!(pair.as_rule(), Self::RULE));
+        let pair = pair.into_inner().next().unwrap();
+        let number = |pair: pest::iterators::Pair<Rule>| {
+            let literal = pair.into_inner().next().unwrap();
+            literal
+                .as_str()
+                .replace('_', "")
+                .parse::<u32>()
+                .map_err(Error::from)
+                .with_span(&literal)
+        };
+        match pair.as_rule() {
+            Rule::entry_point_attribute => Ok(Self::EntryPoint),
+            Rule::test_attribute => Ok(Self::Test),
+            Rule::should_fail_attribute => Ok(Self::ShouldFail),
+            Rule::witness_attribute => {
+                let literal = pair.into_inner().next().unwrap();
+                let path = literal.as_str();
+                Ok(Self::Witness(Arc::from(&path[1..path.len() - 1])))
+            }
+            Rule::lock_time_attribute => number(pair).map(Self::LockTime),
+            Rule::sequence_attribute => number(pair).map(Self::Sequence),
+            Rule::lint_attribute => {
+                let mut it = pair.into_inner();
+                let level = Arc::from(it.next().unwrap().as_str());
+                let names = it.map(|name| Arc::from(name.as_str())).collect();
+                Ok(Self::Lint(level, names))
+            }
+            _ => unreachable!("Corrupt grammar"),
+        }
+    }
+}
+
 impl PestParse for FunctionParam {
     const RULE: Rule = Rule::typed_identifier;
 
@@ -1316,11 +1444,16 @@ impl PestParse for MatchArm {
     fn parse(pair: pest::iterators::Pair<Rule>) -> Result<Self, RichError> {
         assert!(matches!(pair.as_rule(), Self::RULE));
         let mut it = pair.into_inner();
-        let pattern = MatchPattern::parse(it.next().unwrap())?;
-        let expression = Expression::parse(it.next().unwrap()).map(Arc::new)?;
+        let pattern_pair = it.next().unwrap();
+        let start = Span::from(&pattern_pair).start;
+        let pattern = MatchPattern::parse(pattern_pair)?;
+        let expression = Expression::parse(it.next().unwrap())?;
+        // The span of the arm excludes the trailing comma.
+        let span = Span::new(start, Span::from(&expression).end);
         Ok(MatchArm {
             pattern,
-            expression,
+            expression: Arc::new(expression),
+            span,
         })
     }
 }
@@ -1675,6 +1808,12 @@ impl AsRef<Span> for Match {
     }
 }
 
+impl AsRef<Span> for MatchArm {
+    fn as_ref(&self) -> &Span {
+        &self.span
+    }
+}
+
 impl AsRef<Span> for ModuleProgram {
     fn as_ref(&self) -> &Span {
         &self.span
@@ -1727,6 +1866,7 @@ impl crate::ArbitraryRec for Function {
         let ret = Option::<AliasedType>::arbitrary(u)?;
         let body = Expression::arbitrary_rec(u, budget).map(Expression::into_block)?;
         Ok(Self {
+            attributes: Arc::from([]),
             name,
             params,
             ret,
@@ -1920,10 +2060,12 @@ impl crate::ArbitraryRec for Match {
             left: MatchArm {
                 pattern: pat_l,
                 expression: expr_l,
+                span: Span::DUMMY,
             },
             right: MatchArm {
                 pattern: pat_r,
                 expression: expr_r,
+                span: Span::DUMMY,
             },
             span: Span::DUMMY,
         })
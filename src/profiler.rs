@@ -0,0 +1,317 @@
+//! Cost profiler for SimplicityHL programs.
+//!
+//! The profiler records the cost that each node adds during the execution of the Bit Machine
+//! and attributes the cost to the innermost call expression with a debug symbol
+//! whose evaluation executes the node, such as a jet call, an assertion or a match arm.
+//! Nodes outside of any such call expression are attributed to the main function.
+//! The program must be compiled with debug symbols.
+//!
+//! Custom functions are inlined by the compiler, so there is no call stack at runtime.
+//! The profiler reconstructs the chain of inlined functions from the static call graph,
+//! like the [debugger](crate::debugger).
+//! If a function can be reached via different chains of calls, then the profiler picks a shortest chain
+//! that starts inside the enclosing call expression.
+//! The result can be exported as folded stacks, which flame graph tools such as
+//! [`flamegraph.pl`](https://github.com/brendangregg/FlameGraph) or
+//! [`inferno`](https://github.com/jonhoo/inferno) turn into an interactive picture.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fmt::Write as _;
+
+use simplicity::bit_machine::{ExecTracker, FrameIter, NodeOutput, PruneTracker, SetTracker};
+use simplicity::jet::Elements;
+use simplicity::node::Inner;
+use simplicity::{Cmr, Cost, Ihr, RedeemNode};
+
+use crate::debug::{DebugSymbols, TrackedCall, TrackedCallName};
+use crate::debugger::CallGraph;
+use crate::str::FunctionName;
+use crate::tracker::node_cost;
+
+/// Tracker that attributes the execution cost of a SimplicityHL program to functions and lines.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut profiler = Profiler::new(source, satisfied.debug_symbols())?;
+/// let result = satisfied.redeem().prune_with_tracker(&env, &mut profiler);
+///
+/// std::fs::write("profile.folded", profiler.to_folded_stacks())?;
+/// ```
+pub struct Profiler<'a> {
+    debug_symbols: &'a DebugSymbols,
+    call_graph: CallGraph,
+    /// Call expressions that are being evaluated, as pairs of the enclosing context and the call.
+    contexts: Vec<(Option<usize>, &'a TrackedCall)>,
+    context_ids: HashMap<(Option<usize>, Cmr), usize>,
+    /// Contexts of the children that are executed next, with the next child on top.
+    pending: Vec<Option<usize>>,
+    /// Cost that was spent directly in each context.
+    costs: HashMap<Option<usize>, Cost>,
+    inner: SetTracker,
+}
+
+impl<'a> Profiler<'a> {
+    /// Create a profiler for the program that is compiled from the given source
+    /// with the given debug symbols.
+    ///
+    /// ## Errors
+    ///
+    /// The source is not a valid SimplicityHL program.
+    pub fn new(source: &str, debug_symbols: &'a DebugSymbols) -> Result<Self, String> {
+        Ok(Self {
+            debug_symbols,
+            call_graph: CallGraph::new(source)?,
+            contexts: Vec::new(),
+            context_ids: HashMap::new(),
+            pending: Vec::new(),
+            costs: HashMap::new(),
+            inner: SetTracker::default(),
+        })
+    }
+
+    /// Return the total cost of the execution so far, in milliweight units.
+    pub fn cost(&self) -> Cost {
+        self.costs
+            .values()
+            .fold(Cost::from_milliweight(0), |sum, cost| sum + *cost)
+    }
+
+    /// Return the cost that was spent in each function, excluding the functions that it calls,
+    /// in descending order of cost.
+    pub fn functions(&self) -> Vec<(FunctionName, Cost)> {
+        let mut functions = HashMap::<FunctionName, Cost>::new();
+        for (context, cost) in &self.costs {
+            let function = match context {
+                Some(id) => self.call_graph.function_of(self.contexts[*id].1.span()),
+                None => FunctionName::main(),
+            };
+            let total = functions
+                .entry(function)
+                .or_insert(Cost::from_milliweight(0));
+            *total = *total + *cost;
+        }
+        let mut functions = functions.into_iter().collect::<Vec<_>>();
+        functions.sort_by(|(name1, cost1), (name2, cost2)| {
+            cost2.cmp(cost1).then_with(|| name1.cmp(name2))
+        });
+        functions
+    }
+
+    /// Return the cost that was spent in the call expressions that start on each line,
+    /// excluding the call expressions that they contain, in order of lines (starting at 1).
+    ///
+    /// The cost of nodes outside of any call expression with a debug symbol
+    /// is not attributed to any line.
+    pub fn lines(&self) -> BTreeMap<usize, Cost> {
+        let mut lines = BTreeMap::<usize, Cost>::new();
+        for (context, cost) in &self.costs {
+            if let Some(id) = context {
+                let line = self.contexts[*id].1.span().start.line.get();
+                let total = lines.entry(line).or_insert(Cost::from_milliweight(0));
+                *total = *total + *cost;
+            }
+        }
+        lines
+    }
+
+    /// Export the cost as folded stacks: one line per stack with the frames separated by
+    /// semicolons, followed by the cost of the stack in milliweight units.
+    ///
+    /// Frames are functions and call expressions, such as `main;add;jet::add_32:2`.
+    pub fn to_folded_stacks(&self) -> String {
+        let mut stacks = BTreeMap::<String, Cost>::new();
+        for (context, cost) in &self.costs {
+            let stack = self.frames(*context).join(";");
+            let total = stacks.entry(stack).or_insert(Cost::from_milliweight(0));
+            *total = *total + *cost;
+        }
+        let mut folded = String::new();
+        for (stack, cost) in stacks {
+            writeln!(folded, "{stack} {cost}").unwrap();
+        }
+        folded
+    }
+
+    /// Return the frames of the given context, from the main function to the innermost call.
+    fn frames(&self, mut context: Option<usize>) -> Vec<String> {
+        let mut calls = Vec::new();
+        while let Some(id) = context {
+            let (parent, call) = self.contexts[id];
+            calls.push(call);
+            context = parent;
+        }
+
+        let mut function = FunctionName::main();
+        let mut within = None;
+        let mut frames = vec![function.to_string()];
+        for call in calls.into_iter().rev() {
+            let callee = self.call_graph.function_of(call.span());
+            // Functions that are called inside a match arm are inlined into the arm
+            for inlined in self.call_graph.call_chain(&function, within, &callee) {
+                frames.push(inlined.to_string());
+            }
+            within = Some(call.span());
+            frames.push(format!(
+                "{}:{}",
+                call_label(call),
+                call.span().start.line.get()
+            ));
+            function = callee;
+        }
+        frames
+    }
+
+    /// Return the context of the next node and update the contexts of its children.
+    fn visit(&mut self, node: &RedeemNode<Elements>) -> Option<usize> {
+        let mut context = self.pending.pop().unwrap_or(None);
+        let children = match node.inner() {
+            Inner::Comp(..) | Inner::Pair(..) | Inner::Disconnect(..) => 2,
+            Inner::Take(_)
+            | Inner::Drop(_)
+            | Inner::InjL(_)
+            | Inner::InjR(_)
+            | Inner::Case(..)
+            | Inner::AssertL(..)
+            | Inner::AssertR(..) => 1,
+            _ => 0,
+        };
+
+        // Call expressions with debug symbols are compiled to `comp (pair false args) (assertl body cmr)`
+        // The arguments are evaluated in the context of the caller; the body in the context of the call
+        if let Inner::AssertL(_, cmr) = node.inner() {
+            if let Some(call) = self.debug_symbols.get(cmr) {
+                let contexts = &mut self.contexts;
+                let id = *self.context_ids.entry((context, *cmr)).or_insert_with(|| {
+                    contexts.push((context, call));
+                    contexts.len() - 1
+                });
+                context = Some(id);
+            }
+        }
+
+        self.pending
+            .extend(std::iter::repeat(context).take(children));
+        context
+    }
+}
+
+/// Return the label of the frame of the given call expression.
+fn call_label(call: &TrackedCall) -> &str {
+    match call.name() {
+        TrackedCallName::Jet => call.text().split('(').next().unwrap_or("jet"),
+        TrackedCallName::Assert => "assert!",
+        TrackedCallName::Panic => "panic!",
+        TrackedCallName::UnwrapLeft(_) => "unwrap_left",
+        TrackedCallName::UnwrapRight(_) => "unwrap_right",
+        TrackedCallName::Unwrap => "unwrap",
+        TrackedCallName::Debug(_) => "dbg!",
+        TrackedCallName::MatchArm => "match arm",
+    }
+}
+
+impl fmt::Display for Profiler<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Total cost: {} mWU", self.cost())?;
+        writeln!(f, "Functions:")?;
+        for (function, cost) in self.functions() {
+            writeln!(f, "    {function}: {cost} mWU")?;
+        }
+        writeln!(f, "Lines:")?;
+        for (line, cost) in self.lines() {
+            writeln!(f, "    {line}: {cost} mWU")?;
+        }
+        Ok(())
+    }
+}
+
+impl PruneTracker<Elements> for Profiler<'_> {
+    fn contains_left(&self, ihr: Ihr) -> bool {
+        PruneTracker::<Elements>::contains_left(&self.inner, ihr)
+    }
+
+    fn contains_right(&self, ihr: Ihr) -> bool {
+        PruneTracker::<Elements>::contains_right(&self.inner, ihr)
+    }
+}
+
+impl ExecTracker<Elements> for Profiler<'_> {
+    fn visit_node(&mut self, node: &RedeemNode<Elements>, input: FrameIter, output: NodeOutput) {
+        let context = self.visit(node);
+        let cost = self
+            .costs
+            .entry(context)
+            .or_insert(Cost::from_milliweight(0));
+        *cost = *cost + node_cost(node);
+
+        self.inner.visit_node(node, input, output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dummy_env, Arguments, TemplateProgram, WitnessValues};
+
+    const PROGRAM: &str = r#"fn add(a: u32, b: u32) -> u32 {
+    let (_, sum): (bool, u32) = jet::add_32(a, b);
+    sum
+}
+
+fn double(a: u32) -> u32 {
+    add(a, a)
+}
+
+fn main() {
+    let x: u32 = double(1);
+    let y: u32 = match jet::eq_32(x, 2) {
+        true => double(x),
+        false => 0,
+    };
+    assert!(jet::eq_32(y, 4));
+}"#;
+
+    #[test]
+    fn attribute_cost() {
+        let compiled = TemplateProgram::new(PROGRAM)
+            .unwrap()
+            .instantiate(Arguments::default(), true)
+            .unwrap();
+        let satisfied = compiled.satisfy(WitnessValues::default()).unwrap();
+        let mut profiler = Profiler::new(PROGRAM, satisfied.debug_symbols()).unwrap();
+        let pruned = satisfied
+            .redeem()
+            .prune_with_tracker(&dummy_env::dummy(), &mut profiler)
+            .unwrap();
+        assert_eq!(pruned.bounds().cost, profiler.cost());
+
+        let stacks = profiler
+            .to_folded_stacks()
+            .lines()
+            .map(|line| line.rsplit_once(' ').unwrap().0.to_string())
+            .collect::<Vec<String>>();
+        let expected = [
+            "main",
+            "main;assert!:16",
+            "main;double;add;jet::add_32:2",
+            "main;jet::eq_32:12",
+            "main;jet::eq_32:16",
+            "main;match arm:13",
+            "main;match arm:13;double;add;jet::add_32:2",
+        ];
+        assert_eq!(&expected[..], stacks.as_slice());
+
+        let lines = profiler.lines();
+        assert_eq!(
+            vec![2, 12, 13, 16],
+            lines.keys().copied().collect::<Vec<_>>()
+        );
+        let functions = profiler
+            .functions()
+            .into_iter()
+            .map(|(name, _)| name.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(vec!["main", "add"], functions);
+    }
+}
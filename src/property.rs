@@ -0,0 +1,400 @@
+//! Property-based testing of SimplicityHL programs.
+//!
+//! The harness generates random witness values that conform to the witness types of a program,
+//! executes the program in a configurable environment,
+//! and checks a user-specified property of each execution,
+//! such as "the program never succeeds unless the signature is valid".
+//!
+//! Witness values are generated from random bytes via [`ArbitraryOfType`].
+//! If the property fails, then the harness shrinks the bytes while the property keeps failing.
+//! Fewer and smaller bytes generate smaller witness values,
+//! so the counterexample becomes minimal: integers tend to zero, lists become empty,
+//! and sums and options take their first variant.
+//!
+//! Generation is deterministic for a given seed, so counterexamples can be reproduced.
+
+use std::fmt;
+use std::sync::Arc;
+
+use simplicity::bit_machine::SetTracker;
+use simplicity::elements;
+use simplicity::jet::elements::ElementsEnv;
+
+use crate::tracker::{TraceEvent, TraceRecorder};
+use crate::types::StructuralType;
+use crate::{dummy_env, ArbitraryOfType, CompiledProgram, WitnessValues};
+
+/// Execution of a program with generated witness values.
+#[derive(Clone, Debug)]
+pub struct Execution {
+    witness_values: WitnessValues,
+    error: Option<String>,
+    events: Vec<TraceEvent>,
+}
+
+impl Execution {
+    /// Access the generated witness values.
+    pub fn witness_values(&self) -> &WitnessValues {
+        &self.witness_values
+    }
+
+    /// Check if the program executed successfully.
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Access the error if the program could not be satisfied or failed to execute.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Access the evaluated call expressions, in order of evaluation.
+    ///
+    /// Call expressions are recorded only if the program was compiled with debug symbols.
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+}
+
+/// Execution that violates the property, after shrinking.
+#[derive(Clone, Debug)]
+pub struct Counterexample {
+    execution: Execution,
+    case: usize,
+    seed: u64,
+    shrinks: usize,
+}
+
+impl Counterexample {
+    /// Access the execution that violates the property.
+    pub fn execution(&self) -> &Execution {
+        &self.execution
+    }
+
+    /// Access the witness values that violate the property.
+    pub fn witness_values(&self) -> &WitnessValues {
+        &self.execution.witness_values
+    }
+
+    /// Return the number of the test case that first violated the property (starting at 1).
+    pub fn case(&self) -> usize {
+        self.case
+    }
+
+    /// Return the seed of the random number generator.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Return the number of successful shrinking steps.
+    pub fn shrinks(&self) -> usize {
+        self.shrinks
+    }
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Property failed in case {} with seed {} (shrunk {} times)",
+            self.case, self.seed, self.shrinks
+        )?;
+        match self.execution.error() {
+            Some(error) => writeln!(f, "Execution failed: {error}")?,
+            None => writeln!(f, "Execution succeeded")?,
+        }
+        write!(f, "{}", self.execution.witness_values)
+    }
+}
+
+/// Harness that checks a property of a program over generated witness values.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let compiled = CompiledProgram::new(source, Arguments::default(), true)?;
+/// PropertyTest::new(&compiled)
+///     .with_cases(1000)
+///     .check(|execution| !execution.succeeded() || is_valid(execution.witness_values()))
+///     .map_err(|counterexample| counterexample.to_string())?;
+/// ```
+pub struct PropertyTest<'a> {
+    program: &'a CompiledProgram,
+    env: ElementsEnv<Arc<elements::Transaction>>,
+    cases: usize,
+    seed: u64,
+    max_shrinks: usize,
+}
+
+impl<'a> PropertyTest<'a> {
+    /// Create a harness for the given program.
+    ///
+    /// By default, the harness runs 256 cases with seed 0 in the dummy environment,
+    /// and it takes at most 1000 shrinking steps.
+    pub fn new(program: &'a CompiledProgram) -> Self {
+        Self {
+            program,
+            env: dummy_env::dummy(),
+            cases: 256,
+            seed: 0,
+            max_shrinks: 1000,
+        }
+    }
+
+    /// Execute the program in the given environment.
+    pub fn with_environment(mut self, env: ElementsEnv<Arc<elements::Transaction>>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Run the given number of cases.
+    pub fn with_cases(mut self, cases: usize) -> Self {
+        self.cases = cases;
+        self
+    }
+
+    /// Seed the random number generator.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Take at most the given number of shrinking steps.
+    pub fn with_max_shrinks(mut self, max_shrinks: usize) -> Self {
+        self.max_shrinks = max_shrinks;
+        self
+    }
+
+    /// Check that the property holds for each case.
+    ///
+    /// ## Errors
+    ///
+    /// Return a shrunk counterexample if the property does not hold for some case.
+    pub fn check<F>(&self, mut property: F) -> Result<(), Counterexample>
+    where
+        F: FnMut(&Execution) -> bool,
+    {
+        let mut rng = SplitMix64(self.seed);
+        let len = self.input_len();
+        for case in 1..=self.cases {
+            let bytes = (0..len).map(|_| rng.next_u64() as u8).collect::<Vec<u8>>();
+            let execution = self.execute(&bytes);
+            if !property(&execution) {
+                let (execution, shrinks) = self.shrink(bytes, execution, &mut property);
+                return Err(Counterexample {
+                    execution,
+                    case,
+                    seed: self.seed,
+                    shrinks,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the number of random bytes per case.
+    ///
+    /// There are enough bytes to fill each witness twice over, so sums, options and lists
+    /// have room to choose their variant and length.
+    fn input_len(&self) -> usize {
+        let bits = self
+            .program
+            .witness_types()
+            .iter()
+            .map(|(_, ty)| StructuralType::from(ty).as_ref().bit_width())
+            .sum::<usize>();
+        64 + bits.div_ceil(8) * 2
+    }
+
+    /// Generate witness values from the given bytes and execute the program with them.
+    fn execute(&self, bytes: &[u8]) -> Execution {
+        let mut u = arbitrary::Unstructured::new(bytes);
+        let witness_values = WitnessValues::arbitrary_of_type(&mut u, self.program.witness_types())
+            .expect("generation of witness values is infallible");
+        let satisfied = match self.program.satisfy(witness_values.clone()) {
+            Ok(satisfied) => satisfied,
+            Err(error) => {
+                return Execution {
+                    witness_values,
+                    error: Some(error),
+                    events: Vec::new(),
+                }
+            }
+        };
+        let mut recorder = TraceRecorder::new(satisfied.debug_symbols(), SetTracker::default());
+        let error = satisfied
+            .redeem()
+            .prune_with_tracker(&self.env, &mut recorder)
+            .err()
+            .map(|error| error.to_string());
+        Execution {
+            witness_values,
+            error,
+            events: recorder.events().to_vec(),
+        }
+    }
+
+    /// Shrink the bytes of a failing case while the property keeps failing.
+    ///
+    /// Return the smallest failing execution and the number of successful shrinking steps.
+    fn shrink<F>(
+        &self,
+        mut bytes: Vec<u8>,
+        mut execution: Execution,
+        property: &mut F,
+    ) -> (Execution, usize)
+    where
+        F: FnMut(&Execution) -> bool,
+    {
+        let mut shrinks = 0;
+        let mut attempts = 0;
+        let mut improved = true;
+        while improved && attempts < self.max_shrinks {
+            improved = false;
+            for candidate in candidates(&bytes) {
+                if self.max_shrinks <= attempts {
+                    break;
+                }
+                attempts += 1;
+                let candidate_execution = self.execute(&candidate);
+                if !property(&candidate_execution) {
+                    bytes = candidate;
+                    execution = candidate_execution;
+                    shrinks += 1;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+        (execution, shrinks)
+    }
+}
+
+/// Return smaller variants of the given bytes, the most aggressive variants first.
+///
+/// Missing bytes are read as zeros, so truncating and zeroing both make the generated values smaller.
+fn candidates(bytes: &[u8]) -> Vec<Vec<u8>> {
+    let mut candidates = Vec::new();
+    // Truncate trailing bytes
+    let mut len = bytes.len() / 2;
+    while 0 < len {
+        candidates.push(bytes[..bytes.len() - len].to_vec());
+        len /= 2;
+    }
+    // Zero chunks of nonzero bytes
+    let mut chunk = 8;
+    while 0 < chunk {
+        for start in (0..bytes.len()).step_by(chunk) {
+            let end = (start + chunk).min(bytes.len());
+            if bytes[start..end].iter().any(|byte| *byte != 0) {
+                let mut candidate = bytes.to_vec();
+                candidate[start..end].fill(0);
+                candidates.push(candidate);
+            }
+        }
+        chunk /= 2;
+    }
+    // Halve and decrement individual bytes
+    for (index, byte) in bytes.iter().enumerate() {
+        if 1 < *byte {
+            let mut candidate = bytes.to_vec();
+            candidate[index] = byte / 2;
+            candidates.push(candidate);
+        }
+        if 0 < *byte {
+            let mut candidate = bytes.to_vec();
+            candidate[index] = byte - 1;
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+/// Small, fast and deterministic pseudo-random number generator.
+///
+/// See <https://prng.di.unimi.it/splitmix64.c>.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::str::WitnessName;
+    use crate::value::UIntValue;
+    use crate::value::ValueInner;
+    use crate::{Arguments, Value};
+
+    const PROGRAM: &str = r#"fn main() {
+    let x: u32 = witness::X;
+    let flag: bool = witness::FLAG;
+    match flag {
+        true => assert!(jet::lt_32(x, 1000)),
+        false => {},
+    };
+}"#;
+
+    fn witness_u32(execution: &Execution, name: &str) -> u32 {
+        let value = execution
+            .witness_values()
+            .get(&WitnessName::from_str_unchecked(name))
+            .unwrap();
+        match value.inner() {
+            ValueInner::UInt(UIntValue::U32(x)) => *x,
+            _ => panic!("Unexpected value: {value}"),
+        }
+    }
+
+    fn compile() -> CompiledProgram {
+        CompiledProgram::new(PROGRAM, Arguments::default(), true).unwrap()
+    }
+
+    #[test]
+    fn property_holds() {
+        let compiled = compile();
+        PropertyTest::new(&compiled)
+            .check(|execution| {
+                let flag = execution
+                    .witness_values()
+                    .get(&WitnessName::from_str_unchecked("FLAG"))
+                    .unwrap()
+                    == &Value::from(true);
+                execution.succeeded() || flag && 1000 <= witness_u32(execution, "X")
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn shrink_counterexample() {
+        let compiled = compile();
+        // False property: the program always succeeds
+        let counterexample = PropertyTest::new(&compiled)
+            .with_seed(42)
+            .check(Execution::succeeded)
+            .unwrap_err();
+        assert!(counterexample.shrinks() > 0);
+        let execution = counterexample.execution();
+        assert!(!execution.succeeded());
+        // Shrinking finds a small failing witness
+        let x = witness_u32(execution, "X");
+        assert!((1000..1100).contains(&x), "X = {x}");
+        assert_eq!(
+            Some(&Value::from(true)),
+            execution
+                .witness_values()
+                .get(&WitnessName::from_str_unchecked("FLAG"))
+        );
+        assert!(execution
+            .events()
+            .iter()
+            .any(|event| event.name() == "assert!"));
+    }
+}
@@ -0,0 +1,378 @@
+//! Read-eval-print loop for SimplicityHL expressions.
+//!
+//! Trying out a jet should not require writing a whole program with a `main` function.
+//! The [`Repl`] evaluates one statement or expression at a time in a transaction environment.
+//! Statements `let PATTERN: TYPE = EXPRESSION;` bind variables that later inputs can use.
+//! Expressions are evaluated and their value is returned.
+//!
+//! Each input is compiled on its own, as a block expression that first binds
+//! the values of the variables that the input uses.
+//! The type of an expression is inferred from its jet calls, variables and literals.
+//! Decimal literals have no unique type, so they must be bound to a typed variable first.
+
+use std::fmt::Write;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use simplicity::jet::elements::ElementsEnv;
+use simplicity::jet::Elements;
+use simplicity::{elements, BitMachine, NodeBounds};
+
+use crate::error::{Error, Position, RichError, Span};
+use crate::num::Pow2Usize;
+use crate::parse::{self, CallName, ExpressionInner, ParseFromStr, SingleExpressionInner};
+use crate::str::Identifier;
+use crate::types::{AliasedType, ResolvedType, TypeConstructible, UIntType};
+use crate::value::{StructuralValue, Value, ValueInner};
+use crate::{ast, dummy_env, named, WitnessValues};
+
+/// Read-eval-print loop that keeps the variables that previous inputs bound.
+pub struct Repl {
+    variables: Vec<(Identifier, Value)>,
+    env: ElementsEnv<Arc<elements::Transaction>>,
+    optimize: bool,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Repl {
+    /// Create a read-eval-print loop without variables that evaluates in the dummy environment.
+    pub fn new() -> Self {
+        Self {
+            variables: Vec::new(),
+            env: dummy_env::dummy(),
+            optimize: false,
+        }
+    }
+
+    /// Evaluate inputs in the given environment.
+    pub fn with_environment(mut self, env: ElementsEnv<Arc<elements::Transaction>>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Set whether the compiled Simplicity code is simplified by the peephole optimizer.
+    ///
+    /// The optimizer changes the cost of expressions.
+    pub fn with_optimization(mut self, optimize: bool) -> Self {
+        self.optimize = optimize;
+        self
+    }
+
+    /// Access the bound variables and their values, in the order in which they were bound.
+    pub fn variables(&self) -> &[(Identifier, Value)] {
+        &self.variables
+    }
+
+    /// Evaluate a statement or an expression.
+    ///
+    /// A statement `let PATTERN: TYPE = EXPRESSION;` binds the variables of its pattern
+    /// and returns `None`. An expression returns its value.
+    ///
+    /// ## Errors
+    ///
+    /// - The input is not a valid statement or expression.
+    /// - The type of the expression cannot be inferred.
+    /// - The input fails to execute.
+    pub fn eval(&mut self, input: &str) -> Result<Option<Value>, String> {
+        let input = input.trim();
+        if !is_assignment(input) {
+            let ty = self.type_of(input)?;
+            let (value, _) = self.run(input, &ty)?;
+            return Ok(Some(value));
+        }
+
+        let statement = input.strip_suffix(';').unwrap_or(input).trim_end();
+        let assignment = parse::Assignment::parse_from_str(statement)?;
+        let ty = resolve(assignment.ty())?;
+        let mut bound = assignment
+            .pattern()
+            .is_of_type(&ty)
+            .map_err(|error| error.to_string())?
+            .into_iter()
+            .collect::<Vec<(Identifier, ResolvedType)>>();
+        bound.sort_by(|(x, _), (y, _)| x.cmp(y));
+
+        let (output, output_ty) = match bound.as_slice() {
+            [(identifier, ty)] => (identifier.to_string(), ty.clone()),
+            _ => (
+                format!(
+                    "({})",
+                    bound
+                        .iter()
+                        .map(|(identifier, _)| format!("{identifier}, "))
+                        .collect::<String>()
+                ),
+                ResolvedType::tuple(bound.iter().map(|(_, ty)| ty.clone())),
+            ),
+        };
+        let (value, _) = self.run(&format!("{statement};\n{output}"), &output_ty)?;
+        let values = match (bound.len(), value.inner()) {
+            (1, _) => vec![value],
+            (_, ValueInner::Tuple(elements)) => elements.to_vec(),
+            _ => unreachable!("output is a tuple"),
+        };
+        for ((identifier, _), value) in bound.into_iter().zip(values) {
+            self.variables.retain(|(x, _)| x != &identifier);
+            self.variables.push((identifier, value));
+        }
+        Ok(None)
+    }
+
+    /// Infer the type of an expression and check that the expression is of this type.
+    ///
+    /// The expression is not evaluated.
+    ///
+    /// ## Errors
+    ///
+    /// - The input is not a valid expression.
+    /// - The type of the expression cannot be inferred.
+    /// - The expression is not of the inferred type.
+    pub fn type_of(&self, expression: &str) -> Result<ResolvedType, String> {
+        let expression = expression.trim();
+        let parse_expr = parse::Expression::parse_from_str(expression)?;
+        let ty = self.infer(&parse_expr).ok_or_else(|| {
+            "Cannot infer the type of the expression. Bind it to a variable of explicit type: `let x: TYPE = EXPRESSION;`".to_string()
+        })?;
+        self.analyze(expression, &ty)?;
+        Ok(ty)
+    }
+
+    /// Evaluate an expression and return the cost of its execution.
+    ///
+    /// The cost includes binding the values of the variables that the expression uses.
+    ///
+    /// ## Errors
+    ///
+    /// Same as [`Repl::eval`] for expressions.
+    pub fn cost(&self, expression: &str) -> Result<NodeBounds, String> {
+        let ty = self.type_of(expression)?;
+        let (_, bounds) = self.run(expression.trim(), &ty)?;
+        Ok(bounds)
+    }
+
+    /// Analyze the `body` as final part of a block that binds the variables which the body uses.
+    ///
+    /// Errors point into the body.
+    fn analyze(&self, body: &str, ty: &ResolvedType) -> Result<ast::Expression, String> {
+        let mut block = String::from("{\n");
+        let mut offset = 1;
+        for (identifier, value) in &self.variables {
+            if mentions(body, identifier) {
+                writeln!(block, "let {identifier}: {} = {value};", value.ty())
+                    .expect("writing to string works");
+                offset += 1;
+            }
+        }
+        block.push_str(body);
+        block.push_str("\n}");
+
+        let shift = |error: RichError| shift_error(error, offset, body, &block);
+        let parse_expr = parse::Expression::parse_from_str(&block).map_err(shift)?;
+        ast::Expression::analyze_standalone(&parse_expr, ty).map_err(shift)
+    }
+
+    /// Compile and execute the `body` in the environment.
+    ///
+    /// Return the value of the body and the bounds of its execution.
+    fn run(&self, body: &str, ty: &ResolvedType) -> Result<(Value, NodeBounds), String> {
+        let ast_expr = self.analyze(body, ty)?;
+        let commit = ast_expr
+            .compile_standalone(self.optimize)
+            .map_err(|error| error.error().to_string())?;
+        let redeem = named::populate_witnesses(&commit, WitnessValues::default())?;
+        let pruned = redeem
+            .prune(&self.env)
+            .map_err(|e| format!("Execution failed: {e}"))?;
+        let mut mac = BitMachine::for_program(&pruned).map_err(|e| e.to_string())?;
+        let value = mac
+            .exec(&pruned, &self.env)
+            .map_err(|e| format!("Execution failed: {e}"))?;
+        let value = Value::reconstruct(&StructuralValue::from(value), ty)
+            .expect("output value is of the expression type");
+        Ok((value, pruned.bounds()))
+    }
+
+    /// Infer the type of an expression from its jet calls, variables and literals.
+    ///
+    /// Return `None` if the type cannot be inferred.
+    fn infer(&self, expression: &parse::Expression) -> Option<ResolvedType> {
+        let single = match expression.inner() {
+            ExpressionInner::Single(single) => single,
+            ExpressionInner::Block(_, None) => return Some(ResolvedType::unit()),
+            ExpressionInner::Block(_, Some(..)) => return None,
+        };
+        match single.inner() {
+            SingleExpressionInner::Boolean(..) => Some(ResolvedType::boolean()),
+            SingleExpressionInner::Binary(binary) => uint_of_width(binary.as_inner().len()),
+            SingleExpressionInner::Hexadecimal(hexadecimal) => {
+                uint_of_width(4 * hexadecimal.as_inner().len())
+            }
+            SingleExpressionInner::Variable(identifier) => self
+                .variables
+                .iter()
+                .find(|(x, _)| x == identifier)
+                .map(|(_, value)| value.ty().clone()),
+            SingleExpressionInner::Expression(inner) => self.infer(inner),
+            SingleExpressionInner::Option(Some(inner)) => {
+                self.infer(inner).map(ResolvedType::option)
+            }
+            SingleExpressionInner::Tuple(elements) => elements
+                .iter()
+                .map(|element| self.infer(element))
+                .collect::<Option<Vec<ResolvedType>>>()
+                .map(ResolvedType::tuple),
+            SingleExpressionInner::Array(elements) => elements
+                .first()
+                .and_then(|element| self.infer(element))
+                .map(|ty| ResolvedType::array(ty, elements.len())),
+            SingleExpressionInner::Call(call) => match call.name() {
+                CallName::Jet(name) => {
+                    let jet = name.as_inner().parse::<Elements>().ok()?;
+                    crate::jet::target_type(jet).resolve_builtin().ok()
+                }
+                CallName::IsNone(..) => Some(ResolvedType::boolean()),
+                CallName::Assert | CallName::Panic => Some(ResolvedType::unit()),
+                CallName::Debug => call.args().first().and_then(|arg| self.infer(arg)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Check if the input is a statement that assigns variables.
+fn is_assignment(input: &str) -> bool {
+    input
+        .strip_prefix("let")
+        .is_some_and(|rest| rest.starts_with(char::is_whitespace))
+}
+
+/// Resolve a type that may use builtin aliases.
+fn resolve(ty: &AliasedType) -> Result<ResolvedType, String> {
+    ty.resolve_builtin()
+        .map_err(|name| Error::UndefinedAlias(name).to_string())
+}
+
+/// Return the unsigned integer type of the given bit width, if there is one.
+fn uint_of_width(bit_width: usize) -> Option<ResolvedType> {
+    let bit_width = Pow2Usize::new(bit_width)?;
+    UIntType::from_bit_width(bit_width).map(ResolvedType::from)
+}
+
+/// Check if the text contains the identifier as a word.
+fn mentions(text: &str, identifier: &Identifier) -> bool {
+    text.split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .any(|word| word == identifier.as_inner())
+}
+
+/// Move an error inside the `block` that was constructed around the `body`
+/// so that it points into the body.
+///
+/// Errors outside the body point into the block.
+fn shift_error(error: RichError, offset: usize, body: &str, block: &str) -> String {
+    let span = *error.span();
+    let shift = |position: Position| {
+        let line = position.line.get().checked_sub(offset)?;
+        let line = NonZeroUsize::new(line).filter(|line| line.get() <= body.lines().count())?;
+        Some(Position {
+            line,
+            col: position.col,
+        })
+    };
+    match (shift(span.start), shift(span.end)) {
+        (Some(start), Some(end)) => RichError::new(error.error().clone(), Span::new(start, end))
+            .with_file(Arc::from(body))
+            .to_string(),
+        _ => error.with_file(Arc::from(block)).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(repl: &mut Repl, input: &str) -> String {
+        repl.eval(input).unwrap().unwrap().to_string()
+    }
+
+    #[test]
+    fn evaluate_jets() {
+        let mut repl = Repl::new();
+        assert_eq!(
+            "15",
+            eval(
+                &mut repl,
+                "jet::full_multiply_32((0x00000003, 0x00000004), (0x00000001, 0x00000002))"
+            )
+        );
+        assert_eq!("true", eval(&mut repl, "jet::is_zero_8(0x00)"));
+        assert_eq!("()", eval(&mut repl, "assert!(jet::eq_8(0x01, 0x01))"));
+        assert!(repl
+            .eval("assert!(jet::eq_8(0x01, 0x02))")
+            .unwrap_err()
+            .contains("Execution failed"));
+    }
+
+    #[test]
+    fn bind_variables() {
+        let mut repl = Repl::new();
+        assert_eq!(None, repl.eval("let x: u32 = 6;").unwrap());
+        assert_eq!(
+            None,
+            repl.eval("let (carry, y): (bool, u32) = jet::add_32(x, x)")
+                .unwrap()
+        );
+        assert_eq!("12", eval(&mut repl, "y"));
+        assert_eq!("false", eval(&mut repl, "carry"));
+        assert_eq!("(false, 18)", eval(&mut repl, "jet::add_32(x, y)"));
+
+        // Shadow a variable
+        repl.eval("let x: u8 = 1;").unwrap();
+        assert_eq!("u8", repl.type_of("x").unwrap().to_string());
+        assert_eq!(3, repl.variables().len());
+    }
+
+    #[test]
+    fn infer_types() {
+        let repl = Repl::new();
+        assert_eq!(
+            "u64",
+            repl.type_of("jet::multiply_32(0x00000001, 0x00000002)")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "(bool, u16)",
+            repl.type_of("(true, 0xabcd)").unwrap().to_string()
+        );
+        assert_eq!(
+            "(List<u8, 64>, (u64, u256))",
+            repl.type_of("jet::sha_256_ctx_8_init()")
+                .unwrap()
+                .to_string()
+        );
+        assert!(repl
+            .type_of("5")
+            .unwrap_err()
+            .contains("Cannot infer the type"));
+        assert!(repl
+            .type_of("jet::add_32(true, 0x00000001)")
+            .unwrap_err()
+            .contains("1 |"));
+    }
+
+    #[test]
+    fn cost_of_expression() {
+        let repl = Repl::new();
+        let cheap = repl.cost("jet::eq_8(0x01, 0x02)").unwrap();
+        let expensive = repl
+            .cost("(jet::eq_8(0x01, 0x02), jet::eq_8(0x03, 0x04))")
+            .unwrap();
+        assert!(cheap.cost < expensive.cost);
+    }
+}
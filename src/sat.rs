@@ -0,0 +1,578 @@
+//! SAT solver for the [symbolic executor](crate::symbolic).
+//!
+//! The solver implements conflict-driven clause learning with two watched literals,
+//! activity-based branching, phase saving and restarts that follow the Luby sequence.
+//! Learned clauses are never deleted, which is fine for the size of the formulas
+//! that SimplicityHL programs produce.
+
+use std::ops::Not;
+
+/// Literal: a variable or its negation.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub(crate) struct Lit(u32);
+
+impl Lit {
+    /// Create the positive literal of the given variable.
+    pub fn positive(var: usize) -> Self {
+        Self(u32::try_from(var).expect("too many variables") << 1)
+    }
+
+    /// Return the variable of the literal.
+    pub fn var(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    /// Check if the literal is the negation of its variable.
+    pub fn is_negated(self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Not for Lit {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(self.0 ^ 1)
+    }
+}
+
+/// Outcome of a search with a limited number of conflicts.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Outcome {
+    /// The clauses are satisfied by the given value of each variable.
+    Satisfiable(Vec<bool>),
+    /// The clauses are unsatisfiable.
+    Unsatisfiable,
+    /// The search gave up.
+    Unknown,
+}
+
+/// Incremental SAT solver over clauses in conjunctive normal form.
+#[derive(Clone, Debug)]
+pub(crate) struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    /// Clauses that watch the negation of each literal, indexed by literal.
+    watches: Vec<Vec<usize>>,
+    assigns: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    phases: Vec<bool>,
+    trail: Vec<Lit>,
+    trail_lim: Vec<usize>,
+    queue_head: usize,
+    activity: Vec<f64>,
+    activity_inc: f64,
+    heap: VarHeap,
+    seen: Vec<bool>,
+    /// The clauses are unsatisfiable at decision level 0.
+    inconsistent: bool,
+}
+
+impl Solver {
+    /// Create a solver without variables and clauses.
+    pub fn new() -> Self {
+        Self {
+            clauses: Vec::new(),
+            watches: Vec::new(),
+            assigns: Vec::new(),
+            levels: Vec::new(),
+            reasons: Vec::new(),
+            phases: Vec::new(),
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+            queue_head: 0,
+            activity: Vec::new(),
+            activity_inc: 1.0,
+            heap: VarHeap::default(),
+            seen: Vec::new(),
+            inconsistent: false,
+        }
+    }
+
+    /// Create a fresh variable and return its positive literal.
+    pub fn new_var(&mut self) -> Lit {
+        let var = self.assigns.len();
+        self.assigns.push(None);
+        self.levels.push(0);
+        self.reasons.push(None);
+        self.phases.push(false);
+        self.activity.push(0.0);
+        self.seen.push(false);
+        self.watches.push(Vec::new());
+        self.watches.push(Vec::new());
+        self.heap.insert(var, &self.activity);
+        Lit::positive(var)
+    }
+
+    /// Add a clause: a disjunction of literals.
+    ///
+    /// Clauses must be added at decision level 0, that is, outside of [`Solver::solve`].
+    pub fn add_clause(&mut self, lits: &[Lit]) {
+        debug_assert!(self.trail_lim.is_empty());
+        if self.inconsistent {
+            return;
+        }
+        let mut clause = lits.to_vec();
+        clause.sort_unstable();
+        clause.dedup();
+        // Tautologies are always satisfied
+        if clause.windows(2).any(|pair| pair[0] == !pair[1]) {
+            return;
+        }
+        if clause.iter().any(|lit| self.value(*lit) == Some(true)) {
+            return;
+        }
+        clause.retain(|lit| self.value(*lit).is_none());
+        match clause.len() {
+            0 => self.inconsistent = true,
+            1 => {
+                self.enqueue(clause[0], None);
+                if self.propagate().is_some() {
+                    self.inconsistent = true;
+                }
+            }
+            _ => {
+                self.attach(clause);
+            }
+        }
+    }
+
+    /// Search for an assignment that satisfies all clauses,
+    /// giving up after the given number of conflicts.
+    pub fn solve(&mut self, max_conflicts: usize) -> Outcome {
+        if self.inconsistent {
+            return Outcome::Unsatisfiable;
+        }
+        let mut remaining = max_conflicts;
+        let mut restart = 1;
+        loop {
+            if remaining == 0 {
+                return Outcome::Unknown;
+            }
+            let budget = (100 * luby(restart)).min(remaining);
+            match self.search(budget) {
+                Some(true) => {
+                    let model = self
+                        .assigns
+                        .iter()
+                        .map(|value| value.unwrap_or(false))
+                        .collect();
+                    self.backtrack(0);
+                    return Outcome::Satisfiable(model);
+                }
+                Some(false) => {
+                    self.inconsistent = true;
+                    return Outcome::Unsatisfiable;
+                }
+                None => {
+                    self.backtrack(0);
+                    remaining -= budget;
+                    restart += 1;
+                }
+            }
+        }
+    }
+
+    /// Search until the given number of conflicts.
+    ///
+    /// Return `Some(true)` if the clauses are satisfied, `Some(false)` if they are unsatisfiable,
+    /// and `None` if the budget ran out.
+    fn search(&mut self, mut budget: usize) -> Option<bool> {
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.trail_lim.is_empty() {
+                    return Some(false);
+                }
+                let (learned, level) = self.analyze(conflict);
+                self.backtrack(level);
+                if learned.len() == 1 {
+                    self.enqueue(learned[0], None);
+                } else {
+                    let asserting = learned[0];
+                    let index = self.attach(learned);
+                    self.enqueue(asserting, Some(index));
+                }
+                self.activity_inc /= 0.95;
+                budget = budget.saturating_sub(1);
+            } else {
+                if budget == 0 {
+                    return None;
+                }
+                let var = loop {
+                    match self.heap.pop(&self.activity) {
+                        Some(var) if self.assigns[var].is_none() => break Some(var),
+                        Some(_) => continue,
+                        None => break None,
+                    }
+                };
+                let Some(var) = var else {
+                    return Some(true);
+                };
+                self.trail_lim.push(self.trail.len());
+                let lit = Lit::positive(var);
+                self.enqueue(if self.phases[var] { lit } else { !lit }, None);
+            }
+        }
+    }
+
+    fn value(&self, lit: Lit) -> Option<bool> {
+        self.assigns[lit.var()].map(|value| value != lit.is_negated())
+    }
+
+    fn level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    /// Add a clause with at least two literals and watch its first two literals.
+    fn attach(&mut self, clause: Vec<Lit>) -> usize {
+        let index = self.clauses.len();
+        self.watches[(!clause[0]).index()].push(index);
+        self.watches[(!clause[1]).index()].push(index);
+        self.clauses.push(clause);
+        index
+    }
+
+    fn enqueue(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit.var();
+        self.assigns[var] = Some(!lit.is_negated());
+        self.levels[var] = self.level();
+        self.reasons[var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Propagate the assignments on the trail through the clauses.
+    ///
+    /// Return the conflicting clause if some clause became false.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.queue_head < self.trail.len() {
+            let lit = self.trail[self.queue_head];
+            self.queue_head += 1;
+            let false_lit = !lit;
+            let mut watchers = std::mem::take(&mut self.watches[lit.index()]);
+            let mut conflict = None;
+            let mut kept = 0;
+            let mut i = 0;
+
+            while i < watchers.len() {
+                let index = watchers[i];
+                i += 1;
+                let clause = &mut self.clauses[index];
+                // Make sure that the false literal is at position 1
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                let first_value = self.assigns[first.var()].map(|v| v != first.is_negated());
+                if first_value == Some(true) {
+                    watchers[kept] = index;
+                    kept += 1;
+                    continue;
+                }
+                // Look for a new literal to watch
+                let assigns = &self.assigns;
+                let replacement = (2..clause.len()).find(|k| {
+                    let other = clause[*k];
+                    assigns[other.var()] != Some(other.is_negated())
+                });
+                if let Some(k) = replacement {
+                    clause.swap(1, k);
+                    let watched = clause[1];
+                    self.watches[(!watched).index()].push(index);
+                    continue;
+                }
+                // The clause is unit or false
+                watchers[kept] = index;
+                kept += 1;
+                if first_value == Some(false) {
+                    conflict = Some(index);
+                    while i < watchers.len() {
+                        watchers[kept] = watchers[i];
+                        kept += 1;
+                        i += 1;
+                    }
+                } else {
+                    self.enqueue(first, Some(index));
+                }
+            }
+
+            watchers.truncate(kept);
+            self.watches[lit.index()] = watchers;
+            if conflict.is_some() {
+                self.queue_head = self.trail.len();
+                return conflict;
+            }
+        }
+        None
+    }
+
+    /// Learn a clause from the given conflict via the first unique implication point.
+    ///
+    /// Return the learned clause with the asserting literal first,
+    /// and the decision level to which the solver backtracks.
+    fn analyze(&mut self, conflict: usize) -> (Vec<Lit>, usize) {
+        let mut learned = vec![Lit(0)];
+        let mut pending = 0;
+        let mut index = self.trail.len();
+        let mut clause = conflict;
+        let mut implied: Option<Lit> = None;
+
+        loop {
+            // The implied literal of a reason clause is at position 0
+            let skip = usize::from(implied.is_some());
+            for k in skip..self.clauses[clause].len() {
+                let lit = self.clauses[clause][k];
+                let var = lit.var();
+                if !self.seen[var] && 0 < self.levels[var] {
+                    self.seen[var] = true;
+                    self.bump(var);
+                    if self.levels[var] == self.level() {
+                        pending += 1;
+                    } else {
+                        learned.push(lit);
+                    }
+                }
+            }
+            // Continue with the most recent literal that takes part in the conflict
+            let lit = loop {
+                index -= 1;
+                if self.seen[self.trail[index].var()] {
+                    break self.trail[index];
+                }
+            };
+            self.seen[lit.var()] = false;
+            pending -= 1;
+            implied = Some(lit);
+            if pending == 0 {
+                break;
+            }
+            clause = self.reasons[lit.var()].expect("implied literal has a reason");
+        }
+
+        learned[0] = !implied.expect("conflict has an implication point");
+        for lit in &learned[1..] {
+            self.seen[lit.var()] = false;
+        }
+        // The literal with the highest level is watched next to the asserting literal
+        let mut level = 0;
+        if 1 < learned.len() {
+            let (k, _) = learned
+                .iter()
+                .enumerate()
+                .skip(1)
+                .max_by_key(|(_, lit)| self.levels[lit.var()])
+                .unwrap();
+            learned.swap(1, k);
+            level = self.levels[learned[1].var()];
+        }
+        (learned, level)
+    }
+
+    /// Undo all assignments above the given decision level.
+    fn backtrack(&mut self, level: usize) {
+        if level < self.level() {
+            let start = self.trail_lim[level];
+            for lit in self.trail.drain(start..).rev() {
+                let var = lit.var();
+                self.assigns[var] = None;
+                self.reasons[var] = None;
+                self.phases[var] = !lit.is_negated();
+                self.heap.insert(var, &self.activity);
+            }
+            self.trail_lim.truncate(level);
+            self.queue_head = self.trail.len();
+        }
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.activity_inc;
+        if 1e100 < self.activity[var] {
+            for activity in &mut self.activity {
+                *activity *= 1e-100;
+            }
+            self.activity_inc *= 1e-100;
+        }
+        self.heap.increase(var, &self.activity);
+    }
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Return the i-th element of the Luby sequence 1, 1, 2, 1, 1, 2, 4, ... (starting at 1).
+fn luby(mut i: usize) -> usize {
+    loop {
+        let mut k = 1;
+        while (1 << k) - 1 < i {
+            k += 1;
+        }
+        if i == (1 << k) - 1 {
+            return 1 << (k - 1);
+        }
+        i -= (1 << (k - 1)) - 1;
+    }
+}
+
+/// Binary max-heap of variables, ordered by activity.
+#[derive(Clone, Debug, Default)]
+struct VarHeap {
+    vars: Vec<usize>,
+    /// Position of each variable in the heap, if any.
+    positions: Vec<Option<usize>>,
+}
+
+impl VarHeap {
+    fn insert(&mut self, var: usize, activity: &[f64]) {
+        if self.positions.len() <= var {
+            self.positions.resize(var + 1, None);
+        }
+        if self.positions[var].is_none() {
+            self.positions[var] = Some(self.vars.len());
+            self.vars.push(var);
+            self.sift_up(self.vars.len() - 1, activity);
+        }
+    }
+
+    fn increase(&mut self, var: usize, activity: &[f64]) {
+        if let Some(position) = self.positions[var] {
+            self.sift_up(position, activity);
+        }
+    }
+
+    fn pop(&mut self, activity: &[f64]) -> Option<usize> {
+        let top = *self.vars.first()?;
+        let last = self.vars.pop().unwrap();
+        self.positions[top] = None;
+        if !self.vars.is_empty() {
+            self.vars[0] = last;
+            self.positions[last] = Some(0);
+            self.sift_down(0, activity);
+        }
+        Some(top)
+    }
+
+    fn sift_up(&mut self, mut position: usize, activity: &[f64]) {
+        let var = self.vars[position];
+        while 0 < position {
+            let parent = (position - 1) / 2;
+            if activity[self.vars[parent]] >= activity[var] {
+                break;
+            }
+            self.vars[position] = self.vars[parent];
+            self.positions[self.vars[position]] = Some(position);
+            position = parent;
+        }
+        self.vars[position] = var;
+        self.positions[var] = Some(position);
+    }
+
+    fn sift_down(&mut self, mut position: usize, activity: &[f64]) {
+        let var = self.vars[position];
+        loop {
+            let left = 2 * position + 1;
+            if self.vars.len() <= left {
+                break;
+            }
+            let right = left + 1;
+            let child = if right < self.vars.len()
+                && activity[self.vars[left]] < activity[self.vars[right]]
+            {
+                right
+            } else {
+                left
+            };
+            if activity[self.vars[child]] <= activity[var] {
+                break;
+            }
+            self.vars[position] = self.vars[child];
+            self.positions[self.vars[position]] = Some(position);
+            position = child;
+        }
+        self.vars[position] = var;
+        self.positions[var] = Some(position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn satisfies(clauses: &[Vec<Lit>], model: &[bool]) -> bool {
+        clauses.iter().all(|clause| {
+            clause
+                .iter()
+                .any(|lit| model[lit.var()] != lit.is_negated())
+        })
+    }
+
+    #[test]
+    fn luby_sequence() {
+        let sequence = (1..=15).map(luby).collect::<Vec<usize>>();
+        assert_eq!(vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8], sequence);
+    }
+
+    #[test]
+    fn pigeonhole() {
+        // n + 1 pigeons do not fit into n holes
+        for holes in 1..=5 {
+            let mut solver = Solver::new();
+            let pigeons = holes + 1;
+            let vars = (0..pigeons)
+                .map(|_| (0..holes).map(|_| solver.new_var()).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            for pigeon in &vars {
+                solver.add_clause(pigeon);
+            }
+            for hole in 0..holes {
+                for (p, first) in vars.iter().enumerate() {
+                    for second in &vars[p + 1..] {
+                        solver.add_clause(&[!first[hole], !second[hole]]);
+                    }
+                }
+            }
+            assert_eq!(Outcome::Unsatisfiable, solver.solve(usize::MAX));
+        }
+    }
+
+    #[test]
+    fn random_3sat() {
+        // Formulas with few clauses per variable are almost always satisfiable
+        let mut state = 0x1234_5678_u64;
+        let mut next = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+        for _ in 0..20 {
+            let mut solver = Solver::new();
+            let vars = (0..50).map(|_| solver.new_var()).collect::<Vec<_>>();
+            let clauses = (0..150)
+                .map(|_| {
+                    (0..3)
+                        .map(|_| {
+                            let lit = vars[next(vars.len())];
+                            if next(2) == 0 {
+                                lit
+                            } else {
+                                !lit
+                            }
+                        })
+                        .collect::<Vec<Lit>>()
+                })
+                .collect::<Vec<_>>();
+            for clause in &clauses {
+                solver.add_clause(clause);
+            }
+            if let Outcome::Satisfiable(model) = solver.solve(usize::MAX) {
+                assert!(satisfies(&clauses, &model));
+            }
+        }
+    }
+}
@@ -36,8 +36,8 @@ impl WitnessValues {
     /// Deserialize witness values from JSON with compiler-provided type context.
     ///
     /// This method simplifies the witness JSON format by eliminating redundant
-    /// type field annotations. The compiler provides type information through 
-    /// WitnessTypes, allowing users to specify only the values they need to provide.
+    /// type field annotations. The compiler provides type information through
+    /// [`WitnessTypes`], allowing users to specify only the values they need to provide.
     ///
     /// Special features:
     /// - "description" field: Top-level comment for the witness file (ignored)
@@ -67,28 +67,24 @@ impl WitnessValues {
     ///
     /// Returns an error if:
     /// - JSON parsing fails
-    /// - A witness variable is not found in witness_types
+    /// - A witness variable is not found in `witness_types`
     /// - Value parsing fails for the resolved type
     /// - A witness variable is assigned multiple times
-    pub fn from_json_with_types(
-        json: &str,
-        witness_types: &WitnessTypes,
-    ) -> Result<Self, Error> {
-        let json_value: serde_json::Map<String, serde_json::Value> =
-            serde_json::from_str(json)
-                .map_err(|e| Error::InvalidJsonFormat(format!("Failed to parse JSON: {}", e)))?;
+    pub fn from_json_with_types(json: &str, witness_types: &WitnessTypes) -> Result<Self, Error> {
+        let json_value: serde_json::Map<String, serde_json::Value> = serde_json::from_str(json)
+            .map_err(|e| Error::InvalidJsonFormat(format!("Failed to parse JSON: {}", e)))?;
 
         let mut map = HashMap::new();
 
-        for (name_str, value_json) in json_value.iter() {
+        for (name_str, value_json) in &json_value {
             // Skip special fields that are allowed for documentation/comments
             if name_str == "description" {
                 // Top-level description for the entire witness file
                 // Users can add comments here
                 continue;
             }
-            
-            if name_str.starts_with("_") {
+
+            if name_str.starts_with('_') {
                 // Convention: fields starting with _ are comments/hints
                 // Examples: "_pubkey", "_signature", "_note"
                 // These are completely ignored by the compiler
@@ -140,10 +136,10 @@ impl WitnessValues {
 impl Arguments {
     /// Deserialize program arguments from JSON with compiler-provided type context.
     ///
-    /// Similar to WitnessValues::from_json_with_types, but for function parameters.
+    /// Similar to [`WitnessValues::from_json_with_types`], but for function parameters.
     /// Types are resolved from the compiled program through Parameters, not from JSON.
     ///
-    /// Supports the same special fields as WitnessValues:
+    /// Supports the same special fields as [`WitnessValues`]:
     /// - "description": Top-level comment for the arguments file (ignored)
     /// - "_<name>": Comments/hints for specific parameters (ignored)
     ///
@@ -175,20 +171,19 @@ impl Arguments {
         json: &str,
         parameters: &crate::witness::Parameters,
     ) -> Result<Self, Error> {
-        let json_value: serde_json::Map<String, serde_json::Value> =
-            serde_json::from_str(json)
-                .map_err(|e| Error::InvalidJsonFormat(format!("Failed to parse JSON: {}", e)))?;
+        let json_value: serde_json::Map<String, serde_json::Value> = serde_json::from_str(json)
+            .map_err(|e| Error::InvalidJsonFormat(format!("Failed to parse JSON: {}", e)))?;
 
         let mut map = HashMap::new();
 
-        for (name_str, value_json) in json_value.iter() {
+        for (name_str, value_json) in &json_value {
             // Skip special fields that are allowed for documentation/comments
             if name_str == "description" {
                 // Top-level description for the entire arguments file
                 continue;
             }
-            
-            if name_str.starts_with("_") {
+
+            if name_str.starts_with('_') {
                 // Convention: fields starting with _ are comments/hints
                 continue;
             }
@@ -236,28 +231,54 @@ impl Arguments {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::{ResolvedType, UIntType};
+    use crate::value::ValueConstructible;
+
+    fn witness_types() -> WitnessTypes {
+        WitnessTypes::from(HashMap::from([
+            (
+                WitnessName::from_str_unchecked("A"),
+                ResolvedType::from(UIntType::U32),
+            ),
+            (
+                WitnessName::from_str_unchecked("B"),
+                ResolvedType::from(UIntType::U32),
+            ),
+        ]))
+    }
 
-    // Tests for new context-aware deserialization should be added here
-    // Example test case for simplified JSON format:
-    //
-    // #[test]
-    // fn witness_from_json_with_types() {
-    //     let json = r#"{ "A": "42", "B": "0x12345678" }"#;
-    //     let witness_types = /* create WitnessTypes with A: u32, B: u32 */;
-    //     let witness = WitnessValues::from_json_with_types(json, &witness_types).unwrap();
-    //     assert_eq!(witness.get(&WitnessName::from_str_unchecked("A")).unwrap().ty(), u32_type);
-    // }
-    //
-    // #[test]
-    // fn witness_with_description_and_hints() {
-    //     let json = r#"{
-    //         "A": "42",
-    //         "_A": "Important seed value",
-    //         "description": "Test witness with comments"
-    //     }"#;
-    //     let witness_types = /* create WitnessTypes with A: u32 */;
-    //     let witness = WitnessValues::from_json_with_types(json, &witness_types).unwrap();
-    //     // Description and _A should be ignored, only A should be in witness
-    //     assert_eq!(witness.iter().count(), 1);
-    // }
+    #[test]
+    fn witness_from_json_with_types() {
+        let json = r#"{ "A": "42", "B": "0x12345678" }"#;
+        let witness = WitnessValues::from_json_with_types(json, &witness_types()).unwrap();
+        assert_eq!(
+            witness.get(&WitnessName::from_str_unchecked("A")),
+            Some(&Value::u32(42))
+        );
+        assert_eq!(
+            witness.get(&WitnessName::from_str_unchecked("B")),
+            Some(&Value::u32(0x12345678))
+        );
+    }
+
+    #[test]
+    fn witness_with_description_and_hints() {
+        let json = r#"{
+            "A": "42",
+            "_A": "Important seed value",
+            "description": "Test witness with comments"
+        }"#;
+        let witness = WitnessValues::from_json_with_types(json, &witness_types()).unwrap();
+        assert_eq!(witness.iter().count(), 1);
+    }
+
+    #[test]
+    fn witness_undefined() {
+        let json = r#"{ "C": "42" }"#;
+        match WitnessValues::from_json_with_types(json, &witness_types()) {
+            Ok(_) => panic!("Undefined witness was falsely accepted"),
+            Err(Error::UndefinedWitness(name)) => assert_eq!(name.as_inner(), "C"),
+            Err(error) => panic!("Unexpected error: {error}"),
+        }
+    }
 }
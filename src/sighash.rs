@@ -0,0 +1,136 @@
+//! Messages that Simplicity programs sign.
+//!
+//! Programs typically verify signatures on a message that they compute from the transaction,
+//! such as `jet::sig_all_hash()` or a custom hash that is built from a `Ctx8`.
+//! This module computes the same message outside of the program by evaluating
+//! a SimplicityHL expression in the environment of the spending transaction,
+//! so the message can be signed before the witness values are built.
+
+use std::sync::Arc;
+
+use simplicity::jet::elements::ElementsEnv;
+use simplicity::{elements, BitMachine};
+
+use crate::error::WithFile;
+use crate::parse::ParseFromStr;
+use crate::types::{ResolvedType, UIntType};
+use crate::{ast, named, parse, WitnessValues};
+
+/// Evaluate a SimplicityHL expression of type `u256` in the given environment
+/// and return its value as bytes.
+///
+/// The expression may call jets, including introspection jets such as `jet::sig_all_hash()`,
+/// and it may be a block expression that builds a custom hash.
+/// It may use neither parameters nor witnesses.
+///
+/// ## Errors
+///
+/// - The expression is not a valid SimplicityHL expression of type `u256`.
+/// - The expression fails to execute in the given environment.
+pub fn evaluate(
+    expression: &str,
+    env: &ElementsEnv<Arc<elements::Transaction>>,
+) -> Result<[u8; 32], String> {
+    let u256 = ResolvedType::from(UIntType::U256);
+    let parse_expr = parse::Expression::parse_from_str(expression).with_file(expression)?;
+    let ast_expr = ast::Expression::analyze_standalone(&parse_expr, &u256).with_file(expression)?;
+    let commit = ast_expr.compile_standalone(false).with_file(expression)?;
+    let redeem = named::populate_witnesses(&commit, WitnessValues::default())?;
+    let mut mac = BitMachine::for_program(&redeem).map_err(|e| e.to_string())?;
+    let value = mac
+        .exec(&redeem, env)
+        .map_err(|e| format!("Execution failed: {e}"))?;
+    let bytes = value.raw_byte_iter().collect::<Vec<u8>>();
+    Ok(bytes.try_into().expect("u256 value is 32 bytes long"))
+}
+
+/// Return the message of `jet::sig_all_hash()` in the given environment.
+///
+/// This message commits to the entire transaction, to the spent UTXOs,
+/// and to the program that is being executed.
+pub fn sig_all_hash(env: &ElementsEnv<Arc<elements::Transaction>>) -> [u8; 32] {
+    evaluate("jet::sig_all_hash()", env).expect("sig_all_hash is a valid expression")
+}
+
+/// Return the message of `jet::tx_hash()` in the given environment.
+pub fn tx_hash(env: &ElementsEnv<Arc<elements::Transaction>>) -> [u8; 32] {
+    evaluate("jet::tx_hash()", env).expect("tx_hash is a valid expression")
+}
+
+#[cfg(test)]
+mod tests {
+    use elements::hashes::{sha256, Hash};
+    use simplicity::Cmr;
+
+    use super::*;
+    use crate::dummy_env;
+    use crate::{Arguments, CompiledProgram};
+
+    fn env_of(cmr: Cmr) -> ElementsEnv<Arc<elements::Transaction>> {
+        let dummy = dummy_env::dummy();
+        ElementsEnv::new(
+            Arc::new(dummy.tx().clone()),
+            vec![simplicity::jet::elements::ElementsUtxo {
+                script_pubkey: elements::Script::default(),
+                asset: elements::confidential::Asset::default(),
+                value: elements::confidential::Value::default(),
+            }],
+            0,
+            cmr,
+            dummy.control_block().clone(),
+            None,
+            dummy.genesis_hash(),
+        )
+    }
+
+    #[test]
+    fn builtin_messages() {
+        let env = dummy_env::dummy();
+        assert_eq!(
+            env.c_tx_env().sighash_all().to_byte_array(),
+            sig_all_hash(&env)
+        );
+        assert_ne!(sig_all_hash(&env), tx_hash(&env));
+    }
+
+    #[test]
+    fn custom_message() {
+        let env = dummy_env::dummy();
+        let expression = r#"{
+    let ctx: Ctx8 = jet::sha_256_ctx_8_init();
+    let ctx: Ctx8 = jet::sha_256_ctx_8_add_32(ctx, jet::sig_all_hash());
+    jet::sha_256_ctx_8_finalize(ctx)
+}"#;
+        let expected = sha256::Hash::hash(&sig_all_hash(&env)).to_byte_array();
+        assert_eq!(expected, evaluate(expression, &env).unwrap());
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        let env = dummy_env::dummy();
+        assert!(evaluate("jet::num_inputs()", &env).is_err());
+        assert!(evaluate("param::KEY", &env)
+            .unwrap_err()
+            .contains("Parameter `KEY` of type `u256` is missing an argument"));
+        assert!(evaluate("witness::SIG", &env).is_err());
+        assert!(evaluate("panic!()", &env)
+            .unwrap_err()
+            .contains("Execution failed"));
+    }
+
+    #[test]
+    fn message_of_program() {
+        let compiled = CompiledProgram::new("fn main() {}", Arguments::default(), false).unwrap();
+        let env = env_of(compiled.commit().cmr());
+        assert_eq!(
+            sig_all_hash(&env),
+            compiled.sighash("jet::sig_all_hash()", &env).unwrap()
+        );
+
+        let other_env = env_of(Cmr::from_byte_array([0; 32]));
+        assert!(compiled
+            .sighash("jet::sig_all_hash()", &other_env)
+            .unwrap_err()
+            .contains("Environment was created for a different program"));
+    }
+}
@@ -0,0 +1,494 @@
+//! Signatures for the signature witnesses of SimplicityHL programs.
+//!
+//! A signature witness is a witness of type `Signature` that the program passes
+//! to `jet::bip_0340_verify` together with a public key and a message.
+//! The message is found by executing the program with a placeholder signature
+//! and observing the arguments of the failing jet call.
+//! A [`Signer`] signs the message, and the signature replaces the placeholder.
+//! This repeats until every signature witness that the program verifies is signed.
+//!
+//! Signatures may also be nested inside witnesses of other types,
+//! such as `Either<Signature, Signature>` or `[Option<Signature>; 3]`.
+//! The caller chooses the spend path by providing the value of such a witness,
+//! with the zero signature in each slot to sign.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use either::Either;
+
+use simplicity::elements;
+use simplicity::elements::secp256k1_zkp::XOnlyPublicKey;
+use simplicity::elements::secp256k1_zkp::{schnorr, Keypair, Message, Secp256k1, SecretKey};
+use simplicity::jet::elements::ElementsEnv;
+use simplicity::jet::Elements;
+
+use crate::str::WitnessName;
+use crate::tracker::DefaultTracker;
+use crate::types::{ResolvedType, TypeConstructible, TypeInner};
+use crate::value::{UIntValue, ValueConstructible, ValueInner};
+use crate::{CompiledProgram, Value, WitnessValues};
+
+/// Source of BIP-340 signatures for signature witnesses.
+///
+/// Closures of the signature `FnMut(&WitnessName, &XOnlyPublicKey, &[u8; 32]) -> Result<[u8; 64], String>`
+/// are signers, so signing can be delegated to external wallets or hardware devices.
+pub trait Signer {
+    /// Sign the `message` for the signature witness of the given `name`.
+    ///
+    /// The program verifies the signature against the given `public_key`.
+    ///
+    /// ## Errors
+    ///
+    /// The signer cannot produce the signature.
+    fn sign(
+        &mut self,
+        name: &WitnessName,
+        public_key: &XOnlyPublicKey,
+        message: &[u8; 32],
+    ) -> Result<[u8; 64], String>;
+}
+
+impl<F> Signer for F
+where
+    F: FnMut(&WitnessName, &XOnlyPublicKey, &[u8; 32]) -> Result<[u8; 64], String>,
+{
+    fn sign(
+        &mut self,
+        name: &WitnessName,
+        public_key: &XOnlyPublicKey,
+        message: &[u8; 32],
+    ) -> Result<[u8; 64], String> {
+        self(name, public_key, message)
+    }
+}
+
+/// Map of signature witness names to the secret keys that sign them.
+///
+/// A witness with several signatures inside, such as an array of optional signatures,
+/// has several keys. Each signature is signed with the key that the program expects.
+#[derive(Clone, Debug, Default)]
+pub struct KeyStore(HashMap<WitnessName, Vec<Keypair>>);
+
+impl KeyStore {
+    /// Create an empty key store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the secret key to the keys of the signature witness of the given name.
+    pub fn insert(&mut self, name: WitnessName, secret_key: SecretKey) {
+        let keypair = Keypair::from_secret_key(&Secp256k1::signing_only(), &secret_key);
+        self.0.entry(name).or_default().push(keypair);
+    }
+
+    /// Parse a key store from a JSON object that maps witness names to hex-encoded secret keys,
+    /// or to arrays of them.
+    ///
+    /// ## Errors
+    ///
+    /// - The string is not a valid JSON object of strings or arrays of strings.
+    /// - Some string is not a valid secret key.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Keys {
+            One(String),
+            Many(Vec<String>),
+        }
+
+        let map: HashMap<String, Keys> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let mut store = Self::new();
+        for (name, keys) in map {
+            let keys = match keys {
+                Keys::One(hex) => vec![hex],
+                Keys::Many(hexes) => hexes,
+            };
+            for hex in keys {
+                let hex = hex.strip_prefix("0x").unwrap_or(&hex);
+                let secret_key = hex
+                    .parse::<SecretKey>()
+                    .map_err(|e| format!("Invalid secret key for witness `{name}`: {e}"))?;
+                store.insert(WitnessName::from_str_unchecked(&name), secret_key);
+            }
+        }
+        Ok(store)
+    }
+}
+
+impl Signer for KeyStore {
+    fn sign(
+        &mut self,
+        name: &WitnessName,
+        public_key: &XOnlyPublicKey,
+        message: &[u8; 32],
+    ) -> Result<[u8; 64], String> {
+        let keypairs = self
+            .0
+            .get(name)
+            .ok_or_else(|| format!("There is no key for witness `{name}`"))?;
+        let keypair = keypairs
+            .iter()
+            .find(|keypair| &keypair.x_only_public_key().0 == public_key)
+            .ok_or_else(|| {
+                format!(
+                    "The key for witness `{name}` does not match the public key {public_key} that the program expects"
+                )
+            })?;
+        let message = Message::from_digest(*message);
+        let signature: schnorr::Signature =
+            Secp256k1::signing_only().sign_schnorr_no_aux_rand(&message, keypair);
+        Ok(*signature.as_ref())
+    }
+}
+
+/// Request of a signature that the program verifies.
+struct SignatureRequest {
+    name: WitnessName,
+    placeholder: Value,
+    public_key: XOnlyPublicKey,
+    message: [u8; 32],
+}
+
+/// Fill the signature slots of the witness values of the program.
+///
+/// See [`CompiledProgram::sign`].
+pub(crate) fn fill_signatures<S: Signer + ?Sized>(
+    program: &CompiledProgram,
+    witness_values: WitnessValues,
+    signer: &mut S,
+    env: &ElementsEnv<Arc<elements::Transaction>>,
+) -> Result<WitnessValues, String> {
+    let mut placeholders = HashMap::<Value, WitnessName>::new();
+    let mut next_placeholder = |name: &WitnessName| {
+        let value = placeholder(placeholders.len());
+        placeholders.insert(value.clone(), name.clone());
+        value
+    };
+    let mut values = HashMap::<WitnessName, Value>::new();
+    let mut filled = Vec::new();
+    for (name, ty) in program.witness_types().iter() {
+        let value = match witness_values.get(name) {
+            Some(value) => mark_slots(value, &mut || next_placeholder(name)),
+            None if contains_signature(ty) => {
+                filled.push(name.clone());
+                fill_slots(ty, &mut || next_placeholder(name)).ok_or_else(|| {
+                    format!(
+                        "Witness `{name}` of type `{ty}` is missing and cannot be filled with signatures alone: \
+                         provide its value with the zero signature in each slot to sign"
+                    )
+                })?
+            }
+            None => continue,
+        };
+        values.insert(name.clone(), value);
+    }
+    // Keep witness values that the program does not declare, so satisfaction reports them
+    for (name, value) in witness_values.iter() {
+        values.entry(name.clone()).or_insert_with(|| value.clone());
+    }
+
+    let mut signed = HashSet::new();
+    while !placeholders.is_empty() {
+        let Some(request) = next_request(program, values.clone(), &placeholders, env)? else {
+            break;
+        };
+        let signature = signer.sign(&request.name, &request.public_key, &request.message)?;
+        let signature = Value::byte_array(signature);
+        placeholders.remove(&request.placeholder);
+        signed.insert(request.name.clone());
+        let value = values
+            .get_mut(&request.name)
+            .expect("placeholder is in witness");
+        *value = replace(value, &mut |slot| {
+            (slot == &request.placeholder).then(|| signature.clone())
+        });
+    }
+
+    // Missing witnesses without signed slots stay missing;
+    // other slots that the program does not verify in this environment are zeroed again
+    for name in filled.iter().filter(|name| !signed.contains(name)) {
+        values.remove(name);
+    }
+    let zero = Value::byte_array([0; 64]);
+    for value in values.values_mut() {
+        *value = replace(value, &mut |slot| {
+            placeholders.contains_key(slot).then(|| zero.clone())
+        });
+    }
+    Ok(WitnessValues::from(values))
+}
+
+/// Return the type of signatures.
+fn signature_ty() -> ResolvedType {
+    ResolvedType::array(ResolvedType::u8(), 64)
+}
+
+/// Check if the type contains signatures.
+fn contains_signature(ty: &ResolvedType) -> bool {
+    if ty == &signature_ty() {
+        return true;
+    }
+    match ty.as_inner() {
+        TypeInner::Either(left, right) => contains_signature(left) || contains_signature(right),
+        TypeInner::Option(inner) | TypeInner::Array(inner, _) | TypeInner::List(inner, _) => {
+            contains_signature(inner)
+        }
+        TypeInner::Tuple(elements) => elements.iter().any(|ty| contains_signature(ty)),
+        TypeInner::Boolean | TypeInner::UInt(_) => false,
+    }
+}
+
+/// Return a value of the type whose slots are all signatures to sign.
+///
+/// Return `None` if the type has values without signatures,
+/// because there is a choice of left or right, some or none, or the length of a list.
+fn fill_slots(ty: &ResolvedType, next_placeholder: &mut impl FnMut() -> Value) -> Option<Value> {
+    if ty == &signature_ty() {
+        return Some(next_placeholder());
+    }
+    match ty.as_inner() {
+        TypeInner::Tuple(elements) => elements
+            .iter()
+            .map(|ty| fill_slots(ty, next_placeholder))
+            .collect::<Option<Vec<Value>>>()
+            .map(Value::tuple),
+        TypeInner::Array(element, size) => (0..*size)
+            .map(|_| fill_slots(element, next_placeholder))
+            .collect::<Option<Vec<Value>>>()
+            .map(|elements| Value::array(elements, element.as_ref().clone())),
+        _ => None,
+    }
+}
+
+/// Replace the zero signatures inside the value by placeholders.
+///
+/// The zero signature marks a slot to sign because it is never a valid BIP-340 signature:
+/// there is no point with x-coordinate zero on the secp256k1 curve.
+fn mark_slots(value: &Value, next_placeholder: &mut impl FnMut() -> Value) -> Value {
+    let zero = Value::byte_array([0; 64]);
+    replace(value, &mut |slot| {
+        (slot == &zero).then(&mut *next_placeholder)
+    })
+}
+
+/// Replace each signature inside the value by the result of `f`, if there is one.
+fn replace(value: &Value, f: &mut impl FnMut(&Value) -> Option<Value>) -> Value {
+    if value.ty() == &signature_ty() {
+        return f(value).unwrap_or_else(|| value.clone());
+    }
+    match (value.inner(), value.ty().as_inner()) {
+        (ValueInner::Either(Either::Left(left)), TypeInner::Either(_, ty_r)) => {
+            Value::left(replace(left, f), ty_r.as_ref().clone())
+        }
+        (ValueInner::Either(Either::Right(right)), TypeInner::Either(ty_l, _)) => {
+            Value::right(ty_l.as_ref().clone(), replace(right, f))
+        }
+        (ValueInner::Option(Some(inner)), _) => Value::some(replace(inner, f)),
+        (ValueInner::Tuple(elements), _) => {
+            Value::tuple(elements.iter().map(|value| replace(value, f)))
+        }
+        (ValueInner::Array(elements), TypeInner::Array(ty, _)) => Value::array(
+            elements
+                .iter()
+                .map(|value| replace(value, f))
+                .collect::<Vec<_>>(),
+            ty.as_ref().clone(),
+        ),
+        (ValueInner::List(elements, bound), TypeInner::List(ty, _)) => Value::list(
+            elements
+                .iter()
+                .map(|value| replace(value, f))
+                .collect::<Vec<_>>(),
+            ty.as_ref().clone(),
+            *bound,
+        ),
+        _ => value.clone(),
+    }
+}
+
+/// Return a placeholder signature that is unique for the given index.
+fn placeholder(index: usize) -> Value {
+    let mut bytes = [0xff; 64];
+    bytes[..8].copy_from_slice(&(index as u64).to_be_bytes());
+    Value::byte_array(bytes)
+}
+
+/// Execute the program and return the first signature verification of a placeholder.
+fn next_request(
+    program: &CompiledProgram,
+    values: HashMap<WitnessName, Value>,
+    placeholders: &HashMap<Value, WitnessName>,
+    env: &ElementsEnv<Arc<elements::Transaction>>,
+) -> Result<Option<SignatureRequest>, String> {
+    let satisfied = program.satisfy(WitnessValues::from(values))?;
+    let mut request = None;
+    let mut tracker = DefaultTracker::new(satisfied.debug_symbols()).with_jet_trace_sink(
+        |jet, args: Option<&[Value]>, _| {
+            if request.is_some() || jet != Elements::Bip0340Verify {
+                return;
+            }
+            let Some([key_message, signature]) = args else {
+                return;
+            };
+            let Some(name) = placeholders.get(signature) else {
+                return;
+            };
+            let ValueInner::Tuple(key_message) = key_message.inner() else {
+                return;
+            };
+            let (Some(public_key), Some(message)) = (
+                key_message.first().and_then(as_u256),
+                key_message.get(1).and_then(as_u256),
+            ) else {
+                return;
+            };
+            if let Ok(public_key) = XOnlyPublicKey::from_slice(&public_key) {
+                request = Some(SignatureRequest {
+                    name: name.clone(),
+                    placeholder: signature.clone(),
+                    public_key,
+                    message,
+                });
+            }
+        },
+    );
+    // Execution fails at the first placeholder, or it succeeds if no placeholder is verified
+    let _ = satisfied.redeem().prune_with_tracker(env, &mut tracker);
+    drop(tracker);
+    Ok(request)
+}
+
+fn as_u256(value: &Value) -> Option<[u8; 32]> {
+    match value.inner() {
+        ValueInner::UInt(UIntValue::U256(n)) => Some(n.to_byte_array()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use simplicity::hashes::Hash;
+
+    use super::*;
+    use crate::dummy_env;
+    use crate::{Arguments, TemplateProgram};
+
+    fn secret_key(n: u8) -> SecretKey {
+        let mut bytes = [0; 32];
+        bytes[31] = n;
+        SecretKey::from_slice(&bytes).unwrap()
+    }
+
+    fn p2pk() -> CompiledProgram {
+        let template =
+            TemplateProgram::new(std::fs::read_to_string("./examples/p2pk.simf").unwrap()).unwrap();
+        let public_key = Value::parse_from_str(
+            "0x79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            &ResolvedType::u256(),
+        )
+        .unwrap();
+        let arguments = Arguments::from(HashMap::from([(
+            WitnessName::from_str_unchecked("ALICE_PUBLIC_KEY"),
+            public_key,
+        )]));
+        template.instantiate(arguments, false).unwrap()
+    }
+
+    #[test]
+    fn sign_with_key_store() {
+        let program = p2pk();
+        let env = dummy_env::dummy();
+        let mut keys = KeyStore::new();
+        keys.insert(
+            WitnessName::from_str_unchecked("ALICE_SIGNATURE"),
+            secret_key(1),
+        );
+        let values = program
+            .sign(WitnessValues::default(), &mut keys, &env)
+            .unwrap();
+        // Signatures are deterministic, so they match the example witness file
+        let signature = values
+            .get(&WitnessName::from_str_unchecked("ALICE_SIGNATURE"))
+            .unwrap();
+        assert!(std::fs::read_to_string("./examples/p2pk.wit")
+            .unwrap()
+            .contains(&signature.to_string()));
+        let satisfied = program.satisfy(values).unwrap();
+        assert!(satisfied.redeem().prune(&env).is_ok());
+    }
+
+    #[test]
+    fn sign_with_callback() {
+        let program = p2pk();
+        let env = dummy_env::dummy();
+        let mut requests = vec![];
+        let mut signer = |name: &WitnessName, public_key: &XOnlyPublicKey, message: &[u8; 32]| {
+            requests.push((name.clone(), *public_key, *message));
+            Ok([0; 64])
+        };
+        let values = program
+            .sign(WitnessValues::default(), &mut signer, &env)
+            .unwrap();
+        assert_eq!(1, requests.len());
+        assert_eq!(env.c_tx_env().sighash_all().to_byte_array(), requests[0].2);
+        assert_eq!(
+            Some(&Value::byte_array([0; 64])),
+            values.get(&WitnessName::from_str_unchecked("ALICE_SIGNATURE"))
+        );
+    }
+
+    fn zero_signature() -> String {
+        format!("0x{}", "00".repeat(64))
+    }
+
+    #[test]
+    fn sign_nested_signatures() {
+        let template =
+            TemplateProgram::new(std::fs::read_to_string("./examples/p2ms.simf").unwrap()).unwrap();
+        let program = template.instantiate(Arguments::default(), false).unwrap();
+        let env = dummy_env::dummy();
+        let name = WitnessName::from_str_unchecked("MAYBE_SIGS");
+        let mut keys = KeyStore::new();
+        keys.insert(name.clone(), secret_key(1));
+        keys.insert(name.clone(), secret_key(3));
+
+        let ty = program.witness_types().get(&name).unwrap();
+        let zero = zero_signature();
+        let slots =
+            Value::parse_from_str(&format!("[Some({zero}), None, Some({zero})]"), ty).unwrap();
+        let values = WitnessValues::from(HashMap::from([(name.clone(), slots)]));
+        let values = program.sign(values, &mut keys, &env).unwrap();
+        // Signatures are deterministic, so they match the example witness file
+        let expected = std::fs::read_to_string("./examples/p2ms.wit").unwrap();
+        assert!(expected.contains(&values.get(&name).unwrap().to_string()));
+        let satisfied = program.satisfy(values).unwrap();
+        assert!(satisfied.redeem().prune(&env).is_ok());
+
+        // The spend path of the signatures inside `Option` must be chosen by the caller
+        assert!(program
+            .sign(WitnessValues::default(), &mut keys, &env)
+            .unwrap_err()
+            .contains("Witness `MAYBE_SIGS` of type `[Option<[u8; 64]>; 3]` is missing"));
+    }
+
+    #[test]
+    fn sign_with_wrong_key() {
+        let program = p2pk();
+        let env = dummy_env::dummy();
+        let mut keys = KeyStore::new();
+        keys.insert(
+            WitnessName::from_str_unchecked("ALICE_SIGNATURE"),
+            secret_key(2),
+        );
+        assert!(program
+            .sign(WitnessValues::default(), &mut keys, &env)
+            .unwrap_err()
+            .contains("does not match the public key"));
+        assert!(program
+            .sign(WitnessValues::default(), &mut KeyStore::new(), &env)
+            .unwrap_err()
+            .contains("There is no key for witness `ALICE_SIGNATURE`"));
+    }
+}
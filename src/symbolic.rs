@@ -0,0 +1,994 @@
+//! Symbolic execution of SimplicityHL programs.
+//!
+//! The executor runs the compiled Simplicity program on symbolic witness data:
+//! each bit of each witness is a variable, and each node of the program turns
+//! the bits of its input into formulas for the bits of its output.
+//! Both branches of a `case` node are executed, each under its own path condition.
+//! The program fails if some failing node is executed before any earlier node failed.
+//!
+//! Core jets on words, such as arithmetic, comparisons and bitwise operations, are modeled precisely.
+//! All other jets, such as hash functions, signature checks and introspection of the transaction,
+//! are uninterpreted: their outputs are fresh variables,
+//! and jets that return unit succeed or fail depending on a fresh variable.
+//! Calls of the same jet on the same symbolic input return the same output,
+//! except for jets whose output depends on the CMR of the program, such as `jet::sig_all_hash`:
+//! programs with different CMRs see different outputs.
+//!
+//! A bundled SAT solver decides for each match arm and each `panic!` call
+//! whether some witness data makes the program reach it, and for the program as a whole
+//! whether some witness data makes it succeed.
+//! Unreachability is a proof: because uninterpreted jets may return anything,
+//! there is no witness data that reaches the program point in any environment.
+//! Conversely, the witness data that the solver finds relies on the uninterpreted jets
+//! returning the right values, so it may not reach the program point in a given environment.
+//! A witness that reaches a match arm behind a valid signature will typically carry a bogus signature.
+//! If the solver gives up after the maximum number of conflicts, the reachability is unknown.
+//!
+//! The program must be compiled with debug symbols to find its match arms and `panic!` calls.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Not;
+use std::sync::Arc;
+
+use simplicity::dag::{DagLike, InternalSharing};
+use simplicity::jet::Elements;
+use simplicity::node::Inner;
+use simplicity::types::{CompleteBound, Final};
+use simplicity::Cmr;
+
+use crate::debug::{DebugSymbols, TrackedCall, TrackedCallName};
+use crate::named::CommitNode;
+use crate::sat::{Lit, Outcome, Solver};
+use crate::str::WitnessName;
+use crate::types::StructuralType;
+use crate::value::StructuralValue;
+use crate::{CompiledProgram, Value, WitnessTypes, WitnessValues};
+
+/// Result of the symbolic execution of a SimplicityHL program.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let compiled = CompiledProgram::new(source, Arguments::default(), true)?;
+/// let execution = SymbolicExecution::new(&compiled);
+/// for target in execution.targets() {
+///     println!("{}: {}", target.line(), target.reachability());
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SymbolicExecution {
+    success: Reachability,
+    targets: Vec<Target>,
+}
+
+/// Program point whose reachability was analyzed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Target {
+    call: TrackedCall,
+    reachability: Reachability,
+}
+
+/// Reachability of a program point.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reachability {
+    /// The program point is reached with the given witness values,
+    /// assuming that the uninterpreted jets return the right values.
+    Reachable(WitnessValues),
+    /// No witness values reach the program point.
+    Unreachable,
+    /// The SAT solver gave up after the maximum number of conflicts.
+    Unknown,
+}
+
+impl Reachability {
+    /// Check if the program point is reachable.
+    pub fn is_reachable(&self) -> bool {
+        matches!(self, Reachability::Reachable(..))
+    }
+
+    /// Access the witness values that reach the program point, if it is reachable.
+    pub fn witness_values(&self) -> Option<&WitnessValues> {
+        match self {
+            Reachability::Reachable(witness_values) => Some(witness_values),
+            Reachability::Unreachable | Reachability::Unknown => None,
+        }
+    }
+}
+
+impl fmt::Display for Reachability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reachability::Reachable(..) => write!(f, "reachable"),
+            Reachability::Unreachable => write!(f, "unreachable"),
+            Reachability::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+impl Target {
+    /// Access the call expression: a match arm or a `panic!` call.
+    pub fn call(&self) -> &TrackedCall {
+        &self.call
+    }
+
+    /// Access the reachability of the call expression.
+    pub fn reachability(&self) -> &Reachability {
+        &self.reachability
+    }
+
+    /// Return the line where the call expression starts.
+    pub fn line(&self) -> usize {
+        self.call.span().start.line.get()
+    }
+}
+
+impl SymbolicExecution {
+    /// Symbolically execute the given program and decide the reachability of
+    /// its match arms and `panic!` calls.
+    ///
+    /// The SAT solver gives up after 100 000 conflicts for each program point.
+    pub fn new(program: &CompiledProgram) -> Self {
+        Self::with_max_conflicts(program, 100_000)
+    }
+
+    /// Symbolically execute the given program and decide the reachability of
+    /// its match arms and `panic!` calls,
+    /// giving up after the given number of conflicts of the SAT solver for each program point.
+    pub fn with_max_conflicts(program: &CompiledProgram, max_conflicts: usize) -> Self {
+        let debug_symbols = program.debug_symbols();
+        let mut executor = Executor::new(program);
+        // Program points that are never executed are unreachable
+        for data in program
+            .simplicity
+            .as_ref()
+            .post_order_iter::<InternalSharing>()
+        {
+            if let Inner::AssertL(_, cmr) = data.node.inner() {
+                if executor.is_target(cmr) {
+                    executor.reached.insert(*cmr, Bit::FALSE);
+                }
+            }
+        }
+        let succeeds = executor.run(program);
+
+        let solve = |condition: Bit| -> Reachability {
+            match executor.solve(condition, max_conflicts) {
+                Outcome::Satisfiable(model) => Reachability::Reachable(
+                    executor.witness_values(&model, program.witness_types()),
+                ),
+                Outcome::Unsatisfiable => Reachability::Unreachable,
+                Outcome::Unknown => Reachability::Unknown,
+            }
+        };
+
+        let success = solve(succeeds);
+        let mut targets = executor
+            .reached
+            .iter()
+            .map(|(cmr, reached)| Target {
+                call: debug_symbols.get(cmr).unwrap().clone(),
+                reachability: solve(*reached),
+            })
+            .collect::<Vec<Target>>();
+        targets.sort_by_key(|target| {
+            let span = target.call.span();
+            (span.start.line, span.start.col, span.end.line, span.end.col)
+        });
+
+        Self { success, targets }
+    }
+
+    /// Access the reachability of a successful execution of the program as a whole.
+    pub fn success(&self) -> &Reachability {
+        &self.success
+    }
+
+    /// Access the match arms and `panic!` calls of the program, in order of appearance.
+    pub fn targets(&self) -> &[Target] {
+        &self.targets
+    }
+
+    /// Return the match arms of the program that are unreachable.
+    ///
+    /// Match arms of unknown reachability are not included.
+    pub fn dead_arms(&self) -> impl Iterator<Item = &Target> {
+        self.targets.iter().filter(|target| {
+            matches!(target.call.name(), TrackedCallName::MatchArm)
+                && target.reachability == Reachability::Unreachable
+        })
+    }
+
+    /// Return the match arms and `panic!` calls of the program
+    /// whose reachability is unknown because the SAT solver gave up.
+    pub fn unknown_targets(&self) -> impl Iterator<Item = &Target> {
+        self.targets
+            .iter()
+            .filter(|target| target.reachability == Reachability::Unknown)
+    }
+
+    /// Return the `panic!` calls of the program that are reachable.
+    pub fn reachable_panics(&self) -> impl Iterator<Item = &Target> {
+        self.targets.iter().filter(|target| {
+            matches!(target.call.name(), TrackedCallName::Panic)
+                && target.reachability.is_reachable()
+        })
+    }
+}
+
+impl fmt::Display for SymbolicExecution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Success: ")?;
+        write_reachability(f, &self.success)?;
+        for target in &self.targets {
+            let label = match target.call.name() {
+                TrackedCallName::MatchArm => "match arm",
+                _ => "panic!",
+            };
+            let text = target.call.text().lines().next().unwrap_or_default();
+            write!(f, "Line {} ({label}) `{text}`: ", target.line())?;
+            write_reachability(f, &target.reachability)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_reachability(f: &mut fmt::Formatter<'_>, reachability: &Reachability) -> fmt::Result {
+    match reachability {
+        Reachability::Reachable(witness_values) => {
+            writeln!(f, "reachable with witness:")?;
+            for line in witness_values.to_string().lines() {
+                writeln!(f, "    {line}")?;
+            }
+            Ok(())
+        }
+        Reachability::Unreachable => writeln!(f, "unreachable"),
+        Reachability::Unknown => writeln!(
+            f,
+            "unknown: the solver gave up after the maximum number of conflicts"
+        ),
+    }
+}
+
+/// Check if the output of the jet depends on the CMR of the program that calls it.
+///
+/// The CMR is the Taproot leaf script of the program,
+/// so every hash that commits to the leaf or to the script of the spent output depends on it.
+pub(crate) fn depends_on_cmr(jet: Elements) -> bool {
+    matches!(
+        jet,
+        Elements::ScriptCMR
+            | Elements::TapleafHash
+            | Elements::TapEnvHash
+            | Elements::SigAllHash
+            | Elements::TxHash
+            | Elements::CurrentScriptHash
+            | Elements::InputScriptHash
+            | Elements::InputScriptsHash
+            | Elements::InputUtxoHash
+            | Elements::InputUtxosHash
+    )
+}
+
+/// Decode a value of the given type from its padded bit encoding, ignoring the padding.
+fn decode(ty: &Final, bits: &[bool]) -> simplicity::Value {
+    match ty.bound() {
+        CompleteBound::Unit => simplicity::Value::unit(),
+        CompleteBound::Sum(left, right) => {
+            if bits[0] {
+                let inner = decode(right, &bits[1 + left.pad_right(right)..]);
+                simplicity::Value::right(Arc::clone(left), inner)
+            } else {
+                let inner = decode(left, &bits[1 + left.pad_left(right)..]);
+                simplicity::Value::left(inner, Arc::clone(right))
+            }
+        }
+        CompleteBound::Product(left, right) => {
+            let (left_bits, right_bits) = bits.split_at(left.bit_width());
+            simplicity::Value::product(decode(left, left_bits), decode(right, right_bits))
+        }
+    }
+}
+
+/// Bit of a symbolic value: a constant or a literal of the SAT solver.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub(crate) enum Bit {
+    Const(bool),
+    Var(Lit),
+}
+
+impl Bit {
+    pub const FALSE: Self = Bit::Const(false);
+    pub const TRUE: Self = Bit::Const(true);
+
+    /// Return the value of the bit in the given model of the SAT solver.
+    pub fn evaluate(self, model: &[bool]) -> bool {
+        match self {
+            Bit::Const(bit) => bit,
+            Bit::Var(lit) => model[lit.var()] != lit.is_negated(),
+        }
+    }
+}
+
+impl Not for Bit {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        match self {
+            Bit::Const(bit) => Bit::Const(!bit),
+            Bit::Var(lit) => Bit::Var(!lit),
+        }
+    }
+}
+
+/// Boolean circuit that is encoded into clauses of the SAT solver.
+///
+/// Gates are simplified when their inputs are constant and shared when they have the same inputs.
+#[derive(Default)]
+pub(crate) struct Circuit {
+    solver: Solver,
+    and_gates: HashMap<(Lit, Lit), Lit>,
+    xor_gates: HashMap<(Lit, Lit), Lit>,
+}
+
+impl Circuit {
+    fn fresh(&mut self) -> Bit {
+        Bit::Var(self.solver.new_var())
+    }
+
+    pub fn and(&mut self, a: Bit, b: Bit) -> Bit {
+        let (a, b) = match (a, b) {
+            (Bit::Const(false), _) | (_, Bit::Const(false)) => return Bit::FALSE,
+            (Bit::Const(true), other) | (other, Bit::Const(true)) => return other,
+            (Bit::Var(a), Bit::Var(b)) if a == b => return Bit::Var(a),
+            (Bit::Var(a), Bit::Var(b)) if a == !b => return Bit::FALSE,
+            (Bit::Var(a), Bit::Var(b)) => (a.min(b), a.max(b)),
+        };
+        if let Some(c) = self.and_gates.get(&(a, b)) {
+            return Bit::Var(*c);
+        }
+        let c = self.solver.new_var();
+        self.solver.add_clause(&[!c, a]);
+        self.solver.add_clause(&[!c, b]);
+        self.solver.add_clause(&[c, !a, !b]);
+        self.and_gates.insert((a, b), c);
+        Bit::Var(c)
+    }
+
+    pub fn or(&mut self, a: Bit, b: Bit) -> Bit {
+        !self.and(!a, !b)
+    }
+
+    pub fn xor(&mut self, a: Bit, b: Bit) -> Bit {
+        let (a, b) = match (a, b) {
+            (Bit::Const(bit), other) | (other, Bit::Const(bit)) => {
+                return if bit { !other } else { other }
+            }
+            (Bit::Var(a), Bit::Var(b)) => (a, b),
+        };
+        // Negations are pulled out of the gate, so more gates are shared
+        let negated = a.is_negated() != b.is_negated();
+        let (a, b) = (
+            Lit::positive(a.var()).min(Lit::positive(b.var())),
+            Lit::positive(a.var()).max(Lit::positive(b.var())),
+        );
+        if a == b {
+            return Bit::Const(negated);
+        }
+        let c = match self.xor_gates.get(&(a, b)) {
+            Some(c) => *c,
+            None => {
+                let c = self.solver.new_var();
+                self.solver.add_clause(&[!c, a, b]);
+                self.solver.add_clause(&[!c, !a, !b]);
+                self.solver.add_clause(&[c, !a, b]);
+                self.solver.add_clause(&[c, a, !b]);
+                self.xor_gates.insert((a, b), c);
+                c
+            }
+        };
+        if negated {
+            Bit::Var(!c)
+        } else {
+            Bit::Var(c)
+        }
+    }
+
+    /// Return `then` if the condition is true and `otherwise` if it is false.
+    fn mux(&mut self, condition: Bit, then: Bit, otherwise: Bit) -> Bit {
+        match condition {
+            Bit::Const(true) => then,
+            Bit::Const(false) => otherwise,
+            _ if then == otherwise => then,
+            _ => {
+                let then = self.and(condition, then);
+                let otherwise = self.and(!condition, otherwise);
+                self.or(then, otherwise)
+            }
+        }
+    }
+
+    /// Return whether two words are equal.
+    fn equal(&mut self, a: &[Bit], b: &[Bit]) -> Bit {
+        a.iter().zip(b).fold(Bit::TRUE, |acc, (x, y)| {
+            let same = !self.xor(*x, *y);
+            self.and(acc, same)
+        })
+    }
+
+    fn any(&mut self, a: &[Bit]) -> Bit {
+        a.iter().fold(Bit::FALSE, |acc, x| self.or(acc, *x))
+    }
+
+    fn all(&mut self, a: &[Bit]) -> Bit {
+        a.iter().fold(Bit::TRUE, |acc, x| self.and(acc, *x))
+    }
+
+    /// Add two words plus a carry bit.
+    ///
+    /// Words are big-endian, like in the Bit Machine.
+    /// Return the carry bit and the sum.
+    fn add(&mut self, a: &[Bit], b: &[Bit], mut carry: Bit) -> (Bit, Vec<Bit>) {
+        let mut sum = vec![Bit::FALSE; a.len()];
+        for i in (0..a.len()).rev() {
+            let half = self.xor(a[i], b[i]);
+            sum[i] = self.xor(half, carry);
+            let generate = self.and(a[i], b[i]);
+            let propagate = self.and(half, carry);
+            carry = self.or(generate, propagate);
+        }
+        (carry, sum)
+    }
+
+    /// Subtract a word and a borrow bit from a word.
+    ///
+    /// Return the borrow bit and the difference.
+    fn subtract(&mut self, a: &[Bit], b: &[Bit], borrow: Bit) -> (Bit, Vec<Bit>) {
+        let b = b.iter().map(|bit| !*bit).collect::<Vec<Bit>>();
+        let (carry, difference) = self.add(a, &b, !borrow);
+        (!carry, difference)
+    }
+
+    /// Return whether the first word is less than the second word.
+    fn less_than(&mut self, a: &[Bit], b: &[Bit]) -> Bit {
+        self.subtract(a, b, Bit::FALSE).0
+    }
+
+    /// Multiply two words of the same width into a word of double width.
+    fn multiply(&mut self, a: &[Bit], b: &[Bit]) -> Vec<Bit> {
+        let n = a.len();
+        let mut product = vec![Bit::FALSE; 2 * n];
+        for (shift, multiplier) in b.iter().rev().enumerate() {
+            let mut partial = vec![Bit::FALSE; 2 * n];
+            for (i, bit) in a.iter().enumerate() {
+                partial[n + i - shift] = self.and(*bit, *multiplier);
+            }
+            product = self.add(&product, &partial, Bit::FALSE).1;
+        }
+        product
+    }
+
+    /// Model the core jet with the given name on the given input.
+    ///
+    /// Return the output and whether the jet succeeds,
+    /// or `None` if the jet is not modeled precisely.
+    fn jet(&mut self, name: &str, input: &[Bit], output_width: usize) -> Option<(Vec<Bit>, Bit)> {
+        let (family, n) = match name.rsplit_once('_') {
+            Some((family, n)) => match n.parse::<usize>() {
+                Ok(n) => (family, n),
+                Err(..) => (name, 0),
+            },
+            None => (name, 0),
+        };
+        let expected_input = match family {
+            "verify" => 1,
+            "low" | "high" | "one" => 0,
+            "complement" | "some" | "all" | "is_zero" | "is_one" | "increment" | "decrement"
+            | "negate" => n,
+            "and" | "or" | "xor" | "eq" | "lt" | "le" | "min" | "max" | "add" | "subtract"
+            | "multiply" => 2 * n,
+            "full_increment" | "full_decrement" => 1 + n,
+            "full_add" | "full_subtract" => 1 + 2 * n,
+            "full_multiply" => 4 * n,
+            _ => return None,
+        };
+        if input.len() != expected_input {
+            return None;
+        }
+
+        let split = |input: &[Bit]| -> (Vec<Bit>, Vec<Bit>) {
+            (input[..n].to_vec(), input[n..2 * n].to_vec())
+        };
+        let with_flag = |flag: Bit, word: Vec<Bit>| -> Vec<Bit> {
+            let mut output = vec![flag];
+            output.extend(word);
+            output
+        };
+        let mut one = vec![Bit::FALSE; n];
+        if let Some(last) = one.last_mut() {
+            *last = Bit::TRUE;
+        }
+
+        let mut ok = Bit::TRUE;
+        let output = match family {
+            "verify" => {
+                ok = input[0];
+                vec![]
+            }
+            "low" => vec![Bit::FALSE; n],
+            "high" => vec![Bit::TRUE; n],
+            "one" => one,
+            "complement" => input.iter().map(|bit| !*bit).collect(),
+            "and" | "or" | "xor" => {
+                let (a, b) = split(input);
+                a.iter()
+                    .zip(&b)
+                    .map(|(x, y)| match family {
+                        "and" => self.and(*x, *y),
+                        "or" => self.or(*x, *y),
+                        _ => self.xor(*x, *y),
+                    })
+                    .collect()
+            }
+            "some" => vec![self.any(input)],
+            "all" => vec![self.all(input)],
+            "is_zero" => vec![!self.any(input)],
+            "is_one" => vec![self.equal(input, &one)],
+            "eq" => {
+                let (a, b) = split(input);
+                vec![self.equal(&a, &b)]
+            }
+            "lt" => {
+                let (a, b) = split(input);
+                vec![self.less_than(&a, &b)]
+            }
+            "le" => {
+                let (a, b) = split(input);
+                vec![!self.less_than(&b, &a)]
+            }
+            "min" | "max" => {
+                let (a, b) = split(input);
+                let a_less = self.less_than(&a, &b);
+                let (then, otherwise) = match family {
+                    "min" => (&a, &b),
+                    _ => (&b, &a),
+                };
+                then.iter()
+                    .zip(otherwise)
+                    .map(|(x, y)| self.mux(a_less, *x, *y))
+                    .collect()
+            }
+            "add" => {
+                let (a, b) = split(input);
+                let (carry, sum) = self.add(&a, &b, Bit::FALSE);
+                with_flag(carry, sum)
+            }
+            "full_add" => {
+                let (a, b) = split(&input[1..]);
+                let (carry, sum) = self.add(&a, &b, input[0]);
+                with_flag(carry, sum)
+            }
+            "subtract" => {
+                let (a, b) = split(input);
+                let (borrow, difference) = self.subtract(&a, &b, Bit::FALSE);
+                with_flag(borrow, difference)
+            }
+            "full_subtract" => {
+                let (a, b) = split(&input[1..]);
+                let (borrow, difference) = self.subtract(&a, &b, input[0]);
+                with_flag(borrow, difference)
+            }
+            "increment" => {
+                let (carry, sum) = self.add(input, &vec![Bit::FALSE; n], Bit::TRUE);
+                with_flag(carry, sum)
+            }
+            "full_increment" => {
+                let (carry, sum) = self.add(&input[1..], &vec![Bit::FALSE; n], input[0]);
+                with_flag(carry, sum)
+            }
+            "decrement" => {
+                let (borrow, difference) = self.subtract(input, &vec![Bit::FALSE; n], Bit::TRUE);
+                with_flag(borrow, difference)
+            }
+            "full_decrement" => {
+                let (borrow, difference) =
+                    self.subtract(&input[1..], &vec![Bit::FALSE; n], input[0]);
+                with_flag(borrow, difference)
+            }
+            "negate" => {
+                let (borrow, difference) = self.subtract(&vec![Bit::FALSE; n], input, Bit::FALSE);
+                with_flag(borrow, difference)
+            }
+            "multiply" => {
+                let (a, b) = split(input);
+                self.multiply(&a, &b)
+            }
+            "full_multiply" => {
+                // a * b + c + d
+                let (a, b) = split(input);
+                let (addend1, addend2) = split(&input[2 * n..]);
+                let product = self.multiply(&a, &b);
+                let widen = |word: Vec<Bit>| -> Vec<Bit> {
+                    let mut wide = vec![Bit::FALSE; n];
+                    wide.extend(word);
+                    wide
+                };
+                let sum = self.add(&product, &widen(addend1), Bit::FALSE).1;
+                self.add(&sum, &widen(addend2), Bit::FALSE).1
+            }
+            _ => unreachable!("family of jet is checked above"),
+        };
+
+        if output.len() == output_width {
+            Some((output, ok))
+        } else {
+            None
+        }
+    }
+}
+
+/// Jet call with its input, and the CMR of the calling program if the jet depends on it.
+type JetCall = (Elements, Vec<Bit>, Option<Cmr>);
+
+/// Symbolic executor of Simplicity programs with named witness nodes.
+///
+/// Programs that are executed by the same executor share their witness data
+/// and the results of their jet calls.
+pub(crate) struct Executor<'a> {
+    pub circuit: Circuit,
+    debug_symbols: &'a DebugSymbols,
+    /// Bits of each witness, including padding.
+    witnesses: HashMap<WitnessName, Vec<Bit>>,
+    /// Output of each jet call and whether the call succeeds.
+    ///
+    /// Calls of jets that depend on the CMR are keyed by the CMR of the calling program.
+    jet_calls: HashMap<JetCall, (Vec<Bit>, Bit)>,
+    /// CMR of the program that is executed.
+    cmr: Option<Cmr>,
+    /// Whether the program failed in the execution so far.
+    failed: Bit,
+    /// Whether each match arm and `panic!` call is reached.
+    reached: HashMap<Cmr, Bit>,
+}
+
+impl<'a> Executor<'a> {
+    pub fn new(program: &'a CompiledProgram) -> Self {
+        Self {
+            circuit: Circuit::default(),
+            debug_symbols: program.debug_symbols(),
+            witnesses: HashMap::new(),
+            jet_calls: HashMap::new(),
+            cmr: None,
+            failed: Bit::FALSE,
+            reached: HashMap::new(),
+        }
+    }
+
+    /// Execute the given program.
+    ///
+    /// Return whether the program succeeds.
+    pub fn run(&mut self, program: &'a CompiledProgram) -> Bit {
+        self.debug_symbols = program.debug_symbols();
+        self.cmr = Some(program.commit().cmr());
+        self.failed = Bit::FALSE;
+        self.eval(&program.simplicity, &[], Bit::TRUE);
+        !self.failed
+    }
+
+    /// Search for witness data that satisfies the given condition,
+    /// giving up after the given number of conflicts.
+    pub fn solve(&self, condition: Bit, max_conflicts: usize) -> Outcome {
+        let mut solver = self.circuit.solver.clone();
+        match condition {
+            Bit::Const(false) => return Outcome::Unsatisfiable,
+            Bit::Const(true) => {}
+            Bit::Var(lit) => solver.add_clause(&[lit]),
+        }
+        solver.solve(max_conflicts)
+    }
+
+    /// Return the witness values of the given model of the SAT solver.
+    pub fn witness_values(&self, model: &[bool], witness_types: &WitnessTypes) -> WitnessValues {
+        let mut values = HashMap::new();
+        for (name, ty) in witness_types.iter() {
+            let structural = StructuralType::from(ty);
+            let value = match self.witnesses.get(name) {
+                Some(bits) => {
+                    let bits = bits
+                        .iter()
+                        .map(|bit| bit.evaluate(model))
+                        .collect::<Vec<bool>>();
+                    decode(structural.as_ref(), &bits)
+                }
+                // The witness is not used
+                None => simplicity::Value::zero(structural.as_ref()),
+            };
+            let value = Value::reconstruct(&StructuralValue::from(value), ty)
+                .expect("decoded value has the witness type");
+            values.insert(name.shallow_clone(), value);
+        }
+        WitnessValues::from(values)
+    }
+
+    /// Check if the assertion with the given CMR is a match arm or a `panic!` call.
+    fn is_target(&self, cmr: &Cmr) -> bool {
+        matches!(
+            self.debug_symbols.get(cmr).map(TrackedCall::name),
+            Some(TrackedCallName::MatchArm | TrackedCallName::Panic)
+        )
+    }
+
+    /// Fail the program if the path condition holds.
+    fn fail(&mut self, condition: Bit) {
+        self.failed = self.circuit.or(self.failed, condition);
+    }
+
+    /// Execute the node on the given input under the given path condition.
+    ///
+    /// Return the output of the node.
+    fn eval(&mut self, node: &CommitNode<Elements>, input: &[Bit], condition: Bit) -> Vec<Bit> {
+        let arrow = node.cached_data().arrow();
+        // The node is never executed
+        if condition == Bit::FALSE {
+            return vec![Bit::FALSE; arrow.target.bit_width()];
+        }
+
+        match node.inner() {
+            Inner::Iden => input.to_vec(),
+            Inner::Unit => vec![],
+            Inner::InjL(child) | Inner::InjR(child) => {
+                let (left, right) = arrow.target.as_sum().unwrap();
+                let (tag, padding) = match node.inner() {
+                    Inner::InjL(..) => (false, left.pad_left(right)),
+                    _ => (true, left.pad_right(right)),
+                };
+                let mut output = vec![Bit::Const(tag)];
+                output.extend(std::iter::repeat(Bit::FALSE).take(padding));
+                output.extend(self.eval(child, input, condition));
+                output
+            }
+            Inner::Take(child) => {
+                let (left, _) = arrow.source.as_product().unwrap();
+                self.eval(child, &input[..left.bit_width()], condition)
+            }
+            Inner::Drop(child) => {
+                let (left, _) = arrow.source.as_product().unwrap();
+                self.eval(child, &input[left.bit_width()..], condition)
+            }
+            Inner::Comp(left, right) => {
+                let middle = self.eval(left, input, condition);
+                self.eval(right, &middle, condition)
+            }
+            Inner::Pair(left, right) => {
+                let mut output = self.eval(left, input, condition);
+                output.extend(self.eval(right, input, condition));
+                output
+            }
+            Inner::Case(left, right) => self.case(node, Some(left), Some(right), input, condition),
+            Inner::AssertL(left, cmr) => {
+                // Call expressions with debug symbols are compiled to `comp (pair false args) (assertl body cmr)`
+                if self.is_target(cmr) {
+                    let reached = self.circuit.and(condition, !self.failed);
+                    let previous = self.reached.get(cmr).copied().unwrap_or(Bit::FALSE);
+                    let reached = self.circuit.or(previous, reached);
+                    self.reached.insert(*cmr, reached);
+                }
+                self.case(node, Some(left), None, input, condition)
+            }
+            Inner::AssertR(_, right) => self.case(node, None, Some(right), input, condition),
+            Inner::Fail(..) => {
+                self.fail(condition);
+                vec![Bit::FALSE; arrow.target.bit_width()]
+            }
+            Inner::Disconnect(..) => unreachable!("SimplicityHL does not use disconnect right now"),
+            Inner::Witness(name) => {
+                let width = arrow.target.bit_width();
+                let circuit = &mut self.circuit;
+                self.witnesses
+                    .entry(name.shallow_clone())
+                    .or_insert_with(|| (0..width).map(|_| circuit.fresh()).collect())
+                    .clone()
+            }
+            Inner::Jet(jet) => {
+                let width = arrow.target.bit_width();
+                let cmr = self.cmr.filter(|_| depends_on_cmr(*jet));
+                let key = (*jet, input.to_vec(), cmr);
+                let (output, ok) = match self.jet_calls.get(&key) {
+                    Some(call) => call.clone(),
+                    None => {
+                        let call = match self.circuit.jet(&jet.to_string(), input, width) {
+                            Some(call) => call,
+                            None => {
+                                let output = (0..width).map(|_| self.circuit.fresh()).collect();
+                                // Uninterpreted jets that return unit are checks that may fail
+                                let ok = match width {
+                                    0 => self.circuit.fresh(),
+                                    _ => Bit::TRUE,
+                                };
+                                (output, ok)
+                            }
+                        };
+                        self.jet_calls.insert(key, call.clone());
+                        call
+                    }
+                };
+                let fails = self.circuit.and(condition, !ok);
+                self.fail(fails);
+                output
+            }
+            Inner::Word(word) => word.iter().map(Bit::Const).collect(),
+        }
+    }
+
+    /// Execute a `case` node, or an assertion with a hidden branch that fails.
+    ///
+    /// The input is a sum, paired with the input of the branches.
+    fn case(
+        &mut self,
+        node: &CommitNode<Elements>,
+        left: Option<&Arc<CommitNode<Elements>>>,
+        right: Option<&Arc<CommitNode<Elements>>>,
+        input: &[Bit],
+        condition: Bit,
+    ) -> Vec<Bit> {
+        let arrow = node.cached_data().arrow();
+        let (sum, _) = arrow.source.as_product().unwrap();
+        let (a, b) = sum.as_sum().unwrap();
+        let tag = input[0];
+        let rest = &input[sum.bit_width()..];
+        let branch_input = |padding: usize, width: usize| -> Vec<Bit> {
+            let mut branch_input = input[1 + padding..1 + padding + width].to_vec();
+            branch_input.extend_from_slice(rest);
+            branch_input
+        };
+        let left_input = branch_input(a.pad_left(b), a.bit_width());
+        let right_input = branch_input(a.pad_right(b), b.bit_width());
+
+        let left_condition = self.circuit.and(condition, !tag);
+        let right_condition = self.circuit.and(condition, tag);
+        let width = arrow.target.bit_width();
+        let mut branch = |child: Option<&Arc<CommitNode<Elements>>>, input, condition| match child {
+            Some(child) => self.eval(child, input, condition),
+            None => {
+                // Hidden branches fail
+                self.fail(condition);
+                vec![Bit::FALSE; width]
+            }
+        };
+        let left_output = branch(left, &left_input, left_condition);
+        let right_output = branch(right, &right_input, right_condition);
+
+        left_output
+            .into_iter()
+            .zip(right_output)
+            .map(|(l, r)| self.circuit.mux(tag, r, l))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::ParseFromStr;
+    use crate::{dummy_env, Arguments};
+
+    fn execute(program: &str) -> (CompiledProgram, SymbolicExecution) {
+        let compiled = CompiledProgram::new(program, Arguments::default(), true).unwrap();
+        let execution = SymbolicExecution::new(&compiled);
+        (compiled, execution)
+    }
+
+    /// Check that the witness values make the program succeed.
+    fn assert_succeeds(compiled: &CompiledProgram, reachability: &Reachability) {
+        let witness_values = reachability.witness_values().unwrap().clone();
+        let satisfied = compiled.satisfy(witness_values).unwrap();
+        satisfied.redeem().prune(&dummy_env::dummy()).unwrap();
+    }
+
+    #[test]
+    fn solve_arithmetic() {
+        let (compiled, execution) = execute(
+            r#"fn main() {
+    let x: u32 = witness::X;
+    let (_, y): (bool, u32) = jet::add_32(x, 5);
+    let z: u64 = jet::multiply_32(y, 3);
+    assert!(jet::eq_64(z, 36));
+}"#,
+        );
+        let witness_values = execution.success().witness_values().unwrap();
+        let expected = WitnessValues::parse_from_str("mod witness { const X: u32 = 7; }").unwrap();
+        assert_eq!(&expected, witness_values);
+        assert_succeeds(&compiled, execution.success());
+    }
+
+    #[test]
+    fn reach_match_arms() {
+        let (compiled, execution) = execute(
+            r#"fn main() {
+    let x: u8 = witness::X;
+    match jet::lt_8(x, 10) {
+        true => {
+            match jet::lt_8(20, x) {
+                true => panic!(),
+                false => assert!(jet::le_8(x, 5)),
+            };
+        },
+        false => {
+            let (_, y): (bool, u8) = jet::subtract_8(x, 10);
+            assert!(jet::eq_8(y, 32));
+        },
+    };
+}"#,
+        );
+        let targets = execution.targets();
+        assert_eq!(5, targets.len());
+        for target in targets {
+            let reachable = target.reachability().is_reachable();
+            match target.line() {
+                // `true => panic!()` and `panic!()` contradict `x < 10`
+                6 => assert!(!reachable, "{}", target.call().text()),
+                _ => assert!(reachable, "{}", target.call().text()),
+            }
+        }
+        assert_eq!(1, execution.dead_arms().count());
+        assert_eq!(0, execution.reachable_panics().count());
+        assert_succeeds(&compiled, execution.success());
+    }
+
+    #[test]
+    fn uninterpreted_jets() {
+        let (_, execution) = execute(
+            r#"fn main() {
+    let pk: Pubkey = witness::PK;
+    let sig: Signature = witness::SIG;
+    let msg: u256 = jet::sha_256_ctx_8_finalize(jet::sha_256_ctx_8_add_32(jet::sha_256_ctx_8_init(), pk));
+    match jet::eq_256(msg, jet::sha_256_ctx_8_finalize(jet::sha_256_ctx_8_add_32(jet::sha_256_ctx_8_init(), pk))) {
+        true => jet::bip_0340_verify((pk, msg), sig),
+        false => panic!(),
+    };
+}"#,
+        );
+        // The signature check may succeed
+        assert!(execution.success().is_reachable());
+        // Hashing the same data twice gives the same result
+        assert_eq!(0, execution.reachable_panics().count());
+        assert_eq!(1, execution.dead_arms().count());
+    }
+
+    #[test]
+    fn unreachable_success() {
+        let (_, execution) = execute(
+            r#"fn main() {
+    let x: u16 = witness::X;
+    let (carry, _): (bool, u16) = jet::add_16(x, 1);
+    assert!(carry);
+    assert!(jet::lt_16(x, 65535));
+}"#,
+        );
+        assert_eq!(&Reachability::Unreachable, execution.success());
+    }
+
+    #[test]
+    fn conflict_limit() {
+        let program = r#"fn main() {
+    let x: u8 = witness::X;
+    match jet::lt_8(x, 10) {
+        true => assert!(jet::eq_8(x, 3)),
+        false => assert!(jet::eq_8(x, 30)),
+    };
+}"#;
+        let compiled = CompiledProgram::new(program, Arguments::default(), true).unwrap();
+        let execution = SymbolicExecution::with_max_conflicts(&compiled, 0);
+        assert_eq!(&Reachability::Unknown, execution.success());
+        assert_eq!(2, execution.unknown_targets().count());
+        // Match arms of unknown reachability are not dead
+        assert_eq!(0, execution.dead_arms().count());
+        assert!(execution
+            .to_string()
+            .contains("unknown: the solver gave up"));
+
+        let execution = SymbolicExecution::new(&compiled);
+        assert!(execution.success().is_reachable());
+        assert_eq!(0, execution.unknown_targets().count());
+    }
+}
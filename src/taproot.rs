@@ -0,0 +1,296 @@
+//! Taproot outputs that are locked by Simplicity programs.
+//!
+//! A Simplicity program is a Taproot leaf whose script is the CMR of the program
+//! and whose leaf version is the Simplicity leaf version.
+//!
+//! A program with multiple entry points is a Taproot tree with one leaf per entry point.
+
+use elements::secp256k1_zkp::{Secp256k1, XOnlyPublicKey};
+use elements::taproot::{ControlBlock, LeafVersion, TaprootBuilder, TaprootSpendInfo};
+use simplicity::elements;
+use simplicity::Cmr;
+
+use crate::str::FunctionName;
+use crate::{Arguments, CompiledProgram, TemplateProgram};
+
+/// The "nothing up my sleeve" internal key that is suggested by BIP-341.
+///
+/// Nobody knows the discrete logarithm of this key,
+/// so outputs with this internal key can only be spent via their script paths.
+const NUMS_KEY: [u8; 32] = [
+    0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a, 0x5e,
+    0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a, 0xc0,
+];
+
+/// Return the "nothing up my sleeve" internal key that is suggested by BIP-341.
+pub fn nums_key() -> XOnlyPublicKey {
+    XOnlyPublicKey::from_slice(&NUMS_KEY).expect("NUMS key is a valid x-only public key")
+}
+
+/// Return the Taproot leaf script of the Simplicity program with the given CMR.
+pub fn leaf_script(cmr: Cmr) -> elements::Script {
+    elements::Script::from(cmr.as_ref().to_vec())
+}
+
+/// Return the Taproot leaf of the Simplicity program with the given CMR:
+/// its leaf script and the Simplicity leaf version.
+pub fn leaf(cmr: Cmr) -> (elements::Script, LeafVersion) {
+    (leaf_script(cmr), simplicity::leaf_version())
+}
+
+/// Return the Taproot spend info of an output with the given internal key
+/// whose Taproot tree consists of the single Simplicity program with the given CMR.
+pub fn spend_info(cmr: Cmr, internal_key: XOnlyPublicKey) -> TaprootSpendInfo {
+    tree_spend_info(&[cmr], internal_key)
+}
+
+/// Return the Taproot spend info of an output with the given internal key
+/// whose Taproot tree is a balanced tree of the Simplicity programs with the given CMRs.
+///
+/// The leaves appear in the tree in the given order.
+///
+/// ## Panics
+///
+/// There are no CMRs.
+pub fn tree_spend_info(cmrs: &[Cmr], internal_key: XOnlyPublicKey) -> TaprootSpendInfo {
+    assert!(!cmrs.is_empty(), "Taproot tree has at least one leaf");
+    add_balanced(TaprootBuilder::new(), cmrs, 0)
+        .finalize(&Secp256k1::verification_only(), internal_key)
+        .expect("balanced tree is a valid Taproot tree")
+}
+
+/// Add the given leaves as a balanced subtree at the given depth,
+/// in depth-first order as required by the builder.
+fn add_balanced(builder: TaprootBuilder, cmrs: &[Cmr], depth: usize) -> TaprootBuilder {
+    match cmrs {
+        [cmr] => {
+            let (script, version) = leaf(*cmr);
+            builder
+                .add_leaf_with_ver(depth, script, version)
+                .expect("balanced tree is a valid Taproot tree")
+        }
+        _ => {
+            let (left, right) = cmrs.split_at(cmrs.len().div_ceil(2));
+            let builder = add_balanced(builder, left, depth + 1);
+            add_balanced(builder, right, depth + 1)
+        }
+    }
+}
+
+/// A SimplicityHL program with one or more entry points,
+/// compiled into a Taproot tree with one Simplicity leaf per entry point.
+///
+/// Each leaf is a separate [`CompiledProgram`] with its own witness types.
+/// Spending via one leaf reveals neither the code nor the cost of the other leaves.
+#[derive(Clone, Debug)]
+pub struct TaprootProgram {
+    leaves: Vec<(FunctionName, CompiledProgram)>,
+}
+
+impl TaprootProgram {
+    /// Parse and compile each entry point of a SimplicityHL program from the given string.
+    ///
+    /// The same `arguments` are supplied to each entry point.
+    ///
+    /// ## See
+    ///
+    /// - [`TemplateProgram::entry_points`]
+    /// - [`TemplateProgram::instantiate`]
+    pub fn new<Str: Into<std::sync::Arc<str>>>(
+        s: Str,
+        arguments: Arguments,
+        include_debug_symbols: bool,
+    ) -> Result<Self, String> {
+        let leaves = TemplateProgram::entry_points(s)?
+            .into_iter()
+            .map(|template| {
+                let compiled = template.instantiate(arguments.clone(), include_debug_symbols)?;
+                Ok((template.entry_point().clone(), compiled))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self { leaves })
+    }
+
+    /// Iterate over the entry points and their compiled programs, in order of declaration.
+    pub fn leaves(&self) -> impl Iterator<Item = (&FunctionName, &CompiledProgram)> {
+        self.leaves.iter().map(|(name, compiled)| (name, compiled))
+    }
+
+    /// Access the compiled program of the entry point of the given name.
+    pub fn leaf(&self, entry_point: &str) -> Option<&CompiledProgram> {
+        self.leaves
+            .iter()
+            .find(|(name, _)| name.as_inner() == entry_point)
+            .map(|(_, compiled)| compiled)
+    }
+
+    /// Return the Taproot spend info of an output with the given internal key
+    /// that is locked by the Taproot tree of entry points.
+    ///
+    /// Use [`nums_key`] if the output should only be spendable via the entry points.
+    pub fn spend_info(&self, internal_key: XOnlyPublicKey) -> TaprootSpendInfo {
+        let cmrs = self
+            .leaves
+            .iter()
+            .map(|(_, compiled)| compiled.commit().cmr())
+            .collect::<Vec<Cmr>>();
+        tree_spend_info(&cmrs, internal_key)
+    }
+
+    /// Return the control block that proves that the entry point of the given name is a leaf
+    /// of the output with the given internal key.
+    ///
+    /// Return `None` if there is no entry point of the given name.
+    pub fn control_block(
+        &self,
+        entry_point: &str,
+        internal_key: XOnlyPublicKey,
+    ) -> Option<ControlBlock> {
+        let cmr = self.leaf(entry_point)?.commit().cmr();
+        self.spend_info(internal_key).control_block(&leaf(cmr))
+    }
+
+    /// Return the script pubkey of the Taproot output that is locked by the entry points,
+    /// with the NUMS internal key.
+    pub fn script_pubkey(&self) -> elements::Script {
+        let info = self.spend_info(nums_key());
+        elements::Script::new_v1_p2tr_tweaked(info.output_key())
+    }
+
+    /// Return the unconfidential address of the Taproot output that is locked by the entry points,
+    /// with the NUMS internal key.
+    pub fn address(&self, params: &'static elements::AddressParams) -> elements::Address {
+        let info = self.spend_info(nums_key());
+        elements::Address::p2tr_tweaked(info.output_key(), None, params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elements::secp256k1_zkp::Secp256k1;
+    use elements::AddressParams;
+
+    use super::*;
+    use crate::WitnessValues;
+
+    #[test]
+    fn p2tr_of_program() {
+        let compiled = CompiledProgram::new("fn main() {}", Arguments::default(), false).unwrap();
+        let script_pubkey = compiled.script_pubkey();
+        assert!(script_pubkey.is_v1_p2tr());
+        assert_eq!(
+            script_pubkey,
+            compiled.address(&AddressParams::ELEMENTS).script_pubkey()
+        );
+
+        let info = compiled.taproot_spend_info(nums_key());
+        let control_block = compiled.control_block(nums_key());
+        assert_eq!(simplicity::leaf_version(), control_block.leaf_version);
+        assert!(control_block.verify_taproot_commitment(
+            &Secp256k1::verification_only(),
+            &info.output_key(),
+            &leaf_script(compiled.commit().cmr()),
+        ));
+    }
+
+    const CONTRACT: &str = r#"#[entry_point]
+fn cooperative() {
+    let sig: Signature = witness::SIG;
+    assert!(jet::eq_32(32, 32));
+}
+
+#[entry_point]
+fn timeout() {
+    jet::check_lock_height(witness::HEIGHT);
+}
+
+#[entry_point]
+fn burn() {
+    panic!();
+}"#;
+
+    #[test]
+    fn entry_points_in_taproot_tree() {
+        let program = TaprootProgram::new(CONTRACT, Arguments::default(), false).unwrap();
+        let names: Vec<&str> = program.leaves().map(|(name, _)| name.as_inner()).collect();
+        assert_eq!(vec!["cooperative", "timeout", "burn"], names);
+
+        let cooperative = program.leaf("cooperative").unwrap();
+        let timeout = program.leaf("timeout").unwrap();
+        assert_eq!(1, cooperative.witness_types().iter().count());
+        assert!(cooperative
+            .witness_types()
+            .get(&crate::str::WitnessName::from_str_unchecked("SIG"))
+            .is_some());
+        assert!(timeout
+            .witness_types()
+            .get(&crate::str::WitnessName::from_str_unchecked("SIG"))
+            .is_none());
+        assert!(program.leaf("main").is_none());
+        assert!(program.control_block("main", nums_key()).is_none());
+
+        let info = program.spend_info(nums_key());
+        let secp = Secp256k1::verification_only();
+        for (name, compiled) in program.leaves() {
+            let control_block = program.control_block(name.as_inner(), nums_key()).unwrap();
+            assert!(control_block.verify_taproot_commitment(
+                &secp,
+                &info.output_key(),
+                &leaf_script(compiled.commit().cmr()),
+            ));
+        }
+        assert_eq!(
+            program.script_pubkey(),
+            program.address(&AddressParams::ELEMENTS).script_pubkey()
+        );
+
+        let burn = program.leaf("burn").unwrap();
+        let satisfied = burn.satisfy(WitnessValues::default()).unwrap();
+        assert_eq!(burn.commit().cmr(), satisfied.redeem().cmr());
+    }
+
+    #[test]
+    fn single_entry_point_matches_program() {
+        let program = TaprootProgram::new("fn main() {}", Arguments::default(), false).unwrap();
+        let compiled = CompiledProgram::new("fn main() {}", Arguments::default(), false).unwrap();
+        assert_eq!(compiled.script_pubkey(), program.script_pubkey());
+    }
+
+    #[test]
+    fn entry_point_errors() {
+        let redefined = "#[entry_point]\nfn a() {}\n#[entry_point]\nfn a() {}";
+        assert!(TaprootProgram::new(redefined, Arguments::default(), false).is_err());
+        let with_inputs = "#[entry_point]\nfn a(x: u8) {}";
+        assert!(TaprootProgram::new(with_inputs, Arguments::default(), false).is_err());
+        let no_entry_point = "fn f() {}";
+        assert!(
+            TaprootProgram::new(no_entry_point, Arguments::default(), false)
+                .unwrap_err()
+                .contains("Main function is required")
+        );
+        assert!(TemplateProgram::with_entry_point(
+            "fn main() {}",
+            &FunctionName::from_str_unchecked("a")
+        )
+        .unwrap_err()
+        .contains("Entry point `a` is not defined"));
+    }
+
+    #[test]
+    fn entry_points_are_opt_in() {
+        let prog_text = r#"fn main_helper(x: u8) -> u8 {
+    x
+}
+
+fn main_check() {
+    assert!(jet::eq_8(main_helper(1), 1));
+}
+
+fn main() {
+    main_check();
+}"#;
+        let program = TaprootProgram::new(prog_text, Arguments::default(), false).unwrap();
+        let names: Vec<&str> = program.leaves().map(|(name, _)| name.as_inner()).collect();
+        assert_eq!(vec!["main"], names);
+    }
+}
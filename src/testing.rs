@@ -0,0 +1,294 @@
+//! Unit tests inside SimplicityHL programs.
+//!
+//! A unit test is a function with the `#[test]` attribute that takes no parameters and returns nothing,
+//! just like the main function:
+//!
+//! ```text
+//! #[test]
+//! fn add_one() {
+//!     let (_, sum): (bool, u32) = jet::add_32(1, 1);
+//!     assert!(jet::eq_32(sum, 2));
+//! }
+//! ```
+//!
+//! Each unit test is compiled as the main function of its own program
+//! and executed in a dummy environment. The test passes if execution succeeds.
+//! More attributes configure the test:
+//!
+//! - `#[should_fail]`: The test passes if execution fails.
+//! - `#[witness("path")]`: The test takes its witness data from the file at the given path,
+//!   relative to the program file.
+//! - `#[lock_time(n)]`: The transaction of the environment has the given lock time.
+//! - `#[sequence(n)]`: The input of the environment has the given sequence number.
+
+use std::sync::Arc;
+
+use simplicity::bit_machine::SetTracker;
+use simplicity::elements;
+use simplicity::jet::elements::ElementsEnv;
+
+use crate::parse::{self, Attribute, ParseFromStr};
+use crate::str::FunctionName;
+use crate::tracker::TraceRecorder;
+use crate::{
+    dummy_env, Arguments, CompiledProgram, SatisfiedProgram, TemplateProgram, WitnessValues,
+};
+
+/// Unit test inside a SimplicityHL program.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UnitTest {
+    name: FunctionName,
+    should_fail: bool,
+    witness_file: Option<Arc<str>>,
+    lock_time: u32,
+    sequence: u32,
+}
+
+/// Outcome of a unit test.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TestOutcome {
+    /// The test passed.
+    Passed,
+    /// The test failed with the given message.
+    Failed(String),
+}
+
+/// Return the unit tests of the given SimplicityHL program, in order of declaration.
+///
+/// ## Errors
+///
+/// The string is not a valid SimplicityHL program.
+pub fn unit_tests(source: &str) -> Result<Vec<UnitTest>, String> {
+    let program = parse::Program::parse_from_str(source)?;
+    let tests = program
+        .items()
+        .iter()
+        .filter_map(|item| match item {
+            parse::Item::Function(function) if function.is_test() => Some(UnitTest::new(function)),
+            _ => None,
+        })
+        .collect();
+    Ok(tests)
+}
+
+impl UnitTest {
+    fn new(function: &parse::Function) -> Self {
+        let mut test = Self {
+            name: function.name().clone(),
+            should_fail: false,
+            witness_file: None,
+            lock_time: 0,
+            sequence: u32::MAX,
+        };
+        for attribute in function.attributes() {
+            match attribute {
+                Attribute::EntryPoint | Attribute::Test => {}
+                Attribute::ShouldFail => test.should_fail = true,
+                Attribute::Witness(path) => test.witness_file = Some(Arc::clone(path)),
+                Attribute::LockTime(n) => test.lock_time = *n,
+                Attribute::Sequence(n) => test.sequence = *n,
+                Attribute::Lint(..) => {}
+            }
+        }
+        test
+    }
+
+    /// Access the name of the test function.
+    pub fn name(&self) -> &FunctionName {
+        &self.name
+    }
+
+    /// Check if the test passes if its execution fails.
+    pub fn should_fail(&self) -> bool {
+        self.should_fail
+    }
+
+    /// Access the path of the witness file of the test, relative to the program file.
+    pub fn witness_file(&self) -> Option<&str> {
+        self.witness_file.as_deref()
+    }
+
+    /// Return the dummy environment in which the test is executed.
+    pub fn environment(&self) -> ElementsEnv<Arc<elements::Transaction>> {
+        dummy_env::dummy_with(
+            elements::LockTime::from_consensus(self.lock_time),
+            elements::Sequence::from_consensus(self.sequence),
+            false,
+        )
+    }
+
+    /// Compile the test function of the given program as the main function, with debug symbols.
+    ///
+    /// ## Errors
+    ///
+    /// - The string is not a valid SimplicityHL program.
+    /// - The arguments are inconsistent with the parameters of the program.
+    pub fn compile(&self, source: &str, arguments: Arguments) -> Result<CompiledProgram, String> {
+        TemplateProgram::with_entry_point(source, &self.name)?.instantiate(arguments, true)
+    }
+
+    /// Execute the satisfied test program in the environment of the test.
+    ///
+    /// If execution fails, then the message names the call expression that failed.
+    pub fn execute(&self, program: &SatisfiedProgram) -> TestOutcome {
+        let env = self.environment();
+        let mut recorder = TraceRecorder::new(program.debug_symbols(), SetTracker::default());
+        let result = program.redeem().prune_with_tracker(&env, &mut recorder);
+        match (result, self.should_fail) {
+            (Ok(_), false) => TestOutcome::Passed,
+            (Ok(_), true) => {
+                TestOutcome::Failed("Execution succeeded, but the test should fail".to_string())
+            }
+            (Err(_), true) => TestOutcome::Passed,
+            (Err(error), false) => {
+                let message = match recorder.events().last() {
+                    Some(event) => format!(
+                        "`{}` failed at line {}: {}",
+                        event.name(),
+                        event.call().span().start.line,
+                        event.call().text()
+                    ),
+                    None => format!("Execution failed: {error}"),
+                };
+                TestOutcome::Failed(message)
+            }
+        }
+    }
+
+    /// Compile, satisfy and execute the test function of the given program.
+    ///
+    /// Errors during compilation or satisfaction fail the test.
+    pub fn run(
+        &self,
+        source: &str,
+        arguments: Arguments,
+        witness_values: WitnessValues,
+    ) -> TestOutcome {
+        match self
+            .compile(source, arguments)
+            .and_then(|compiled| compiled.satisfy(witness_values))
+        {
+            Ok(satisfied) => self.execute(&satisfied),
+            Err(error) => TestOutcome::Failed(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = r#"fn add(a: u32, b: u32) -> u32 {
+    let (_, sum): (bool, u32) = jet::add_32(a, b);
+    sum
+}
+
+fn main() {
+    assert!(jet::eq_32(add(1, 1), 2));
+}
+
+#[test]
+fn add_one() {
+    assert!(jet::eq_32(add(1, 1), 2));
+}
+
+#[test]
+fn add_wrong() {
+    let x: u32 = add(1, 1);
+    assert!(jet::eq_32(x, 3));
+}
+
+#[test]
+#[should_fail]
+fn overflow() {
+    let (carry, _): (bool, u32) = jet::add_32(4294967295, 1);
+    assert!(jet::eq_1(<bool>::into(carry), 0));
+}
+
+#[test]
+#[lock_time(1000)]
+#[sequence(0)]
+fn lock_time() {
+    jet::check_lock_height(1000);
+}
+
+#[test]
+fn uses_witness() {
+    assert!(jet::eq_32(witness::X, 7));
+}"#;
+
+    fn outcome(name: &str, witness_values: WitnessValues) -> TestOutcome {
+        let tests = unit_tests(PROGRAM).unwrap();
+        let test = tests
+            .iter()
+            .find(|test| test.name().as_inner() == name)
+            .unwrap();
+        test.run(PROGRAM, Arguments::default(), witness_values)
+    }
+
+    #[test]
+    fn find_unit_tests() {
+        let tests = unit_tests(PROGRAM).unwrap();
+        let names = tests
+            .iter()
+            .map(|test| test.name().as_inner())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            vec![
+                "add_one",
+                "add_wrong",
+                "overflow",
+                "lock_time",
+                "uses_witness"
+            ],
+            names
+        );
+        assert!(tests[2].should_fail());
+        // Tests are ignored when compiling the main function
+        TemplateProgram::new(PROGRAM).unwrap();
+    }
+
+    #[test]
+    fn run_unit_tests() {
+        assert_eq!(
+            TestOutcome::Passed,
+            outcome("add_one", WitnessValues::default())
+        );
+        assert_eq!(
+            TestOutcome::Failed(
+                "`assert!` failed at line 18: assert!(jet::eq_32(x, 3))".to_string()
+            ),
+            outcome("add_wrong", WitnessValues::default())
+        );
+        assert_eq!(
+            TestOutcome::Passed,
+            outcome("overflow", WitnessValues::default())
+        );
+        assert_eq!(
+            TestOutcome::Passed,
+            outcome("lock_time", WitnessValues::default())
+        );
+
+        let witness_values =
+            WitnessValues::parse_from_str("mod witness { const X: u32 = 7; }").unwrap();
+        assert_eq!(TestOutcome::Passed, outcome("uses_witness", witness_values));
+        assert!(matches!(
+            outcome("uses_witness", WitnessValues::default()),
+            TestOutcome::Failed(_)
+        ));
+    }
+
+    #[test]
+    fn test_attributes_require_test() {
+        let prog_text = "#[should_fail]\nfn helper() {}\n\nfn main() {}";
+        let error = TemplateProgram::new(prog_text).unwrap_err();
+        assert!(
+            error.contains(
+                "Attribute `#[should_fail]` is only allowed on unit tests with `#[test]`"
+            ),
+            "Unexpected error: {error}"
+        );
+        let prog_text = "#[lock_time(1000)]\nfn main() {}";
+        assert!(unit_tests(prog_text).is_err());
+    }
+}
@@ -1,13 +1,14 @@
 use simplicity::bit_machine::{ExecTracker, FrameIter, NodeOutput, PruneTracker, SetTracker};
 use simplicity::jet::{Elements, Jet};
 use simplicity::node::Inner;
-use simplicity::{Ihr, RedeemNode, Value as SimValue, ValueRef};
+use simplicity::types::Final;
+use simplicity::{Cost, Ihr, RedeemNode, Value as SimValue, ValueRef};
 
-use crate::debug::{DebugSymbols, TrackedCallName};
+use crate::debug::{DebugSymbols, TrackedCall, TrackedCallName};
 use crate::either::Either;
 use crate::jet::{source_type, target_type};
 use crate::str::AliasName;
-use crate::types::AliasedType;
+use crate::types::{AliasedType, TypeConstructible};
 use crate::value::StructuralValue;
 use crate::{ResolvedType, Value};
 
@@ -87,6 +88,7 @@ pub struct DefaultTracker<'a> {
     debug_sink: Option<DebugSink<'a>>,
     jet_trace_sink: Option<JetTraceSink<'a>>,
     warning_sink: Option<WarningSink<'a>>,
+    offsets: FrameOffsets,
     inner: SetTracker,
 }
 
@@ -98,6 +100,7 @@ impl<'a> DefaultTracker<'a> {
             debug_sink: None,
             jet_trace_sink: None,
             warning_sink: None,
+            offsets: FrameOffsets::default(),
             inner: SetTracker::default(),
         }
     }
@@ -177,25 +180,19 @@ impl<'a> DefaultTracker<'a> {
         jet: Elements,
         input: &FrameIter,
         output: &NodeOutput,
+        (read, write): (usize, usize),
     ) {
         if self.jet_trace_sink.is_none() {
             return;
         }
 
-        let mut input_frame = input.clone();
-
-        // The reason we need to advance by a bit is that the AssertL combinator is actually a Case combinator,
-        // which takes a bit of input to decide which branch to take. But this bit is "meaningless" and
-        // is always 0 because it's an assertion.
-        let _ = input_frame.next();
-
-        let args = match parse_jet_arguments(jet, &mut input_frame) {
+        let args = match parse_jet_arguments(jet, input, read) {
             Ok(args) => args,
             Err(e) => {
                 self.warn(&format!("Failed to parse arguments for jet {jet:?}: {e}"));
 
                 // Still call the sink to report the jet execution, but without arguments.
-                let result = Self::parse_jet_result(node, jet, output);
+                let result = parse_jet_result(node, jet, output, write);
                 if let Some(sink) = self.jet_trace_sink.as_mut() {
                     sink(jet, None, result);
                 }
@@ -204,40 +201,13 @@ impl<'a> DefaultTracker<'a> {
             }
         };
 
-        let result = Self::parse_jet_result(node, jet, output);
+        let result = parse_jet_result(node, jet, output, write);
 
         if let Some(sink) = self.jet_trace_sink.as_mut() {
             sink(jet, Some(&args), result);
         }
     }
 
-    /// Parses the result of a jet execution from the output frame.
-    fn parse_jet_result(
-        node: &RedeemNode<Elements>,
-        jet: Elements,
-        output: &NodeOutput,
-    ) -> Option<Value> {
-        match output.clone() {
-            NodeOutput::Success(mut output_frame) => {
-                let target_ty = &node.arrow().target;
-
-                // Skip the leading bit when the frame has extra padding.
-                // This occurs because jets are wrapped in AssertL (a Case combinator),
-                // which adds structure to the output frame for some jets.
-                if output_frame.len() > target_ty.bit_width() {
-                    let _ = output_frame.next();
-                }
-
-                let output_value = SimValue::from_padded_bits(&mut output_frame, target_ty)
-                    .expect("output from bit machine is always well-formed");
-
-                let target_ty = resolve_jet_type(&target_type(jet));
-                Value::reconstruct(&StructuralValue::from(output_value), &target_ty)
-            }
-            _ => None,
-        }
-    }
-
     /// Sends a warning to the warning sink if configured.
     fn warn(&self, message: &str) {
         if let Some(sink) = self.warning_sink.as_ref() {
@@ -251,6 +221,7 @@ impl<'a> DefaultTracker<'a> {
         node: &RedeemNode<Elements>,
         input: &FrameIter,
         cmr: &simplicity::Cmr,
+        read: usize,
     ) {
         if self.debug_sink.is_none() {
             return;
@@ -265,15 +236,13 @@ impl<'a> DefaultTracker<'a> {
             return;
         };
 
-        let mut input_frame = input.clone();
-
-        // Skip the Case combinator's branch selection bit (see handle_jet).
-        let _ = input_frame.next();
-
+        // The AssertL combinator is actually a Case combinator, which takes a bit of input
+        // to decide which branch to take. This bit is "meaningless" and is always 0
+        // because it's an assertion, so skip it.
+        //
         // The debug call has signature `dbg!(T) -> T`, so the target type
         // matches the value being debugged
-        let Ok(input_val) = SimValue::from_padded_bits(&mut input_frame, &node.arrow().target)
-        else {
+        let Some(input_val) = read_value(input, read + 1, &node.arrow().target) else {
             self.warn(&format!("Failed to decode debug value for CMR {cmr}"));
             return;
         };
@@ -318,9 +287,269 @@ impl PruneTracker<Elements> for DefaultTracker<'_> {
 
 impl ExecTracker<Elements> for DefaultTracker<'_> {
     fn visit_node(&mut self, node: &RedeemNode<Elements>, input: FrameIter, output: NodeOutput) {
+        let offsets = self.offsets.visit(node, &input);
+        match node.inner() {
+            Inner::Jet(jet) => self.handle_jet(node, *jet, &input, &output, offsets),
+            Inner::AssertL(_, cmr) => self.handle_debug(node, &input, cmr, offsets.0),
+            _ => {}
+        }
+
+        self.inner.visit_node(node, input, output);
+    }
+}
+
+/// Evaluation of a tracked call expression, as recorded by [`TraceRecorder`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TraceEvent {
+    call: TrackedCall,
+    name: String,
+    inputs: Option<Vec<Value>>,
+    output: Option<Value>,
+    failed: bool,
+    cost: Cost,
+}
+
+impl TraceEvent {
+    /// Access the tracked call expression, including its span inside the SimplicityHL source file.
+    pub fn call(&self) -> &TrackedCall {
+        &self.call
+    }
+
+    /// Access the name of the jet or builtin function that is called,
+    /// such as `jet::add_32` or `assert!`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Access the input values of the call.
+    ///
+    /// Return `None` if the input values could not be decoded.
+    pub fn inputs(&self) -> Option<&[Value]> {
+        self.inputs.as_deref()
+    }
+
+    /// Access the output value of the call.
+    ///
+    /// Only jets and `dbg!` have known output values.
+    pub fn output(&self) -> Option<&Value> {
+        self.output.as_ref()
+    }
+
+    /// Check if the call failed.
+    ///
+    /// Only jet failures are detected.
+    /// Failing assertions and unwraps are the last event of the trace.
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Return the cost of the program execution up to and including this call,
+    /// in milliweight units.
+    pub fn cost(&self) -> Cost {
+        self.cost
+    }
+
+    /// Serialize the event as a single line of JSON, without trailing newline.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        let span = self.call.span();
+        let json = serde_json::json!({
+            "call": self.call.text(),
+            "name": self.name,
+            "start": { "line": span.start.line, "column": span.start.col },
+            "end": { "line": span.end.line, "column": span.end.col },
+            "inputs": self.inputs.as_ref().map(|inputs| {
+                inputs.iter().map(Value::to_string).collect::<Vec<String>>()
+            }),
+            "output": self.output.as_ref().map(Value::to_string),
+            "failed": self.failed,
+            "cost": self.cost.to_string().parse::<u64>().expect("cost is a number"),
+        });
+        json.to_string()
+    }
+}
+
+/// Tracker that records the evaluation of each tracked call expression.
+///
+/// Each [`TraceEvent`] links a jet or builtin call to its span in the SimplicityHL source,
+/// together with the decoded input and output values and the cumulative cost of the execution.
+/// The trace can be exported as JSON Lines, so failed spends can be analyzed offline
+/// and diffed between runs.
+///
+/// The program must be compiled with debug symbols.
+/// The recorder forwards all nodes to an inner tracker.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut recorder = TraceRecorder::new(&debug_symbols, SetTracker::default());
+/// let result = program.prune_with_tracker(&env, &mut recorder);
+///
+/// std::fs::write("trace.jsonl", recorder.to_json_lines())?;
+/// ```
+pub struct TraceRecorder<'a, T> {
+    debug_symbols: &'a DebugSymbols,
+    events: Vec<TraceEvent>,
+    pending_jet: Option<&'a TrackedCall>,
+    cost: Cost,
+    offsets: FrameOffsets,
+    inner: T,
+}
+
+impl<'a, T> TraceRecorder<'a, T> {
+    /// Creates a new recorder bound to the given debug symbol table
+    /// that forwards all nodes to the `inner` tracker.
+    pub fn new(debug_symbols: &'a DebugSymbols, inner: T) -> Self {
+        Self {
+            debug_symbols,
+            events: Vec::new(),
+            pending_jet: None,
+            cost: Cost::from_milliweight(0),
+            offsets: FrameOffsets::default(),
+            inner,
+        }
+    }
+
+    /// Access the recorded events, in order of evaluation.
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    /// Return the cost of the execution so far, in milliweight units.
+    pub fn cost(&self) -> Cost {
+        self.cost
+    }
+
+    /// Access the inner tracker.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Serialize the recorded events as JSON Lines: one JSON object per line.
+    #[cfg(feature = "serde")]
+    pub fn to_json_lines(&self) -> String {
+        self.events
+            .iter()
+            .map(|event| event.to_json() + "\n")
+            .collect()
+    }
+
+    /// Handles the assertion that wraps a tracked call expression.
+    ///
+    /// Jet calls are recorded when the jet is executed, so the output is known.
+    /// Other calls are recorded immediately.
+    fn handle_call(
+        &mut self,
+        node: &RedeemNode<Elements>,
+        input: &FrameIter,
+        read: usize,
+        call: &'a TrackedCall,
+    ) {
+        let (name, input_ty) = match call.name() {
+            TrackedCallName::Jet => {
+                self.pending_jet = Some(call);
+                return;
+            }
+            TrackedCallName::Assert => ("assert!", Some(ResolvedType::boolean())),
+            TrackedCallName::Panic => ("panic!", None),
+            TrackedCallName::UnwrapLeft(ty) => ("unwrap_left", Some(ty.clone())),
+            TrackedCallName::UnwrapRight(ty) => ("unwrap_right", Some(ty.clone())),
+            TrackedCallName::Unwrap => ("unwrap", None),
+            TrackedCallName::Debug(ty) => ("dbg!", Some(ty.clone())),
+            TrackedCallName::MatchArm => ("match arm", None),
+        };
+
+        // The input of the assertion is the pair of the branch bit and the call arguments
+        let inputs = match (call.name(), input_ty) {
+            (TrackedCallName::Panic | TrackedCallName::MatchArm, _) => Some(vec![]),
+            (_, Some(ty)) => read_value(input, read, &node.arrow().source)
+                .and_then(|value| value.as_product().map(|(_, args)| args.to_value()))
+                .and_then(|args| Value::reconstruct(&StructuralValue::from(args), &ty))
+                .map(|value| vec![value]),
+            (_, None) => None,
+        };
+        let output = match call.name() {
+            TrackedCallName::Debug(_) => inputs.as_ref().and_then(|inputs| inputs.first().cloned()),
+            _ => None,
+        };
+
+        self.events.push(TraceEvent {
+            call: call.clone(),
+            name: name.to_string(),
+            inputs,
+            output,
+            failed: false,
+            cost: self.cost,
+        });
+    }
+
+    /// Handles the execution of the jet of a tracked call expression.
+    fn handle_jet(
+        &mut self,
+        node: &RedeemNode<Elements>,
+        jet: Elements,
+        input: &FrameIter,
+        output: &NodeOutput,
+        (read, write): (usize, usize),
+        call: &TrackedCall,
+    ) {
+        let inputs = parse_jet_arguments(jet, input, read).ok();
+        let failed = matches!(output, NodeOutput::JetFailed);
+
+        self.events.push(TraceEvent {
+            call: call.clone(),
+            name: format!("jet::{jet}"),
+            inputs,
+            output: parse_jet_result(node, jet, output, write),
+            failed,
+            cost: self.cost,
+        });
+    }
+}
+
+/// Returns the cost that the execution of the given node adds,
+/// excluding the cost of its children.
+///
+/// Summed over all executed nodes, this matches the cost bounds of the pruned program.
+pub(crate) fn node_cost(node: &RedeemNode<Elements>) -> Cost {
+    let overhead = Cost::from_milliweight(100);
+    match node.inner() {
+        Inner::Iden | Inner::Witness(_) | Inner::Word(_) => {
+            overhead + Cost::of_type(node.arrow().target.bit_width())
+        }
+        Inner::Comp(left, _) => overhead + Cost::of_type(left.arrow().target.bit_width()),
+        Inner::Jet(jet) => overhead + jet.cost(),
+        Inner::Fail(_) => Cost::from_milliweight(0),
+        Inner::Disconnect(..) => unreachable!("SimplicityHL does not use disconnect right now"),
+        _ => overhead,
+    }
+}
+
+impl<T: PruneTracker<Elements>> PruneTracker<Elements> for TraceRecorder<'_, T> {
+    fn contains_left(&self, ihr: Ihr) -> bool {
+        self.inner.contains_left(ihr)
+    }
+
+    fn contains_right(&self, ihr: Ihr) -> bool {
+        self.inner.contains_right(ihr)
+    }
+}
+
+impl<T: ExecTracker<Elements>> ExecTracker<Elements> for TraceRecorder<'_, T> {
+    fn visit_node(&mut self, node: &RedeemNode<Elements>, input: FrameIter, output: NodeOutput) {
+        self.cost = self.cost + node_cost(node);
+        let offsets = self.offsets.visit(node, &input);
         match node.inner() {
-            Inner::Jet(jet) => self.handle_jet(node, *jet, &input, &output),
-            Inner::AssertL(_, cmr) => self.handle_debug(node, &input, cmr),
+            Inner::AssertL(_, cmr) => {
+                if let Some(call) = self.debug_symbols.get(cmr) {
+                    self.handle_call(node, &input, offsets.0, call);
+                }
+            }
+            Inner::Jet(jet) => {
+                if let Some(call) = self.pending_jet.take() {
+                    self.handle_jet(node, *jet, &input, &output, offsets, call);
+                }
+            }
             _ => {}
         }
 
@@ -328,14 +557,118 @@ impl ExecTracker<Elements> for DefaultTracker<'_> {
     }
 }
 
+/// Offsets at which the nodes of a program read and write inside the frames of the Bit Machine.
+///
+/// The Bit Machine passes the entire topmost read and write frames to the tracker,
+/// but a node reads and writes at the current cursors of these frames,
+/// which depend on the ancestors of the node.
+/// The offsets are computed by visiting the nodes in the same pre-order as the Bit Machine.
+#[derive(Debug, Default)]
+pub(crate) struct FrameOffsets {
+    /// Offsets of the children that are visited next, with the next child on top.
+    stack: Vec<(usize, usize)>,
+}
+
+impl FrameOffsets {
+    /// Visit the next node of the execution.
+    ///
+    /// Return the offset of the node's input in the read frame
+    /// and the offset of the node's output in the write frame.
+    pub(crate) fn visit(
+        &mut self,
+        node: &RedeemNode<Elements>,
+        input: &FrameIter,
+    ) -> (usize, usize) {
+        let (read, write) = self.stack.pop().unwrap_or((0, 0));
+        let arrow = node.arrow();
+        match node.inner() {
+            Inner::Comp(..) => {
+                // The left child writes a fresh frame that the right child reads
+                self.stack.push((0, write));
+                self.stack.push((read, 0));
+            }
+            Inner::Pair(left, _) => {
+                self.stack
+                    .push((read, write + left.arrow().target.bit_width()));
+                self.stack.push((read, write));
+            }
+            Inner::Take(_) => self.stack.push((read, write)),
+            Inner::Drop(_) => {
+                let (a, _) = arrow.source.as_product().expect("drop has product source");
+                self.stack.push((read + a.bit_width(), write));
+            }
+            Inner::InjL(_) | Inner::InjR(_) => {
+                let (b, c) = arrow.target.as_sum().expect("injection has sum target");
+                let padding = match node.inner() {
+                    Inner::InjL(_) => b.pad_left(c),
+                    _ => b.pad_right(c),
+                };
+                self.stack.push((read, write + 1 + padding));
+            }
+            Inner::Case(..) | Inner::AssertL(..) | Inner::AssertR(..) => {
+                let (sum, _) = arrow.source.as_product().expect("case has product source");
+                let (a, b) = sum.as_sum().expect("case has sum source");
+                let padding = match input.clone().nth(read) {
+                    Some(true) => a.pad_right(b),
+                    _ => a.pad_left(b),
+                };
+                self.stack.push((read + 1 + padding, write));
+            }
+            Inner::Disconnect(left, right) => {
+                let b = left.arrow().target.bit_width() - right.arrow().source.bit_width();
+                self.stack.push((b, write + b));
+                self.stack.push((0, 0));
+            }
+            _ => {}
+        }
+        (read, write)
+    }
+}
+
+/// Reads a value of the given type at the given offset of the frame.
+pub(crate) fn read_value(frame: &FrameIter, offset: usize, ty: &Final) -> Option<SimValue> {
+    let mut frame = frame.clone();
+    for _ in 0..offset {
+        frame.next()?;
+    }
+    SimValue::from_padded_bits(&mut frame, ty).ok()
+}
+
+/// Parses the result of a jet execution from the output frame.
+///
+/// The jet writes its result at the given offset of the output frame.
+fn parse_jet_result(
+    node: &RedeemNode<Elements>,
+    jet: Elements,
+    output: &NodeOutput,
+    write: usize,
+) -> Option<Value> {
+    match output {
+        NodeOutput::Success(output_frame) => {
+            let output_value = read_value(output_frame, write, &node.arrow().target)
+                .expect("output from bit machine is always well-formed");
+
+            let target_ty = resolve_jet_type(&target_type(jet));
+            Value::reconstruct(&StructuralValue::from(output_value), &target_ty)
+        }
+        _ => None,
+    }
+}
+
 /// Parses jet input arguments from the bit machine's read frame.
-fn parse_jet_arguments(jet: Elements, input_frame: &mut FrameIter) -> Result<Vec<Value>, String> {
+///
+/// The jet reads its arguments at the given offset of the input frame.
+fn parse_jet_arguments(
+    jet: Elements,
+    input_frame: &FrameIter,
+    read: usize,
+) -> Result<Vec<Value>, String> {
     let source_types = source_type(jet);
     if source_types.is_empty() {
         return Ok(vec![]);
     }
 
-    let arguments_blob = SimValue::from_padded_bits(input_frame, &jet.source_ty().to_final())
+    let arguments_blob = read_value(input_frame, read, &jet.source_ty().to_final())
         .expect("input from bit machine is always well-formed");
 
     let mut args = Vec::with_capacity(source_types.len());
@@ -524,4 +857,113 @@ mod tests {
             Some("Some((Right(0x6d521c38ec1ea15734ae22b7c46064412829c0d0579f0a713d1c04ede979026f), Right(1000)))")
         );
     }
+
+    #[test]
+    fn test_trace_recorder() {
+        let program = TemplateProgram::new(TEST_PROGRAM).unwrap();
+        let program = program.instantiate(Arguments::default(), true).unwrap();
+        let satisfied = program.satisfy(WitnessValues::default()).unwrap();
+        let env = create_test_env();
+
+        let mut recorder = TraceRecorder::new(&satisfied.debug_symbols, SetTracker::default());
+        let pruned = satisfied
+            .redeem()
+            .prune_with_tracker(&env, &mut recorder)
+            .unwrap();
+
+        let events = recorder.events();
+        let names: Vec<&str> = events.iter().map(TraceEvent::name).collect();
+        assert_eq!(
+            vec![
+                "jet::num_inputs",
+                "jet::eq_32",
+                "dbg!",
+                "jet::input_amount",
+                "unwrap",
+                "dbg!",
+                "unwrap_right",
+                "unwrap_right",
+                "dbg!",
+            ],
+            names
+        );
+        assert_eq!("jet::eq_32(20, 21)", events[1].call().text());
+        assert_eq!(12, events[1].call().span().start.line.get());
+        assert_eq!(
+            Some(vec!["20".to_string(), "21".to_string()]),
+            events[1]
+                .inputs()
+                .map(|inputs| inputs.iter().map(Value::to_string).collect())
+        );
+        assert_eq!(
+            Some("false".to_string()),
+            events[1].output().map(Value::to_string)
+        );
+        assert!(events.iter().all(|event| !event.failed()));
+        assert!(events.windows(2).all(|w| w[0].cost() < w[1].cost()));
+        // The cumulative cost of the execution matches the cost bounds of the pruned program
+        assert_eq!(pruned.bounds().cost, recorder.cost());
+    }
+
+    #[test]
+    fn test_jet_output_offsets() {
+        // The jets write their outputs after other values of the same frame
+        let program = TemplateProgram::new(
+            "fn main() {\n    let pair: (u32, bool) = (7, jet::eq_32(1, 1));\n    let b: bool = match jet::eq_32(2, 2) {\n        true => jet::eq_32(3, 3),\n        false => false,\n    };\n}",
+        )
+        .unwrap()
+        .instantiate(Arguments::default(), true)
+        .unwrap();
+        let satisfied = program.satisfy(WitnessValues::default()).unwrap();
+        let mut recorder = TraceRecorder::new(&satisfied.debug_symbols, SetTracker::default());
+        satisfied
+            .redeem()
+            .prune_with_tracker(&create_test_env(), &mut recorder)
+            .unwrap();
+
+        let jets: Vec<(String, String)> = recorder
+            .events()
+            .iter()
+            .filter(|event| event.name() == "jet::eq_32")
+            .map(|event| {
+                let inputs = event.inputs().unwrap();
+                (inputs[0].to_string(), event.output().unwrap().to_string())
+            })
+            .collect();
+        let expected = [("1", "true"), ("2", "true"), ("3", "true")]
+            .map(|(input, output)| (input.to_string(), output.to_string()));
+        assert_eq!(expected.as_slice(), jets);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_trace_recorder_failure() {
+        let program = TemplateProgram::new(
+            "fn main() {\n    let a: u32 = jet::num_inputs();\n    jet::check_lock_height(100)\n}",
+        )
+        .unwrap()
+        .instantiate(Arguments::default(), true)
+        .unwrap();
+        let satisfied = program.satisfy(WitnessValues::default()).unwrap();
+        let mut recorder = TraceRecorder::new(&satisfied.debug_symbols, SetTracker::default());
+        assert!(satisfied
+            .redeem()
+            .prune_with_tracker(&create_test_env(), &mut recorder)
+            .is_err());
+
+        let lines: Vec<serde_json::Value> = recorder
+            .to_json_lines()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(2, lines.len());
+        assert_eq!("jet::num_inputs", lines[0]["name"]);
+        assert_eq!(false, lines[0]["failed"]);
+        assert_eq!("jet::check_lock_height", lines[1]["name"]);
+        assert_eq!("jet::check_lock_height(100)", lines[1]["call"]);
+        assert_eq!(3, lines[1]["start"]["line"]);
+        assert_eq!(serde_json::json!(["100"]), lines[1]["inputs"]);
+        assert_eq!(serde_json::Value::Null, lines[1]["output"]);
+        assert_eq!(true, lines[1]["failed"]);
+    }
 }
@@ -164,9 +164,9 @@ impl Arguments {
     /// Arguments without a corresponding parameter are ignored.
     pub fn is_consistent(&self, parameters: &Parameters) -> Result<(), Error> {
         for (name, parameter_ty) in parameters.iter() {
-            let argument = self
-                .get(name)
-                .ok_or_else(|| Error::ArgumentMissing(name.shallow_clone()))?;
+            let argument = self.get(name).ok_or_else(|| {
+                Error::ArgumentMissing(name.shallow_clone(), parameter_ty.clone())
+            })?;
             if !argument.is_of_type(parameter_ty) {
                 return Err(Error::ArgumentTypeMismatch(
                     name.clone(),
@@ -199,6 +199,28 @@ impl crate::ArbitraryOfType for Arguments {
     }
 }
 
+#[cfg(feature = "arbitrary")]
+impl crate::ArbitraryOfType for WitnessValues {
+    type Type = WitnessTypes;
+
+    fn arbitrary_of_type(
+        u: &mut arbitrary::Unstructured,
+        ty: &Self::Type,
+    ) -> arbitrary::Result<Self> {
+        // Generate the values in order of names, so the same bytes produce the same values
+        let mut witness_types = ty.iter().collect::<Vec<_>>();
+        witness_types.sort_by_key(|(name, _)| *name);
+        let mut map = HashMap::new();
+        for (name, witness_ty) in witness_types {
+            map.insert(
+                name.shallow_clone(),
+                Value::arbitrary_of_type(u, witness_ty)?,
+            );
+        }
+        Ok(Self::from(map))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,79 @@
+//! Tests of the `simc` command line tool.
+
+#![cfg(feature = "serde")]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const TX: &str = r#"
+lock_time = 1000
+
+[[inputs]]
+previous_output = "0101010101010101010101010101010101010101010101010101010101010101:1"
+sequence = 4294967294
+utxo = { script_pubkey = "51", value = 100000 }
+
+[[outputs]]
+script_pubkey = "0014000102030405060708090a0b0c0d0e0f10111213"
+value = 99000
+
+[[outputs]]
+value = 1000
+"#;
+
+/// Secret key of the public key in `examples/p2pk.args`.
+const KEYS: &str = r#"{
+    "ALICE_SIGNATURE": "0000000000000000000000000000000000000000000000000000000000000001"
+}"#;
+
+fn simc<P: AsRef<Path>>(args: &[P]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_simc"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(args.iter().map(AsRef::as_ref))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn write_tmp(name: &str, contents: &[u8]) -> PathBuf {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn run_accepts_signed_witness() {
+    let tx_file = write_tmp("sign_then_run.toml", TX.as_bytes());
+    let keys_file = write_tmp("sign_then_run.keys.json", KEYS.as_bytes());
+
+    let signed = simc(&[
+        Path::new("sign"),
+        Path::new("examples/p2pk.simf"),
+        Path::new("--args"),
+        Path::new("examples/p2pk.args"),
+        Path::new("--keys"),
+        &keys_file,
+        Path::new("--tx"),
+        &tx_file,
+    ]);
+    let wit_file = write_tmp("sign_then_run.wit", &signed.stdout);
+
+    let run = simc(&[
+        Path::new("run"),
+        Path::new("examples/p2pk.simf"),
+        &wit_file,
+        Path::new("--args"),
+        Path::new("examples/p2pk.args"),
+        Path::new("--tx"),
+        &tx_file,
+    ]);
+    assert_eq!(
+        "Program executed successfully\n",
+        String::from_utf8_lossy(&run.stdout)
+    );
+}